@@ -32,14 +32,14 @@ use std::str::FromStr;
 
 use amplify::Wrapper;
 use bc::{
-    CompressedPk, ControlBlock, InternalPk, LeafScript, LegacyPk, PubkeyHash, RedeemScript,
-    ScriptPubkey, TapNodeHash, WitnessScript, XOnlyPk,
+    CompressedPk, ControlBlock, InternalPk, LeafScript, LegacyPk, RedeemScript, ScriptPubkey,
+    TapNodeHash, WPubkeyHash, WitnessScript, XOnlyPk,
 };
 use indexmap::IndexMap;
 
 use crate::{
     Address, AddressNetwork, AddressParseError, ControlBlockFactory, DerivationIndex, IdxBase,
-    IndexParseError, NormalIndex, TapTree, XpubAccount, XpubDerivable,
+    IndexParseError, KeyOrigin, NormalIndex, TapTree, XprivDerivable, XpubAccount, XpubDerivable,
 };
 
 #[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Default, Debug, Display, From)]
@@ -230,7 +230,7 @@ impl DerivedScript {
             DerivedScript::Bip13(redeem_script) => Some(redeem_script.clone()),
             DerivedScript::Segwit(_) => None,
             DerivedScript::NestedKey(pk) => Some(RedeemScript::from_checked(
-                ScriptPubkey::p2pkh(PubkeyHash::from(*pk)).into_inner().into_vec(),
+                ScriptPubkey::p2wpkh(WPubkeyHash::from(*pk)).into_inner().into_vec(),
             )),
             DerivedScript::NestedScript(witness_script) => Some(witness_script.to_redeem_script()),
             DerivedScript::TaprootKeyOnly(_) => None,
@@ -374,7 +374,30 @@ pub trait Derive<D> {
 }
 
 pub trait DeriveKey<D>: Derive<D> + Clone + Eq + Hash + Debug + Display {
+    /// Extended public key the keys are derived from.
+    ///
+    /// # Panics
+    ///
+    /// If the key is not derived from an extended public key; use [`DeriveKey::try_xpub_spec`]
+    /// for such keys.
     fn xpub_spec(&self) -> &XpubAccount;
+
+    /// Extended public key the keys are derived from, if any.
+    fn try_xpub_spec(&self) -> Option<&XpubAccount> { Some(self.xpub_spec()) }
+
+    /// Origin of the key derived for the `terminal`, as it is reported in PSBTs.
+    fn key_origin(&self, terminal: Terminal) -> KeyOrigin {
+        KeyOrigin::with(self.xpub_spec().origin().clone(), terminal)
+    }
+
+    /// Detects whether a key with the `origin` is derived from this key.
+    fn is_origin_of(&self, origin: &KeyOrigin) -> bool {
+        self.xpub_spec().origin().is_subset_of(origin)
+    }
+
+    /// Detects whether the key can be used where keys of type `D` are required without being
+    /// converted, for instance an uncompressed public key can't be used in segwit descriptors.
+    fn is_valid_in_context(&self) -> bool { true }
 }
 
 pub trait DeriveLegacy: DeriveKey<LegacyPk> {}
@@ -478,6 +501,68 @@ impl Derive<XOnlyPk> for XpubDerivable {
     }
 }
 
+impl DeriveKey<LegacyPk> for XprivDerivable {
+    fn xpub_spec(&self) -> &XpubAccount { self.as_ref().spec() }
+}
+
+impl Derive<LegacyPk> for XprivDerivable {
+    #[inline]
+    fn default_keychain(&self) -> Keychain { Derive::<LegacyPk>::default_keychain(self.as_ref()) }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { Derive::<LegacyPk>::keychains(self.as_ref()) }
+
+    fn derive(
+        &self,
+        keychain: impl Into<Keychain>,
+        index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = LegacyPk> {
+        Derive::<LegacyPk>::derive(self.as_ref(), keychain, index)
+    }
+}
+
+impl DeriveKey<CompressedPk> for XprivDerivable {
+    fn xpub_spec(&self) -> &XpubAccount { self.as_ref().spec() }
+}
+
+impl Derive<CompressedPk> for XprivDerivable {
+    #[inline]
+    fn default_keychain(&self) -> Keychain {
+        Derive::<CompressedPk>::default_keychain(self.as_ref())
+    }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { Derive::<CompressedPk>::keychains(self.as_ref()) }
+
+    fn derive(
+        &self,
+        keychain: impl Into<Keychain>,
+        index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = CompressedPk> {
+        Derive::<CompressedPk>::derive(self.as_ref(), keychain, index)
+    }
+}
+
+impl DeriveKey<XOnlyPk> for XprivDerivable {
+    fn xpub_spec(&self) -> &XpubAccount { self.as_ref().spec() }
+}
+
+impl Derive<XOnlyPk> for XprivDerivable {
+    #[inline]
+    fn default_keychain(&self) -> Keychain { Derive::<XOnlyPk>::default_keychain(self.as_ref()) }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { Derive::<XOnlyPk>::keychains(self.as_ref()) }
+
+    fn derive(
+        &self,
+        keychain: impl Into<Keychain>,
+        index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = XOnlyPk> {
+        Derive::<XOnlyPk>::derive(self.as_ref(), keychain, index)
+    }
+}
+
 pub trait DeriveSet {
     type Legacy: DeriveLegacy;
     type Compr: DeriveCompr;
@@ -489,3 +574,9 @@ impl DeriveSet for XpubDerivable {
     type Compr = XpubDerivable;
     type XOnly = XpubDerivable;
 }
+
+impl DeriveSet for XprivDerivable {
+    type Legacy = XprivDerivable;
+    type Compr = XprivDerivable;
+    type XOnly = XprivDerivable;
+}
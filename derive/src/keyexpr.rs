@@ -0,0 +1,441 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Descriptor key expressions (BIP-380) which may be used in place of a ranged extended key.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::iter;
+use std::str::FromStr;
+
+use amplify::hex::ToHex;
+use bc::secp256k1::{Parity, PublicKey};
+use bc::{CompressedPk, LegacyPk, UncompressedPk, XOnlyPk};
+use commit_verify::{Digest, Ripemd160};
+use sha2::Sha256;
+
+use crate::{
+    DerivationIndex, DerivationPath, Derive, DeriveKey, DeriveSet, Idx, KeyOrigin, Keychain,
+    NormalIndex, Terminal, XkeyParseError, XpubAccount, XpubDerivable, XpubFp,
+};
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum KeyExprParseError {
+    /// invalid public key '{0}'.
+    InvalidPk(String),
+
+    /// key origin is not terminated with ']'.
+    NoOriginEnd,
+
+    #[from]
+    #[display(inner)]
+    Xkey(XkeyParseError),
+}
+
+/// Key expression of a descriptor.
+///
+/// Keys which are not ranged derive the same public key for all terminals and report a single
+/// [`Keychain::OUTER`] keychain. Uncompressed and x-only keys are not valid in segwit v0
+/// descriptors (see [`DeriveKey::is_valid_in_context`]). In other contexts a key which doesn't
+/// belong to them is converted: x-only keys are lifted to the point with even Y coordinate, as in
+/// BIP-340, and uncompressed keys used in taproot lose their Y coordinate.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
+pub enum KeyExpr {
+    /// Hex-encoded compressed public key, with an optional origin.
+    Compr {
+        origin: Option<KeyOrigin>,
+        key: CompressedPk,
+    },
+
+    /// Hex-encoded uncompressed public key, with an optional origin.
+    Uncompr {
+        origin: Option<KeyOrigin>,
+        key: UncompressedPk,
+    },
+
+    /// Hex-encoded x-only public key, with an optional origin.
+    XOnly {
+        origin: Option<KeyOrigin>,
+        key: XOnlyPk,
+    },
+
+    /// Extended public key with a fixed (non-ranged) derivation path.
+    XpubFixed {
+        spec: XpubAccount,
+        path: DerivationPath<NormalIndex>,
+    },
+
+    /// Extended public key with a ranged derivation.
+    #[from]
+    Xpub(XpubDerivable),
+}
+
+impl From<CompressedPk> for KeyExpr {
+    fn from(key: CompressedPk) -> Self { KeyExpr::Compr { origin: None, key } }
+}
+
+impl From<XOnlyPk> for KeyExpr {
+    fn from(key: XOnlyPk) -> Self { KeyExpr::XOnly { origin: None, key } }
+}
+
+impl KeyExpr {
+    /// Detects whether the key derives different public keys for different terminals.
+    pub fn is_ranged(&self) -> bool { matches!(self, KeyExpr::Xpub(_)) }
+
+    /// Extended public key the key expression is based on, if any.
+    pub fn try_xpub_spec(&self) -> Option<&XpubAccount> {
+        match self {
+            KeyExpr::Compr { .. } | KeyExpr::Uncompr { .. } | KeyExpr::XOnly { .. } => None,
+            KeyExpr::XpubFixed { spec, .. } => Some(spec),
+            KeyExpr::Xpub(xpub) => Some(xpub.spec()),
+        }
+    }
+
+    /// Origin of the key derived for a `terminal`.
+    ///
+    /// Raw public keys without an explicit origin use the first four bytes of their HASH160 as
+    /// the master key fingerprint with an empty derivation path.
+    pub fn key_origin(&self, terminal: Terminal) -> KeyOrigin {
+        let raw_origin = |origin: &Option<KeyOrigin>, data: &[u8]| {
+            origin.clone().unwrap_or_else(|| {
+                let hash = Ripemd160::digest(Sha256::digest(data));
+                let mut fp = [0u8; 4];
+                fp.copy_from_slice(&hash[..4]);
+                KeyOrigin::new(XpubFp::from(fp), DerivationPath::new())
+            })
+        };
+        match self {
+            KeyExpr::Compr { origin, key } => raw_origin(origin, &key.to_byte_array()),
+            KeyExpr::Uncompr { origin, key } => raw_origin(origin, &key.to_byte_array()),
+            KeyExpr::XOnly { origin, key } => raw_origin(origin, &key.to_byte_array()),
+            KeyExpr::XpubFixed { spec, path } => {
+                let mut derivation = spec.origin().to_derivation();
+                derivation.extend(path.iter().copied().map(DerivationIndex::Normal));
+                KeyOrigin::new(spec.origin().master_fp(), derivation)
+            }
+            KeyExpr::Xpub(xpub) => KeyOrigin::with(xpub.origin().clone(), terminal),
+        }
+    }
+
+    /// Detects whether a key with the given `origin` is derived from this key expression.
+    pub fn is_origin_of(&self, origin: &KeyOrigin) -> bool {
+        match self {
+            KeyExpr::Xpub(xpub) => xpub.origin().is_subset_of(origin),
+            _ => self.key_origin(Terminal::new(Keychain::OUTER, NormalIndex::ZERO)) == *origin,
+        }
+    }
+
+    fn derive_pubkey(&self, terminal: Terminal) -> (PublicKey, bool) {
+        match self {
+            KeyExpr::Compr { key, .. } => (**key, true),
+            KeyExpr::Uncompr { key, .. } => (**key, false),
+            KeyExpr::XOnly { key, .. } => (key.public_key(Parity::Even), true),
+            KeyExpr::XpubFixed { spec, path } => {
+                (*spec.xpub().derive_pub(path).to_compr_pk(), true)
+            }
+            KeyExpr::Xpub(xpub) => (
+                *xpub.xpub().derive_pub([terminal.keychain.into(), terminal.index]).to_compr_pk(),
+                true,
+            ),
+        }
+    }
+}
+
+impl Display for KeyExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let fmt_origin = |f: &mut Formatter<'_>, origin: &Option<KeyOrigin>| {
+            if let Some(origin) = origin {
+                f.write_str("[")?;
+                Display::fmt(origin, f)?;
+                f.write_str("]")?;
+            }
+            Ok(())
+        };
+        match self {
+            KeyExpr::Compr { origin, key } => {
+                fmt_origin(f, origin)?;
+                f.write_str(&key.to_byte_array().to_hex())
+            }
+            KeyExpr::Uncompr { origin, key } => {
+                fmt_origin(f, origin)?;
+                f.write_str(&key.to_byte_array().to_hex())
+            }
+            KeyExpr::XOnly { origin, key } => {
+                fmt_origin(f, origin)?;
+                f.write_str(&key.to_byte_array().to_hex())
+            }
+            KeyExpr::XpubFixed { spec, path } => {
+                Display::fmt(spec, f)?;
+                Display::fmt(path, f)
+            }
+            KeyExpr::Xpub(xpub) => Display::fmt(xpub, f),
+        }
+    }
+}
+
+impl FromStr for KeyExpr {
+    type Err = KeyExprParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (origin, key) = match s.strip_prefix('[') {
+            Some(rest) => {
+                let (origin, key) = rest.split_once(']').ok_or(KeyExprParseError::NoOriginEnd)?;
+                (Some(origin), key)
+            }
+            None => (None, s),
+        };
+
+        if key.chars().all(|c| c.is_ascii_hexdigit()) {
+            let origin = origin.map(KeyOrigin::from_str).transpose()?;
+            let invalid = || KeyExprParseError::InvalidPk(key.to_owned());
+            return Ok(match key.len() {
+                66 => KeyExpr::Compr {
+                    origin,
+                    key: CompressedPk::from_str(key).map_err(|_| invalid())?,
+                },
+                130 => KeyExpr::Uncompr {
+                    origin,
+                    key: UncompressedPk::from_str(key).map_err(|_| invalid())?,
+                },
+                64 => KeyExpr::XOnly {
+                    origin,
+                    key: XOnlyPk::from_str(key).map_err(|_| invalid())?,
+                },
+                _ => return Err(invalid()),
+            });
+        }
+
+        if key.ends_with("/*") {
+            return XpubDerivable::from_str(s).map(KeyExpr::from).map_err(KeyExprParseError::from);
+        }
+        let origin = origin.ok_or(XkeyParseError::NoOrigin)?;
+        let (xpub, path) = match key.split_once('/') {
+            Some((xpub, path)) => {
+                (xpub, DerivationPath::from_str(path).map_err(XkeyParseError::from)?)
+            }
+            None => (key, DerivationPath::new()),
+        };
+        let spec = XpubAccount::from_str(&format!("[{origin}]{xpub}"))?;
+        Ok(KeyExpr::XpubFixed { spec, path })
+    }
+}
+
+impl Derive<LegacyPk> for KeyExpr {
+    fn default_keychain(&self) -> Keychain {
+        match self {
+            KeyExpr::Xpub(xpub) => Derive::<LegacyPk>::default_keychain(xpub),
+            _ => Keychain::OUTER,
+        }
+    }
+
+    fn keychains(&self) -> BTreeSet<Keychain> {
+        match self {
+            KeyExpr::Xpub(xpub) => Derive::<LegacyPk>::keychains(xpub),
+            _ => bset![Keychain::OUTER],
+        }
+    }
+
+    fn derive(
+        &self,
+        keychain: impl Into<Keychain>,
+        index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = LegacyPk> {
+        let (pubkey, compressed) = self.derive_pubkey(Terminal::new(keychain, index.into()));
+        iter::once(LegacyPk { compressed, pubkey })
+    }
+}
+
+impl Derive<CompressedPk> for KeyExpr {
+    fn default_keychain(&self) -> Keychain { Derive::<LegacyPk>::default_keychain(self) }
+
+    fn keychains(&self) -> BTreeSet<Keychain> { Derive::<LegacyPk>::keychains(self) }
+
+    fn derive(
+        &self,
+        keychain: impl Into<Keychain>,
+        index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = CompressedPk> {
+        let (pubkey, _) = self.derive_pubkey(Terminal::new(keychain, index.into()));
+        iter::once(CompressedPk::from(pubkey))
+    }
+}
+
+impl Derive<XOnlyPk> for KeyExpr {
+    fn default_keychain(&self) -> Keychain { Derive::<LegacyPk>::default_keychain(self) }
+
+    fn keychains(&self) -> BTreeSet<Keychain> { Derive::<LegacyPk>::keychains(self) }
+
+    fn derive(
+        &self,
+        keychain: impl Into<Keychain>,
+        index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = XOnlyPk> {
+        let (pubkey, _) = self.derive_pubkey(Terminal::new(keychain, index.into()));
+        iter::once(XOnlyPk::from(pubkey))
+    }
+}
+
+impl DeriveKey<LegacyPk> for KeyExpr {
+    fn xpub_spec(&self) -> &XpubAccount {
+        self.try_xpub_spec().expect("key expression is not an extended public key")
+    }
+    fn try_xpub_spec(&self) -> Option<&XpubAccount> { KeyExpr::try_xpub_spec(self) }
+    fn key_origin(&self, terminal: Terminal) -> KeyOrigin { KeyExpr::key_origin(self, terminal) }
+    fn is_origin_of(&self, origin: &KeyOrigin) -> bool { KeyExpr::is_origin_of(self, origin) }
+}
+
+impl DeriveKey<CompressedPk> for KeyExpr {
+    fn xpub_spec(&self) -> &XpubAccount {
+        self.try_xpub_spec().expect("key expression is not an extended public key")
+    }
+    fn try_xpub_spec(&self) -> Option<&XpubAccount> { KeyExpr::try_xpub_spec(self) }
+    fn key_origin(&self, terminal: Terminal) -> KeyOrigin { KeyExpr::key_origin(self, terminal) }
+    fn is_origin_of(&self, origin: &KeyOrigin) -> bool { KeyExpr::is_origin_of(self, origin) }
+    fn is_valid_in_context(&self) -> bool {
+        !matches!(self, KeyExpr::Uncompr { .. } | KeyExpr::XOnly { .. })
+    }
+}
+
+impl DeriveKey<XOnlyPk> for KeyExpr {
+    fn xpub_spec(&self) -> &XpubAccount {
+        self.try_xpub_spec().expect("key expression is not an extended public key")
+    }
+    fn try_xpub_spec(&self) -> Option<&XpubAccount> { KeyExpr::try_xpub_spec(self) }
+    fn key_origin(&self, terminal: Terminal) -> KeyOrigin { KeyExpr::key_origin(self, terminal) }
+    fn is_origin_of(&self, origin: &KeyOrigin) -> bool { KeyExpr::is_origin_of(self, origin) }
+}
+
+impl DeriveSet for KeyExpr {
+    type Legacy = KeyExpr;
+    type Compr = KeyExpr;
+    type XOnly = KeyExpr;
+}
+
+#[cfg(feature = "serde")]
+mod _serde {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::*;
+
+    impl Serialize for KeyExpr {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+            serializer.serialize_str(&self.to_string())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for KeyExpr {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+            let s = String::deserialize(deserializer)?;
+            KeyExpr::from_str(&s).map_err(|err| {
+                de::Error::custom(format!("invalid key expression string representation; {err}"))
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const XPUB: &str = "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2";
+    const PK: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const PK_UNCOMPR: &str = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+    #[test]
+    fn display_from_str() {
+        for s in [
+            PK.to_owned(),
+            PK_UNCOMPR.to_owned(),
+            PK[2..].to_owned(),
+            format!("[d34db33f/44h/0h/0h/0/5]{PK}"),
+            format!("{XPUB}/0/5"),
+            XPUB.to_owned(),
+            format!("{XPUB}/<0;1>/*"),
+            format!("{XPUB}/1/*"),
+        ] {
+            let key = KeyExpr::from_str(&s).unwrap();
+            assert_eq!(key.to_string(), s);
+        }
+
+        assert!(matches!(KeyExpr::from_str(PK).unwrap(), KeyExpr::Compr { origin: None, .. }));
+        assert!(matches!(KeyExpr::from_str(PK_UNCOMPR).unwrap(), KeyExpr::Uncompr { .. }));
+        assert!(matches!(KeyExpr::from_str(&PK[2..]).unwrap(), KeyExpr::XOnly { .. }));
+        assert!(matches!(
+            KeyExpr::from_str(&format!("{XPUB}/0/5")).unwrap(),
+            KeyExpr::XpubFixed { .. }
+        ));
+        assert!(matches!(KeyExpr::from_str(&format!("{XPUB}/<0;1>/*")).unwrap(), KeyExpr::Xpub(_)));
+
+        assert!(KeyExpr::from_str(&PK[..62]).is_err());
+        assert!(KeyExpr::from_str(&format!("0{PK}")).is_err());
+        assert!(KeyExpr::from_str(&format!("[d34db33f{PK}")).is_err());
+        assert!(KeyExpr::from_str(&XPUB[20..]).is_err());
+    }
+
+    #[test]
+    fn derive_fixed() {
+        let ranged = KeyExpr::from_str(&format!("{XPUB}/<0;1>/*")).unwrap();
+        let fixed = KeyExpr::from_str(&format!("{XPUB}/1/7")).unwrap();
+        let terminal = Terminal::new(Keychain::INNER, NormalIndex::from(7u8));
+        let expected = Derive::<CompressedPk>::derive(&ranged, Keychain::INNER, 7u8).next();
+        for keychain in [Keychain::OUTER, Keychain::INNER] {
+            assert_eq!(Derive::<CompressedPk>::derive(&fixed, keychain, 0u8).next(), expected);
+        }
+        assert_eq!(
+            fixed.key_origin(Terminal::new(Keychain::OUTER, NormalIndex::ZERO)),
+            ranged.key_origin(terminal)
+        );
+        assert!(fixed.is_origin_of(&ranged.key_origin(terminal)));
+        assert!(ranged.is_origin_of(&fixed.key_origin(terminal)));
+        assert_eq!(Derive::<LegacyPk>::keychains(&fixed), bset![Keychain::OUTER]);
+    }
+
+    #[test]
+    fn derive_raw() {
+        let compr = KeyExpr::from_str(PK).unwrap();
+        let uncompr = KeyExpr::from_str(PK_UNCOMPR).unwrap();
+        let xonly = KeyExpr::from_str(&PK[2..]).unwrap();
+        let pk = CompressedPk::from_str(PK).unwrap();
+        for key in [&compr, &uncompr, &xonly] {
+            assert_eq!(Derive::<CompressedPk>::derive(key, 0u8, 0u8).next(), Some(pk));
+            assert_eq!(Derive::<XOnlyPk>::derive(key, 1u8, 5u8).next(), Some(XOnlyPk::from(pk)));
+        }
+        let legacy = Derive::<LegacyPk>::derive(&uncompr, 0u8, 0u8).next().unwrap();
+        assert!(!legacy.compressed);
+        assert_eq!(legacy.pubkey, *pk);
+
+        let terminal = Terminal::new(Keychain::OUTER, NormalIndex::ZERO);
+        // HASH160 of the generator point, compressed
+        assert_eq!(compr.key_origin(terminal).to_string(), "751e76e8");
+        assert_ne!(uncompr.key_origin(terminal), compr.key_origin(terminal));
+        let with_origin = KeyExpr::from_str(&format!("[d34db33f/44h/0h/0h/0/5]{PK}")).unwrap();
+        assert_eq!(
+            with_origin.key_origin(terminal),
+            KeyOrigin::from_str("d34db33f/44h/0h/0h/0/5").unwrap()
+        );
+    }
+}
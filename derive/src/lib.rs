@@ -32,8 +32,11 @@ mod index;
 mod path;
 mod xkey;
 mod derive;
+mod keyexpr;
+mod wif;
 pub mod taptree;
 mod sign;
+pub mod musig;
 
 pub use bc::*;
 pub use derive::{
@@ -45,14 +48,17 @@ pub use index::{
     HARDENED_INDEX_BOUNDARY,
 };
 pub use invoice::*;
+pub use keyexpr::{KeyExpr, KeyExprParseError};
+pub use musig::{KeyAggContext, MusigError, PartialSig, PubNonce, SecNonce, Session};
 pub use path::{DerivationParseError, DerivationPath, DerivationSeg, SegParseError};
 pub use sign::Sign;
 pub use taptree::{
     ControlBlockFactory, FinalizedTree, InvalidTree, LeafInfo, TapDerivation, TapTree,
     TapTreeBuilder, UnfinalizedTree,
 };
+pub use wif::{WifKey, WifParseError};
 pub use xkey::{
     ChainCode, KeyOrigin, OriginParseError, XkeyAccountError, XkeyDecodeError, XkeyMeta,
-    XkeyOrigin, XkeyParseError, Xpriv, XprivAccount, XprivCore, Xpub, XpubAccount, XpubCore,
-    XpubDerivable, XpubFp, XpubId, XPRIV_MAINNET_MAGIC, XPRIV_TESTNET_MAGIC,
+    XkeyOrigin, XkeyParseError, Xpriv, XprivAccount, XprivCore, XprivDerivable, Xpub, XpubAccount,
+    XpubCore, XpubDerivable, XpubFp, XpubId, XPRIV_MAINNET_MAGIC, XPRIV_TESTNET_MAGIC,
 };
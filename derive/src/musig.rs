@@ -0,0 +1,772 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! MuSig2 multi-signature scheme for BIP-340 Schnorr signatures (BIP-327).
+
+use amplify::{ByteArray, Bytes, Bytes32, Wrapper};
+use bc::secp256k1::constants::CURVE_ORDER;
+use bc::secp256k1::{schnorr as bip340, PublicKey, Scalar, SecretKey, SECP256K1};
+use bc::{CompressedPk, InternalPk, TapNodeHash, XOnlyPk};
+use sha2::{Digest, Sha256};
+
+/// Errors happening during MuSig2 key aggregation and signing.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum MusigError {
+    /// MuSig2 requires at least one participant key.
+    NoKeys,
+
+    /// aggregated MuSig2 public key is the point at infinity.
+    InfiniteKey,
+
+    /// tweak value exceeds secp256k1 curve order.
+    InvalidTweak,
+
+    /// public nonce {0} doesn't consist of two valid compressed curve points.
+    InvalidNonce(PubNonce),
+
+    /// secret nonce is invalid or was generated for a different signing key.
+    InvalidSecNonce,
+
+    /// key {0} is not a participant of the MuSig2 session.
+    UnknownParticipant(CompressedPk),
+
+    /// partial signature value exceeds secp256k1 curve order.
+    InvalidPartialSig,
+}
+
+/// Scalar modulo secp256k1 curve order, which unlike [`SecretKey`] may be zero.
+#[derive(Copy, Clone)]
+struct Sc(Option<SecretKey>);
+
+impl Sc {
+    const ZERO: Sc = Sc(None);
+
+    fn one() -> Sc {
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        Sc::from_canonical(one).expect("one is a valid scalar")
+    }
+
+    /// Constructs scalar from big-endian bytes, failing if the value is not less than curve order.
+    fn from_canonical(bytes: [u8; 32]) -> Option<Sc> {
+        if bytes >= CURVE_ORDER {
+            return None;
+        }
+        Some(Sc(SecretKey::from_byte_array(&bytes).ok()))
+    }
+
+    /// Interprets big-endian bytes (usually a hash value) as an integer modulo curve order.
+    fn reduce(mut bytes: [u8; 32]) -> Sc {
+        if bytes >= CURVE_ORDER {
+            let mut borrow = 0u16;
+            for i in (0..32).rev() {
+                let sub = CURVE_ORDER[i] as u16 + borrow;
+                borrow = (sub > bytes[i] as u16) as u16;
+                bytes[i] = (bytes[i] as u16 + (borrow << 8) - sub) as u8;
+            }
+        }
+        Sc::from_canonical(bytes).expect("value is reduced")
+    }
+
+    fn to_byte_array(self) -> [u8; 32] { self.0.map(|sk| sk.secret_bytes()).unwrap_or_default() }
+
+    fn add(self, other: Sc) -> Sc {
+        match (self.0, other.0) {
+            (None, _) => other,
+            (_, None) => self,
+            (Some(a), Some(b)) => Sc(a.add_tweak(&Scalar::from(b)).ok()),
+        }
+    }
+
+    fn mul(self, other: Sc) -> Sc {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => Sc(a.mul_tweak(&Scalar::from(b)).ok()),
+            _ => Sc::ZERO,
+        }
+    }
+
+    fn neg(self) -> Sc { Sc(self.0.map(SecretKey::negate)) }
+
+    /// Multiplies a curve point by the scalar; `None` stands for the point at infinity.
+    fn mul_point(self, point: Option<PublicKey>) -> Option<PublicKey> {
+        let (sk, point) = (self.0?, point?);
+        point.mul_tweak(SECP256K1, &Scalar::from(sk)).ok()
+    }
+
+    fn mul_base(self) -> Option<PublicKey> {
+        self.0.map(|sk| PublicKey::from_secret_key(SECP256K1, &sk))
+    }
+}
+
+fn add_points(a: Option<PublicKey>, b: Option<PublicKey>) -> Option<PublicKey> {
+    match (a, b) {
+        (None, p) | (p, None) => p,
+        (Some(a), Some(b)) => a.combine(&b).ok(),
+    }
+}
+
+fn has_even_y(point: PublicKey) -> bool { point.serialize()[0] == 0x02 }
+
+fn xbytes(point: PublicKey) -> [u8; 32] { point.x_only_public_key().0.serialize() }
+
+fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag = Sha256::digest(tag.as_bytes());
+    let mut engine = Sha256::new();
+    engine.update(tag);
+    engine.update(tag);
+    for chunk in data {
+        engine.update(chunk);
+    }
+    engine.finalize().into()
+}
+
+/// Key aggregation context of a MuSig2 participant set with the tweaks applied to it.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct KeyAggContext {
+    keys: Vec<CompressedPk>,
+    list_hash: [u8; 32],
+    second_key: Option<CompressedPk>,
+    agg_pk: CompressedPk,
+    q: PublicKey,
+    gacc: [u8; 32],
+    tacc: [u8; 32],
+}
+
+impl KeyAggContext {
+    /// Aggregates participant keys, which order matters.
+    pub fn new(keys: impl IntoIterator<Item = CompressedPk>) -> Result<Self, MusigError> {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        let first = *keys.first().ok_or(MusigError::NoKeys)?;
+        let list = keys.iter().flat_map(CompressedPk::to_byte_array).collect::<Vec<_>>();
+        let list_hash = tagged_hash("KeyAgg list", &[&list]);
+        let second_key = keys.iter().find(|pk| **pk != first).copied();
+        let mut ctx = KeyAggContext {
+            keys,
+            list_hash,
+            second_key,
+            agg_pk: first,
+            q: *first,
+            gacc: Sc::one().to_byte_array(),
+            tacc: [0u8; 32],
+        };
+        let q = ctx
+            .keys
+            .iter()
+            .map(|pk| ctx.coefficient(*pk).mul_point(Some(**pk)))
+            .fold(None, add_points)
+            .ok_or(MusigError::InfiniteKey)?;
+        ctx.q = q;
+        ctx.agg_pk = CompressedPk::from(q);
+        Ok(ctx)
+    }
+
+    fn coefficient(&self, pk: CompressedPk) -> Sc {
+        if Some(pk) == self.second_key {
+            return Sc::one();
+        }
+        Sc::reduce(tagged_hash("KeyAgg coefficient", &[&self.list_hash, &pk.to_byte_array()]))
+    }
+
+    fn gacc(&self) -> Sc { Sc::from_canonical(self.gacc).expect("canonical scalar") }
+
+    fn tacc(&self) -> Sc { Sc::from_canonical(self.tacc).expect("canonical scalar") }
+
+    /// Participant keys in the order they were aggregated.
+    pub fn participants(&self) -> &[CompressedPk] { &self.keys }
+
+    /// Aggregated public key before any tweaks were applied.
+    pub fn agg_pk(&self) -> CompressedPk { self.agg_pk }
+
+    /// Aggregated public key with all applied tweaks.
+    pub fn output_pk(&self) -> CompressedPk { CompressedPk::from(self.q) }
+
+    /// Aggregated public key with all applied tweaks as an x-only key.
+    pub fn output_xonly(&self) -> XOnlyPk { XOnlyPk::from(self.q.x_only_public_key().0) }
+
+    /// Applies plain or x-only (BIP-341 style) tweak to the aggregated key.
+    pub fn apply_tweak(&mut self, tweak: [u8; 32], xonly: bool) -> Result<(), MusigError> {
+        let t = Sc::from_canonical(tweak).ok_or(MusigError::InvalidTweak)?;
+        let g = if xonly && !has_even_y(self.q) { Sc::one().neg() } else { Sc::one() };
+        self.q =
+            add_points(g.mul_point(Some(self.q)), t.mul_base()).ok_or(MusigError::InfiniteKey)?;
+        self.gacc = g.mul(self.gacc()).to_byte_array();
+        self.tacc = t.add(g.mul(self.tacc())).to_byte_array();
+        Ok(())
+    }
+
+    /// Applies BIP-341 taproot tweak with an optional script tree merkle root, making the
+    /// aggregated key usable as a taproot internal key.
+    pub fn apply_taproot_tweak(
+        &mut self,
+        merkle_root: Option<TapNodeHash>,
+    ) -> Result<(), MusigError> {
+        let internal_pk = xbytes(self.q);
+        let root = merkle_root.map(|root| root.to_byte_array());
+        let tweak = tagged_hash("TapTweak", &[&internal_pk, root.as_ref().map_or(&[], |r| &r[..])]);
+        self.apply_tweak(tweak, true)
+    }
+
+    /// Taproot internal key corresponding to the aggregated key (ignoring tweaks).
+    pub fn internal_pk(&self) -> InternalPk {
+        InternalPk::from_unchecked(XOnlyPk::from(self.agg_pk.x_only_public_key().0))
+    }
+}
+
+/// Secret nonce of a MuSig2 signer.
+///
+/// The nonce can't be copied or cloned and is consumed by signing, since its reuse leaks the
+/// secret key.
+#[derive(Eq, PartialEq)]
+pub struct SecNonce([u8; 97]);
+
+impl SecNonce {
+    /// Generates nonce pair for a signer with public key `pk`.
+    ///
+    /// The `rand` value must be freshly generated with a cryptographically secure random number
+    /// generator; other optional arguments provide additional protection against a weak source of
+    /// randomness.
+    pub fn generate(
+        rand: [u8; 32],
+        sk: Option<SecretKey>,
+        pk: CompressedPk,
+        agg_pk: Option<XOnlyPk>,
+        msg: Option<&[u8]>,
+        extra: &[u8],
+    ) -> (SecNonce, PubNonce) {
+        let mut rand = rand;
+        if let Some(sk) = sk {
+            let aux = tagged_hash("MuSig/aux", &[&rand]);
+            for (r, (s, a)) in rand.iter_mut().zip(sk.secret_bytes().iter().zip(aux)) {
+                *r = s ^ a;
+            }
+        }
+        let pk = pk.to_byte_array();
+        let agg_pk = agg_pk.map(|pk| pk.to_byte_array()).unwrap_or_default();
+        let agg_pk = if agg_pk == [0u8; 32] { &[][..] } else { &agg_pk[..] };
+        let mut msg_prefixed = vec![];
+        if let Some(msg) = msg {
+            msg_prefixed.push(1u8);
+            msg_prefixed.extend((msg.len() as u64).to_be_bytes());
+            msg_prefixed.extend(msg);
+        } else {
+            msg_prefixed.push(0u8);
+        }
+        let extra_len = (extra.len() as u32).to_be_bytes();
+
+        let mut sec = [0u8; 97];
+        let mut public = [0u8; 66];
+        for i in 0..2u8 {
+            let k = Sc::reduce(tagged_hash("MuSig/nonce", &[
+                &rand,
+                &[pk.len() as u8],
+                &pk,
+                &[agg_pk.len() as u8],
+                agg_pk,
+                &msg_prefixed,
+                &extra_len,
+                extra,
+                &[i],
+            ]));
+            let r = k.mul_base().expect("nonce hash equal to zero");
+            let i = i as usize;
+            sec[i * 32..(i + 1) * 32].copy_from_slice(&k.to_byte_array());
+            public[i * 33..(i + 1) * 33].copy_from_slice(&r.serialize());
+        }
+        sec[64..].copy_from_slice(&pk);
+        (SecNonce(sec), PubNonce::from(public))
+    }
+}
+
+/// Public nonce of a MuSig2 signer, consisting of two compressed curve points.
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
+#[wrapper(Deref, Hex, FromStr)]
+#[display(LowerHex)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct PubNonce(
+    #[from]
+    #[from([u8; 66])]
+    Bytes<66>,
+);
+
+impl PubNonce {
+    pub fn from_byte_array(bytes: [u8; 66]) -> Self { PubNonce(bytes.into()) }
+
+    pub fn to_byte_array(&self) -> [u8; 66] { self.0.to_byte_array() }
+
+    fn points(&self) -> Result<[Option<PublicKey>; 2], MusigError> {
+        let mut points = [None; 2];
+        for (i, point) in points.iter_mut().enumerate() {
+            let bytes = &self.0[i * 33..(i + 1) * 33];
+            // Aggregated nonces encode the point at infinity as 33 zero bytes
+            if bytes.iter().any(|b| *b != 0) {
+                *point = Some(
+                    PublicKey::from_slice(bytes).map_err(|_| MusigError::InvalidNonce(*self))?,
+                );
+            }
+        }
+        Ok(points)
+    }
+
+    /// Aggregates public nonces of all session participants.
+    pub fn aggregate<'n>(
+        nonces: impl IntoIterator<Item = &'n PubNonce>,
+    ) -> Result<PubNonce, MusigError> {
+        let mut sum = [None; 2];
+        for nonce in nonces {
+            let [r1, r2] = nonce.points()?;
+            if r1.is_none() || r2.is_none() {
+                return Err(MusigError::InvalidNonce(*nonce));
+            }
+            sum = [add_points(sum[0], r1), add_points(sum[1], r2)];
+        }
+        let mut bytes = [0u8; 66];
+        for (i, point) in sum.iter().enumerate() {
+            if let Some(point) = point {
+                bytes[i * 33..(i + 1) * 33].copy_from_slice(&point.serialize());
+            }
+        }
+        Ok(PubNonce::from(bytes))
+    }
+}
+
+/// Partial signature of a MuSig2 signer.
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
+#[wrapper(Deref, Hex, FromStr)]
+#[display(LowerHex)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct PartialSig(
+    #[from]
+    #[from([u8; 32])]
+    Bytes32,
+);
+
+impl PartialSig {
+    pub fn from_byte_array(bytes: [u8; 32]) -> Self { PartialSig(bytes.into()) }
+
+    pub fn to_byte_array(&self) -> [u8; 32] { self.0.to_byte_array() }
+}
+
+/// MuSig2 signing session for a specific message, aggregated key and aggregated nonce.
+#[derive(Clone, Debug)]
+pub struct Session {
+    key_agg: KeyAggContext,
+    b: [u8; 32],
+    r: PublicKey,
+    e: [u8; 32],
+}
+
+impl Session {
+    /// Starts signing session over a 32-byte `msg` (i.e. a BIP-341 sighash).
+    pub fn new(
+        key_agg: &KeyAggContext,
+        agg_nonce: &PubNonce,
+        msg: [u8; 32],
+    ) -> Result<Self, MusigError> {
+        let q = xbytes(key_agg.q);
+        let b = Sc::reduce(tagged_hash("MuSig/noncecoef", &[&agg_nonce.to_byte_array(), &q, &msg]));
+        let [r1, r2] = agg_nonce.points()?;
+        let r = add_points(r1, b.mul_point(r2)).unwrap_or_else(|| Sc::one().mul_base().unwrap());
+        let e = Sc::reduce(tagged_hash("BIP0340/challenge", &[&xbytes(r), &q, &msg]));
+        Ok(Session {
+            key_agg: key_agg.clone(),
+            b: b.to_byte_array(),
+            r,
+            e: e.to_byte_array(),
+        })
+    }
+
+    fn b(&self) -> Sc { Sc::from_canonical(self.b).expect("canonical scalar") }
+
+    fn e(&self) -> Sc { Sc::from_canonical(self.e).expect("canonical scalar") }
+
+    /// Multiplier applied to each participant secret key to account for the parity of the
+    /// final key and the accumulated tweaks.
+    fn key_multiplier(&self) -> Sc {
+        let g = if has_even_y(self.key_agg.q) { Sc::one() } else { Sc::one().neg() };
+        g.mul(self.key_agg.gacc())
+    }
+
+    /// Creates partial signature, consuming the signer secret nonce.
+    pub fn partial_sign(
+        &self,
+        sec_nonce: SecNonce,
+        sk: SecretKey,
+    ) -> Result<PartialSig, MusigError> {
+        let nonce = sec_nonce.0;
+        let mut k1 = [0u8; 32];
+        let mut k2 = [0u8; 32];
+        k1.copy_from_slice(&nonce[..32]);
+        k2.copy_from_slice(&nonce[32..64]);
+        let (Some(mut k1), Some(mut k2)) = (Sc::from_canonical(k1), Sc::from_canonical(k2)) else {
+            return Err(MusigError::InvalidSecNonce);
+        };
+        if k1.0.is_none() || k2.0.is_none() {
+            return Err(MusigError::InvalidSecNonce);
+        }
+        if !has_even_y(self.r) {
+            k1 = k1.neg();
+            k2 = k2.neg();
+        }
+        let pk = CompressedPk::from(PublicKey::from_secret_key(SECP256K1, &sk));
+        if pk.to_byte_array()[..] != nonce[64..] {
+            return Err(MusigError::InvalidSecNonce);
+        }
+        if !self.key_agg.keys.contains(&pk) {
+            return Err(MusigError::UnknownParticipant(pk));
+        }
+        let a = self.key_agg.coefficient(pk);
+        let d = self.key_multiplier().mul(Sc(Some(sk)));
+        let s = k1.add(self.b().mul(k2)).add(self.e().mul(a).mul(d));
+        Ok(PartialSig::from_byte_array(s.to_byte_array()))
+    }
+
+    /// Verifies partial signature of a participant with key `pk` and public nonce `pub_nonce`.
+    pub fn partial_verify(&self, sig: PartialSig, pub_nonce: &PubNonce, pk: CompressedPk) -> bool {
+        let Some(s) = Sc::from_canonical(sig.to_byte_array()) else {
+            return false;
+        };
+        let Ok([Some(r1), r2]) = pub_nonce.points() else {
+            return false;
+        };
+        if !self.key_agg.keys.contains(&pk) {
+            return false;
+        }
+        let mut r = add_points(Some(r1), self.b().mul_point(r2));
+        if !has_even_y(self.r) {
+            r = r.map(|r| r.negate(SECP256K1));
+        }
+        let a = self.key_agg.coefficient(pk);
+        let p = self.key_multiplier().mul(self.e()).mul(a).mul_point(Some(*pk));
+        s.mul_base() == add_points(r, p)
+    }
+
+    /// Aggregates partial signatures of all participants into a BIP-340 signature valid for the
+    /// tweaked aggregated key.
+    pub fn aggregate<'s>(
+        &self,
+        sigs: impl IntoIterator<Item = &'s PartialSig>,
+    ) -> Result<bip340::Signature, MusigError> {
+        let mut s = Sc::ZERO;
+        for sig in sigs {
+            let sig =
+                Sc::from_canonical(sig.to_byte_array()).ok_or(MusigError::InvalidPartialSig)?;
+            s = s.add(sig);
+        }
+        let g = if has_even_y(self.key_agg.q) { Sc::one() } else { Sc::one().neg() };
+        s = s.add(self.e().mul(g).mul(self.key_agg.tacc()));
+        let mut sig = [0u8; 64];
+        sig[..32].copy_from_slice(&xbytes(self.r));
+        sig[32..].copy_from_slice(&s.to_byte_array());
+        Ok(bip340::Signature::from_byte_array(sig))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::hex::FromHex;
+    use secp256k1::rand::{self, RngCore};
+
+    use super::*;
+
+    fn pk(s: &str) -> CompressedPk { s.parse().unwrap() }
+
+    fn bytes<const LEN: usize>(s: &str) -> [u8; LEN] {
+        Vec::<u8>::from_hex(s).unwrap().try_into().unwrap()
+    }
+
+    fn nonce(s: &str) -> PubNonce { PubNonce::from_byte_array(bytes(s)) }
+
+    // Common data of BIP-327 `sign_verify_vectors.json`
+    const SK: &str = "7FB9E0E687ADA1EEBF7ECFE2F21E73EBDB51A7D450948DFE8D76D7F2D1007671";
+    const SECNONCE: &str = "508B81A611F100A6B2B6B29656590898AF488BCF2E1F55CF22E5CFB84421FE61FA27FD49B1D50085B481285E1CA205D55C82CC1B31FF5CD54A489829355901F703935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9";
+    const PUBKEYS: [&str; 3] = [
+        "03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9",
+        "02F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+        "02DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA661",
+    ];
+    const PNONCES: [&str; 4] = [
+        "0337C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA0287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+        "0279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F817980279BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+        "032DE2662628C90B03F5E720284EB52FF7D71F4284F627B68A853D78C78E1FFE9303E4C5524E83FFE1493B9077CF1CA6BEB2090C93D930321071AD40B2F44E599046",
+        "0237C87821AFD50A8644D820A8F3E02E499C931865C2360FB43D0A0D20DAFE07EA0387BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+    ];
+    const AGGNONCE: &str = "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9";
+    const MSG: &str = "F95466D086770E689964664219266FE5ED215C92AE20BAB5C9D79ADDDDF3C0CF";
+    const CURVE_ORDER_HEX: &str =
+        "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141";
+
+    #[test]
+    fn key_agg_vectors() {
+        let x1 = pk("02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9");
+        let x2 = pk("03dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659");
+        let x3 = pk("023590a94e768f8e1815c2f24b4d80a8e3149316c3518ce7b7ad338368d038ca66");
+
+        let agg = |keys: &[CompressedPk]| {
+            KeyAggContext::new(keys.iter().copied()).unwrap().output_xonly().to_string()
+        };
+        assert_eq!(
+            agg(&[x1, x2, x3]),
+            "90539eede565f5d054f32cc0c220126889ed1e5d193baf15aef344fe59d4610c"
+        );
+        assert_eq!(
+            agg(&[x3, x2, x1]),
+            "6204de8b083426dc6eaf9502d27024d53fc826bf7d2012148a0575435df54b2b"
+        );
+        assert_eq!(
+            agg(&[x1, x1, x1]),
+            "b436e3bad62b8cd409969a224731c193d051162d8c5ae8b109306127da3aa935"
+        );
+        assert_eq!(
+            agg(&[x1, x1, x2, x2]),
+            "69bc22bfa5d106306e48a20679de1d7389386124d07571d0d872686028c26a3e"
+        );
+
+        assert_eq!(KeyAggContext::new([]), Err(MusigError::NoKeys));
+        let mut key_agg = KeyAggContext::new([x1, x2]).unwrap();
+        assert_eq!(
+            key_agg.apply_tweak(bytes(CURVE_ORDER_HEX), true),
+            Err(MusigError::InvalidTweak)
+        );
+    }
+
+    #[test]
+    fn nonce_gen_vectors() {
+        let pk = pk("024D4B6CD1361032CA9BD2AEB9D900AA4D45D9EAD80AC9423374C451A7254D0766");
+        let sk = SecretKey::from_byte_array(&[0x02; 32]).unwrap();
+        let agg_pk = XOnlyPk::from_byte_array([0x07; 32]).unwrap();
+        for (msg, sec_nonce, pub_nonce) in [
+            (
+                &[0x01; 32][..],
+                "B114E502BEAA4E301DD08A50264172C84E41650E6CB726B410C0694D59EFFB6495B5CAF28D045B973D63E3C99A44B807BDE375FD6CB39E46DC4A511708D0E9D2024D4B6CD1361032CA9BD2AEB9D900AA4D45D9EAD80AC9423374C451A7254D0766",
+                "02F7BE7089E8376EB355272368766B17E88E7DB72047D05E56AA881EA52B3B35DF02C29C8046FDD0DED4C7E55869137200FBDBFE2EB654267B6D7013602CAED3115A",
+            ),
+            (
+                &[][..],
+                "E862B068500320088138468D47E0E6F147E01B6024244AE45EAC40ACE5929B9F0789E051170B9E705D0B9EB49049A323BBBBB206D8E05C19F46C6228742AA7A9024D4B6CD1361032CA9BD2AEB9D900AA4D45D9EAD80AC9423374C451A7254D0766",
+                "023034FA5E2679F01EE66E12225882A7A48CC66719B1B9D3B6C4DBD743EFEDA2C503F3FD6F01EB3A8E9CB315D73F1F3D287CAFBB44AB321153C6287F407600205109",
+            ),
+            (
+                &[0x26; 38][..],
+                "3221975ACBDEA6820EABF02A02B7F27D3A8EF68EE42787B88CBEFD9AA06AF3632EE85B1A61D8EF31126D4663A00DD96E9D1D4959E72D70FE5EBB6E7696EBA66F024D4B6CD1361032CA9BD2AEB9D900AA4D45D9EAD80AC9423374C451A7254D0766",
+                "02E5BBC21C69270F59BD634FCBFA281BE9D76601295345112C58954625BF23793A021307511C79F95D38ACACFF1B4DA98228B77E65AA216AD075E9673286EFB4EAF3",
+            ),
+        ] {
+            let (sec, public) =
+                SecNonce::generate([0x0F; 32], Some(sk), pk, Some(agg_pk), Some(msg), &[0x08; 32]);
+            assert_eq!(sec.0, bytes(sec_nonce));
+            assert_eq!(public, nonce(pub_nonce));
+        }
+    }
+
+    #[test]
+    fn sign_verify_vectors() {
+        let sk = SecretKey::from_byte_array(&bytes(SK)).unwrap();
+        let pks = PUBKEYS.map(pk);
+        let pnonces = PNONCES.map(nonce);
+        let msg = bytes(MSG);
+        let agg_nonce = PubNonce::aggregate(&pnonces[..3]).unwrap();
+        assert_eq!(agg_nonce, nonce(AGGNONCE));
+
+        let start_session = |keys: &[usize], nonces: &[usize]| {
+            let key_agg = KeyAggContext::new(keys.iter().map(|i| pks[*i])).unwrap();
+            let agg_nonce = PubNonce::aggregate(nonces.iter().map(|i| &pnonces[*i])).unwrap();
+            Session::new(&key_agg, &agg_nonce, msg).unwrap()
+        };
+        for (keys, nonces, expected) in [
+            (
+                &[0, 1, 2][..],
+                &[0, 1, 2][..],
+                "012ABBCB52B3016AC03AD82395A1A415C48B93DEF78718E62A7A90052FE224FB",
+            ),
+            (
+                &[1, 0, 2],
+                &[1, 0, 2],
+                "9FF2F7AAA856150CC8819254218D3ADEEB0535269051897724F9DB3789513A52",
+            ),
+            (
+                &[1, 2, 0],
+                &[1, 2, 0],
+                "FA23C359F6FAC4E7796BB93BC9F0532A95468C539BA20FF86D7C76ED92227900",
+            ),
+            // Aggregated nonce is the point at infinity
+            (&[0, 1], &[0, 3], "AE386064B26105404798F75DE2EB9AF5EDA5387B064B83D049CB7C5E08879531"),
+        ] {
+            let session = start_session(keys, nonces);
+            let sig = session.partial_sign(SecNonce(bytes(SECNONCE)), sk).unwrap();
+            assert_eq!(sig, PartialSig::from_byte_array(bytes(expected)));
+            assert!(session.partial_verify(sig, &pnonces[0], pks[0]));
+        }
+
+        // Verification failures: negated signature, wrong signer and signature exceeding the
+        // curve order
+        let session = start_session(&[0, 1, 2], &[0, 1, 2]);
+        let sig = session.partial_sign(SecNonce(bytes(SECNONCE)), sk).unwrap();
+        let negated = Sc::from_canonical(sig.to_byte_array()).unwrap().neg().to_byte_array();
+        assert!(!session.partial_verify(PartialSig::from_byte_array(negated), &pnonces[0], pks[0]));
+        assert!(!session.partial_verify(sig, &pnonces[1], pks[1]));
+        let overflow = PartialSig::from_byte_array(bytes(CURVE_ORDER_HEX));
+        assert!(!session.partial_verify(overflow, &pnonces[0], pks[0]));
+
+        // Signing errors: signer is not a participant, secret nonce is zero or belongs to a
+        // different key
+        let err = start_session(&[1, 2], &[1, 2]).partial_sign(SecNonce(bytes(SECNONCE)), sk);
+        assert_eq!(err, Err(MusigError::UnknownParticipant(pks[0])));
+        let mut zero_nonce = bytes::<97>(SECNONCE);
+        zero_nonce[..64].fill(0);
+        assert_eq!(
+            session.partial_sign(SecNonce(zero_nonce), sk),
+            Err(MusigError::InvalidSecNonce)
+        );
+        let other_sk = SecretKey::from_byte_array(&[0x01; 32]).unwrap();
+        assert_eq!(
+            session.partial_sign(SecNonce(bytes(SECNONCE)), other_sk),
+            Err(MusigError::InvalidSecNonce)
+        );
+
+        // Invalid aggregated and individual public nonces
+        let key_agg = KeyAggContext::new(pks).unwrap();
+        for agg_nonce in [
+            "048465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61037496A3CC86926D452CAFCFD55D25972CA1675D549310DE296BFF42F72EEEA8C9",
+            "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD61020000000000000000000000000000000000000000000000000000000000000009",
+            "028465FCF0BBDBCF443AABCCE533D42B4B5A10966AC09A49655E8C42DAAB8FCD6102FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30",
+        ] {
+            let agg_nonce = nonce(agg_nonce);
+            assert_eq!(
+                Session::new(&key_agg, &agg_nonce, msg).unwrap_err(),
+                MusigError::InvalidNonce(agg_nonce)
+            );
+        }
+        let invalid = nonce(
+            "0200000000000000000000000000000000000000000000000000000000000000090287BF891D2A6DEAEBADC909352AA9405D1428C15F4B75F04DAE642A95C2548480",
+        );
+        assert_eq!(
+            PubNonce::aggregate([&pnonces[0], &invalid]),
+            Err(MusigError::InvalidNonce(invalid))
+        );
+        assert!(!session.partial_verify(sig, &invalid, pks[0]));
+    }
+
+    #[test]
+    fn sig_agg_vectors() {
+        // Data of BIP-327 `sig_agg_vectors.json`
+        let pks = [
+            "03935F972DA013F80AE011890FA89B67A27B7BE6CCB24D3274D18B2D4067F261A9",
+            "02D2DC6F5DF7C56ACF38C7FA0AE7A759AE30E19B37359DFDE015872324C7EF6E05",
+            "03C7FB101D97FF930ACD0C6760852EF64E69083DE0B06AC6335724754BB4B0522C",
+            "02352433B21E7E05D3B452B81CAE566E06D2E003ECE16D1074AABA4289E0E3D581",
+        ]
+        .map(pk);
+        let pnonces = [
+            "036E5EE6E28824029FEA3E8A9DDD2C8483F5AF98F7177C3AF3CB6F47CAF8D94AE902DBA67E4A1F3680826172DA15AFB1A8CA85C7C5CC88900905C8DC8C328511B53E",
+            "03E4F798DA48A76EEC1C9CC5AB7A880FFBA201A5F064E627EC9CB0031D1D58FC5103E06180315C5A522B7EC7C08B69DCD721C313C940819296D0A7AB8E8795AC1F00",
+            "02C0068FD25523A31578B8077F24F78F5BD5F2422AFF47C1FADA0F36B3CEB6C7D202098A55D1736AA5FCC21CF0729CCE852575C06C081125144763C2C4C4A05C09B6",
+            "031F5C87DCFBFCF330DEE4311D85E8F1DEA01D87A6F1C14CDFC7E4F1D8C441CFA40277BF176E9F747C34F81B0D9F072B1B404A86F402C2D86CF9EA9E9C69876EA3B9",
+            "023F7042046E0397822C4144A17F8B63D78748696A46C3B9F0A901D296EC3406C302022B0B464292CF9751D699F10980AC764E6F671EFCA15069BBE62B0D1C62522A",
+        ]
+        .map(nonce);
+        let tweaks = [
+            "B511DA492182A91B0FFB9A98020D55F260AE86D7ECBD0399C7383D59A5F2AF7C",
+            "A815FE049EE3C5AAB66310477FBC8BCCCAC2F3395F59F921C364ACD78A2F48DC",
+            "75448A87274B056468B977BE06EB1E9F657577B7320B0A3376EA51FD420D18A8",
+        ]
+        .map(bytes);
+        let psigs = [
+            "B15D2CD3C3D22B04DAE438CE653F6B4ECF042F42CFDED7C41B64AAF9B4AF53FB",
+            "6193D6AC61B354E9105BBDC8937A3454A6D705B6D57322A5A472A02CE99FCB64",
+            "9A87D3B79EC67228CB97878B76049B15DBD05B8158D17B5B9114D3C226887505",
+            "66F82EA90923689B855D36C6B7E032FB9970301481B99E01CDB4D6AC7C347A15",
+            "4F5AEE41510848A6447DCD1BBC78457EF69024944C87F40250D3EF2C25D33EFE",
+            "DDEF427BBB847CC027BEFF4EDB01038148917832253EBC355FC33F4A8E2FCCE4",
+            "97B890A26C981DA8102D3BC294159D171D72810FDF7C6A691DEF02F0F7AF3FDC",
+            "53FA9E08BA5243CBCB0D797C5EE83BC6728E539EB76C2D0BF0F971EE4E909971",
+            CURVE_ORDER_HEX,
+        ]
+        .map(|s| PartialSig::from_byte_array(bytes(s)));
+        let msg = bytes("599C67EA410D005B9DA90817CF03ED3B1C868E4DA4EDF00A5880B0082C237869");
+
+        let session = |keys: &[usize], nonces: &[usize], tweaks_xonly: &[(usize, bool)]| {
+            let mut key_agg = KeyAggContext::new(keys.iter().map(|i| pks[*i])).unwrap();
+            for (tweak, xonly) in tweaks_xonly {
+                key_agg.apply_tweak(tweaks[*tweak], *xonly).unwrap();
+            }
+            let agg_nonce = PubNonce::aggregate(nonces.iter().map(|i| &pnonces[*i])).unwrap();
+            (Session::new(&key_agg, &agg_nonce, msg).unwrap(), key_agg.output_xonly())
+        };
+        for (keys, nonces, tweaks, sigs, expected) in [
+            (&[0, 1][..], &[0, 1][..], &[][..], [0, 1], "041DA22223CE65C92C9A0D6C2CAC828AAF1EEE56304FEC371DDF91EBB2B9EF0912F1038025857FEDEB3FF696F8B99FA4BB2C5812F6095A2E0004EC99CE18DE1E"),
+            (&[0, 2], &[0, 2], &[], [2, 3], "1069B67EC3D2F3C7C08291ACCB17A9C9B8F2819A52EB5DF8726E17E7D6B52E9F01800260A7E9DAC450F4BE522DE4CE12BA91AEAF2B4279219EF74BE1D286ADD9"),
+            (&[0, 2], &[0, 3], &[(0, false)], [4, 5], "5C558E1DCADE86DA0B2F02626A512E30A22CF5255CAEA7EE32C38E9A71A0E9148BA6C0E6EC7683B64220F0298696F1B878CD47B107B81F7188812D593971E0CC"),
+            (&[0, 3], &[0, 4], &[(0, true), (1, false), (2, true)], [6, 7], "839B08820B681DBA8DAF4CC7B104E8F2638F9388F8D7A555DC17B6E6971D7426CE07BF6AB01F1DB50E4E33719295F4094572B79868E440FB3DEFD3FAC1DB589E"),
+        ] {
+            let (session, output_pk) = session(keys, nonces, tweaks);
+            let sig = session.aggregate(sigs.iter().map(|i| &psigs[*i])).unwrap();
+            assert_eq!(sig, bip340::Signature::from_byte_array(bytes(expected)));
+            SECP256K1.verify_schnorr(&sig, &msg, &output_pk).unwrap();
+        }
+
+        let (session, _) = session(&[0, 1], &[0, 1], &[]);
+        assert_eq!(session.aggregate([&psigs[7], &psigs[8]]), Err(MusigError::InvalidPartialSig));
+    }
+
+    #[test]
+    fn sign_aggregate() {
+        let mut rng = rand::thread_rng();
+        let sks = (0..3).map(|_| SecretKey::new(&mut rng)).collect::<Vec<_>>();
+        let pks = sks
+            .iter()
+            .map(|sk| CompressedPk::from(PublicKey::from_secret_key(SECP256K1, sk)))
+            .collect::<Vec<_>>();
+        let mut key_agg = KeyAggContext::new(pks.iter().copied()).unwrap();
+        key_agg.apply_taproot_tweak(None).unwrap();
+        let (output_pk, _) = key_agg.internal_pk().to_output_pk(None);
+        assert_eq!(output_pk.to_xonly_pk(), key_agg.output_xonly());
+
+        let mut msg = [0u8; 32];
+        rng.fill_bytes(&mut msg);
+        let mut sec_nonces = vec![];
+        let mut pub_nonces = vec![];
+        for (sk, pk) in sks.iter().zip(&pks) {
+            let mut rand = [0u8; 32];
+            rng.fill_bytes(&mut rand);
+            let (sec, public) = SecNonce::generate(rand, Some(*sk), *pk, None, Some(&msg), &[]);
+            sec_nonces.push(sec);
+            pub_nonces.push(public);
+        }
+        let agg_nonce = PubNonce::aggregate(&pub_nonces).unwrap();
+        let session = Session::new(&key_agg, &agg_nonce, msg).unwrap();
+        let psigs = sec_nonces
+            .into_iter()
+            .zip(&sks)
+            .map(|(nonce, sk)| session.partial_sign(nonce, *sk).unwrap())
+            .collect::<Vec<_>>();
+        for ((sig, nonce), pk) in psigs.iter().zip(&pub_nonces).zip(&pks) {
+            assert!(session.partial_verify(*sig, nonce, *pk));
+        }
+        assert!(!session.partial_verify(psigs[0], &pub_nonces[1], pks[1]));
+
+        let sig = session.aggregate(&psigs).unwrap();
+        SECP256K1.verify_schnorr(&sig, &msg, &key_agg.output_xonly()).unwrap();
+    }
+}
@@ -24,37 +24,44 @@
 
 use bc::secp256k1::{ecdsa, schnorr as bip340};
 use bc::{
-    InternalPk, LegacyPk, Sighash, TapLeafHash, TapMerklePath, TapNodeHash, TapSighash, XOnlyPk,
+    InternalPk, LegacyPk, Sighash, SighashType, TapLeafHash, TapMerklePath, TapNodeHash,
+    TapSighash, XOnlyPk,
 };
 
 use crate::KeyOrigin;
 
 /// Trait used for signing transactions.
+///
+/// Signing methods receive the sighash type the `message` commits to, and may refuse signing
+/// with it by returning `None`.
 pub trait Sign {
     /// Create signature with a given key for inputs requiring ECDSA signatures (bare, pre-segwit
     /// and segwit v0).
     fn sign_ecdsa(
         &self,
         message: Sighash,
+        sighash_type: SighashType,
         pk: LegacyPk,
         origin: Option<&KeyOrigin>,
     ) -> Option<ecdsa::Signature>;
 
     /// Create signature with a given internal key using Schnorr signatures with BIP-340 signing
-    /// scheme (taproot).
+    /// scheme (taproot). Sighash type `None` stands for `SIGHASH_DEFAULT`.
     fn sign_bip340_key_only(
         &self,
         message: TapSighash,
+        sighash_type: Option<SighashType>,
         pk: InternalPk,
         origin: Option<&KeyOrigin>,
         merkle_root: Option<TapNodeHash>,
     ) -> Option<bip340::Signature>;
 
     /// Create signature with a given script path and x-only public key using Schnorr signatures
-    /// with BIP-340 signing scheme (taproot).
+    /// with BIP-340 signing scheme (taproot). Sighash type `None` stands for `SIGHASH_DEFAULT`.
     fn sign_bip340_script_path(
         &self,
         message: TapSighash,
+        sighash_type: Option<SighashType>,
         pk: XOnlyPk,
         origin: Option<&KeyOrigin>,
     ) -> Option<bip340::Signature>;
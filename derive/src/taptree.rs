@@ -27,9 +27,10 @@ use std::ops::Deref;
 use std::{slice, vec};
 
 use amplify::num::u7;
+use amplify::Wrapper;
 use bc::{
-    ControlBlock, InternalPk, LeafScript, OutputPk, Parity, TapLeafHash, TapMerklePath,
-    TapNodeHash, TapScript,
+    ControlBlock, InternalPk, LeafScript, OutputPk, Parity, TapBranchHash, TapLeafHash,
+    TapMerklePath, TapNodeHash, TapScript,
 };
 use commit_verify::merkle::MerkleBuoy;
 
@@ -141,12 +142,44 @@ impl TapTree {
         }])
     }
 
-    pub fn merkle_root(&self) -> TapNodeHash {
-        if self.0.len() == 1 {
-            TapLeafHash::with_leaf_script(&self.0[0].script).into()
-        } else {
-            todo!("#10 implement TapTree::merkle_root for trees with more than one leaf")
+    pub fn merkle_root(&self) -> TapNodeHash { self.merkle_tree().0 }
+
+    /// Merkle paths for each of the tree leaves, in the same order as the leaves.
+    pub fn merkle_paths(&self) -> Vec<TapMerklePath> { self.merkle_tree().1 }
+
+    /// Computes BIP-341 merkle root together with merkle paths for all of the leaves.
+    ///
+    /// Leaves are stored in the depth-first order, thus sibling nodes always end up on top of the
+    /// stack at the same depth, where they are combined into a branch node one level up.
+    fn merkle_tree(&self) -> (TapNodeHash, Vec<TapMerklePath>) {
+        let mut paths = vec![Vec::<TapBranchHash>::new(); self.0.len()];
+        let mut stack = Vec::<(u7, TapNodeHash, Vec<usize>)>::with_capacity(self.0.len());
+        for (no, leaf) in self.0.iter().enumerate() {
+            let hash = TapLeafHash::with_leaf_script(&leaf.script).into();
+            stack.push((leaf.depth, hash, vec![no]));
+            while stack.len() > 1 && stack[stack.len() - 1].0 == stack[stack.len() - 2].0 {
+                let (depth, node2, leaves2) = stack.pop().expect("stack has two elements");
+                let (_, node1, mut leaves1) = stack.pop().expect("stack has two elements");
+                for no in &leaves1 {
+                    paths[*no].push(TapBranchHash::from_inner(node2.into_inner()));
+                }
+                for no in &leaves2 {
+                    paths[*no].push(TapBranchHash::from_inner(node1.into_inner()));
+                }
+                leaves1.extend(leaves2);
+                let branch = TapBranchHash::with_nodes(node1, node2);
+                stack.push((depth - u7::ONE, branch.into(), leaves1));
+            }
         }
+        let Some((depth, root, _)) = stack.pop() else {
+            unreachable!("tap tree is non-empty")
+        };
+        debug_assert!(stack.is_empty() && depth == u7::ZERO, "tap tree is not finalized");
+        let paths = paths
+            .into_iter()
+            .map(|path| TapMerklePath::try_from(path).expect("tap tree depth is limited to 128"))
+            .collect();
+        (root, paths)
     }
 }
 
@@ -182,19 +215,19 @@ impl<L: Display> Display for TapTree<L> {
         let mut buoy = MerkleBuoy::<u7>::default();
 
         let mut depth = u7::ZERO;
-        for leaf in &self.0 {
+        for (no, leaf) in self.0.iter().enumerate() {
+            if no > 0 {
+                f.write_char(',')?;
+            }
             for _ in depth.into_u8()..leaf.depth.into_u8() {
                 f.write_char('{')?;
             }
+            Display::fmt(&leaf.script, f)?;
             buoy.push(leaf.depth);
-            if depth == leaf.depth {
-                f.write_char(',')?;
-            }
-            depth = leaf.depth;
-            for _ in buoy.level().into_u8()..depth.into_u8() {
+            depth = buoy.level();
+            for _ in depth.into_u8()..leaf.depth.into_u8() {
                 f.write_char('}')?;
             }
-            debug_assert_ne!(buoy.level(), u7::ZERO);
         }
         debug_assert_eq!(buoy.level(), u7::ZERO);
         Ok(())
@@ -226,7 +259,7 @@ pub struct ControlBlockFactory {
     merkle_root: TapNodeHash,
 
     #[getter(skip)]
-    merkle_path: TapMerklePath,
+    merkle_paths: Vec<TapMerklePath>,
     #[getter(skip)]
     remaining_leaves: Vec<LeafInfo>,
 }
@@ -234,14 +267,14 @@ pub struct ControlBlockFactory {
 impl ControlBlockFactory {
     #[inline]
     pub fn with(internal_pk: InternalPk, tap_tree: TapTree) -> Self {
-        let merkle_root = tap_tree.merkle_root();
+        let (merkle_root, merkle_paths) = tap_tree.merkle_tree();
         let (output_pk, parity) = internal_pk.to_output_pk(Some(merkle_root));
         ControlBlockFactory {
             internal_pk,
             output_pk,
             parity,
             merkle_root,
-            merkle_path: empty!(),
+            merkle_paths,
             remaining_leaves: tap_tree.into_vec(),
         }
     }
@@ -255,13 +288,10 @@ impl Iterator for ControlBlockFactory {
 
     fn next(&mut self) -> Option<Self::Item> {
         let leaf = self.remaining_leaves.pop()?;
+        let merkle_path = self.merkle_paths.pop()?;
         let leaf_script = leaf.script;
-        let control_block = ControlBlock::with(
-            leaf_script.version,
-            self.internal_pk,
-            self.parity,
-            self.merkle_path.clone(),
-        );
+        let control_block =
+            ControlBlock::with(leaf_script.version, self.internal_pk, self.parity, merkle_path);
         Some((control_block, leaf_script))
     }
 }
@@ -282,10 +312,115 @@ pub struct TapDerivation {
 
 impl TapDerivation {
     pub fn with_internal_pk(xpub_origin: XkeyOrigin, terminal: Terminal) -> Self {
-        let origin = KeyOrigin::with(xpub_origin, terminal);
+        Self::with_origin(KeyOrigin::with(xpub_origin, terminal))
+    }
+
+    pub fn with_origin(origin: KeyOrigin) -> Self {
         TapDerivation {
             leaf_hashes: empty!(),
             origin,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use amplify::hex::FromHex;
+    use bc::{ConsensusDecode, LeafVer};
+
+    use super::*;
+
+    fn leaf(depth: u8, script: &str) -> LeafInfo {
+        LeafInfo {
+            depth: u7::with(depth),
+            script: LeafScript::with_bytes(LeafVer::TapScript, Vec::from_hex(script).unwrap())
+                .unwrap(),
+        }
+    }
+
+    // Test vectors from BIP-341 `wallet-test-vectors.json`, `scriptPubKey` section
+    fn bip341_vectors(
+    ) -> [(&'static str, TapTree, &'static str, &'static str, Vec<&'static str>); 3] {
+        [
+            (
+                "187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27",
+                TapTree::from_leaves([leaf(
+                    0,
+                    "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac",
+                )])
+                .unwrap(),
+                "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21",
+                "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+                vec!["c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"],
+            ),
+            (
+                "f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd8",
+                TapTree::from_leaves([
+                    leaf(1, "2044b178d64c32c4a05cc4f4d1407268f764c940d20ce97abfd44db5c3592b72fdac"),
+                    leaf(1, "07546170726f6f74"),
+                ])
+                .unwrap(),
+                "ab179431c28d3b68fb798957faf5497d69c883c6fb1e1cd9f81483d87bac90cc",
+                "77e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220",
+                vec![
+                    "c1f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd8\
+                     2cb2b90daa543b544161530c925f285b06196940d6085ca9474d41dc3822c5cb",
+                    "c1f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd8\
+                     64512fecdb5afa04f98839b50e6f0cb7b1e539bf6f205f67934083cdcc3c8d89",
+                ],
+            ),
+            (
+                "55adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d",
+                TapTree::from_leaves([
+                    leaf(1, "2071981521ad9fc9036687364118fb6ccd2035b96a423c59c5430e98310a11abe2ac"),
+                    leaf(2, "20d5094d2dbe9b76e2c245a2b89b6006888952e2faa6a149ae318d69e520617748ac"),
+                    leaf(2, "20c440b462ad48c7a77f94cd4532d8f2119dcebbd7c9764557e62726419b08ad4cac"),
+                ])
+                .unwrap(),
+                "2f6b2c5397b6d68ca18e09a3f05161668ffe93a988582d55c6f07bd5b3329def",
+                "75169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831",
+                vec![
+                    "c155adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d\
+                     3cd369a528b326bc9d2133cbd2ac21451acb31681a410434672c8e34fe757e91",
+                    "c155adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d\
+                     d7485025fceb78b9ed667db36ed8b8dc7b1f0b307ac167fa516fe4352b9f4ef7\
+                     f154e8e8e17c31d3462d7132589ed29353c6fafdb884c5a6e04ea938834f0d9d",
+                    "c155adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d\
+                     737ed1fe30bc42b8022d717b44f0d93516617af64a64753b7a06bf16b26cd711\
+                     f154e8e8e17c31d3462d7132589ed29353c6fafdb884c5a6e04ea938834f0d9d",
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn merkle_root() {
+        for (_, tree, root, _, _) in bip341_vectors() {
+            assert_eq!(tree.merkle_root().to_string(), root);
+        }
+    }
+
+    #[test]
+    fn control_blocks() {
+        for (internal_pk, tree, _, output_pk, control_blocks) in bip341_vectors() {
+            let internal_pk = InternalPk::from_str(internal_pk).unwrap();
+            let leaves = tree.clone().into_vec();
+            let factory = ControlBlockFactory::with(internal_pk, tree);
+            assert_eq!(factory.output_pk().to_string(), output_pk);
+
+            let mut generated = factory.collect::<Vec<_>>();
+            generated.reverse();
+            assert_eq!(generated.len(), control_blocks.len());
+            for ((cb, leaf_script), (expected, leaf)) in
+                generated.into_iter().zip(control_blocks.into_iter().zip(leaves))
+            {
+                let expected =
+                    ControlBlock::consensus_deserialize(Vec::from_hex(expected).unwrap()).unwrap();
+                assert_eq!(cb, expected);
+                assert_eq!(leaf_script, leaf.script);
+            }
+        }
+    }
+}
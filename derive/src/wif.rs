@@ -0,0 +1,280 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::str::FromStr;
+
+use bc::secp256k1::{SecretKey, SECP256K1};
+use bc::{CompressedPk, LegacyPk, XOnlyPk};
+
+use crate::{
+    base58, Derive, DeriveKey, DeriveSet, KeyExpr, KeyOrigin, Keychain, NormalIndex, Terminal,
+    XpubAccount,
+};
+
+const WIF_MAINNET_PREFIX: u8 = 0x80;
+const WIF_TESTNET_PREFIX: u8 = 0xEF;
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum WifParseError {
+    /// wrong Base58 encoding of WIF private key - {0}
+    #[from]
+    Base58(base58::Error),
+
+    /// invalid WIF private key length {0}.
+    InvalidLength(usize),
+
+    /// unknown WIF private key network prefix {0:#04x}.
+    InvalidPrefix(u8),
+
+    /// invalid WIF private key compression flag {0:#04x}.
+    InvalidCompression(u8),
+
+    /// WIF data do not represent a valid secret key.
+    InvalidSecretKey,
+}
+
+/// Single private key in wallet import format (WIF).
+///
+/// In descriptors the key behaves as its public counterpart (see [`WifKey::to_key_expr`]): it
+/// derives the same public key for all terminals on the [`Keychain::OUTER`] keychain.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct WifKey {
+    pub secret: SecretKey,
+    pub compressed: bool,
+    pub testnet: bool,
+}
+
+impl WifKey {
+    pub fn new(secret: SecretKey, testnet: bool) -> Self {
+        WifKey {
+            secret,
+            compressed: true,
+            testnet,
+        }
+    }
+
+    pub fn to_legacy_pk(&self) -> LegacyPk {
+        LegacyPk {
+            compressed: self.compressed,
+            pubkey: self.secret.public_key(SECP256K1),
+        }
+    }
+
+    /// Returns public key expression which can be used in descriptors.
+    pub fn to_key_expr(&self) -> KeyExpr {
+        let pubkey = self.secret.public_key(SECP256K1);
+        match self.compressed {
+            true => KeyExpr::from(CompressedPk::from(pubkey)),
+            false => KeyExpr::Uncompr {
+                origin: None,
+                key: pubkey.into(),
+            },
+        }
+    }
+}
+
+impl Hash for WifKey {
+    // Public key is fully determined by the secret key, so hashing it is consistent with `Eq`
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_legacy_pk().hash(state);
+        self.testnet.hash(state);
+    }
+}
+
+impl Derive<LegacyPk> for WifKey {
+    #[inline]
+    fn default_keychain(&self) -> Keychain { Keychain::OUTER }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { bset![Keychain::OUTER] }
+
+    fn derive(
+        &self,
+        _keychain: impl Into<Keychain>,
+        _index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = LegacyPk> {
+        iter::once(self.to_legacy_pk())
+    }
+}
+
+impl Derive<CompressedPk> for WifKey {
+    #[inline]
+    fn default_keychain(&self) -> Keychain { Keychain::OUTER }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { bset![Keychain::OUTER] }
+
+    fn derive(
+        &self,
+        _keychain: impl Into<Keychain>,
+        _index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = CompressedPk> {
+        iter::once(CompressedPk::from(self.secret.public_key(SECP256K1)))
+    }
+}
+
+impl Derive<XOnlyPk> for WifKey {
+    #[inline]
+    fn default_keychain(&self) -> Keychain { Keychain::OUTER }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { bset![Keychain::OUTER] }
+
+    fn derive(
+        &self,
+        _keychain: impl Into<Keychain>,
+        _index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = XOnlyPk> {
+        iter::once(XOnlyPk::from(self.secret.public_key(SECP256K1)))
+    }
+}
+
+impl DeriveKey<LegacyPk> for WifKey {
+    fn xpub_spec(&self) -> &XpubAccount { panic!("WIF key is not an extended public key") }
+    fn try_xpub_spec(&self) -> Option<&XpubAccount> { None }
+    fn key_origin(&self, terminal: Terminal) -> KeyOrigin {
+        self.to_key_expr().key_origin(terminal)
+    }
+    fn is_origin_of(&self, origin: &KeyOrigin) -> bool { self.to_key_expr().is_origin_of(origin) }
+}
+
+impl DeriveKey<CompressedPk> for WifKey {
+    fn xpub_spec(&self) -> &XpubAccount { panic!("WIF key is not an extended public key") }
+    fn try_xpub_spec(&self) -> Option<&XpubAccount> { None }
+    fn key_origin(&self, terminal: Terminal) -> KeyOrigin {
+        self.to_key_expr().key_origin(terminal)
+    }
+    fn is_origin_of(&self, origin: &KeyOrigin) -> bool { self.to_key_expr().is_origin_of(origin) }
+    fn is_valid_in_context(&self) -> bool { self.compressed }
+}
+
+impl DeriveKey<XOnlyPk> for WifKey {
+    fn xpub_spec(&self) -> &XpubAccount { panic!("WIF key is not an extended public key") }
+    fn try_xpub_spec(&self) -> Option<&XpubAccount> { None }
+    fn key_origin(&self, terminal: Terminal) -> KeyOrigin {
+        self.to_key_expr().key_origin(terminal)
+    }
+    fn is_origin_of(&self, origin: &KeyOrigin) -> bool { self.to_key_expr().is_origin_of(origin) }
+}
+
+impl DeriveSet for WifKey {
+    type Legacy = WifKey;
+    type Compr = WifKey;
+    type XOnly = WifKey;
+}
+
+impl Display for WifKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut data = Vec::with_capacity(34);
+        data.push(if self.testnet { WIF_TESTNET_PREFIX } else { WIF_MAINNET_PREFIX });
+        data.extend(self.secret.secret_bytes());
+        if self.compressed {
+            data.push(0x01);
+        }
+        base58::encode_check_to_fmt(f, &data)
+    }
+}
+
+impl FromStr for WifKey {
+    type Err = WifParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data = base58::decode_check(s)?;
+        let compressed = match data.len() {
+            33 => false,
+            34 if data[33] == 0x01 => true,
+            34 => return Err(WifParseError::InvalidCompression(data[33])),
+            len => return Err(WifParseError::InvalidLength(len)),
+        };
+        let testnet = match data[0] {
+            WIF_MAINNET_PREFIX => false,
+            WIF_TESTNET_PREFIX => true,
+            prefix => return Err(WifParseError::InvalidPrefix(prefix)),
+        };
+        let secret =
+            SecretKey::from_slice(&data[1..33]).map_err(|_| WifParseError::InvalidSecretKey)?;
+        Ok(WifKey {
+            secret,
+            compressed,
+            testnet,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::hex::FromHex;
+
+    use super::*;
+
+    #[test]
+    fn display_from_str() {
+        // Private key `0C28FCA386C7A227600B2FE50B7CAE11EC86D3BF1FBE471BE89827E19D72AA1D` from the
+        // Bitcoin wiki WIF example
+        let secret = SecretKey::from_byte_array(
+            &<[u8; 32]>::from_hex(
+                "0c28fca386c7a227600b2fe50b7cae11ec86d3bf1fbe471be89827e19d72aa1d",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let uncompressed = "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ";
+        let wif = WifKey::from_str(uncompressed).unwrap();
+        assert_eq!(wif, WifKey {
+            secret,
+            compressed: false,
+            testnet: false
+        });
+        assert_eq!(wif.to_string(), uncompressed);
+
+        for wif in [WifKey::new(secret, false), WifKey::new(secret, true), WifKey {
+            secret,
+            compressed: false,
+            testnet: true,
+        }] {
+            assert_eq!(WifKey::from_str(&wif.to_string()).unwrap(), wif);
+        }
+        assert!(WifKey::new(secret, false).to_string().starts_with(['K', 'L']));
+        assert!(WifKey::new(secret, true).to_string().starts_with('c'));
+
+        assert!(matches!(WifKey::from_str(&uncompressed[1..]), Err(WifParseError::Base58(_))));
+    }
+
+    #[test]
+    fn public_key() {
+        let wif = WifKey::from_str("5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ").unwrap();
+        assert!(!wif.to_legacy_pk().compressed);
+        assert!(matches!(wif.to_key_expr(), KeyExpr::Uncompr { .. }));
+        let wif = WifKey {
+            compressed: true,
+            ..wif
+        };
+        assert!(matches!(wif.to_key_expr(), KeyExpr::Compr { origin: None, .. }));
+    }
+}
@@ -24,6 +24,7 @@
 
 use std::borrow::Borrow;
 use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
 use amplify::{hex, ByteArray, Bytes20, Bytes32, Bytes4, Wrapper};
@@ -814,7 +815,7 @@ impl FromStr for XpubAccount {
     }
 }
 
-#[derive(Getters, Eq, PartialEq, Debug)]
+#[derive(Getters, Clone, Eq, PartialEq, Debug)]
 pub struct XprivAccount {
     origin: XkeyOrigin,
     xpriv: Xpriv,
@@ -1021,6 +1022,94 @@ impl FromStr for XpubDerivable {
     }
 }
 
+/// Extended private key with a ranged derivation, which can be used in private descriptors.
+#[derive(Getters, Clone, Eq, PartialEq, Debug)]
+pub struct XprivDerivable {
+    spec: XprivAccount,
+    #[getter(skip)]
+    xpub: XpubDerivable,
+}
+
+impl Hash for XprivDerivable {
+    // Public part is fully determined by the private key, so hashing it is consistent with `Eq`
+    fn hash<H: Hasher>(&self, state: &mut H) { self.xpub.hash(state) }
+}
+
+impl From<XprivAccount> for XprivDerivable {
+    fn from(spec: XprivAccount) -> Self {
+        let xpub = XpubDerivable::from(spec.to_xpub_account());
+        XprivDerivable { spec, xpub }
+    }
+}
+
+impl XprivDerivable {
+    pub fn with(spec: XprivAccount, keychains: &'static [Keychain]) -> Self {
+        let xpub = XpubDerivable::with(spec.to_xpub_account(), keychains);
+        XprivDerivable { spec, xpub }
+    }
+
+    pub fn xpriv(&self) -> Xpriv { *self.spec.xpriv() }
+
+    pub fn origin(&self) -> &XkeyOrigin { self.spec.origin() }
+
+    /// Returns public counterpart of the key with the same derivation.
+    pub fn to_xpub_derivable(&self) -> XpubDerivable { self.xpub.clone() }
+
+    /// Derives extended private key for a key with a given `origin`, if the key belongs to this
+    /// account.
+    pub fn xpriv_for(&self, origin: &KeyOrigin) -> Option<Xpriv> {
+        if !self.origin().is_subset_of(origin) {
+            return None;
+        }
+        let path = &origin.as_derivation()[self.origin().derivation().len()..];
+        Some(self.spec.xpriv().derive_priv(path))
+    }
+}
+
+impl AsRef<XpubDerivable> for XprivDerivable {
+    fn as_ref(&self) -> &XpubDerivable { &self.xpub }
+}
+
+impl Display for XprivDerivable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.spec, f)?;
+        f.write_str("/")?;
+        if let Some(variant) = self.xpub.variant {
+            write!(f, "{variant}/")?;
+        }
+        Display::fmt(&self.xpub.keychains, f)?;
+        f.write_str("/*")
+    }
+}
+
+impl FromStr for XprivDerivable {
+    type Err = XkeyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let origin_end = s.find(']').ok_or(XkeyParseError::NoOrigin)?;
+        let pos = s[origin_end..].find('/').ok_or(XkeyParseError::InvalidTerminal)?;
+        let (account, remains) = s.split_at(origin_end + pos);
+        let spec = XprivAccount::from_str(account)?;
+        let remains = &remains[1..];
+
+        let mut segs = remains.split('/');
+        let (variant, keychains) = match (segs.next(), segs.next(), segs.next(), segs.next()) {
+            (Some(var), Some(keychains), Some("*"), None) => {
+                (Some(var.parse()?), keychains.parse()?)
+            }
+            (Some(keychains), Some("*"), None, None) => (None, keychains.parse()?),
+            _ => return Err(XkeyParseError::InvalidTerminal),
+        };
+
+        let xpub = XpubDerivable {
+            spec: spec.to_xpub_account(),
+            variant,
+            keychains,
+        };
+        Ok(XprivDerivable { spec, xpub })
+    }
+}
+
 #[cfg(feature = "serde")]
 mod _serde {
     use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
@@ -1089,6 +1178,23 @@ mod _serde {
             })
         }
     }
+
+    impl Serialize for XprivDerivable {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+            serializer.serialize_str(&self.to_string())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for XprivDerivable {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de> {
+            let s = String::deserialize(deserializer)?;
+            XprivDerivable::from_str(&s).map_err(|err| {
+                de::Error::custom(format!("invalid xpriv derivation string representation; {err}"))
+            })
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1151,4 +1257,39 @@ mod test {
         assert_eq!(derivable.spec.origin, xpriv_account.origin);
         assert_eq!(derivable.spec.origin.derivation, derivation);
     }
+
+    #[test]
+    fn xpriv_derivable_display_fromstr() {
+        let xpriv_account = XprivAccount::with_seed(true, &[0xA5; 32]).derive(h![84, 1, 0]);
+        for suffix in ["<0;1>/*", "0/*", "9/<0;1;9;10>/*"] {
+            let s = format!("{xpriv_account}/{suffix}");
+            let derivable = XprivDerivable::from_str(&s).unwrap();
+            assert_eq!(derivable.to_string(), s);
+            assert_eq!(derivable.spec(), &xpriv_account);
+            assert_eq!(
+                derivable.to_xpub_derivable(),
+                XpubDerivable::from_str(&format!("{}/{suffix}", xpriv_account.to_xpub_account()))
+                    .unwrap()
+            );
+        }
+        assert!(XprivDerivable::from_str(&format!("{xpriv_account}/<0;1>")).is_err());
+        assert!(XprivDerivable::from_str(&xpriv_account.to_string()).is_err());
+    }
+
+    #[test]
+    fn xpriv_derivable_key_for_origin() {
+        let xpriv_account = XprivAccount::with_seed(true, &[0xA5; 32]).derive(h![84, 1, 0]);
+        let derivable = XprivDerivable::with(xpriv_account.clone(), &[Keychain::OUTER]);
+        let terminal = [NormalIndex::ONE, NormalIndex::from(5u8)];
+        let origin = KeyOrigin::with(
+            xpriv_account.origin().clone(),
+            Terminal::new(1u8, NormalIndex::from(5u8)),
+        );
+        assert_eq!(derivable.xpriv_for(&origin), Some(xpriv_account.xpriv().derive_priv(terminal)));
+
+        let other = XprivAccount::with_seed(true, &[0xA5; 32]).derive(h![84, 1, 1]);
+        let origin =
+            KeyOrigin::with(other.origin().clone(), Terminal::new(1u8, NormalIndex::from(5u8)));
+        assert_eq!(derivable.xpriv_for(&origin), None);
+    }
 }
@@ -0,0 +1,132 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display, Formatter};
+
+use super::DescrLexerError;
+
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!\
+                             ^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u64; 5] = [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd];
+
+/// Length of the descriptor checksum, in characters.
+pub const DESCR_CHECKSUM_LEN: usize = 8;
+
+fn polymod(chk: u64, value: u64) -> u64 {
+    let top = chk >> 35;
+    let mut chk = ((chk & 0x7ffffffff) << 5) ^ value;
+    for (i, gen) in GENERATOR.iter().enumerate() {
+        if (top >> i) & 1 == 1 {
+            chk ^= gen;
+        }
+    }
+    chk
+}
+
+/// Computes BIP-380 checksum for a descriptor string (which must not contain the checksum
+/// itself).
+///
+/// # Errors
+///
+/// If the descriptor contains characters outside of the BIP-380 input character set.
+pub fn descr_checksum(descr: &str) -> Result<String, DescrLexerError> {
+    let mut chk = 1u64;
+    let mut class = 0u64;
+    let mut class_count = 0;
+    for (idx, ch) in descr.chars().enumerate() {
+        let pos = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| DescrLexerError::InvalidDescrChar(descr.to_owned(), ch, idx))?
+            as u64;
+        chk = polymod(chk, pos & 31);
+        class = class * 3 + (pos >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            chk = polymod(chk, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        chk = polymod(chk, class);
+    }
+    for _ in 0..DESCR_CHECKSUM_LEN {
+        chk = polymod(chk, 0);
+    }
+    chk ^= 1;
+
+    Ok((0..DESCR_CHECKSUM_LEN)
+        .map(|i| CHECKSUM_CHARSET[((chk >> (5 * (7 - i))) & 31) as usize] as char)
+        .collect())
+}
+
+/// Writes descriptor followed by `#` and its checksum.
+///
+/// # Errors
+///
+/// If the descriptor contains characters outside of the BIP-380 input character set, and thus
+/// can't be checksummed.
+pub(crate) fn fmt_checksummed(descr: &impl Display, f: &mut Formatter<'_>) -> fmt::Result {
+    let descr = descr.to_string();
+    let checksum = descr_checksum(&descr).map_err(|_| fmt::Error)?;
+    write!(f, "{descr}#{checksum}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bip380_vectors() {
+        assert_eq!(descr_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+        assert_eq!(descr_checksum("addr(mkmZxiEcEd8ZqjQWVZuC6so5dFMKEFpN2j)").unwrap(), "02wpgw69");
+    }
+
+    #[test]
+    fn invalid_char() {
+        assert_eq!(
+            descr_checksum("raw(dead\tbeef)").unwrap_err(),
+            DescrLexerError::InvalidDescrChar(s!("raw(dead\tbeef)"), '\t', 8)
+        );
+    }
+
+    #[test]
+    fn checksummed() {
+        struct Descr(&'static str);
+        impl Display for Descr {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                if f.alternate() {
+                    fmt_checksummed(self, f)
+                } else {
+                    f.write_str(self.0)
+                }
+            }
+        }
+
+        assert_eq!(format!("{:#}", Descr("raw(deadbeef)")), "raw(deadbeef)#89f8spxm");
+        let mut s = String::new();
+        assert!(fmt::write(&mut s, format_args!("{:#}", Descr("raw(dead\tbeef)"))).is_err());
+    }
+}
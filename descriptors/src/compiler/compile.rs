@@ -22,6 +22,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use core::convert::Infallible;
 use core::fmt::Display;
 use core::str::FromStr;
 use std::collections::BTreeSet;
@@ -29,17 +30,20 @@ use std::fmt::Debug;
 use std::iter;
 
 use amplify::confinement::ConfinedVec;
-use amplify::num::u4;
+use amplify::hex::FromHex;
+use amplify::num::{u4, u7};
 use derive::{
-    Derive, DeriveCompr, DeriveKey, DeriveLegacy, DeriveSet, DeriveXOnly, Keychain, NormalIndex,
+    Address, CompressedPk, Derive, DeriveCompr, DeriveKey, DeriveLegacy, DeriveSet, DeriveXOnly,
+    Keychain, LeafInfo, LegacyPk, NormalIndex, ScriptPubkey, TapCode, TapTree, XOnlyPk,
     XkeyDecodeError, XpubAccount,
 };
 
-use crate::compiler::{DescrAst, DescrParseError, ScriptExpr};
+use crate::compiler::{parse_miniscript, DescrAst, DescrParseError, ScriptExpr};
+use crate::interpreter::TAPROOT_CONTROL_MAX_NODE_COUNT;
 use crate::{
-    Pkh, Sh, ShMulti, ShScript, ShSortedMulti, ShWpkh, ShWsh, ShWshMulti, ShWshScript,
-    ShWshSortedMulti, StdDescr, Tr, TrKey, TrMulti, TrScript, TrSortedMulti, Wpkh, Wsh, WshMulti,
-    WshScript, WshSortedMulti,
+    Addr, Combo, Miniscript, MsContext, Pkh, Raw, RawTr, ScriptDescr, Sh, ShMulti, ShScript,
+    ShSortedMulti, ShWpkh, ShWsh, ShWshMulti, ShWshScript, ShWshSortedMulti, StdDescr, Tr, TrKey,
+    TrMulti, TrMusig, TrScript, TrSortedMulti, Wpkh, Wsh, WshMulti, WshScript, WshSortedMulti,
 };
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -133,6 +137,21 @@ where
     Some(ast.children)
 }
 
+/// Checks that the keys can be used in the `name` script expression without being converted (see
+/// [`DeriveKey::is_valid_in_context`]).
+fn check_keys<'k, D, K: DeriveKey<D> + FromStr + 'k>(
+    keys: impl IntoIterator<Item = &'k K>,
+    name: &'static str,
+) -> Result<(), DescrParseError<K::Err>>
+where
+    K::Err: core::error::Error,
+{
+    match keys.into_iter().find(|key| !key.is_valid_in_context()) {
+        Some(key) => Err(DescrParseError::InvalidKey(name, key.to_string())),
+        None => Ok(()),
+    }
+}
+
 ////////////////////////////////////////
 // Key-only pre-taproot
 
@@ -164,6 +183,7 @@ where K::Err: core::error::Error
         let Some(DescrAst::Key(key, _)) = form.pop() else {
             unreachable!();
         };
+        check_keys::<CompressedPk, _>([&key], "wpkh")?;
         Ok(Wpkh::from(key))
     }
 }
@@ -186,10 +206,27 @@ where K::Err: core::error::Error
         let Some(DescrAst::Key(key, _)) = form.pop() else {
             unreachable!();
         };
+        check_keys::<CompressedPk, _>([&key], "wpkh")?;
         Ok(ShWpkh::from(key))
     }
 }
 
+impl<K: DeriveLegacy + FromStr> FromStr for Combo<K>
+where K::Err: core::error::Error
+{
+    type Err = DescrParseError<K::Err>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ast = ScriptExpr::<K>::from_str(s)?;
+        let mut form = check_forms(ast, "combo", &[DescrExpr::Key][..])
+            .ok_or(DescrParseError::InvalidArgs("combo"))?;
+        let Some(DescrAst::Key(key, _)) = form.pop() else {
+            unreachable!();
+        };
+        Ok(Combo::from(key))
+    }
+}
+
 ////////////////////////////////////////
 // Multisigs pre-taproot
 
@@ -263,6 +300,7 @@ where K::Err: core::error::Error
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (threshold, keys) = parse_multi_form(s, "wsh", None, "multi")?;
+        check_keys::<CompressedPk, _>(&keys, "wsh")?;
         Ok(WshMulti { threshold, keys })
     }
 }
@@ -274,6 +312,7 @@ where K::Err: core::error::Error
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (threshold, keys) = parse_multi_form(s, "wsh", None, "sortedmulti")?;
+        check_keys::<CompressedPk, _>(&keys, "wsh")?;
         Ok(WshSortedMulti { threshold, keys })
     }
 }
@@ -285,6 +324,7 @@ where K::Err: core::error::Error
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (threshold, keys) = parse_multi_form(s, "sh", Some("wsh"), "multi")?;
+        check_keys::<CompressedPk, _>(&keys, "wsh")?;
         Ok(ShWshMulti { threshold, keys })
     }
 }
@@ -296,6 +336,7 @@ where K::Err: core::error::Error
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (threshold, keys) = parse_multi_form(s, "sh", Some("wsh"), "sortedmulti")?;
+        check_keys::<CompressedPk, _>(&keys, "wsh")?;
         Ok(ShWshSortedMulti { threshold, keys })
     }
 }
@@ -303,15 +344,43 @@ where K::Err: core::error::Error
 ////////////////////////////////////////
 // Scripts pre-taproot
 
-// TODO: Implement with support for script templates and miniscript
+fn parse_script_form<K: Display + FromStr>(
+    s: &str,
+    outer: &'static str,
+    medium: Option<&'static str>,
+    ctx: MsContext,
+) -> Result<Miniscript<K>, DescrParseError<K::Err>>
+where
+    K::Err: core::error::Error,
+{
+    let ast = ScriptExpr::<K>::from_str(s)?;
+
+    let mut form = check_forms(ast, outer, &[DescrExpr::Script][..])
+        .ok_or(DescrParseError::InvalidArgs(outer))?;
+    let Some(DescrAst::Script(mut script)) = form.pop() else {
+        unreachable!();
+    };
+
+    if let Some(medium) = medium {
+        let mut form = check_forms(*script, medium, &[DescrExpr::Script][..])
+            .ok_or(DescrParseError::InvalidArgs(medium))?;
+        let Some(DescrAst::Script(script2)) = form.pop() else {
+            unreachable!();
+        };
+        script = script2;
+    }
+
+    parse_miniscript(DescrAst::Script(script), ctx)
+}
 
 impl<K: DeriveLegacy + FromStr> FromStr for ShScript<K>
 where K::Err: core::error::Error
 {
     type Err = DescrParseError<K::Err>;
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Err(DescrParseError::NotSupported("scripts"))
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ms = parse_script_form(s, "sh", None, MsContext::Legacy)?;
+        Ok(ShScript::from(ScriptDescr::with_miniscript::<LegacyPk>(ms)))
     }
 }
 
@@ -320,8 +389,10 @@ where K::Err: core::error::Error
 {
     type Err = DescrParseError<K::Err>;
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Err(DescrParseError::NotSupported("scripts"))
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ms = parse_script_form(s, "wsh", None, MsContext::Segwit)?;
+        check_keys::<CompressedPk, _>(ms.keys(), "wsh")?;
+        Ok(WshScript::from(ScriptDescr::with_miniscript::<CompressedPk>(ms)))
     }
 }
 
@@ -330,8 +401,10 @@ where K::Err: core::error::Error
 {
     type Err = DescrParseError<K::Err>;
 
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        Err(DescrParseError::NotSupported("scripts"))
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ms = parse_script_form(s, "sh", Some("wsh"), MsContext::Segwit)?;
+        check_keys::<CompressedPk, _>(ms.keys(), "wsh")?;
+        Ok(ShWshScript::from(ScriptDescr::with_miniscript::<CompressedPk>(ms)))
     }
 }
 
@@ -417,6 +490,43 @@ where K::Err: core::error::Error
     }
 }
 
+fn parse_tap_leaf<K: DeriveXOnly + FromStr>(
+    expr: ScriptExpr<K>,
+) -> Result<ScriptDescr<TapCode, K>, DescrParseError<K::Err>>
+where K::Err: core::error::Error {
+    let ms = parse_miniscript(DescrAst::Script(Box::new(expr)), MsContext::Tapscript)?;
+    Ok(ScriptDescr::with_miniscript::<XOnlyPk>(ms))
+}
+
+fn parse_tap_tree<K: DeriveXOnly + FromStr>(
+    ast: DescrAst<K>,
+    depth: u8,
+    leaves: &mut Vec<LeafInfo<ScriptDescr<TapCode, K>>>,
+) -> Result<(), DescrParseError<K::Err>>
+where
+    K::Err: core::error::Error,
+{
+    match ast {
+        DescrAst::Script(expr) => {
+            let full = expr.full.to_owned();
+            let depth = u7::try_from(depth).map_err(|_| DescrParseError::InvalidTreeExpr(full))?;
+            let script = parse_tap_leaf(*expr)?;
+            leaves.push(LeafInfo { depth, script });
+        }
+        DescrAst::Tree(tree) => {
+            // Leaves of the node must fit into the control block merkle path
+            if depth as usize >= TAPROOT_CONTROL_MAX_NODE_COUNT {
+                return Err(DescrParseError::InvalidTreeExpr(tree.full.to_owned()));
+            }
+            parse_tap_tree(tree.first, depth + 1, leaves)?;
+            parse_tap_tree(tree.second, depth + 1, leaves)?;
+        }
+        DescrAst::Key(key, _) => return Err(DescrParseError::InvalidTreeExpr(key.to_string())),
+        DescrAst::Lit(lit, _) => return Err(DescrParseError::InvalidTreeExpr(lit.to_owned())),
+    }
+    Ok(())
+}
+
 impl<K: DeriveXOnly + FromStr> FromStr for TrScript<K>
 where K::Err: core::error::Error
 {
@@ -425,18 +535,69 @@ where K::Err: core::error::Error
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let ast = ScriptExpr::<K>::from_str(s)?;
 
-        let mut form = check_forms(ast, "tr", &[DescrExpr::Key, DescrExpr::Tree][..])
+        let mut form = check_forms(ast.clone(), "tr", &[DescrExpr::Key, DescrExpr::Tree][..])
+            .or_else(|| check_forms(ast, "tr", &[DescrExpr::Key, DescrExpr::Script][..]))
             .ok_or(DescrParseError::InvalidArgs("tr"))?;
-        let Some(DescrAst::Key(_internal_key, _)) = form.pop() else {
+        let Some(tree) = form.pop() else {
             unreachable!();
         };
-        let Some(DescrAst::Tree(_tree)) = form.pop() else {
+        let Some(DescrAst::Key(internal_key, _)) = form.pop() else {
             unreachable!();
         };
 
-        // TODO: Process taproot tree
+        let mut leaves = vec![];
+        parse_tap_tree(tree, 0, &mut leaves)?;
+        let tap_tree = TapTree::from_leaves(leaves)
+            .map_err(|_| DescrParseError::InvalidTreeExpr(s.to_owned()))?;
 
-        Err(DescrParseError::NotSupported("scripts"))
+        Ok(TrScript::new(internal_key, tap_tree))
+    }
+}
+
+////////////////////////////////////////
+// Raw scripts and addresses
+
+/// Extracts the single literal argument of a script expression `name`. Literals which are valid
+/// key expressions are lexed as keys, so they are converted back to strings.
+fn single_lit_arg<K: Display + FromStr>(
+    s: &str,
+    name: &'static str,
+) -> Result<String, DescrParseError<K::Err>>
+where
+    K::Err: core::error::Error,
+{
+    let ast = ScriptExpr::<K>::from_str(s)?;
+    if ast.name != name {
+        return Err(DescrParseError::InvalidArgs(name));
+    }
+    match ast.children.as_slice() {
+        [DescrAst::Lit(lit, _)] => Ok(lit.to_string()),
+        [DescrAst::Key(key, _)] => Ok(key.to_string()),
+        _ => Err(DescrParseError::InvalidArgs(name)),
+    }
+}
+
+fn parse_addr<K: Display + FromStr>(s: &str) -> Result<Addr, DescrParseError<K::Err>>
+where K::Err: core::error::Error {
+    let addr = single_lit_arg::<K>(s, "addr")?;
+    Address::from_str(&addr).map(Addr::from).map_err(|_| DescrParseError::InvalidArgs("addr"))
+}
+
+impl FromStr for Addr {
+    type Err = DescrParseError<Infallible>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> { parse_addr::<String>(s) }
+}
+
+impl<K: DeriveLegacy + FromStr> FromStr for Raw<K>
+where K::Err: core::error::Error
+{
+    type Err = DescrParseError<K::Err>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = single_lit_arg::<K>(s, "raw")?;
+        let script = Vec::<u8>::from_hex(&hex).map_err(|_| DescrParseError::InvalidArgs("raw"))?;
+        Ok(Raw::from(ScriptPubkey::from_checked(script)))
     }
 }
 
@@ -461,6 +622,8 @@ where
         };
         if rest.starts_with("wsh") {
             Ok(ShWsh::from_str(s)?.into())
+        } else if rest.starts_with("wpkh") {
+            ShWpkh::from_str(s).map(Sh::Wpkh)
         } else if rest.starts_with("sortedmulti") {
             ShSortedMulti::from_str(s).map(Sh::ShSortedMulti)
         } else if rest.starts_with("multi") {
@@ -513,6 +676,56 @@ where K::Err: core::error::Error
     }
 }
 
+impl<K: DeriveCompr + FromStr> FromStr for TrMusig<K>
+where K::Err: core::error::Error
+{
+    type Err = DescrParseError<K::Err>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ast = match ScriptExpr::<K>::from_str(s) {
+            Err(DescrParseError::UnexpectedToken { token, .. }) if token.starts_with('/') => {
+                return Err(DescrParseError::NotSupported(
+                    "derivation from musig() aggregated key",
+                ));
+            }
+            res => res?,
+        };
+        if ast.name == "tr" && ast.children.len() > 1 {
+            return Err(DescrParseError::NotSupported("musig() internal key with a script tree"));
+        }
+        let mut form = check_forms(ast, "tr", &[DescrExpr::Script][..])
+            .ok_or(DescrParseError::InvalidArgs("tr"))?;
+        let Some(DescrAst::Script(musig)) = form.pop() else {
+            unreachable!();
+        };
+        let form = check_forms(*musig, "musig", &[DescrExpr::Key, DescrExpr::VariadicKey][..])
+            .ok_or(DescrParseError::InvalidArgs("musig"))?;
+        let participants = ConfinedVec::try_from_iter(form.into_iter().map(|el| {
+            let DescrAst::Key(key, _) = el else {
+                unreachable!()
+            };
+            key
+        }))?;
+        Ok(TrMusig { participants })
+    }
+}
+
+impl<K: DeriveXOnly + FromStr> FromStr for RawTr<K>
+where K::Err: core::error::Error
+{
+    type Err = DescrParseError<K::Err>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ast = ScriptExpr::<K>::from_str(s)?;
+        let mut form = check_forms(ast, "rawtr", &[DescrExpr::Key][..])
+            .ok_or(DescrParseError::InvalidArgs("rawtr"))?;
+        let Some(DescrAst::Key(key, _)) = form.pop() else {
+            unreachable!();
+        };
+        Ok(RawTr::from(key))
+    }
+}
+
 impl<K: DeriveXOnly + FromStr> FromStr for Tr<K>
 where K::Err: core::error::Error
 {
@@ -542,11 +755,18 @@ where
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s.trim_start() {
+            s if s.starts_with("addr") => Self::Addr(parse_addr::<K>(s)?),
+            s if s.starts_with("rawtr") => Self::RawTr(RawTr::from_str(s)?),
+            s if s.starts_with("raw") => Self::Raw(Raw::from_str(s)?),
+            s if s.starts_with("combo") => Self::Combo(Combo::from_str(s)?),
             s if s.starts_with("pkh") => Self::Pkh(Pkh::from_str(s)?),
             s if s.starts_with("wpkh") => Self::Wpkh(Wpkh::from_str(s)?),
 
             s if s.starts_with("sh") => Sh::from_str(s)?.into(),
             s if s.starts_with("wsh") => Wsh::from_str(s)?.into(),
+            s if trim_start_expr(s, "tr").is_some_and(|rest| rest.starts_with("musig")) => {
+                Self::TrMusig(TrMusig::from_str(s)?)
+            }
             s if s.starts_with("tr") => Tr::from_str(s)?.into(),
 
             _ => return Err(DescrParseError::InvalidScriptExpr(s.to_owned())),
@@ -559,11 +779,14 @@ mod tests {
     use std::collections::BTreeSet;
     use std::error::Error;
     use std::iter;
+    use std::sync::OnceLock;
 
-    use derive::{Derive, DeriveKey, Keychain, NormalIndex, XkeyDecodeError, XpubAccount};
+    use amplify::hex::ToHex;
+    use derive::{KeyExpr, OpCode, XpubDerivable};
 
     use super::*;
-    use crate::Descriptor;
+    use crate::compiler::descr_checksum;
+    use crate::{Descriptor, MiniscriptError, MsWrapper, ScriptItem, SpkClass};
 
     #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
     #[display("KEY")]
@@ -590,7 +813,13 @@ mod tests {
     }
     impl<K> DeriveKey<K> for DumbKey {
         fn xpub_spec(&self) -> &XpubAccount {
-            unreachable!();
+            static XPUB: OnceLock<XpubAccount> = OnceLock::new();
+            XPUB.get_or_init(|| {
+                XpubAccount::from_str(
+                    "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2",
+                )
+                .unwrap()
+            })
         }
     }
     impl FromStr for DumbKey {
@@ -613,12 +842,19 @@ mod tests {
         assert_eq!(s, d2.to_string());
         assert_eq!(d1, expect);
         assert_eq!(d2.into(), expect);
+
+        let checksummed = format!("{s}#{}", descr_checksum(s).unwrap());
+        assert_eq!(checksummed, format!("{d1:#}"));
+        assert_eq!(StdDescr::from_str(&checksummed).unwrap(), expect);
+        assert_eq!(D::from_str(&checksummed).unwrap().into(), expect);
     }
 
     #[test]
     fn pkh() { roundtrip("pkh(KEY)", Pkh::from(DumbKey)); }
     #[test]
     fn wpkh() { roundtrip("wpkh(KEY)", Wpkh::from(DumbKey)); }
+    #[test]
+    fn sh_wpkh() { roundtrip("sh(wpkh(KEY))", ShWpkh::from(DumbKey)); }
 
     #[test]
     fn sh_multi() {
@@ -664,6 +900,168 @@ mod tests {
     #[test]
     fn tr_key_only() { roundtrip("tr(KEY)", TrKey::from(DumbKey)); }
 
+    #[test]
+    fn tr_musig() {
+        roundtrip("tr(musig(KEY,KEY,KEY))", TrMusig::new_checked([DumbKey, DumbKey, DumbKey]));
+        assert!(TrMusig::<DumbKey>::from_str("tr(musig())").is_err());
+        assert!(TrMusig::<DumbKey>::from_str("tr(KEY,musig(KEY))").is_err());
+        assert!(matches!(
+            StdDescr::<DumbKey>::from_str("tr(musig(KEY,KEY),pk(KEY))"),
+            Err(DescrParseError::NotSupported(_))
+        ));
+        assert!(matches!(
+            StdDescr::<DumbKey>::from_str("tr(musig(KEY,KEY)/0/*)"),
+            Err(DescrParseError::NotSupported(_))
+        ));
+    }
+
+    #[test]
+    fn raw_outputs() {
+        // Test vectors from BIP-384, BIP-385 and BIP-386
+        for (descr, spk) in [
+            ("raw(deadbeef)", "deadbeef"),
+            (
+                "addr(1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs)",
+                "76a914f54a5851e9372b87810a8e60cdd2e7cfd80b6e3188ac",
+            ),
+            (
+                "addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)",
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            (
+                "rawtr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)",
+                "5120a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+            ),
+        ] {
+            let d = StdDescr::<KeyExpr>::from_str(descr).unwrap();
+            assert_eq!(d.to_string(), descr);
+            assert_eq!(StdDescr::<KeyExpr>::from_str(&format!("{d:#}")).unwrap(), d);
+            let script = d.derive(Keychain::OUTER, 0u8).next().unwrap();
+            assert_eq!(script.to_script_pubkey().as_slice().to_hex(), spk);
+        }
+
+        let d = StdDescr::<XpubDerivable>::from_str(
+            "raw(00149a1c78a507689f6f54b847ad1cef1e614ee23f1e)",
+        )
+        .unwrap();
+        assert_eq!(d.class(), SpkClass::P2wpkh);
+        let d = StdDescr::<XpubDerivable>::from_str(
+            "addr(tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c)",
+        )
+        .unwrap();
+        assert_eq!(d.class(), SpkClass::P2tr);
+        assert_eq!(d.keys().count(), 0);
+
+        assert!(StdDescr::<XpubDerivable>::from_str("raw(deadbee)").is_err());
+        assert!(StdDescr::<XpubDerivable>::from_str("raw(deadbeef,00)").is_err());
+        assert!(StdDescr::<XpubDerivable>::from_str("addr(1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAt)")
+            .is_err());
+    }
+
+    #[test]
+    fn combo() {
+        let d = StdDescr::<KeyExpr>::from_str(
+            "combo(03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)",
+        )
+        .unwrap();
+        let spks = d
+            .derive(Keychain::OUTER, 0u8)
+            .map(|script| script.to_script_pubkey().as_slice().to_hex())
+            .collect::<Vec<_>>();
+        assert_eq!(spks, [
+            "2103a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bdac",
+            "76a9149a1c78a507689f6f54b847ad1cef1e614ee23f1e88ac",
+            "00149a1c78a507689f6f54b847ad1cef1e614ee23f1e",
+            "a91484ab21b1b2fd065d4504ff693d832434b6108d7b87",
+        ]);
+
+        // Uncompressed keys can't be used in segwit outputs
+        let d = StdDescr::<KeyExpr>::from_str(
+            "combo(0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8)",
+        )
+        .unwrap();
+        assert_eq!(d.derive(Keychain::OUTER, 0u8).count(), 2);
+
+        let descr = "combo([643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1>/*)";
+        let d = StdDescr::<XpubDerivable>::from_str(descr).unwrap();
+        assert_eq!(d.to_string(), descr);
+        assert_eq!(d.derive(Keychain::INNER, 7u8).count(), 4);
+    }
+
+    #[test]
+    fn key_expressions() {
+        const XPUB: &str = "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2";
+        // Test vectors from BIP-381, BIP-382 and BIP-386
+        for (descr, spk) in [
+            (
+                "pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)",
+                "76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac",
+            ),
+            (
+                "wpkh(03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)",
+                "00149a1c78a507689f6f54b847ad1cef1e614ee23f1e",
+            ),
+            (
+                "tr(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd)",
+                "512077aab6e066f8a7419c5ab714c12c67d25007ed55a43cadcacb4d7a970a093f11",
+            ),
+        ] {
+            let d = StdDescr::<KeyExpr>::from_str(descr).unwrap();
+            assert_eq!(d.to_string(), descr);
+            for index in [0u8, 1] {
+                let script = d.derive(Keychain::INNER, index).next().unwrap();
+                assert_eq!(script.to_script_pubkey().as_slice().to_hex(), spk);
+            }
+        }
+
+        for descr in [
+            format!("pkh({XPUB}/0/5)"),
+            format!(
+                "wsh(multi(1,{XPUB}/<0;1>/*,[d34db33f/44h/0h/\
+                 0h]03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd))"
+            ),
+            format!(
+                "tr({XPUB}/1/3,\
+                 pk(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd))"
+            ),
+            format!(
+                "sh(wsh(and_v(v:pk({XPUB}/0/*),\
+                 sha256(a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd))))"
+            ),
+        ] {
+            let d = StdDescr::<KeyExpr>::from_str(&descr).unwrap();
+            assert_eq!(d.to_string(), descr);
+        }
+
+        // Hex keys are not accepted as extended keys
+        assert!(StdDescr::<XpubDerivable>::from_str(
+            "wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)"
+        )
+        .is_err());
+
+        // Uncompressed and x-only keys are not valid in segwit v0 descriptors (BIP-382)
+        const UNCOMPR: &str = "04a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd5b8dec5235a0fa8722476c7709c02559e3aa73aa03918ba2d492eea75abea235";
+        const XONLY: &str = "a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd";
+        for key in [UNCOMPR, XONLY] {
+            for (descr, ctx) in [
+                (format!("wpkh({key})"), "wpkh"),
+                (format!("sh(wpkh({key}))"), "wpkh"),
+                (format!("wsh(pk({key}))"), "wsh"),
+                (format!("sh(wsh(pk({key})))"), "wsh"),
+                (format!("wsh(multi(1,{XPUB}/<0;1>/*,{key}))"), "wsh"),
+                (format!("sh(wsh(sortedmulti(1,{key})))"), "wsh"),
+            ] {
+                assert_eq!(
+                    StdDescr::<KeyExpr>::from_str(&descr).unwrap_err(),
+                    DescrParseError::InvalidKey(ctx, key.to_owned()),
+                    "{descr}"
+                );
+            }
+        }
+        // Legacy descriptors accept uncompressed keys
+        assert!(StdDescr::<KeyExpr>::from_str(&format!("sh(multi(1,{UNCOMPR}))")).is_ok());
+    }
+
     #[test]
     fn tr_multi_a() {
         roundtrip(
@@ -678,4 +1076,143 @@ mod tests {
             TrSortedMulti::new_checked(DumbKey, 2, [DumbKey, DumbKey, DumbKey]),
         );
     }
+
+    fn pk_leaf(depth: u8) -> LeafInfo<ScriptDescr<TapCode, DumbKey>> {
+        LeafInfo {
+            depth: u7::with(depth),
+            script: ScriptDescr::with_miniscript::<XOnlyPk>(Miniscript::Wrap(
+                MsWrapper::Check,
+                Box::new(Miniscript::PkK(DumbKey)),
+            )),
+        }
+    }
+
+    #[test]
+    fn tr_single_leaf() {
+        roundtrip(
+            "tr(KEY,pk(KEY))",
+            TrScript::new(DumbKey, TapTree::from_leaves([pk_leaf(0)]).unwrap()),
+        );
+    }
+    #[test]
+    fn tr_tree() {
+        roundtrip(
+            "tr(KEY,{pk(KEY),pk(KEY)})",
+            TrScript::new(DumbKey, TapTree::from_leaves([pk_leaf(1), pk_leaf(1)]).unwrap()),
+        );
+        roundtrip(
+            "tr(KEY,{pk(KEY),{pk(KEY),pk(KEY)}})",
+            TrScript::new(
+                DumbKey,
+                TapTree::from_leaves([pk_leaf(1), pk_leaf(2), pk_leaf(2)]).unwrap(),
+            ),
+        );
+        roundtrip(
+            "tr(KEY,{{pk(KEY),{pk(KEY),pk(KEY)}},{pk(KEY),pk(KEY)}})",
+            TrScript::new(
+                DumbKey,
+                TapTree::from_leaves([pk_leaf(2), pk_leaf(3), pk_leaf(3), pk_leaf(2), pk_leaf(2)])
+                    .unwrap(),
+            ),
+        );
+    }
+    #[test]
+    fn tr_invalid_tree() {
+        assert!(TrScript::<DumbKey>::from_str("tr(KEY,{pk(KEY)})").is_err());
+        assert!(TrScript::<DumbKey>::from_str("tr(KEY,{pk(KEY),KEY})").is_err());
+
+        let nested = |levels: usize| {
+            format!("tr(KEY,{}pk(KEY){})", "{".repeat(levels), ",pk(KEY)}".repeat(levels))
+        };
+        assert!(TrScript::<DumbKey>::from_str(&nested(127)).is_ok());
+        for levels in [129, 300] {
+            assert!(matches!(
+                TrScript::<DumbKey>::from_str(&nested(levels)),
+                Err(DescrParseError::InvalidTreeExpr(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn wsh_miniscript() {
+        let ms = Miniscript::AndV(
+            Box::new(Miniscript::Wrap(
+                MsWrapper::Verify,
+                Box::new(Miniscript::Wrap(MsWrapper::Check, Box::new(Miniscript::PkK(DumbKey)))),
+            )),
+            Box::new(Miniscript::Older(144)),
+        );
+        let descr = ScriptDescr::<OpCode, _>::with_miniscript::<CompressedPk>(ms);
+        assert_eq!(descr.condition, vec![
+            ScriptItem::Key(
+                DeriveKey::<CompressedPk>::xpub_spec(&DumbKey).origin().clone(),
+                DumbKey
+            ),
+            ScriptItem::Code(vec![OpCode::CheckSigVerify]),
+            ScriptItem::Data(vec![0x90, 0x00]),
+            ScriptItem::Code(vec![OpCode::Csv]),
+        ]);
+        roundtrip("wsh(and_v(v:pk(KEY),older(144)))", WshScript::from(descr));
+    }
+
+    #[test]
+    fn miniscript_roundtrips() {
+        fn check<D: FromStr + Display + Into<StdDescr<DumbKey>>>(s: &str)
+        where D::Err: Debug {
+            let d = D::from_str(s).unwrap();
+            assert_eq!(s, d.to_string());
+            assert_eq!(s, StdDescr::<DumbKey>::from_str(s).unwrap().to_string());
+        }
+
+        check::<ShWshScript<DumbKey>>("sh(wsh(or_d(pk(KEY),and_v(v:pkh(KEY),after(500000)))))");
+        check::<ShScript<DumbKey>>(
+            "sh(and_v(v:pk(KEY),\
+             sha256(0b0dc2ef5e6f4bd9bf1b4d4fa5a4b7a21fb53ac4d2b1bd0fcd8bc4e6f7c5c0b4)))",
+        );
+        check::<WshScript<DumbKey>>("wsh(thresh(2,pk(KEY),s:pk(KEY),sln:older(12960)))");
+        check::<WshScript<DumbKey>>(
+            "wsh(andor(pk(KEY),older(1008),and_n(pk(KEY),\
+             hash160(ffffffffffffffffffffffffffffffffffffffff))))",
+        );
+        check::<WshScript<DumbKey>>(
+            "wsh(or_i(and_v(v:pkh(KEY),\
+             hash256(1111111111111111111111111111111111111111111111111111111111111111)),pk(KEY)))",
+        );
+        check::<TrScript<DumbKey>>("tr(KEY,{and_v(v:pk(KEY),older(144)),multi_a(1,KEY,KEY)})");
+    }
+
+    #[test]
+    fn miniscript_errors() {
+        fn err(s: &str) -> DescrParseError<XkeyDecodeError> {
+            StdDescr::<DumbKey>::from_str(s).unwrap_err()
+        }
+
+        assert!(matches!(
+            err("wsh(and_v(pk(KEY),older(144)))"),
+            DescrParseError::Miniscript(4, MiniscriptError::TypeMismatch("and_v", "V", _))
+        ));
+        assert_eq!(
+            err("wsh(older(144))"),
+            DescrParseError::Miniscript(4, MiniscriptError::NoSignature)
+        );
+        assert_eq!(
+            err("wsh(or_d(pk(KEY),older(xx)))"),
+            DescrParseError::Miniscript(23, MiniscriptError::InvalidNumber(s!("xx")))
+        );
+        assert_eq!(
+            err("wsh(and_v(x:pk(KEY),older(1)))"),
+            DescrParseError::Miniscript(10, MiniscriptError::UnknownWrapper('x'))
+        );
+        assert_eq!(
+            err("wsh(and_v(v:pk(KEY),foo(1)))"),
+            DescrParseError::Miniscript(20, MiniscriptError::UnknownFragment(s!("foo")))
+        );
+        assert_eq!(
+            TrScript::<DumbKey>::from_str("tr(KEY,multi(1,KEY))").unwrap_err(),
+            DescrParseError::Miniscript(
+                7,
+                MiniscriptError::ContextMismatch("multi", MsContext::Tapscript)
+            )
+        );
+    }
 }
@@ -0,0 +1,213 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::fmt::Display;
+use core::str::FromStr;
+
+use amplify::hex::FromHex;
+use amplify::{Bytes20, Bytes32};
+
+use crate::compiler::{DescrAst, DescrParseError};
+use crate::{HashLock, Miniscript, MiniscriptError, MsContext, MsWrapper};
+
+impl<K: Display + FromStr> DescrAst<'_, K>
+where K::Err: core::error::Error
+{
+    /// Position of the expression inside the descriptor string.
+    pub fn pos(&self) -> Option<usize> {
+        match self {
+            DescrAst::Key(_, pos) | DescrAst::Lit(_, pos) => Some(*pos),
+            DescrAst::Script(expr) => Some(expr.offset),
+            DescrAst::Tree(_) => None,
+        }
+    }
+}
+
+/// Parses miniscript expression and checks that it is valid as a top-level script in the given
+/// context.
+pub fn parse_miniscript<K: Display + FromStr>(
+    ast: DescrAst<K>,
+    ctx: MsContext,
+) -> Result<Miniscript<K>, DescrParseError<K::Err>>
+where
+    K::Err: core::error::Error,
+{
+    let pos = ast.pos().unwrap_or_default();
+    let ms = parse_fragment(ast, ctx)?;
+    ms.check_top_level(ctx).map_err(|err| DescrParseError::Miniscript(pos, err))?;
+    Ok(ms)
+}
+
+fn parse_fragment<K: Display + FromStr>(
+    ast: DescrAst<K>,
+    ctx: MsContext,
+) -> Result<Miniscript<K>, DescrParseError<K::Err>>
+where
+    K::Err: core::error::Error,
+{
+    let (name, pos, mut args) = match ast {
+        DescrAst::Script(expr) => (expr.name, expr.offset, expr.children),
+        DescrAst::Lit(lit, pos) => (lit, pos, vec![]),
+        DescrAst::Key(key, pos) => {
+            return Err(DescrParseError::Miniscript(
+                pos,
+                MiniscriptError::UnknownFragment(key.to_string()),
+            ))
+        }
+        DescrAst::Tree(tree) => return Err(DescrParseError::InvalidTreeExpr(tree.full.to_owned())),
+    };
+    let err = |err: MiniscriptError| DescrParseError::Miniscript(pos, err);
+    let (wrappers, fragment) = name.rsplit_once(':').unwrap_or(("", name));
+
+    let invalid_args = || err(MiniscriptError::InvalidArgs(fragment.to_owned()));
+    let sub = |ast: DescrAst<K>| parse_fragment(ast, ctx).map(Box::new);
+
+    let mut ms = match (fragment, args.len()) {
+        ("0", 0) => Miniscript::False,
+        ("1", 0) => Miniscript::True,
+        ("pk_k" | "pk_h" | "pk" | "pkh", 1) => {
+            let key = parse_key(args.remove(0))?;
+            match fragment {
+                "pk_k" => Miniscript::PkK(key),
+                "pk_h" => Miniscript::PkH(key),
+                "pk" => Miniscript::Wrap(MsWrapper::Check, Box::new(Miniscript::PkK(key))),
+                _ => Miniscript::Wrap(MsWrapper::Check, Box::new(Miniscript::PkH(key))),
+            }
+        }
+        ("older", 1) => Miniscript::Older(parse_num(args.remove(0))?),
+        ("after", 1) => Miniscript::After(parse_num(args.remove(0))?),
+        ("sha256" | "hash256" | "ripemd160" | "hash160", 1) => {
+            Miniscript::Hash(parse_hash(fragment, args.remove(0))?)
+        }
+        ("andor", 3) => {
+            let z = sub(args.pop().expect("three args"))?;
+            let y = sub(args.pop().expect("three args"))?;
+            let x = sub(args.pop().expect("three args"))?;
+            Miniscript::AndOr(x, y, z)
+        }
+        ("and_v" | "and_b" | "and_n" | "or_b" | "or_c" | "or_d" | "or_i", 2) => {
+            let y = sub(args.pop().expect("two args"))?;
+            let x = sub(args.pop().expect("two args"))?;
+            match fragment {
+                "and_v" => Miniscript::AndV(x, y),
+                "and_b" => Miniscript::AndB(x, y),
+                "and_n" => Miniscript::AndOr(x, y, Box::new(Miniscript::False)),
+                "or_b" => Miniscript::OrB(x, y),
+                "or_c" => Miniscript::OrC(x, y),
+                "or_d" => Miniscript::OrD(x, y),
+                _ => Miniscript::OrI(x, y),
+            }
+        }
+        ("thresh", 2..) => {
+            let k = parse_num(args.remove(0))? as usize;
+            let subs =
+                args.into_iter().map(|ast| parse_fragment(ast, ctx)).collect::<Result<_, _>>()?;
+            Miniscript::Thresh(k, subs)
+        }
+        ("multi" | "multi_a", 2..) => {
+            let k = parse_num(args.remove(0))? as usize;
+            let keys = args.into_iter().map(parse_key).collect::<Result<_, _>>()?;
+            if fragment == "multi" {
+                Miniscript::Multi(k, keys)
+            } else {
+                Miniscript::MultiA(k, keys)
+            }
+        }
+        (
+            "0" | "1" | "pk_k" | "pk_h" | "pk" | "pkh" | "older" | "after" | "sha256" | "hash256"
+            | "ripemd160" | "hash160" | "andor" | "and_v" | "and_b" | "and_n" | "or_b" | "or_c"
+            | "or_d" | "or_i" | "thresh" | "multi" | "multi_a",
+            _,
+        ) => return Err(invalid_args()),
+        _ => return Err(err(MiniscriptError::UnknownFragment(fragment.to_owned()))),
+    };
+    ms.ty(ctx).map_err(err)?;
+
+    for wrapper in wrappers.chars().rev() {
+        ms = match wrapper {
+            'a' => Miniscript::Wrap(MsWrapper::Alt, Box::new(ms)),
+            's' => Miniscript::Wrap(MsWrapper::Swap, Box::new(ms)),
+            'c' => Miniscript::Wrap(MsWrapper::Check, Box::new(ms)),
+            'd' => Miniscript::Wrap(MsWrapper::DupIf, Box::new(ms)),
+            'v' => Miniscript::Wrap(MsWrapper::Verify, Box::new(ms)),
+            'j' => Miniscript::Wrap(MsWrapper::NonZero, Box::new(ms)),
+            'n' => Miniscript::Wrap(MsWrapper::ZeroNotEqual, Box::new(ms)),
+            't' => Miniscript::AndV(Box::new(ms), Box::new(Miniscript::True)),
+            'l' => Miniscript::OrI(Box::new(Miniscript::False), Box::new(ms)),
+            'u' => Miniscript::OrI(Box::new(ms), Box::new(Miniscript::False)),
+            _ => return Err(err(MiniscriptError::UnknownWrapper(wrapper))),
+        };
+        ms.ty(ctx).map_err(err)?;
+    }
+
+    Ok(ms)
+}
+
+fn parse_key<K: Display + FromStr>(ast: DescrAst<K>) -> Result<K, DescrParseError<K::Err>>
+where K::Err: core::error::Error {
+    let pos = ast.pos().unwrap_or_default();
+    match ast {
+        DescrAst::Key(key, _) => Ok(key),
+        other => {
+            Err(DescrParseError::Miniscript(pos, MiniscriptError::InvalidKey(other.to_string())))
+        }
+    }
+}
+
+fn parse_num<K: Display + FromStr>(ast: DescrAst<K>) -> Result<u32, DescrParseError<K::Err>>
+where K::Err: core::error::Error {
+    let pos = ast.pos().unwrap_or_default();
+    match ast {
+        DescrAst::Lit(lit, _) => u32::from_str(lit).map_err(|_| {
+            DescrParseError::Miniscript(pos, MiniscriptError::InvalidNumber(lit.to_owned()))
+        }),
+        other => {
+            Err(DescrParseError::Miniscript(pos, MiniscriptError::InvalidNumber(other.to_string())))
+        }
+    }
+}
+
+fn parse_hash<K: Display + FromStr>(
+    fragment: &str,
+    ast: DescrAst<K>,
+) -> Result<HashLock, DescrParseError<K::Err>>
+where
+    K::Err: core::error::Error,
+{
+    let pos = ast.pos().unwrap_or_default();
+    // Hex strings which don't start with a digit are lexed as identifiers and may be
+    // successfully parsed as keys
+    let hex = match &ast {
+        DescrAst::Lit(lit, _) => lit.to_string(),
+        DescrAst::Key(key, _) => key.to_string(),
+        other => other.to_string(),
+    };
+    let invalid = || DescrParseError::Miniscript(pos, MiniscriptError::InvalidHash(hex.clone()));
+    Ok(match fragment {
+        "sha256" => HashLock::Sha256(Bytes32::from_hex(&hex).map_err(|_| invalid())?),
+        "hash256" => HashLock::Hash256(Bytes32::from_hex(&hex).map_err(|_| invalid())?),
+        "ripemd160" => HashLock::Ripemd160(Bytes20::from_hex(&hex).map_err(|_| invalid())?),
+        _ => HashLock::Hash160(Bytes20::from_hex(&hex).map_err(|_| invalid())?),
+    })
+}
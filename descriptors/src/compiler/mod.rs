@@ -25,7 +25,12 @@
 mod lexer;
 mod parser;
 mod compile;
+mod miniscript;
+mod checksum;
 
+pub(crate) use checksum::fmt_checksummed;
+pub use checksum::{descr_checksum, DESCR_CHECKSUM_LEN};
 pub use compile::{check_forms, DescrExpr, NoKey};
 pub use lexer::{parse_descr_str, DescrLexerError, DescrToken};
+pub use miniscript::parse_miniscript;
 pub use parser::{DescrAst, DescrParseError, ScriptExpr, TreeExpr};
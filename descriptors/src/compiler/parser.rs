@@ -30,12 +30,27 @@ use std::str::FromStr;
 use amplify::confinement;
 use derive::XpubDerivable;
 
-use super::{parse_descr_str, DescrLexerError, DescrToken};
+use super::{descr_checksum, parse_descr_str, DescrLexerError, DescrToken};
+use crate::MiniscriptError;
 
 impl<'s, K: Display + FromStr> ScriptExpr<'s, K>
 where K::Err: Error
 {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &'s str) -> Result<Self, DescrParseError<K::Err>> {
+        let s = match s.rsplit_once('#') {
+            None => s,
+            Some((descr, checksum)) => {
+                let expected = descr_checksum(descr)?;
+                if checksum != expected {
+                    return Err(DescrParseError::ChecksumMismatch {
+                        expected,
+                        found: checksum.to_owned(),
+                    });
+                }
+                descr
+            }
+        };
         let tokens = parse_descr_str(s)?;
         Self::parse_tokens(s, &tokens)
     }
@@ -92,8 +107,17 @@ pub enum DescrParseError<E: Error> {
     /// invalid arguments are given for the descriptor script expression {0}.
     InvalidArgs(&'static str),
 
+    /// key '{1}' can't be used in {0} expression.
+    InvalidKey(&'static str, String),
+
     /// parsing {0} is not yet supported.
     NotSupported(&'static str),
+
+    /// invalid miniscript expression at position {0}. {1}
+    Miniscript(usize, MiniscriptError),
+
+    /// descriptor checksum '{found}' doesn't match the expected value '{expected}'.
+    ChecksumMismatch { expected: String, found: String },
 }
 
 impl<E1: Error> DescrParseError<E1> {
@@ -130,7 +154,12 @@ impl<E1: Error> DescrParseError<E1> {
             DescrParseError::Lit(err) => Self::Lit(err),
             DescrParseError::Confinement(err) => Self::Confinement(err),
             DescrParseError::InvalidArgs(s) => Self::InvalidArgs(s),
+            DescrParseError::InvalidKey(s, key) => Self::InvalidKey(s, key),
             DescrParseError::NotSupported(s) => Self::NotSupported(s),
+            DescrParseError::Miniscript(pos, err) => Self::Miniscript(pos, err),
+            DescrParseError::ChecksumMismatch { expected, found } => {
+                Self::ChecksumMismatch { expected, found }
+            }
         }
     }
 }
@@ -187,7 +216,9 @@ where K::Err: Error
         };
 
         Ok(match first {
-            DescrToken::Ident(_, _)
+            // Miniscript fragment names may contain wrappers separated with ':' or digits (like
+            // in `sha256`), so they are lexed as literals.
+            DescrToken::Ident(_, _) | DescrToken::Lit(_, _)
                 if matches!(tokens.get(1), Some(DescrToken::OpeningParenthesis(_))) =>
             {
                 let matched_bracket = matching_bracket_close::<K>(descr, &tokens[1..])?;
@@ -236,7 +267,9 @@ where K::Err: Error
     ) -> Result<Self, DescrParseError<K::Err>> {
         let full = descr_substr(descr, tokens);
 
-        let Some(DescrToken::Ident(name, offset)) = tokens.split_off_first() else {
+        let Some(DescrToken::Ident(name, offset) | DescrToken::Lit(name, offset)) =
+            tokens.split_off_first()
+        else {
             return Err(DescrParseError::NoName(descr.to_string()));
         };
         if !matches!(tokens.split_off_first(), Some(DescrToken::OpeningParenthesis(_)))
@@ -471,4 +504,30 @@ mod tests {
     #[test]
     #[should_panic(expected = "Empty")]
     fn empty_braces() { ScriptExpr::<String>::from_str("tree({})").unwrap(); }
+
+    #[test]
+    fn checksum() {
+        let ast = ScriptExpr::<String>::from_str("raw(deadbeef)#89f8spxm").unwrap();
+        assert_eq!(ast.full, "raw(deadbeef)");
+        ScriptExpr::<String>::from_str("raw(deadbeef)").unwrap();
+
+        for (descr, found) in [
+            ("raw(deadbeef)#", ""),
+            ("raw(deadbeef)#89f8spxmx", "89f8spxmx"),
+            ("raw(deadbeef)#89f8spx", "89f8spx"),
+            ("raw(deadbeef)#8yf8spxm", "8yf8spxm"),
+        ] {
+            assert_eq!(
+                ScriptExpr::<String>::from_str(descr).unwrap_err(),
+                DescrParseError::ChecksumMismatch {
+                    expected: s!("89f8spxm"),
+                    found: found.to_owned()
+                }
+            );
+        }
+        assert!(matches!(
+            ScriptExpr::<String>::from_str("raw(deadbeef)#89f8spxm#"),
+            Err(DescrParseError::ChecksumMismatch { .. })
+        ));
+    }
 }
@@ -31,21 +31,24 @@ use amplify::hex;
 use amplify::hex::{FromHex, ToHex};
 use commit_verify::{Digest, DigestExt, Sha256};
 use derive::{
-    Bip340Sig, ControlBlock, Derive, DeriveCompr, DeriveLegacy, DeriveScripts, DeriveSet,
-    DeriveXOnly, DerivedScript, Idx, KeyOrigin, Keychain, LegacyPk, LegacySig, NormalIndex,
-    RedeemScript, Sats, SigScript, TapDerivation, Terminal, Witness, WitnessScript, XOnlyPk,
-    XpubAccount, XpubDerivable,
+    Bip340Sig, CompressedPk, ControlBlock, Derive, DeriveCompr, DeriveLegacy, DeriveScripts,
+    DeriveSet, DeriveXOnly, DerivedScript, Idx, KeyOrigin, Keychain, LegacyPk, LegacySig,
+    NormalIndex, RedeemScript, Sats, ScriptPubkey, SigScript, TapDerivation, Terminal, WeightUnits,
+    Witness, WitnessScript, XOnlyPk, XkeyOrigin, XprivDerivable, XpubAccount, XpubDerivable,
+    XpubFp,
 };
 use indexmap::IndexMap;
 
 use crate::{
-    Pkh, Raw, Sh, ShMulti, ShScript, ShSortedMulti, ShWpkh, ShWsh, ShWshMulti, ShWshScript,
-    ShWshSortedMulti, Tr, TrKey, TrMulti, TrScript, TrSortedMulti, Wpkh, Wsh, WshMulti, WshScript,
-    WshSortedMulti,
+    Addr, Combo, HashLock, PathPolicy, Pkh, Raw, RawTr, SatisfactionWeight, Sh, ShMulti, ShScript,
+    ShSortedMulti, ShWpkh, ShWsh, ShWshMulti, ShWshScript, ShWshSortedMulti, SpendContext,
+    Timelocks, Tr, TrKey, TrMulti, TrMusig, TrScript, TrSortedMulti, Wpkh, Wsh, WshMulti,
+    WshScript, WshSortedMulti,
 };
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
 #[display(lowercase)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
 pub enum SpkClass {
     Bare,
     P2pkh,
@@ -56,6 +59,24 @@ pub enum SpkClass {
 }
 
 impl SpkClass {
+    /// Detects class of the given `scriptPubkey`. Scripts not matching any of the standard
+    /// templates are classified as [`SpkClass::Bare`].
+    pub fn detect(script_pubkey: &ScriptPubkey) -> Self {
+        if script_pubkey.is_p2pkh() {
+            SpkClass::P2pkh
+        } else if script_pubkey.is_p2sh() {
+            SpkClass::P2sh
+        } else if script_pubkey.is_p2wpkh() {
+            SpkClass::P2wpkh
+        } else if script_pubkey.is_p2wsh() {
+            SpkClass::P2wsh
+        } else if script_pubkey.is_p2tr() {
+            SpkClass::P2tr
+        } else {
+            SpkClass::Bare
+        }
+    }
+
     pub const fn dust_limit(self) -> Sats {
         match self {
             SpkClass::Bare => Sats(0),
@@ -206,12 +227,60 @@ pub trait Descriptor<K = XpubDerivable, V = ()>: DeriveScripts + Clone + Display
     where V: 'a;
     fn xpubs(&self) -> impl Iterator<Item = &XpubAccount>;
 
+    /// Hash locks used by the descriptor scripts. Their preimages must be provided to
+    /// [`Descriptor::legacy_witness`] or [`Descriptor::taproot_witness`] for satisfying the
+    /// spending paths depending on them.
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> { iter::empty() }
+
+    /// Timelocks required by each of the descriptor spending paths, which must be matched by the
+    /// lock time and sequence number of the spending transaction. Descriptors without timelock
+    /// conditions have a single spending path requiring no timelocks.
+    fn timelocks(&self) -> Vec<Timelocks> { vec![Timelocks::default()] }
+
+    /// Weights of the data satisfying each of the descriptor spending paths, for estimating fees
+    /// of the spending transactions. Descriptors whose satisfaction is unknown, like `raw()` or
+    /// `addr()`, have no spending paths listed.
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> { vec![] }
+
+    /// The largest of the satisfaction weights among all descriptor spending paths.
+    fn max_satisfaction_weight(&self) -> Option<WeightUnits> {
+        self.satisfaction_weights().into_iter().map(|weight| weight.max).max()
+    }
+
+    /// Semantic policies of the descriptor spending paths, with keys identified by their account
+    /// origins. Paths whose conditions are unknown, like those of `raw()` and `addr()`
+    /// descriptors or of scripts not compiled from miniscript, are not listed.
+    fn policies(&self) -> Vec<PathPolicy> { vec![] }
+
+    /// Detects whether signers holding keys with the given master key fingerprints can use any of
+    /// the descriptor spending paths in the `context`.
+    fn can_spend(&self, signers: &[XpubFp], context: &SpendContext) -> bool {
+        self.policies().iter().any(|path| path.policy.can_spend(signers, context))
+    }
+
+    /// Keys which must sign for spending in the `context`, whichever of the spending paths
+    /// available in it is used. Returns `None` if none of the paths can be used in the `context`.
+    fn required_keys(&self, context: &SpendContext) -> Option<BTreeSet<XkeyOrigin>> {
+        self.policies()
+            .iter()
+            .filter_map(|path| path.policy.required_keys(context))
+            .reduce(|a, b| a.intersection(&b).cloned().collect())
+    }
+
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin>;
     fn xonly_keyset(&self, terminal: Terminal) -> IndexMap<XOnlyPk, TapDerivation>;
 
+    /// MuSig2 aggregated keys used by the descriptor, mapped to the participant keys they
+    /// aggregate.
+    fn musig_keyset(&self, _terminal: Terminal) -> IndexMap<CompressedPk, Vec<CompressedPk>> {
+        IndexMap::new()
+    }
+
     fn legacy_witness(
         &self,
+        script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)>;
@@ -220,6 +289,7 @@ pub trait Descriptor<K = XpubDerivable, V = ()>: DeriveScripts + Clone + Display
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness>;
 }
 
@@ -289,6 +359,18 @@ pub enum StdDescr<S: DeriveSet = XpubDerivable> {
 
     #[from]
     TrTree(TrScript<S::XOnly>),
+
+    #[from]
+    TrMusig(TrMusig<S::Compr>),
+
+    #[from]
+    RawTr(RawTr<S::XOnly>),
+
+    #[from]
+    Combo(Combo<S::Legacy>),
+
+    #[from]
+    Addr(Addr),
 }
 
 impl<S: DeriveSet> From<Sh<S>> for StdDescr<S> {
@@ -336,6 +418,40 @@ impl<S: DeriveSet> From<Tr<S::XOnly>> for StdDescr<S> {
     }
 }
 
+impl<K: DeriveSet<Legacy = K, Compr = K, XOnly = K> + DeriveLegacy + DeriveCompr + DeriveXOnly>
+    StdDescr<K>
+{
+    /// Constructs the same descriptor with each key replaced by the result of `f`.
+    pub fn map_keys<M>(&self, f: impl Fn(&K) -> M) -> StdDescr<M>
+    where M: DeriveSet<Legacy = M, Compr = M, XOnly = M> + DeriveLegacy + DeriveCompr + DeriveXOnly
+    {
+        let f = &f;
+        match self {
+            StdDescr::Raw(d) => StdDescr::Raw(d.map_keys(f)),
+            StdDescr::Pkh(d) => StdDescr::Pkh(d.map_keys(f)),
+            StdDescr::ShScript(d) => StdDescr::ShScript(d.map_keys(f)),
+            StdDescr::ShMulti(d) => StdDescr::ShMulti(d.map_keys(f)),
+            StdDescr::ShSortedMulti(d) => StdDescr::ShSortedMulti(d.map_keys(f)),
+            StdDescr::Wpkh(d) => StdDescr::Wpkh(d.map_keys(f)),
+            StdDescr::WshScript(d) => StdDescr::WshScript(d.map_keys(f)),
+            StdDescr::WshMulti(d) => StdDescr::WshMulti(d.map_keys(f)),
+            StdDescr::WshSortedMulti(d) => StdDescr::WshSortedMulti(d.map_keys(f)),
+            StdDescr::ShWpkh(d) => StdDescr::ShWpkh(d.map_keys(f)),
+            StdDescr::ShWshScript(d) => StdDescr::ShWshScript(d.map_keys(f)),
+            StdDescr::ShWshMulti(d) => StdDescr::ShWshMulti(d.map_keys(f)),
+            StdDescr::ShWshSortedMulti(d) => StdDescr::ShWshSortedMulti(d.map_keys(f)),
+            StdDescr::TrKey(d) => StdDescr::TrKey(d.map_keys(f)),
+            StdDescr::TrMulti(d) => StdDescr::TrMulti(d.map_keys(f)),
+            StdDescr::TrSortedMulti(d) => StdDescr::TrSortedMulti(d.map_keys(f)),
+            StdDescr::TrTree(d) => StdDescr::TrTree(d.map_keys(f)),
+            StdDescr::TrMusig(d) => StdDescr::TrMusig(d.map_keys(f)),
+            StdDescr::RawTr(d) => StdDescr::RawTr(d.map_keys(f)),
+            StdDescr::Combo(d) => StdDescr::Combo(d.map_keys(f)),
+            StdDescr::Addr(d) => StdDescr::Addr(*d),
+        }
+    }
+}
+
 impl<S: DeriveSet> Derive<DerivedScript> for StdDescr<S> {
     fn default_keychain(&self) -> Keychain {
         match self {
@@ -356,6 +472,10 @@ impl<S: DeriveSet> Derive<DerivedScript> for StdDescr<S> {
             StdDescr::TrMulti(d) => d.default_keychain(),
             StdDescr::TrSortedMulti(d) => d.default_keychain(),
             StdDescr::TrTree(d) => d.default_keychain(),
+            StdDescr::TrMusig(d) => d.default_keychain(),
+            StdDescr::RawTr(d) => d.default_keychain(),
+            StdDescr::Combo(d) => d.default_keychain(),
+            StdDescr::Addr(d) => d.default_keychain(),
         }
     }
 
@@ -378,6 +498,10 @@ impl<S: DeriveSet> Derive<DerivedScript> for StdDescr<S> {
             StdDescr::TrMulti(d) => d.keychains(),
             StdDescr::TrSortedMulti(d) => d.keychains(),
             StdDescr::TrTree(d) => d.keychains(),
+            StdDescr::TrMusig(d) => d.keychains(),
+            StdDescr::RawTr(d) => d.keychains(),
+            StdDescr::Combo(d) => d.keychains(),
+            StdDescr::Addr(d) => d.keychains(),
         }
     }
 
@@ -408,6 +532,10 @@ impl<S: DeriveSet> Derive<DerivedScript> for StdDescr<S> {
             StdDescr::TrMulti(d) => d.derive(keychain, index).collect::<Vec<_>>().into_iter(),
             StdDescr::TrSortedMulti(d) => d.derive(keychain, index).collect::<Vec<_>>().into_iter(),
             StdDescr::TrTree(d) => d.derive(keychain, index).collect::<Vec<_>>().into_iter(),
+            StdDescr::TrMusig(d) => d.derive(keychain, index).collect::<Vec<_>>().into_iter(),
+            StdDescr::RawTr(d) => d.derive(keychain, index).collect::<Vec<_>>().into_iter(),
+            StdDescr::Combo(d) => d.derive(keychain, index).collect::<Vec<_>>().into_iter(),
+            StdDescr::Addr(d) => d.derive(keychain, index).collect::<Vec<_>>().into_iter(),
         }
     }
 }
@@ -435,6 +563,10 @@ where Self: Derive<DerivedScript>
             StdDescr::TrMulti(d) => d.class(),
             StdDescr::TrSortedMulti(d) => d.class(),
             StdDescr::TrTree(d) => d.class(),
+            StdDescr::TrMusig(d) => d.class(),
+            StdDescr::RawTr(d) => d.class(),
+            StdDescr::Combo(d) => d.class(),
+            StdDescr::Addr(d) => Descriptor::<K>::class(d),
         }
     }
 
@@ -458,6 +590,10 @@ where Self: Derive<DerivedScript>
             StdDescr::TrMulti(d) => d.keys().collect::<Vec<_>>(),
             StdDescr::TrSortedMulti(d) => d.keys().collect::<Vec<_>>(),
             StdDescr::TrTree(d) => d.keys().collect::<Vec<_>>(),
+            StdDescr::TrMusig(d) => d.keys().collect::<Vec<_>>(),
+            StdDescr::RawTr(d) => d.keys().collect::<Vec<_>>(),
+            StdDescr::Combo(d) => d.keys().collect::<Vec<_>>(),
+            StdDescr::Addr(d) => Descriptor::<K>::keys(d).collect::<Vec<_>>(),
         }
         .into_iter()
     }
@@ -486,10 +622,119 @@ where Self: Derive<DerivedScript>
             StdDescr::TrMulti(d) => d.xpubs().collect::<Vec<_>>(),
             StdDescr::TrSortedMulti(d) => d.xpubs().collect::<Vec<_>>(),
             StdDescr::TrTree(d) => d.xpubs().collect::<Vec<_>>(),
+            StdDescr::TrMusig(d) => d.xpubs().collect::<Vec<_>>(),
+            StdDescr::RawTr(d) => d.xpubs().collect::<Vec<_>>(),
+            StdDescr::Combo(d) => d.xpubs().collect::<Vec<_>>(),
+            StdDescr::Addr(d) => Descriptor::<K>::xpubs(d).collect::<Vec<_>>(),
         }
         .into_iter()
     }
 
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> {
+        match self {
+            StdDescr::Raw(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::Pkh(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::ShScript(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::ShMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::ShSortedMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::ShWpkh(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::Wpkh(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::WshScript(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::WshMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::WshSortedMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::ShWshScript(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::ShWshMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::ShWshSortedMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::TrKey(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::TrMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::TrSortedMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::TrTree(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::TrMusig(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::RawTr(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::Combo(d) => d.hash_locks().collect::<Vec<_>>(),
+            StdDescr::Addr(d) => Descriptor::<K>::hash_locks(d).collect::<Vec<_>>(),
+        }
+        .into_iter()
+    }
+
+    fn timelocks(&self) -> Vec<Timelocks> {
+        match self {
+            StdDescr::Raw(d) => d.timelocks(),
+            StdDescr::Pkh(d) => d.timelocks(),
+            StdDescr::ShScript(d) => d.timelocks(),
+            StdDescr::ShMulti(d) => d.timelocks(),
+            StdDescr::ShSortedMulti(d) => d.timelocks(),
+            StdDescr::ShWpkh(d) => d.timelocks(),
+            StdDescr::Wpkh(d) => d.timelocks(),
+            StdDescr::WshScript(d) => d.timelocks(),
+            StdDescr::WshMulti(d) => d.timelocks(),
+            StdDescr::WshSortedMulti(d) => d.timelocks(),
+            StdDescr::ShWshScript(d) => d.timelocks(),
+            StdDescr::ShWshMulti(d) => d.timelocks(),
+            StdDescr::ShWshSortedMulti(d) => d.timelocks(),
+            StdDescr::TrKey(d) => d.timelocks(),
+            StdDescr::TrMulti(d) => d.timelocks(),
+            StdDescr::TrSortedMulti(d) => d.timelocks(),
+            StdDescr::TrTree(d) => d.timelocks(),
+            StdDescr::TrMusig(d) => d.timelocks(),
+            StdDescr::RawTr(d) => d.timelocks(),
+            StdDescr::Combo(d) => d.timelocks(),
+            StdDescr::Addr(d) => Descriptor::<K>::timelocks(d),
+        }
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        match self {
+            StdDescr::Raw(d) => d.satisfaction_weights(),
+            StdDescr::Pkh(d) => d.satisfaction_weights(),
+            StdDescr::ShScript(d) => d.satisfaction_weights(),
+            StdDescr::ShMulti(d) => d.satisfaction_weights(),
+            StdDescr::ShSortedMulti(d) => d.satisfaction_weights(),
+            StdDescr::ShWpkh(d) => d.satisfaction_weights(),
+            StdDescr::Wpkh(d) => d.satisfaction_weights(),
+            StdDescr::WshScript(d) => d.satisfaction_weights(),
+            StdDescr::WshMulti(d) => d.satisfaction_weights(),
+            StdDescr::WshSortedMulti(d) => d.satisfaction_weights(),
+            StdDescr::ShWshScript(d) => d.satisfaction_weights(),
+            StdDescr::ShWshMulti(d) => d.satisfaction_weights(),
+            StdDescr::ShWshSortedMulti(d) => d.satisfaction_weights(),
+            StdDescr::TrKey(d) => d.satisfaction_weights(),
+            StdDescr::TrMulti(d) => d.satisfaction_weights(),
+            StdDescr::TrSortedMulti(d) => d.satisfaction_weights(),
+            StdDescr::TrTree(d) => d.satisfaction_weights(),
+            StdDescr::TrMusig(d) => d.satisfaction_weights(),
+            StdDescr::RawTr(d) => d.satisfaction_weights(),
+            StdDescr::Combo(d) => d.satisfaction_weights(),
+            StdDescr::Addr(d) => Descriptor::<K>::satisfaction_weights(d),
+        }
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        match self {
+            StdDescr::Raw(d) => d.policies(),
+            StdDescr::Pkh(d) => d.policies(),
+            StdDescr::ShScript(d) => d.policies(),
+            StdDescr::ShMulti(d) => d.policies(),
+            StdDescr::ShSortedMulti(d) => d.policies(),
+            StdDescr::ShWpkh(d) => d.policies(),
+            StdDescr::Wpkh(d) => d.policies(),
+            StdDescr::WshScript(d) => d.policies(),
+            StdDescr::WshMulti(d) => d.policies(),
+            StdDescr::WshSortedMulti(d) => d.policies(),
+            StdDescr::ShWshScript(d) => d.policies(),
+            StdDescr::ShWshMulti(d) => d.policies(),
+            StdDescr::ShWshSortedMulti(d) => d.policies(),
+            StdDescr::TrKey(d) => d.policies(),
+            StdDescr::TrMulti(d) => d.policies(),
+            StdDescr::TrSortedMulti(d) => d.policies(),
+            StdDescr::TrTree(d) => d.policies(),
+            StdDescr::TrMusig(d) => d.policies(),
+            StdDescr::RawTr(d) => d.policies(),
+            StdDescr::Combo(d) => d.policies(),
+            StdDescr::Addr(d) => Descriptor::<K>::policies(d),
+        }
+    }
+
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         match self {
             StdDescr::Raw(d) => d.legacy_keyset(terminal),
@@ -509,6 +754,10 @@ where Self: Derive<DerivedScript>
             StdDescr::TrMulti(d) => d.legacy_keyset(terminal),
             StdDescr::TrSortedMulti(d) => d.legacy_keyset(terminal),
             StdDescr::TrTree(d) => d.legacy_keyset(terminal),
+            StdDescr::TrMusig(d) => d.legacy_keyset(terminal),
+            StdDescr::RawTr(d) => d.legacy_keyset(terminal),
+            StdDescr::Combo(d) => d.legacy_keyset(terminal),
+            StdDescr::Addr(d) => Descriptor::<K>::legacy_keyset(d, terminal),
         }
     }
 
@@ -531,35 +780,97 @@ where Self: Derive<DerivedScript>
             StdDescr::TrMulti(d) => d.xonly_keyset(terminal),
             StdDescr::TrSortedMulti(d) => d.xonly_keyset(terminal),
             StdDescr::TrTree(d) => d.xonly_keyset(terminal),
+            StdDescr::TrMusig(d) => d.xonly_keyset(terminal),
+            StdDescr::RawTr(d) => d.xonly_keyset(terminal),
+            StdDescr::Combo(d) => d.xonly_keyset(terminal),
+            StdDescr::Addr(d) => Descriptor::<K>::xonly_keyset(d, terminal),
+        }
+    }
+
+    fn musig_keyset(&self, terminal: Terminal) -> IndexMap<CompressedPk, Vec<CompressedPk>> {
+        match self {
+            StdDescr::TrMusig(d) => d.musig_keyset(terminal),
+            _ => IndexMap::new(),
         }
     }
 
     fn legacy_witness(
         &self,
+        script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
         match self {
-            StdDescr::Raw(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::Pkh(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::ShScript(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::ShMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::ShSortedMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::ShWpkh(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::Wpkh(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::WshScript(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::WshMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::WshSortedMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::ShWshScript(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::ShWshMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
+            StdDescr::Raw(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::Pkh(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::ShScript(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::ShMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::ShSortedMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::ShWpkh(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::Wpkh(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::WshScript(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::WshMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::WshSortedMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::ShWshScript(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::ShWshMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
             StdDescr::ShWshSortedMulti(d) => {
-                d.legacy_witness(keysigs, redeem_script, witness_script)
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::TrKey(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::TrMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::TrSortedMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::TrTree(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::TrMusig(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::RawTr(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            StdDescr::Combo(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
             }
-            StdDescr::TrKey(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::TrMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::TrSortedMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            StdDescr::TrTree(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
+            StdDescr::Addr(d) => Descriptor::<K>::legacy_witness(
+                d,
+                script_pubkey,
+                keysigs,
+                preimages,
+                redeem_script,
+                witness_script,
+            ),
         }
     }
 
@@ -567,25 +878,30 @@ where Self: Derive<DerivedScript>
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         match self {
-            StdDescr::Raw(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::Pkh(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::ShScript(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::ShMulti(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::ShSortedMulti(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::ShWpkh(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::Wpkh(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::WshScript(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::WshMulti(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::WshSortedMulti(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::ShWshScript(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::ShWshMulti(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::ShWshSortedMulti(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::TrKey(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::TrMulti(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::TrSortedMulti(d) => d.taproot_witness(cb, keysigs),
-            StdDescr::TrTree(d) => d.taproot_witness(cb, keysigs),
+            StdDescr::Raw(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::Pkh(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::ShScript(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::ShMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::ShSortedMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::ShWpkh(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::Wpkh(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::WshScript(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::WshMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::WshSortedMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::ShWshScript(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::ShWshMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::ShWshSortedMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::TrKey(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::TrMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::TrSortedMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::TrTree(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::TrMusig(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::RawTr(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::Combo(d) => d.taproot_witness(cb, keysigs, preimages),
+            StdDescr::Addr(d) => Descriptor::<K>::taproot_witness(d, cb, keysigs, preimages),
         }
     }
 }
@@ -615,14 +931,37 @@ where
             StdDescr::TrMulti(d) => Display::fmt(d, f),
             StdDescr::TrSortedMulti(d) => Display::fmt(d, f),
             StdDescr::TrTree(d) => Display::fmt(d, f),
+            StdDescr::TrMusig(d) => Display::fmt(d, f),
+            StdDescr::RawTr(d) => Display::fmt(d, f),
+            StdDescr::Combo(d) => Display::fmt(d, f),
+            StdDescr::Addr(d) => Display::fmt(d, f),
         }
     }
 }
 
+impl StdDescr<XprivDerivable> {
+    /// Converts private descriptor into the public one, replacing all extended private keys with
+    /// their extended public keys while preserving derivation paths.
+    pub fn to_public(&self) -> StdDescr<XpubDerivable> {
+        self.map_keys(XprivDerivable::to_xpub_derivable)
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use derive::{HardenedIndex, XprivAccount};
+
     use super::*;
 
+    fn xpriv(account: u16) -> XprivDerivable {
+        let xpriv = XprivAccount::with_seed(true, &[0xA5; 32]).derive([
+            HardenedIndex::from(84u16),
+            HardenedIndex::ONE,
+            HardenedIndex::from(account),
+        ]);
+        XprivDerivable::with(xpriv, &[Keychain::OUTER, Keychain::INNER])
+    }
+
     #[test]
     fn descr_id_baid64() {
         let descr_id = DescrId::from([0xde, 0xad, 0xbe, 0xef, 0xbe, 0xad, 0xca, 0xfe]);
@@ -630,4 +969,35 @@ mod test {
         assert_eq!(s, "deadbeef-beadcafe");
         assert_eq!(DescrId::from_str(&s).unwrap(), descr_id);
     }
+
+    #[test]
+    fn private_to_public() {
+        let (key1, key2) = (xpriv(0), xpriv(1));
+        let (pub1, pub2) = (key1.to_xpub_derivable(), key2.to_xpub_derivable());
+        for (private, public) in [
+            (format!("pkh({key1})"), format!("pkh({pub1})")),
+            (format!("wpkh({key1})"), format!("wpkh({pub1})")),
+            (
+                format!("sh(sortedmulti(1,{key1},{key2}))"),
+                format!("sh(sortedmulti(1,{pub1},{pub2}))"),
+            ),
+            (format!("wsh(multi(2,{key1},{key2}))"), format!("wsh(multi(2,{pub1},{pub2}))")),
+            (format!("sh(wpkh({key1}))"), format!("sh(wpkh({pub1}))")),
+            (format!("tr({key1})"), format!("tr({pub1})")),
+            (
+                format!("wsh(and_v(v:pk({key1}),older(144)))"),
+                format!("wsh(and_v(v:pk({pub1}),older(144)))"),
+            ),
+            (
+                format!("tr({key1},{{pk({key2}),and_v(v:pk({key1}),older(10))}})"),
+                format!("tr({pub1},{{pk({pub2}),and_v(v:pk({pub1}),older(10))}})"),
+            ),
+        ] {
+            let descr = StdDescr::<XprivDerivable>::from_str(&private).unwrap();
+            assert_eq!(descr.to_string(), private);
+            let descr = descr.to_public();
+            assert_eq!(descr.to_string(), public);
+            assert_eq!(descr, StdDescr::<XpubDerivable>::from_str(&public).unwrap());
+        }
+    }
 }
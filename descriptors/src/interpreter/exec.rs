@@ -0,0 +1,986 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use commit_verify::{Digest, Ripemd160, Sha256};
+use derive::secp256k1::{ecdsa, schnorr, PublicKey, XOnlyPublicKey, SECP256K1};
+use derive::{
+    Annex, OpCode, Sats, ScriptBytes, ScriptCode, ScriptPubkey, SighashCache, SighashType,
+    TapLeafHash, Tx, TxOut,
+};
+use sha1::Sha1;
+
+use super::{
+    ScriptFailure, VerifyFlags, MAX_OPS_PER_SCRIPT, MAX_PUBKEYS_PER_MULTISIG,
+    MAX_SCRIPT_ELEMENT_SIZE, MAX_SCRIPT_SIZE, MAX_STACK_SIZE,
+};
+
+const OP_CHECKSIGADD: u8 = 0xba;
+const VALIDATION_WEIGHT_PER_SIGOP: i64 = 50;
+const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+const SEQUENCE_DISABLE_FLAG: i64 = 1 << 31;
+const SEQUENCE_TYPE_FLAG: i64 = 1 << 22;
+const SEQUENCE_MASK: i64 = 0x0000_ffff;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(super) enum SigVersion {
+    Base,
+    WitnessV0,
+    Tapscript,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(super) struct OpFailure {
+    pub opcode: Option<u8>,
+    pub failure: ScriptFailure,
+}
+
+impl From<ScriptFailure> for OpFailure {
+    fn from(failure: ScriptFailure) -> Self {
+        OpFailure {
+            opcode: None,
+            failure,
+        }
+    }
+}
+
+/// Data on the spent tapscript leaf and the state of its execution.
+pub(super) struct TapscriptState {
+    pub leaf_hash: TapLeafHash,
+    pub annex: Option<Annex>,
+    /// Opcode position of the last executed `OP_CODESEPARATOR`, `u32::MAX` if none.
+    pub codesep_pos: u32,
+    pub weight_left: i64,
+}
+
+/// Transaction input which is being verified.
+pub(super) struct Context<'c, 'a> {
+    pub tx: &'a Tx,
+    pub index: usize,
+    pub amount: Sats,
+    pub sig_hasher: &'c mut SighashCache<&'a TxOut, &'a Tx>,
+}
+
+impl Context<'_, '_> {
+    fn check_ecdsa(
+        &mut self,
+        sig: &[u8],
+        pubkey: &[u8],
+        script_code: &[u8],
+        version: SigVersion,
+    ) -> bool {
+        let Some((sighash_type, sig)) = sig.split_last() else {
+            return false;
+        };
+        let Ok(pubkey) = PublicKey::from_slice(pubkey) else {
+            return false;
+        };
+        let Ok(sighash_type) = SighashType::from_standard_u32(*sighash_type as u32) else {
+            return false;
+        };
+        let sighash = match version {
+            SigVersion::Base => {
+                let script_code = ScriptPubkey::from_checked(strip_codeseparators(script_code));
+                self.sig_hasher.legacy_sighash(self.index, &script_code, sighash_type)
+            }
+            SigVersion::WitnessV0 => {
+                let script_code = ScriptCode::from(ScriptBytes::from_checked(script_code.to_vec()));
+                self.sig_hasher.segwit_sighash(self.index, &script_code, self.amount, sighash_type)
+            }
+            SigVersion::Tapscript => unreachable!("ECDSA signatures are not used in tapscript"),
+        };
+        let Ok(sighash) = sighash else {
+            return false;
+        };
+        let Ok(mut sig) = ecdsa::Signature::from_der_lax(sig) else {
+            return false;
+        };
+        // Consensus rules accept signatures with high S values, while libsecp256k1 doesn't
+        sig.normalize_s();
+        SECP256K1.verify_ecdsa(&sighash.into(), &sig, &pubkey).is_ok()
+    }
+
+    pub fn check_schnorr(
+        &mut self,
+        sig: &[u8],
+        pubkey: &[u8],
+        annex: Option<Annex>,
+        leaf: Option<(TapLeafHash, u32)>,
+    ) -> Result<(), ScriptFailure> {
+        let (sig, sighash_type) = match sig.len() {
+            64 => (sig, None),
+            65 => {
+                let sighash_type = SighashType::from_standard_u32(sig[64] as u32)
+                    .map_err(|_| ScriptFailure::SchnorrSigHashType)?;
+                (&sig[..64], Some(sighash_type))
+            }
+            _ => return Err(ScriptFailure::SchnorrSigSize),
+        };
+        let sighash = self
+            .sig_hasher
+            .tap_sighash_custom(self.index, annex, leaf, sighash_type)
+            .map_err(|_| ScriptFailure::SchnorrSigHashType)?;
+        let pubkey = XOnlyPublicKey::from_slice(pubkey).map_err(|_| ScriptFailure::SchnorrSig)?;
+        let sig = schnorr::Signature::from_slice(sig).map_err(|_| ScriptFailure::SchnorrSig)?;
+        SECP256K1
+            .verify_schnorr(&sig, sighash.as_ref(), &pubkey)
+            .map_err(|_| ScriptFailure::SchnorrSig)
+    }
+
+    fn check_lock_time(&self, lock_time: i64) -> bool {
+        let tx_lock_time = self.tx.lock_time.to_consensus_u32() as i64;
+        if (tx_lock_time < LOCKTIME_THRESHOLD) != (lock_time < LOCKTIME_THRESHOLD) {
+            return false;
+        }
+        if lock_time > tx_lock_time {
+            return false;
+        }
+        // Final inputs make the transaction lock time ineffective
+        self.tx.inputs[self.index].sequence.to_consensus_u32() != u32::MAX
+    }
+
+    fn check_sequence(&self, sequence: i64) -> bool {
+        let tx_sequence = self.tx.inputs[self.index].sequence.to_consensus_u32() as i64;
+        if self.tx.version.to_consensus_i32() < 2 || tx_sequence & SEQUENCE_DISABLE_FLAG != 0 {
+            return false;
+        }
+        let mask = SEQUENCE_TYPE_FLAG | SEQUENCE_MASK;
+        let tx_sequence = tx_sequence & mask;
+        let sequence = sequence & mask;
+        if (tx_sequence < SEQUENCE_TYPE_FLAG) != (sequence < SEQUENCE_TYPE_FLAG) {
+            return false;
+        }
+        sequence <= tx_sequence
+    }
+}
+
+/// Evaluates `script` over the provided `stack`.
+pub(super) fn eval_script(
+    stack: &mut Vec<Vec<u8>>,
+    script: &[u8],
+    version: SigVersion,
+    flags: &VerifyFlags,
+    ctx: &mut Context,
+    tapscript: Option<&mut TapscriptState>,
+) -> Result<(), OpFailure> {
+    if version != SigVersion::Tapscript && script.len() > MAX_SCRIPT_SIZE {
+        return Err(ScriptFailure::ScriptSize.into());
+    }
+    let mut exec = Exec {
+        script,
+        version,
+        flags,
+        ctx,
+        tapscript,
+        stack,
+        altstack: vec![],
+        conditions: vec![],
+        op_count: 0,
+        code_start: 0,
+    };
+    exec.run()
+}
+
+struct Exec<'e, 'c, 'a> {
+    script: &'e [u8],
+    version: SigVersion,
+    flags: &'e VerifyFlags,
+    ctx: &'e mut Context<'c, 'a>,
+    tapscript: Option<&'e mut TapscriptState>,
+    stack: &'e mut Vec<Vec<u8>>,
+    altstack: Vec<Vec<u8>>,
+    conditions: Vec<bool>,
+    op_count: usize,
+    /// Start of the script code used in signature hashes, updated by `OP_CODESEPARATOR`.
+    code_start: usize,
+}
+
+impl Exec<'_, '_, '_> {
+    fn run(&mut self) -> Result<(), OpFailure> {
+        let mut pc = 0usize;
+        let mut op_pos = 0u32;
+        while pc < self.script.len() {
+            let opcode = self.script[pc];
+            let fail = |failure| OpFailure {
+                opcode: Some(opcode),
+                failure,
+            };
+            let (_, data) = read_op(self.script, &mut pc).ok_or(fail(ScriptFailure::BadOpcode))?;
+            self.step(opcode, data, pc, op_pos).map_err(fail)?;
+            op_pos += 1;
+        }
+        if !self.conditions.is_empty() {
+            return Err(ScriptFailure::UnbalancedConditional.into());
+        }
+        Ok(())
+    }
+
+    fn step(
+        &mut self,
+        opcode: u8,
+        data: &[u8],
+        pc: usize,
+        op_pos: u32,
+    ) -> Result<(), ScriptFailure> {
+        let executing = self.conditions.iter().all(|cond| *cond);
+
+        if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+            return Err(ScriptFailure::PushSize);
+        }
+        if self.version != SigVersion::Tapscript {
+            if opcode > OpCode::PushNum16 as u8 {
+                self.op_count += 1;
+                if self.op_count > MAX_OPS_PER_SCRIPT {
+                    return Err(ScriptFailure::OpCount);
+                }
+            }
+            if is_disabled(opcode) {
+                return Err(ScriptFailure::DisabledOpcode);
+            }
+        }
+
+        if executing && opcode <= OpCode::PushData4 as u8 {
+            if self.flags.minimal_data && !is_minimal_push(opcode, data) {
+                return Err(ScriptFailure::MinimalData);
+            }
+            self.stack.push(data.to_vec());
+        } else if executing || (OpCode::If as u8..=OpCode::EndIf as u8).contains(&opcode) {
+            self.execute(opcode, executing, pc, op_pos)?;
+        }
+
+        if self.stack.len() + self.altstack.len() > MAX_STACK_SIZE {
+            return Err(ScriptFailure::StackSize);
+        }
+        Ok(())
+    }
+
+    fn execute(
+        &mut self,
+        opcode: u8,
+        executing: bool,
+        pc: usize,
+        op_pos: u32,
+    ) -> Result<(), ScriptFailure> {
+        let tapscript = self.version == SigVersion::Tapscript;
+        if tapscript && opcode == OP_CHECKSIGADD {
+            return self.checksigadd();
+        }
+        if (OpCode::PushNum1 as u8..=OpCode::PushNum16 as u8).contains(&opcode) {
+            self.push_num((opcode - OpCode::PushNum1 as u8) as i64 + 1);
+            return Ok(());
+        }
+
+        let op = OpCode::try_from(opcode).map_err(|_| ScriptFailure::BadOpcode)?;
+        match op {
+            OpCode::PushNumNeg1 => self.push_num(-1),
+            OpCode::Nop => {}
+
+            OpCode::Cltv if self.flags.check_lock_time_verify => {
+                let lock_time = decode_num(self.top(1)?, self.flags.minimal_data, 5)?;
+                if lock_time < 0 {
+                    return Err(ScriptFailure::NegativeLocktime);
+                }
+                if !self.ctx.check_lock_time(lock_time) {
+                    return Err(ScriptFailure::UnsatisfiedLocktime);
+                }
+            }
+            OpCode::Csv if self.flags.check_sequence_verify => {
+                let sequence = decode_num(self.top(1)?, self.flags.minimal_data, 5)?;
+                if sequence < 0 {
+                    return Err(ScriptFailure::NegativeLocktime);
+                }
+                if sequence & SEQUENCE_DISABLE_FLAG == 0 && !self.ctx.check_sequence(sequence) {
+                    return Err(ScriptFailure::UnsatisfiedLocktime);
+                }
+            }
+            // Before BIP-65 and BIP-112 activation these were `OP_NOP2` and `OP_NOP3`
+            OpCode::Cltv | OpCode::Csv => {}
+            OpCode::Nop1
+            | OpCode::Nop4
+            | OpCode::Nop5
+            | OpCode::Nop6
+            | OpCode::Nop7
+            | OpCode::Nop8
+            | OpCode::Nop9
+            | OpCode::Nop10 => {
+                if self.flags.discourage_upgradable_nops {
+                    return Err(ScriptFailure::DiscourageUpgradableNops);
+                }
+            }
+
+            OpCode::If | OpCode::NotIf => {
+                let mut value = false;
+                if executing {
+                    let top = self.top(1).map_err(|_| ScriptFailure::UnbalancedConditional)?;
+                    let minimal_if = tapscript
+                        || (self.version == SigVersion::WitnessV0 && self.flags.minimal_if);
+                    if minimal_if && (top.len() > 1 || (top.len() == 1 && top[0] != 1)) {
+                        return Err(if tapscript {
+                            ScriptFailure::TapscriptMinimalIf
+                        } else {
+                            ScriptFailure::MinimalIf
+                        });
+                    }
+                    value = cast_to_bool(top) == (op == OpCode::If);
+                    self.pop()?;
+                }
+                self.conditions.push(value);
+            }
+            OpCode::Else => {
+                let cond =
+                    self.conditions.last_mut().ok_or(ScriptFailure::UnbalancedConditional)?;
+                *cond = !*cond;
+            }
+            OpCode::EndIf => {
+                self.conditions.pop().ok_or(ScriptFailure::UnbalancedConditional)?;
+            }
+            OpCode::Verify => {
+                if !cast_to_bool(self.top(1)?) {
+                    return Err(ScriptFailure::VerifyFailed);
+                }
+                self.pop()?;
+            }
+            OpCode::Return => return Err(ScriptFailure::OpReturn),
+
+            OpCode::ToAltStack => {
+                let elem = self.pop()?;
+                self.altstack.push(elem);
+            }
+            OpCode::FromAltStack => {
+                let elem = self.altstack.pop().ok_or(ScriptFailure::InvalidAltstackOperation)?;
+                self.stack.push(elem);
+            }
+            OpCode::Drop2 => {
+                self.require(2)?;
+                self.stack.truncate(self.stack.len() - 2);
+            }
+            OpCode::Dup2 => self.copy(2, 2)?,
+            OpCode::Dup3 => self.copy(3, 3)?,
+            OpCode::Over2 => self.copy(4, 2)?,
+            OpCode::Rot2 => {
+                self.require(6)?;
+                let len = self.stack.len();
+                let elems = self.stack.drain(len - 6..len - 4).collect::<Vec<_>>();
+                self.stack.extend(elems);
+            }
+            OpCode::Swap2 => {
+                self.require(4)?;
+                let len = self.stack.len();
+                self.stack.swap(len - 4, len - 2);
+                self.stack.swap(len - 3, len - 1);
+            }
+            OpCode::IfDup => {
+                let top = self.top(1)?.clone();
+                if cast_to_bool(&top) {
+                    self.stack.push(top);
+                }
+            }
+            OpCode::Depth => self.push_num(self.stack.len() as i64),
+            OpCode::Drop => {
+                self.pop()?;
+            }
+            OpCode::Dup => self.copy(1, 1)?,
+            OpCode::Nip => {
+                self.require(2)?;
+                self.stack.remove(self.stack.len() - 2);
+            }
+            OpCode::Over => self.copy(2, 1)?,
+            OpCode::Pick | OpCode::Roll => {
+                self.require(2)?;
+                let depth = self.pop_num()?;
+                if depth < 0 || depth as usize >= self.stack.len() {
+                    return Err(ScriptFailure::InvalidStackOperation);
+                }
+                let index = self.stack.len() - 1 - depth as usize;
+                let elem = match op {
+                    OpCode::Roll => self.stack.remove(index),
+                    _ => self.stack[index].clone(),
+                };
+                self.stack.push(elem);
+            }
+            OpCode::Rot => {
+                self.require(3)?;
+                let elem = self.stack.remove(self.stack.len() - 3);
+                self.stack.push(elem);
+            }
+            OpCode::Swap => {
+                self.require(2)?;
+                let len = self.stack.len();
+                self.stack.swap(len - 2, len - 1);
+            }
+            OpCode::Tuck => {
+                self.require(2)?;
+                let top = self.top(1)?.clone();
+                self.stack.insert(self.stack.len() - 2, top);
+            }
+            OpCode::Size => self.push_num(self.top(1)?.len() as i64),
+
+            OpCode::Equal | OpCode::EqualVerify => {
+                self.require(2)?;
+                let equal = self.pop()? == self.pop()?;
+                if op == OpCode::Equal {
+                    self.push_bool(equal);
+                } else if !equal {
+                    return Err(ScriptFailure::VerifyFailed);
+                }
+            }
+
+            OpCode::Add1
+            | OpCode::Sub1
+            | OpCode::Negate
+            | OpCode::Abs
+            | OpCode::Not
+            | OpCode::NotEqual0 => {
+                let a = self.pop_num()?;
+                let res = match op {
+                    OpCode::Add1 => a + 1,
+                    OpCode::Sub1 => a - 1,
+                    OpCode::Negate => -a,
+                    OpCode::Abs => a.abs(),
+                    OpCode::Not => (a == 0) as i64,
+                    _ => (a != 0) as i64,
+                };
+                self.push_num(res);
+            }
+            OpCode::Add
+            | OpCode::Sub
+            | OpCode::BoolAnd
+            | OpCode::BoolOr
+            | OpCode::NumEqual
+            | OpCode::NumEqualVerify
+            | OpCode::NumNotEqual
+            | OpCode::LessThan
+            | OpCode::GreaterThan
+            | OpCode::LessThanOrEqual
+            | OpCode::GreaterThanOrEqual
+            | OpCode::Min
+            | OpCode::Max => {
+                self.require(2)?;
+                let b = self.pop_num()?;
+                let a = self.pop_num()?;
+                let res = match op {
+                    OpCode::Add => a + b,
+                    OpCode::Sub => a - b,
+                    OpCode::BoolAnd => (a != 0 && b != 0) as i64,
+                    OpCode::BoolOr => (a != 0 || b != 0) as i64,
+                    OpCode::NumEqual | OpCode::NumEqualVerify => (a == b) as i64,
+                    OpCode::NumNotEqual => (a != b) as i64,
+                    OpCode::LessThan => (a < b) as i64,
+                    OpCode::GreaterThan => (a > b) as i64,
+                    OpCode::LessThanOrEqual => (a <= b) as i64,
+                    OpCode::GreaterThanOrEqual => (a >= b) as i64,
+                    OpCode::Min => a.min(b),
+                    _ => a.max(b),
+                };
+                if op != OpCode::NumEqualVerify {
+                    self.push_num(res);
+                } else if res == 0 {
+                    return Err(ScriptFailure::VerifyFailed);
+                }
+            }
+            OpCode::Within => {
+                self.require(3)?;
+                let max = self.pop_num()?;
+                let min = self.pop_num()?;
+                let x = self.pop_num()?;
+                self.push_bool(min <= x && x < max);
+            }
+
+            OpCode::Ripemd160
+            | OpCode::Sha1
+            | OpCode::Sha256
+            | OpCode::Hash160
+            | OpCode::Hash256 => {
+                let data = self.pop()?;
+                let hash = match op {
+                    OpCode::Ripemd160 => Ripemd160::digest(&data).to_vec(),
+                    OpCode::Sha1 => Sha1::digest(&data).to_vec(),
+                    OpCode::Sha256 => sha256(&data).to_vec(),
+                    OpCode::Hash160 => Ripemd160::digest(sha256(&data)).to_vec(),
+                    _ => sha256(&sha256(&data)).to_vec(),
+                };
+                self.stack.push(hash);
+            }
+
+            OpCode::CodeSeparator => {
+                self.code_start = pc;
+                if let Some(tapscript) = self.tapscript.as_mut() {
+                    tapscript.codesep_pos = op_pos;
+                }
+            }
+            OpCode::CheckSig | OpCode::CheckSigVerify => {
+                self.require(2)?;
+                let pubkey = self.top(1)?.clone();
+                let sig = self.top(2)?.clone();
+                let success = if tapscript {
+                    self.check_tapscript_sig(&sig, &pubkey)?
+                } else {
+                    let script_code = self.script_code(&[&sig]);
+                    let success = self.check_ecdsa_sig(&sig, &pubkey, &script_code)?;
+                    if !success && self.flags.null_fail && !sig.is_empty() {
+                        return Err(ScriptFailure::SigNullFail);
+                    }
+                    success
+                };
+                self.pop()?;
+                self.pop()?;
+                if op == OpCode::CheckSig {
+                    self.push_bool(success);
+                } else if !success {
+                    return Err(ScriptFailure::VerifyFailed);
+                }
+            }
+            OpCode::CheckMultiSig | OpCode::CheckMultisigVerify if tapscript => {
+                return Err(ScriptFailure::TapscriptCheckMultisig);
+            }
+            OpCode::CheckMultiSig | OpCode::CheckMultisigVerify => {
+                let success = self.checkmultisig()?;
+                if op == OpCode::CheckMultiSig {
+                    self.push_bool(success);
+                } else if !success {
+                    return Err(ScriptFailure::VerifyFailed);
+                }
+            }
+
+            _ => return Err(ScriptFailure::BadOpcode),
+        }
+        Ok(())
+    }
+
+    fn checkmultisig(&mut self) -> Result<bool, ScriptFailure> {
+        let minimal = self.flags.minimal_data;
+
+        let mut i = 1;
+        let mut key_count = decode_num(self.top(i)?, minimal, 4)?;
+        if !(0..=MAX_PUBKEYS_PER_MULTISIG as i64).contains(&key_count) {
+            return Err(ScriptFailure::PubkeyCount);
+        }
+        self.op_count += key_count as usize;
+        if self.op_count > MAX_OPS_PER_SCRIPT {
+            return Err(ScriptFailure::OpCount);
+        }
+        i += 1;
+        let mut key_pos = i;
+        // Position of the last key which must be checked for the NULLFAIL rule
+        let mut nullfail_keys = key_count as usize + 2;
+        i += key_count as usize;
+
+        let mut sig_count = decode_num(self.top(i)?, minimal, 4)?;
+        if sig_count < 0 || sig_count > key_count {
+            return Err(ScriptFailure::SigCount);
+        }
+        i += 1;
+        let mut sig_pos = i;
+        i += sig_count as usize;
+        self.require(i)?;
+
+        let sigs = (0..sig_count as usize)
+            .map(|n| self.top(sig_pos + n).cloned())
+            .collect::<Result<Vec<_>, _>>()?;
+        let script_code = self.script_code(&sigs.iter().map(Vec::as_slice).collect::<Vec<_>>());
+
+        let mut success = true;
+        while success && sig_count > 0 {
+            let sig = self.top(sig_pos)?.clone();
+            let pubkey = self.top(key_pos)?.clone();
+            if self.check_ecdsa_sig(&sig, &pubkey, &script_code)? {
+                sig_pos += 1;
+                sig_count -= 1;
+            }
+            key_pos += 1;
+            key_count -= 1;
+            // There are not enough keys left to check the remaining signatures
+            if sig_count > key_count {
+                success = false;
+            }
+        }
+
+        // Clean up all the arguments except the dummy one
+        while i > 1 {
+            if !success && self.flags.null_fail && nullfail_keys == 0 && !self.top(1)?.is_empty() {
+                return Err(ScriptFailure::SigNullFail);
+            }
+            nullfail_keys = nullfail_keys.saturating_sub(1);
+            self.pop()?;
+            i -= 1;
+        }
+
+        // The dummy element consumed due to the off-by-one bug in the original implementation
+        let dummy = self.pop()?;
+        if self.flags.null_dummy && !dummy.is_empty() {
+            return Err(ScriptFailure::SigNullDummy);
+        }
+        Ok(success)
+    }
+
+    fn checksigadd(&mut self) -> Result<(), ScriptFailure> {
+        self.require(3)?;
+        let pubkey = self.top(1)?.clone();
+        let num = decode_num(self.top(2)?, self.flags.minimal_data, 4)?;
+        let sig = self.top(3)?.clone();
+        let success = self.check_tapscript_sig(&sig, &pubkey)?;
+        self.stack.truncate(self.stack.len() - 3);
+        self.push_num(num + success as i64);
+        Ok(())
+    }
+
+    fn check_ecdsa_sig(
+        &mut self,
+        sig: &[u8],
+        pubkey: &[u8],
+        script_code: &[u8],
+    ) -> Result<bool, ScriptFailure> {
+        self.check_sig_encoding(sig)?;
+        self.check_pubkey_encoding(pubkey)?;
+        Ok(self.ctx.check_ecdsa(sig, pubkey, script_code, self.version))
+    }
+
+    fn check_tapscript_sig(&mut self, sig: &[u8], pubkey: &[u8]) -> Result<bool, ScriptFailure> {
+        let tapscript = self.tapscript.as_mut().expect("tapscript execution state");
+        let success = !sig.is_empty();
+        if success {
+            tapscript.weight_left -= VALIDATION_WEIGHT_PER_SIGOP;
+            if tapscript.weight_left < 0 {
+                return Err(ScriptFailure::TapscriptValidationWeight);
+            }
+        }
+        match pubkey.len() {
+            0 => return Err(ScriptFailure::PubkeyType),
+            32 if success => {
+                let leaf = Some((tapscript.leaf_hash, tapscript.codesep_pos));
+                self.ctx.check_schnorr(sig, pubkey, tapscript.annex.clone(), leaf)?;
+            }
+            32 => {}
+            _ if self.flags.discourage_upgradable_pubkey_type => {
+                return Err(ScriptFailure::DiscourageUpgradablePubkeyType);
+            }
+            _ => {}
+        }
+        Ok(success)
+    }
+
+    fn check_sig_encoding(&self, sig: &[u8]) -> Result<(), ScriptFailure> {
+        // Empty signatures are allowed to fail the check without failing the script
+        if sig.is_empty() {
+            return Ok(());
+        }
+        if (self.flags.der_sig || self.flags.low_s || self.flags.strict_enc)
+            && !is_valid_der_sig(sig)
+        {
+            return Err(ScriptFailure::SigDer);
+        }
+        if self.flags.low_s && !is_low_s_sig(sig) {
+            return Err(ScriptFailure::SigHighS);
+        }
+        if self.flags.strict_enc && !matches!(sig[sig.len() - 1] & !0x80, 0x01..=0x03) {
+            return Err(ScriptFailure::SigHashType);
+        }
+        Ok(())
+    }
+
+    fn check_pubkey_encoding(&self, pubkey: &[u8]) -> Result<(), ScriptFailure> {
+        let compressed = pubkey.len() == 33 && matches!(pubkey[0], 0x02 | 0x03);
+        let uncompressed = pubkey.len() == 65 && pubkey[0] == 0x04;
+        if self.flags.strict_enc && !compressed && !uncompressed {
+            return Err(ScriptFailure::PubkeyType);
+        }
+        if self.flags.witness_pubkey_type && self.version == SigVersion::WitnessV0 && !compressed {
+            return Err(ScriptFailure::WitnessPubkeyType);
+        }
+        Ok(())
+    }
+
+    /// Script code for ECDSA signature hashes, starting after the last executed
+    /// `OP_CODESEPARATOR`. Legacy scripts can't commit to their own signatures, so the pushes of
+    /// the checked signatures are removed from the code.
+    fn script_code(&self, sigs: &[&[u8]]) -> Vec<u8> {
+        let mut script_code = self.script[self.code_start..].to_vec();
+        if self.version == SigVersion::Base {
+            for sig in sigs {
+                script_code = find_and_delete(&script_code, &push_data(sig));
+            }
+        }
+        script_code
+    }
+
+    fn require(&self, depth: usize) -> Result<(), ScriptFailure> {
+        if self.stack.len() < depth {
+            return Err(ScriptFailure::InvalidStackOperation);
+        }
+        Ok(())
+    }
+
+    /// Returns stack element at the given `depth`, where the top element has depth 1.
+    fn top(&self, depth: usize) -> Result<&Vec<u8>, ScriptFailure> {
+        self.require(depth)?;
+        Ok(&self.stack[self.stack.len() - depth])
+    }
+
+    /// Pushes copies of `count` elements starting at the given `depth`.
+    fn copy(&mut self, depth: usize, count: usize) -> Result<(), ScriptFailure> {
+        self.require(depth)?;
+        let start = self.stack.len() - depth;
+        let elems = self.stack[start..start + count].to_vec();
+        self.stack.extend(elems);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Vec<u8>, ScriptFailure> {
+        self.stack.pop().ok_or(ScriptFailure::InvalidStackOperation)
+    }
+
+    fn pop_num(&mut self) -> Result<i64, ScriptFailure> {
+        let elem = self.pop()?;
+        decode_num(&elem, self.flags.minimal_data, 4)
+    }
+
+    fn push_num(&mut self, num: i64) { self.stack.push(encode_num(num)); }
+
+    fn push_bool(&mut self, value: bool) { self.stack.push(if value { vec![1] } else { vec![] }); }
+}
+
+/// Reads the next operation from the `script` at position `pc`, advancing it.
+///
+/// # Returns
+///
+/// Opcode with the pushed data (empty for non-push opcodes), or `None` if the script ends
+/// or the push exceeds the script length.
+pub(super) fn read_op<'s>(script: &'s [u8], pc: &mut usize) -> Option<(u8, &'s [u8])> {
+    let opcode = *script.get(*pc)?;
+    *pc += 1;
+    let len_bytes = match opcode {
+        op if op < OpCode::PushData1 as u8 => return read_push(script, pc, op as usize, opcode),
+        op if op == OpCode::PushData1 as u8 => 1,
+        op if op == OpCode::PushData2 as u8 => 2,
+        op if op == OpCode::PushData4 as u8 => 4,
+        _ => return Some((opcode, &[])),
+    };
+    let len = script.get(*pc..*pc + len_bytes)?;
+    *pc += len_bytes;
+    let len = len.iter().rev().fold(0usize, |len, byte| (len << 8) | *byte as usize);
+    read_push(script, pc, len, opcode)
+}
+
+fn read_push<'s>(
+    script: &'s [u8],
+    pc: &mut usize,
+    len: usize,
+    opcode: u8,
+) -> Option<(u8, &'s [u8])> {
+    let data = script.get(*pc..pc.checked_add(len)?)?;
+    *pc += len;
+    Some((opcode, data))
+}
+
+/// Serializes the minimal push of the `data`.
+pub(super) fn push_data(data: &[u8]) -> Vec<u8> {
+    let len = data.len();
+    let mut script = Vec::with_capacity(len + 5);
+    if len < OpCode::PushData1 as usize {
+        script.push(len as u8);
+    } else if len <= 0xff {
+        script.extend([OpCode::PushData1 as u8, len as u8]);
+    } else if len <= 0xffff {
+        script.push(OpCode::PushData2 as u8);
+        script.extend((len as u16).to_le_bytes());
+    } else {
+        script.push(OpCode::PushData4 as u8);
+        script.extend((len as u32).to_le_bytes());
+    }
+    script.extend(data);
+    script
+}
+
+fn is_minimal_push(opcode: u8, data: &[u8]) -> bool {
+    match data.len() {
+        0 => opcode == OpCode::PushBytes0 as u8,
+        // Must have used OP_1-OP_16 or OP_1NEGATE
+        1 if (1..=16).contains(&data[0]) || data[0] == 0x81 => false,
+        len if len < OpCode::PushData1 as usize => opcode as usize == len,
+        len if len <= 0xff => opcode == OpCode::PushData1 as u8,
+        len if len <= 0xffff => opcode == OpCode::PushData2 as u8,
+        _ => true,
+    }
+}
+
+pub(super) fn is_push_only(script: &[u8]) -> bool {
+    let mut pc = 0;
+    while pc < script.len() {
+        match read_op(script, &mut pc) {
+            Some((opcode, _)) if opcode <= OpCode::PushNum16 as u8 => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn is_disabled(opcode: u8) -> bool {
+    matches!(
+        OpCode::try_from(opcode),
+        Ok(OpCode::Cat
+            | OpCode::SubStr
+            | OpCode::Left
+            | OpCode::Right
+            | OpCode::Invert
+            | OpCode::And
+            | OpCode::Or
+            | OpCode::Xor
+            | OpCode::Mul2
+            | OpCode::Div2
+            | OpCode::Mul
+            | OpCode::Div
+            | OpCode::Mod
+            | OpCode::LShift
+            | OpCode::RShift)
+    )
+}
+
+/// Detects `OP_SUCCESSx` opcodes as defined in BIP-342.
+pub(super) fn is_op_success(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        80 | 98 | 126..=129 | 131..=134 | 137..=138 | 141..=142 | 149..=153 | 187..=254
+    )
+}
+
+fn strip_codeseparators(script: &[u8]) -> Vec<u8> {
+    let mut stripped = Vec::with_capacity(script.len());
+    let mut pc = 0;
+    while pc < script.len() {
+        let start = pc;
+        match read_op(script, &mut pc) {
+            Some((opcode, _)) if opcode == OpCode::CodeSeparator as u8 => {}
+            Some(_) => stripped.extend(&script[start..pc]),
+            None => {
+                stripped.extend(&script[start..]);
+                break;
+            }
+        }
+    }
+    stripped
+}
+
+/// Removes all occurrences of the `pattern` located at the opcode boundaries of the `script`.
+fn find_and_delete(script: &[u8], pattern: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(script.len());
+    let mut pc = 0;
+    let mut copied = 0;
+    loop {
+        result.extend(&script[copied..pc]);
+        while script[pc..].starts_with(pattern) {
+            pc += pattern.len();
+        }
+        copied = pc;
+        if read_op(script, &mut pc).is_none() {
+            break;
+        }
+    }
+    result.extend(&script[copied..]);
+    result
+}
+
+fn is_valid_der_sig(sig: &[u8]) -> bool {
+    // Format: 0x30 [total-length] 0x02 [R-length] [R] 0x02 [S-length] [S] [sighash]
+    let len = sig.len();
+    if !(9..=73).contains(&len) || sig[0] != 0x30 || sig[1] as usize != len - 3 {
+        return false;
+    }
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= len {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != len {
+        return false;
+    }
+    let is_valid_int = |pos: usize, int_len: usize| {
+        sig[pos - 2] == 0x02
+            && int_len > 0
+            && sig[pos] & 0x80 == 0
+            && !(int_len > 1 && sig[pos] == 0 && sig[pos + 1] & 0x80 == 0)
+    };
+    is_valid_int(4, len_r) && is_valid_int(len_r + 6, len_s)
+}
+
+fn is_low_s_sig(sig: &[u8]) -> bool {
+    let Ok(sig) = ecdsa::Signature::from_der_lax(&sig[..sig.len() - 1]) else {
+        return false;
+    };
+    let mut normalized = sig;
+    normalized.normalize_s();
+    normalized == sig
+}
+
+pub(super) fn cast_to_bool(elem: &[u8]) -> bool {
+    let Some((last, rest)) = elem.split_last() else {
+        return false;
+    };
+    // Negative zero is false as well
+    rest.iter().any(|byte| *byte != 0) || (*last != 0 && *last != 0x80)
+}
+
+fn decode_num(elem: &[u8], minimal: bool, max_len: usize) -> Result<i64, ScriptFailure> {
+    if elem.len() > max_len {
+        return Err(ScriptFailure::InvalidNumber);
+    }
+    let Some((last, rest)) = elem.split_last() else {
+        return Ok(0);
+    };
+    // The most significant byte may be zero only if its sign bit is required by the next byte
+    if minimal && *last & 0x7f == 0 && rest.last().is_none_or(|byte| *byte & 0x80 == 0) {
+        return Err(ScriptFailure::InvalidNumber);
+    }
+    let abs =
+        elem.iter().enumerate().fold(0i64, |acc, (i, byte)| acc | ((*byte as i64) << (8 * i)));
+    if *last & 0x80 == 0 {
+        return Ok(abs);
+    }
+    Ok(-(abs & !(0x80i64 << (8 * (elem.len() - 1)))))
+}
+
+fn encode_num(num: i64) -> Vec<u8> {
+    let mut abs = num.unsigned_abs();
+    let mut elem = vec![];
+    while abs > 0 {
+        elem.push((abs & 0xff) as u8);
+        abs >>= 8;
+    }
+    match elem.last_mut() {
+        None => {}
+        Some(last) if *last & 0x80 != 0 => elem.push(if num < 0 { 0x80 } else { 0x00 }),
+        Some(last) if num < 0 => *last |= 0x80,
+        Some(_) => {}
+    }
+    elem
+}
+
+pub(super) fn sha256(data: &[u8]) -> [u8; 32] { Sha256::digest(data).into() }
+
+pub(super) fn compact_size(len: usize) -> Vec<u8> {
+    match len {
+        0..=0xfc => vec![len as u8],
+        0xfd..=0xffff => [&[0xfd][..], &(len as u16).to_le_bytes()].concat(),
+        0x10000..=0xffff_ffff => [&[0xfe][..], &(len as u32).to_le_bytes()].concat(),
+        _ => [&[0xff][..], &(len as u64).to_le_bytes()].concat(),
+    }
+}
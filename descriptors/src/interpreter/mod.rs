@@ -0,0 +1,961 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Script interpreter verifying that transaction inputs satisfy the spent outputs, following the
+//! consensus and standardness rules of Bitcoin Core for legacy, P2SH, SegWit v0 and taproot
+//! (including tapscript) spendings.
+
+mod exec;
+
+use std::fmt::{self, Display, Formatter};
+
+use derive::{
+    Annex, InternalPk, OpCode, OutputPk, SighashCache, TapBranchHash, TapCode, TapLeafHash,
+    TapNodeHash, Tx, TxOut,
+};
+
+use self::exec::{
+    cast_to_bool, compact_size, is_op_success, is_push_only, push_data, read_op, sha256, Context,
+    OpFailure, SigVersion, TapscriptState,
+};
+
+/// Maximal number of elements in the stack and altstack together.
+pub const MAX_STACK_SIZE: usize = 1000;
+/// Maximal size of a stack element.
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+/// Maximal size of non-tapscript scripts.
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+/// Maximal number of non-push operations in non-tapscript scripts.
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+/// Maximal number of public keys in `OP_CHECKMULTISIG`.
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
+const ANNEX_TAG: u8 = 0x50;
+const TAPROOT_LEAF_MASK: u8 = 0xfe;
+const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+pub(crate) const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
+/// Flags selecting which of the consensus and standardness rules are applied by the interpreter.
+///
+/// Each flag matches one of the `SCRIPT_VERIFY_*` flags of Bitcoin Core.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct VerifyFlags {
+    /// Evaluate P2SH subscripts (BIP-16).
+    pub p2sh: bool,
+    /// Require strict encoding of signatures and public keys.
+    pub strict_enc: bool,
+    /// Require strict DER encoding of ECDSA signatures (BIP-66).
+    pub der_sig: bool,
+    /// Require low S values in ECDSA signatures (BIP-62 rule 5).
+    pub low_s: bool,
+    /// Require the `OP_CHECKMULTISIG` dummy argument to be empty (BIP-147).
+    pub null_dummy: bool,
+    /// Require the signature script to contain only pushes (BIP-62 rule 2).
+    pub sig_push_only: bool,
+    /// Require minimal encoding of pushes and numbers (BIP-62 rules 3 and 4).
+    pub minimal_data: bool,
+    /// Fail on upgradable `OP_NOP` opcodes.
+    pub discourage_upgradable_nops: bool,
+    /// Require the stack to contain a single element after the execution (BIP-62 rule 6).
+    pub clean_stack: bool,
+    /// Verify `OP_CHECKLOCKTIMEVERIFY` (BIP-65).
+    pub check_lock_time_verify: bool,
+    /// Verify `OP_CHECKSEQUENCEVERIFY` (BIP-112).
+    pub check_sequence_verify: bool,
+    /// Verify witness programs (BIP-141).
+    pub witness: bool,
+    /// Fail on witness programs of unknown versions.
+    pub discourage_upgradable_witness_program: bool,
+    /// Require `OP_IF` and `OP_NOTIF` arguments in SegWit v0 scripts to be minimal.
+    pub minimal_if: bool,
+    /// Require failed signatures to be empty (BIP-146).
+    pub null_fail: bool,
+    /// Require compressed public keys in SegWit v0 scripts.
+    pub witness_pubkey_type: bool,
+    /// Verify taproot spendings (BIP-341 and BIP-342).
+    pub taproot: bool,
+    /// Fail on unknown taproot leaf versions.
+    pub discourage_upgradable_taproot_version: bool,
+    /// Fail on `OP_SUCCESSx` opcodes in tapscript.
+    pub discourage_op_success: bool,
+    /// Fail on public keys of unknown types in tapscript.
+    pub discourage_upgradable_pubkey_type: bool,
+}
+
+impl Default for VerifyFlags {
+    fn default() -> Self { Self::STANDARD }
+}
+
+impl VerifyFlags {
+    /// Rules enforced by the consensus.
+    pub const CONSENSUS: Self = VerifyFlags {
+        p2sh: true,
+        strict_enc: false,
+        der_sig: true,
+        low_s: false,
+        null_dummy: true,
+        sig_push_only: false,
+        minimal_data: false,
+        discourage_upgradable_nops: false,
+        clean_stack: false,
+        check_lock_time_verify: true,
+        check_sequence_verify: true,
+        witness: true,
+        discourage_upgradable_witness_program: false,
+        minimal_if: false,
+        null_fail: false,
+        witness_pubkey_type: false,
+        taproot: true,
+        discourage_upgradable_taproot_version: false,
+        discourage_op_success: false,
+        discourage_upgradable_pubkey_type: false,
+    };
+
+    /// Rules enforced by the standardness policy of the nodes relaying transactions.
+    pub const STANDARD: Self = VerifyFlags {
+        p2sh: true,
+        strict_enc: true,
+        der_sig: true,
+        low_s: true,
+        null_dummy: true,
+        sig_push_only: true,
+        minimal_data: true,
+        discourage_upgradable_nops: true,
+        clean_stack: true,
+        check_lock_time_verify: true,
+        check_sequence_verify: true,
+        witness: true,
+        discourage_upgradable_witness_program: true,
+        minimal_if: true,
+        null_fail: true,
+        witness_pubkey_type: true,
+        taproot: true,
+        discourage_upgradable_taproot_version: true,
+        discourage_op_success: true,
+        discourage_upgradable_pubkey_type: true,
+    };
+}
+
+/// Reasons for a script verification failure.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum ScriptFailure {
+    /// script evaluated without error but finished with a false or empty top stack element.
+    EvalFalse,
+
+    /// `OP_RETURN` was encountered.
+    OpReturn,
+
+    /// script is too large.
+    ScriptSize,
+
+    /// pushed data exceed the maximal stack element size.
+    PushSize,
+
+    /// operation limit exceeded.
+    OpCount,
+
+    /// stack size limit exceeded.
+    StackSize,
+
+    /// signature count is negative or greater than the public key count.
+    SigCount,
+
+    /// public key count is negative or exceeds the limit.
+    PubkeyCount,
+
+    /// verification operation has failed.
+    VerifyFailed,
+
+    /// opcode is missing or not understood.
+    BadOpcode,
+
+    /// attempt to use a disabled opcode.
+    DisabledOpcode,
+
+    /// operation is not valid with the current stack size.
+    InvalidStackOperation,
+
+    /// operation is not valid with the current altstack size.
+    InvalidAltstackOperation,
+
+    /// unbalanced conditional.
+    UnbalancedConditional,
+
+    /// negative lock time.
+    NegativeLocktime,
+
+    /// lock time requirement is not satisfied.
+    UnsatisfiedLocktime,
+
+    /// signature hash type is missing or not understood.
+    SigHashType,
+
+    /// non-canonical DER signature.
+    SigDer,
+
+    /// data push is larger than necessary.
+    MinimalData,
+
+    /// only push operators are allowed in the signature script.
+    SigPushOnly,
+
+    /// non-canonical signature: S value is unnecessarily high.
+    SigHighS,
+
+    /// dummy `OP_CHECKMULTISIG` argument must be zero.
+    SigNullDummy,
+
+    /// public key is neither compressed nor uncompressed.
+    PubkeyType,
+
+    /// stack size must be exactly one after the execution.
+    CleanStack,
+
+    /// `OP_IF` and `OP_NOTIF` argument must be minimal.
+    MinimalIf,
+
+    /// signature must be empty if the check has failed.
+    SigNullFail,
+
+    /// upgradable `OP_NOP` opcodes are reserved for soft-fork upgrades.
+    DiscourageUpgradableNops,
+
+    /// witness version is reserved for soft-fork upgrades.
+    DiscourageUpgradableWitnessProgram,
+
+    /// taproot leaf version is reserved for soft-fork upgrades.
+    DiscourageUpgradableTaprootVersion,
+
+    /// `OP_SUCCESSx` opcodes are reserved for soft-fork upgrades.
+    DiscourageOpSuccess,
+
+    /// public key type is reserved for soft-fork upgrades.
+    DiscourageUpgradablePubkeyType,
+
+    /// witness program has incorrect length.
+    WitnessProgramWrongLength,
+
+    /// witness program was passed an empty witness.
+    WitnessProgramWitnessEmpty,
+
+    /// witness program doesn't match the provided witness.
+    WitnessProgramMismatch,
+
+    /// witness requires an empty signature script.
+    WitnessMalleated,
+
+    /// witness requires only-redeem-script signature script.
+    WitnessMalleatedP2sh,
+
+    /// witness is provided for a non-witness script.
+    WitnessUnexpected,
+
+    /// using non-compressed public key in a SegWit v0 script.
+    WitnessPubkeyType,
+
+    /// invalid BIP-340 signature size.
+    SchnorrSigSize,
+
+    /// invalid BIP-340 signature hash type.
+    SchnorrSigHashType,
+
+    /// invalid BIP-340 signature.
+    SchnorrSig,
+
+    /// invalid taproot control block size.
+    TaprootWrongControlSize,
+
+    /// too many signature operations relative to the witness weight.
+    TapscriptValidationWeight,
+
+    /// `OP_CHECKMULTISIG` is not available in tapscript.
+    TapscriptCheckMultisig,
+
+    /// `OP_IF` and `OP_NOTIF` argument must be minimal in tapscript.
+    TapscriptMinimalIf,
+
+    /// stack element is not a valid script number.
+    InvalidNumber,
+
+    /// spent output is not provided.
+    NoPrevout,
+}
+
+/// Error verifying transaction input scripts, which details the failed input, the opcode at which
+/// the execution has stopped (if any) and the failure reason.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Error)]
+pub struct ScriptError {
+    /// Index of the failed input.
+    pub input: usize,
+    /// Opcode at which the execution has failed, or `None` for failures outside the execution of
+    /// specific opcodes.
+    pub opcode: Option<u8>,
+    /// Failure reason.
+    pub failure: ScriptFailure,
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "input #{} failed", self.input)?;
+        if let Some(opcode) = self.opcode {
+            match (OpCode::try_from(opcode), TapCode::try_from(opcode)) {
+                (Ok(op), _) => write!(f, " at {op}")?,
+                (_, Ok(op)) => write!(f, " at {op}")?,
+                _ => write!(f, " at unknown opcode {opcode:#04x}")?,
+            }
+        }
+        write!(f, ": {}", self.failure)
+    }
+}
+
+/// Extension trait verifying scripts of a transaction against the outputs it spends.
+pub trait TxVerifyExt {
+    /// Runs the script interpreter for each of the transaction inputs, checking that it satisfies
+    /// the spent output under the provided verification `flags`.
+    ///
+    /// The `prevouts` must list the outputs spent by the transaction in the order of its inputs.
+    ///
+    /// Signatures using non-standard sighash types are considered invalid, since such sighashes
+    /// can't be computed with [`SighashCache`].
+    ///
+    /// # Errors
+    ///
+    /// On the first input failing the verification, or if some of the inputs lack a spent output.
+    fn verify_scripts(&self, prevouts: &[TxOut], flags: VerifyFlags) -> Result<(), ScriptError>;
+}
+
+impl TxVerifyExt for Tx {
+    fn verify_scripts(&self, prevouts: &[TxOut], flags: VerifyFlags) -> Result<(), ScriptError> {
+        if prevouts.len() < self.inputs.len() {
+            return Err(ScriptError {
+                input: prevouts.len(),
+                opcode: None,
+                failure: ScriptFailure::NoPrevout,
+            });
+        }
+        let prevouts = &prevouts[..self.inputs.len()];
+        let mut sig_hasher = SighashCache::new(self, prevouts.iter().collect())
+            .expect("number of inputs and prevouts match");
+        for (index, prevout) in prevouts.iter().enumerate() {
+            let mut ctx = Context {
+                tx: self,
+                index,
+                amount: prevout.value,
+                sig_hasher: &mut sig_hasher,
+            };
+            let mut verifier = Verifier {
+                flags,
+                ctx: &mut ctx,
+            };
+            verifier.verify(prevout).map_err(|err| ScriptError {
+                input: index,
+                opcode: err.opcode,
+                failure: err.failure,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+struct Verifier<'v, 'c, 'a> {
+    flags: VerifyFlags,
+    ctx: &'v mut Context<'c, 'a>,
+}
+
+impl Verifier<'_, '_, '_> {
+    fn verify(&mut self, prevout: &TxOut) -> Result<(), OpFailure> {
+        let flags = self.flags;
+        let input = &self.ctx.tx.inputs[self.ctx.index];
+        let script_sig = input.sig_script.as_slice();
+        let script_pubkey = prevout.script_pubkey.as_slice();
+        let witness = input.witness.elements().map(<[u8]>::to_vec).collect::<Vec<_>>();
+
+        if flags.sig_push_only && !is_push_only(script_sig) {
+            return Err(ScriptFailure::SigPushOnly.into());
+        }
+
+        let mut stack = vec![];
+        exec::eval_script(&mut stack, script_sig, SigVersion::Base, &flags, self.ctx, None)?;
+        let stack_copy = if flags.p2sh { stack.clone() } else { vec![] };
+        exec::eval_script(&mut stack, script_pubkey, SigVersion::Base, &flags, self.ctx, None)?;
+        if !stack.last().is_some_and(|top| cast_to_bool(top)) {
+            return Err(ScriptFailure::EvalFalse.into());
+        }
+
+        let mut had_witness = false;
+        if flags.witness {
+            if let Some((version, program)) = witness_program(script_pubkey) {
+                had_witness = true;
+                if !script_sig.is_empty() {
+                    return Err(ScriptFailure::WitnessMalleated.into());
+                }
+                self.verify_witness_program(witness.clone(), version, program, false)?;
+                stack.truncate(1);
+            }
+        }
+
+        if flags.p2sh && is_p2sh(script_pubkey) {
+            if !is_push_only(script_sig) {
+                return Err(ScriptFailure::SigPushOnly.into());
+            }
+            stack = stack_copy;
+            let redeem_script = stack.pop().ok_or(ScriptFailure::InvalidStackOperation)?;
+            exec::eval_script(
+                &mut stack,
+                &redeem_script,
+                SigVersion::Base,
+                &flags,
+                self.ctx,
+                None,
+            )?;
+            if !stack.last().is_some_and(|top| cast_to_bool(top)) {
+                return Err(ScriptFailure::EvalFalse.into());
+            }
+
+            if flags.witness {
+                if let Some((version, program)) = witness_program(&redeem_script) {
+                    had_witness = true;
+                    if script_sig != push_data(&redeem_script) {
+                        return Err(ScriptFailure::WitnessMalleatedP2sh.into());
+                    }
+                    self.verify_witness_program(witness.clone(), version, program, true)?;
+                    stack.truncate(1);
+                }
+            }
+        }
+
+        if flags.clean_stack && stack.len() != 1 {
+            return Err(ScriptFailure::CleanStack.into());
+        }
+        if flags.witness && !had_witness && !witness.is_empty() {
+            return Err(ScriptFailure::WitnessUnexpected.into());
+        }
+        Ok(())
+    }
+
+    fn verify_witness_program(
+        &mut self,
+        mut witness: Vec<Vec<u8>>,
+        version: u8,
+        program: &[u8],
+        is_p2sh: bool,
+    ) -> Result<(), OpFailure> {
+        let flags = self.flags;
+        match (version, program.len()) {
+            (0, 32) => {
+                let script = witness.pop().ok_or(ScriptFailure::WitnessProgramWitnessEmpty)?;
+                if sha256(&script) != program {
+                    return Err(ScriptFailure::WitnessProgramMismatch.into());
+                }
+                self.execute_witness_script(witness, &script, SigVersion::WitnessV0, None)
+            }
+            (0, 20) => {
+                if witness.len() != 2 {
+                    return Err(ScriptFailure::WitnessProgramMismatch.into());
+                }
+                let mut script = vec![OpCode::Dup as u8, OpCode::Hash160 as u8];
+                script.extend(push_data(program));
+                script.extend([OpCode::EqualVerify as u8, OpCode::CheckSig as u8]);
+                self.execute_witness_script(witness, &script, SigVersion::WitnessV0, None)
+            }
+            (0, _) => Err(ScriptFailure::WitnessProgramWrongLength.into()),
+            (1, 32) if !is_p2sh => {
+                if !flags.taproot {
+                    return Ok(());
+                }
+                if witness.is_empty() {
+                    return Err(ScriptFailure::WitnessProgramWitnessEmpty.into());
+                }
+                let witness_size = serialized_size(&witness);
+                let annex = match witness.last() {
+                    Some(last) if witness.len() >= 2 && last.first() == Some(&ANNEX_TAG) => {
+                        witness.pop().and_then(|annex| Annex::new(annex).ok())
+                    }
+                    _ => None,
+                };
+
+                if witness.len() == 1 {
+                    // Key path spending
+                    return self
+                        .ctx
+                        .check_schnorr(&witness[0], program, annex, None)
+                        .map_err(OpFailure::from);
+                }
+
+                // Script path spending
+                let control = witness.pop().expect("witness has at least two elements");
+                let script = witness.pop().expect("witness has at least two elements");
+                let path_len = control.len().wrapping_sub(TAPROOT_CONTROL_BASE_SIZE);
+                if control.len() < TAPROOT_CONTROL_BASE_SIZE
+                    || !path_len.is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+                    || path_len / TAPROOT_CONTROL_NODE_SIZE > TAPROOT_CONTROL_MAX_NODE_COUNT
+                {
+                    return Err(ScriptFailure::TaprootWrongControlSize.into());
+                }
+                let leaf_version = control[0] & TAPROOT_LEAF_MASK;
+                let leaf_hash = tap_leaf_hash(leaf_version, &script);
+                if !verify_taproot_commitment(&control, program, leaf_hash) {
+                    return Err(ScriptFailure::WitnessProgramMismatch.into());
+                }
+                if leaf_version == TAPROOT_LEAF_TAPSCRIPT {
+                    let tapscript = TapscriptState {
+                        leaf_hash,
+                        annex,
+                        codesep_pos: u32::MAX,
+                        weight_left: witness_size + VALIDATION_WEIGHT_OFFSET,
+                    };
+                    self.execute_witness_script(
+                        witness,
+                        &script,
+                        SigVersion::Tapscript,
+                        Some(tapscript),
+                    )
+                } else if flags.discourage_upgradable_taproot_version {
+                    Err(ScriptFailure::DiscourageUpgradableTaprootVersion.into())
+                } else {
+                    Ok(())
+                }
+            }
+            _ if flags.discourage_upgradable_witness_program => {
+                Err(ScriptFailure::DiscourageUpgradableWitnessProgram.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn execute_witness_script(
+        &mut self,
+        mut stack: Vec<Vec<u8>>,
+        script: &[u8],
+        version: SigVersion,
+        mut tapscript: Option<TapscriptState>,
+    ) -> Result<(), OpFailure> {
+        if version == SigVersion::Tapscript {
+            let mut pc = 0;
+            while pc < script.len() {
+                let opcode = script[pc];
+                let (opcode, _) = read_op(script, &mut pc).ok_or(OpFailure {
+                    opcode: Some(opcode),
+                    failure: ScriptFailure::BadOpcode,
+                })?;
+                if is_op_success(opcode) {
+                    if self.flags.discourage_op_success {
+                        return Err(OpFailure {
+                            opcode: Some(opcode),
+                            failure: ScriptFailure::DiscourageOpSuccess,
+                        });
+                    }
+                    return Ok(());
+                }
+            }
+            if stack.len() > MAX_STACK_SIZE {
+                return Err(ScriptFailure::StackSize.into());
+            }
+        }
+        if stack.iter().any(|elem| elem.len() > MAX_SCRIPT_ELEMENT_SIZE) {
+            return Err(ScriptFailure::PushSize.into());
+        }
+
+        exec::eval_script(&mut stack, script, version, &self.flags, self.ctx, tapscript.as_mut())?;
+
+        if stack.len() != 1 {
+            return Err(ScriptFailure::CleanStack.into());
+        }
+        if !cast_to_bool(&stack[0]) {
+            return Err(ScriptFailure::EvalFalse.into());
+        }
+        Ok(())
+    }
+}
+
+fn witness_program(script: &[u8]) -> Option<(u8, &[u8])> {
+    if !(4..=42).contains(&script.len()) || script[1] as usize + 2 != script.len() {
+        return None;
+    }
+    let version = match script[0] {
+        0 => 0,
+        op if (OpCode::PushNum1 as u8..=OpCode::PushNum16 as u8).contains(&op) => {
+            op - OpCode::PushNum1 as u8 + 1
+        }
+        _ => return None,
+    };
+    Some((version, &script[2..]))
+}
+
+fn is_p2sh(script: &[u8]) -> bool {
+    script.len() == 23
+        && script[0] == OpCode::Hash160 as u8
+        && script[1] == OpCode::PushBytes20 as u8
+        && script[22] == OpCode::Equal as u8
+}
+
+fn serialized_size(stack: &[Vec<u8>]) -> i64 {
+    let size = stack.iter().map(|elem| compact_size(elem.len()).len() + elem.len()).sum::<usize>()
+        + compact_size(stack.len()).len();
+    size as i64
+}
+
+// Leaf versions which can't be represented with `LeafVer` (like 0x50) are still valid in control
+// blocks, thus we hash the leaf manually.
+fn tap_leaf_hash(leaf_version: u8, script: &[u8]) -> TapLeafHash {
+    use commit_verify::{DigestExt, Sha256};
+
+    let mut engine = Sha256::from_tag(b"TapLeaf");
+    engine.input_raw(&[leaf_version]);
+    engine.input_raw(&compact_size(script.len()));
+    engine.input_raw(script);
+    TapLeafHash::from(engine.finish())
+}
+
+fn verify_taproot_commitment(control: &[u8], program: &[u8], leaf_hash: TapLeafHash) -> bool {
+    let Ok(internal_pk) = InternalPk::from_bytes(&control[1..TAPROOT_CONTROL_BASE_SIZE]) else {
+        return false;
+    };
+    let merkle_root = control[TAPROOT_CONTROL_BASE_SIZE..]
+        .chunks_exact(TAPROOT_CONTROL_NODE_SIZE)
+        .fold(TapNodeHash::from(leaf_hash), |node, sibling| {
+            let sibling = <[u8; 32]>::try_from(sibling).expect("chunks are 32 bytes long");
+            TapBranchHash::with_nodes(node, TapNodeHash::from(sibling)).into()
+        });
+    let (output_pk, parity) = internal_pk.to_output_pk(Some(merkle_root));
+    OutputPk::from_bytes(program).is_ok_and(|pk| pk == output_pk)
+        && parity.to_consensus_u8() == control[0] & 1
+}
+
+#[cfg(test)]
+mod test {
+    use std::slice;
+
+    use commit_verify::{Digest, Ripemd160};
+    use derive::opcodes::*;
+    use derive::secp256k1::{Keypair, SecretKey, SECP256K1};
+    use derive::{
+        InternalKeypair, LockTime, Outpoint, Sats, ScriptBytes, ScriptCode, ScriptPubkey, SeqNo,
+        SigScript, SighashType, TapScript, TxIn, TxVer, VarIntArray, Witness,
+    };
+
+    use super::*;
+
+    fn hash160(data: &[u8]) -> [u8; 20] { Ripemd160::digest(sha256(data)).into() }
+
+    fn keypair(seed: u8) -> Keypair { Keypair::from_seckey_slice(SECP256K1, &[seed; 32]).unwrap() }
+
+    fn pubkey(seed: u8) -> Vec<u8> { keypair(seed).public_key().serialize().to_vec() }
+
+    fn ecdsa_sig(sighash: impl Into<derive::secp256k1::Message>, seed: u8) -> Vec<u8> {
+        let sk = SecretKey::from_keypair(&keypair(seed));
+        let mut sig = sk.sign_ecdsa(sighash.into()).serialize_der().to_vec();
+        sig.push(SighashType::all().into_consensus_u8());
+        sig
+    }
+
+    fn txout(script_pubkey: Vec<u8>) -> TxOut {
+        TxOut {
+            value: Sats::from_sats(100_000u64),
+            script_pubkey: ScriptPubkey::from_checked(script_pubkey),
+        }
+    }
+
+    fn tx(lock_time: u32, sequence: u32) -> Tx {
+        Tx {
+            version: TxVer::V2,
+            inputs: VarIntArray::from_checked(vec![TxIn {
+                prev_output: Outpoint::coinbase(),
+                sig_script: none!(),
+                sequence: SeqNo::from_consensus_u32(sequence),
+                witness: none!(),
+            }]),
+            outputs: VarIntArray::from_checked(vec![txout(vec![OP_RETURN])]),
+            lock_time: LockTime::from_consensus_u32(lock_time),
+        }
+    }
+
+    fn sig_hasher(tx: &Tx, prevout: &TxOut) -> SighashCache<TxOut> {
+        SighashCache::new(tx.clone(), vec![prevout.clone()]).unwrap()
+    }
+
+    fn satisfy(mut tx: Tx, script_sig: Vec<u8>, witness: Vec<Vec<u8>>) -> Tx {
+        tx.inputs[0].sig_script = SigScript::from_checked(script_sig);
+        tx.inputs[0].witness = Witness::from_consensus_stack(witness);
+        tx
+    }
+
+    fn verify(tx: &Tx, prevout: &TxOut) -> Result<(), ScriptError> {
+        tx.verify_scripts(slice::from_ref(prevout), VerifyFlags::STANDARD)
+    }
+
+    fn failure(opcode: Option<u8>, failure: ScriptFailure) -> Result<(), ScriptError> {
+        Err(ScriptError {
+            input: 0,
+            opcode,
+            failure,
+        })
+    }
+
+    #[test]
+    fn arithmetic() {
+        let prevout = txout(vec![OP_PUSHNUM_2, OP_PUSHNUM_3, OP_ADD, OP_PUSHNUM_5, OP_EQUAL]);
+        assert_eq!(verify(&tx(0, u32::MAX), &prevout), Ok(()));
+
+        let prevout = txout(vec![OP_PUSHNUM_1, OP_PUSHNUM_2, OP_EQUALVERIFY]);
+        let err = verify(&tx(0, u32::MAX), &prevout).unwrap_err();
+        assert_eq!(Err(err), failure(Some(OP_EQUALVERIFY), ScriptFailure::VerifyFailed));
+        assert_eq!(
+            err.to_string(),
+            "input #0 failed at OP_EQUALVERIFY: verification operation has failed."
+        );
+    }
+
+    #[test]
+    fn hashlock() {
+        let mut script = vec![OP_SHA256, OP_PUSHBYTES_32];
+        script.extend(sha256(b"preimage"));
+        script.push(OP_EQUAL);
+        let prevout = txout(script);
+
+        let tx = satisfy(tx(0, u32::MAX), push_data(b"preimage"), vec![]);
+        assert_eq!(verify(&tx, &prevout), Ok(()));
+
+        let tx = satisfy(tx.clone(), push_data(b"other"), vec![]);
+        assert_eq!(verify(&tx, &prevout), failure(None, ScriptFailure::EvalFalse));
+    }
+
+    #[test]
+    fn p2pkh() {
+        let mut script = vec![OP_DUP, OP_HASH160, OP_PUSHBYTES_20];
+        script.extend(hash160(&pubkey(1)));
+        script.extend([OP_EQUALVERIFY, OP_CHECKSIG]);
+        let prevout = txout(script);
+
+        let unsigned = tx(0, u32::MAX);
+        let sighash = sig_hasher(&unsigned, &prevout)
+            .legacy_sighash(0, &prevout.script_pubkey, SighashType::all())
+            .unwrap();
+        let script_sig = [push_data(&ecdsa_sig(sighash, 1)), push_data(&pubkey(1))].concat();
+        let tx = satisfy(unsigned.clone(), script_sig, vec![]);
+        assert_eq!(verify(&tx, &prevout), Ok(()));
+
+        let script_sig = [push_data(&ecdsa_sig(sighash, 2)), push_data(&pubkey(1))].concat();
+        let tx = satisfy(unsigned.clone(), script_sig, vec![]);
+        assert_eq!(verify(&tx, &prevout), failure(Some(OP_CHECKSIG), ScriptFailure::SigNullFail));
+        assert_eq!(
+            tx.verify_scripts(slice::from_ref(&prevout), VerifyFlags::CONSENSUS),
+            failure(None, ScriptFailure::EvalFalse)
+        );
+
+        assert_eq!(
+            tx.verify_scripts(&[], VerifyFlags::STANDARD),
+            failure(None, ScriptFailure::NoPrevout)
+        );
+    }
+
+    #[test]
+    fn p2wpkh() {
+        let mut script = vec![OP_PUSHBYTES_0, OP_PUSHBYTES_20];
+        script.extend(hash160(&pubkey(1)));
+        let prevout = txout(script);
+
+        let unsigned = tx(0, u32::MAX);
+        let script_code = ScriptCode::with_p2wpkh(&prevout.script_pubkey);
+        let sighash = sig_hasher(&unsigned, &prevout)
+            .segwit_sighash(0, &script_code, prevout.value, SighashType::all())
+            .unwrap();
+        let tx = satisfy(unsigned.clone(), vec![], vec![ecdsa_sig(sighash, 1), pubkey(1)]);
+        assert_eq!(verify(&tx, &prevout), Ok(()));
+
+        let tx = satisfy(unsigned.clone(), vec![], vec![ecdsa_sig(sighash, 1), pubkey(2)]);
+        assert_eq!(
+            verify(&tx, &prevout),
+            failure(Some(OP_EQUALVERIFY), ScriptFailure::VerifyFailed)
+        );
+
+        let tx =
+            satisfy(unsigned.clone(), vec![OP_PUSHNUM_1], vec![ecdsa_sig(sighash, 1), pubkey(1)]);
+        assert_eq!(verify(&tx, &prevout), failure(None, ScriptFailure::WitnessMalleated));
+    }
+
+    #[test]
+    fn p2sh_p2wsh_multisig() {
+        let mut witness_script = vec![OP_PUSHNUM_2];
+        witness_script.extend(push_data(&pubkey(1)));
+        witness_script.extend(push_data(&pubkey(2)));
+        witness_script.extend([OP_PUSHNUM_2, OP_CHECKMULTISIG]);
+        let mut redeem_script = vec![OP_PUSHBYTES_0, OP_PUSHBYTES_32];
+        redeem_script.extend(sha256(&witness_script));
+        let mut script = vec![OP_HASH160, OP_PUSHBYTES_20];
+        script.extend(hash160(&redeem_script));
+        script.push(OP_EQUAL);
+        let prevout = txout(script);
+
+        let unsigned = tx(0, u32::MAX);
+        let script_code = ScriptCode::from(ScriptBytes::from_checked(witness_script.clone()));
+        let sighash = sig_hasher(&unsigned, &prevout)
+            .segwit_sighash(0, &script_code, prevout.value, SighashType::all())
+            .unwrap();
+        let sigs = [ecdsa_sig(sighash, 1), ecdsa_sig(sighash, 2)];
+        let witness = vec![vec![], sigs[0].clone(), sigs[1].clone(), witness_script.clone()];
+        let tx = satisfy(unsigned.clone(), push_data(&redeem_script), witness);
+        assert_eq!(verify(&tx, &prevout), Ok(()));
+
+        // Signatures in the wrong order
+        let witness = vec![vec![], sigs[1].clone(), sigs[0].clone(), witness_script.clone()];
+        let tx = satisfy(unsigned.clone(), push_data(&redeem_script), witness);
+        assert_eq!(
+            verify(&tx, &prevout),
+            failure(Some(OP_CHECKMULTISIG), ScriptFailure::SigNullFail)
+        );
+
+        let witness = vec![vec![1], sigs[0].clone(), sigs[1].clone(), witness_script];
+        let tx = satisfy(unsigned, push_data(&redeem_script), witness);
+        assert_eq!(
+            verify(&tx, &prevout),
+            failure(Some(OP_CHECKMULTISIG), ScriptFailure::SigNullDummy)
+        );
+    }
+
+    #[test]
+    fn p2tr_key_path() {
+        let internal_pair = InternalKeypair::from(keypair(1));
+        let (output_pair, _) = internal_pair.to_output_keypair(None);
+        let mut script = vec![OP_PUSHNUM_1, OP_PUSHBYTES_32];
+        script.extend(output_pair.x_only_public_key().0.serialize());
+        let prevout = txout(script);
+
+        let unsigned = tx(0, u32::MAX);
+        let sighash = sig_hasher(&unsigned, &prevout).tap_sighash_key(0, None).unwrap();
+        let sig = SECP256K1
+            .sign_schnorr_no_aux_rand(sighash.as_ref(), &output_pair)
+            .to_byte_array()
+            .to_vec();
+        let tx = satisfy(unsigned.clone(), vec![], vec![sig.clone()]);
+        assert_eq!(verify(&tx, &prevout), Ok(()));
+
+        let mut tampered = sig.clone();
+        tampered[0] ^= 1;
+        let tx = satisfy(unsigned.clone(), vec![], vec![tampered]);
+        assert_eq!(verify(&tx, &prevout), failure(None, ScriptFailure::SchnorrSig));
+
+        let tx = satisfy(unsigned, vec![], vec![[&sig[..], &[0]].concat()]);
+        assert_eq!(verify(&tx, &prevout), failure(None, ScriptFailure::SchnorrSigHashType));
+    }
+
+    #[test]
+    fn p2tr_script_path() {
+        let mut leaf_script = vec![OP_PUSHBYTES_32];
+        leaf_script.extend(keypair(2).x_only_public_key().0.serialize());
+        leaf_script.push(OP_CHECKSIG);
+        let leaf_hash = TapLeafHash::with_tap_script(&TapScript::from_checked(leaf_script.clone()));
+        assert_eq!(tap_leaf_hash(TAPROOT_LEAF_TAPSCRIPT, &leaf_script), leaf_hash);
+
+        let internal_pk =
+            InternalPk::from_bytes(keypair(1).x_only_public_key().0.serialize()).unwrap();
+        let (output_pk, parity) = internal_pk.to_output_pk(Some(leaf_hash.into()));
+        let mut script = vec![OP_PUSHNUM_1, OP_PUSHBYTES_32];
+        script.extend(output_pk.to_byte_array());
+        let prevout = txout(script);
+        let mut control = vec![TAPROOT_LEAF_TAPSCRIPT | parity.to_consensus_u8()];
+        control.extend(internal_pk.to_byte_array());
+
+        let unsigned = tx(0, u32::MAX);
+        let sighash =
+            sig_hasher(&unsigned, &prevout).tap_sighash_script(0, leaf_hash, None).unwrap();
+        let sig = SECP256K1
+            .sign_schnorr_no_aux_rand(sighash.as_ref(), &keypair(2))
+            .to_byte_array()
+            .to_vec();
+        let witness = vec![sig.clone(), leaf_script.clone(), control.clone()];
+        let tx = satisfy(unsigned.clone(), vec![], witness);
+        assert_eq!(verify(&tx, &prevout), Ok(()));
+
+        let sig = SECP256K1
+            .sign_schnorr_no_aux_rand(sighash.as_ref(), &keypair(3))
+            .to_byte_array()
+            .to_vec();
+        let witness = vec![sig, leaf_script.clone(), control.clone()];
+        let tx = satisfy(unsigned.clone(), vec![], witness);
+        assert_eq!(verify(&tx, &prevout), failure(Some(OP_CHECKSIG), ScriptFailure::SchnorrSig));
+
+        let witness = vec![vec![], leaf_script.clone(), control[..32].to_vec()];
+        let tx = satisfy(unsigned.clone(), vec![], witness);
+        assert_eq!(verify(&tx, &prevout), failure(None, ScriptFailure::TaprootWrongControlSize));
+
+        control[0] ^= 1;
+        let witness = vec![vec![], leaf_script, control];
+        let tx = satisfy(unsigned, vec![], witness);
+        assert_eq!(verify(&tx, &prevout), failure(None, ScriptFailure::WitnessProgramMismatch));
+    }
+
+    #[test]
+    fn timelocks() {
+        let prevout = txout(vec![OP_PUSHBYTES_1, 100, OP_CLTV, OP_DROP, OP_PUSHNUM_1]);
+        assert_eq!(verify(&tx(100, 0xffff_fffe), &prevout), Ok(()));
+        assert_eq!(
+            verify(&tx(99, 0xffff_fffe), &prevout),
+            failure(Some(OP_CLTV), ScriptFailure::UnsatisfiedLocktime)
+        );
+        assert_eq!(
+            verify(&tx(100, u32::MAX), &prevout),
+            failure(Some(OP_CLTV), ScriptFailure::UnsatisfiedLocktime)
+        );
+
+        let prevout = txout(vec![OP_PUSHNUM_10, OP_CSV, OP_DROP, OP_PUSHNUM_1]);
+        assert_eq!(verify(&tx(0, 10), &prevout), Ok(()));
+        assert_eq!(
+            verify(&tx(0, 9), &prevout),
+            failure(Some(OP_CSV), ScriptFailure::UnsatisfiedLocktime)
+        );
+    }
+
+    #[test]
+    fn script_rules() {
+        let prevout = txout(vec![OP_PUSHNUM_1, OP_IF, OP_PUSHNUM_1]);
+        assert_eq!(
+            verify(&tx(0, u32::MAX), &prevout),
+            failure(None, ScriptFailure::UnbalancedConditional)
+        );
+
+        let prevout = txout(vec![OP_PUSHNUM_1, OP_PUSHNUM_1, OP_CAT]);
+        assert_eq!(
+            verify(&tx(0, u32::MAX), &prevout),
+            failure(Some(OP_CAT), ScriptFailure::DisabledOpcode)
+        );
+
+        let prevout = txout(vec![OP_PUSHBYTES_1, 5, OP_PUSHNUM_5, OP_EQUAL]);
+        assert_eq!(
+            verify(&tx(0, u32::MAX), &prevout),
+            failure(Some(OP_PUSHBYTES_1), ScriptFailure::MinimalData)
+        );
+        assert_eq!(tx(0, u32::MAX).verify_scripts(&[prevout], VerifyFlags::CONSENSUS), Ok(()));
+
+        let prevout = txout(vec![OP_PUSHNUM_1, OP_NOP4]);
+        assert_eq!(
+            verify(&tx(0, u32::MAX), &prevout),
+            failure(Some(OP_NOP4), ScriptFailure::DiscourageUpgradableNops)
+        );
+    }
+}
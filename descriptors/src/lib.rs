@@ -33,15 +33,28 @@ mod descriptor;
 mod singlesig;
 mod multisig;
 mod tr;
+mod miniscript;
+mod weight;
+mod policy;
+#[cfg(feature = "rand")]
+mod signer;
 
 pub mod compiler;
+pub mod interpreter;
 
 pub use descriptor::{DescrId, Descriptor, LegacyKeySig, SpkClass, StdDescr, TaprootKeySig};
+pub use miniscript::{
+    HashLock, Miniscript, MiniscriptError, MsBase, MsContext, MsType, MsWrapper, Satisfier,
+    Timelocks, MAX_MULTI_A_KEYS, MAX_MULTI_KEYS,
+};
 pub use multisig::{
     ShMulti, ShSortedMulti, ShWshMulti, ShWshSortedMulti, WshMulti, WshSortedMulti,
 };
+pub use policy::{PathPolicy, Policy, SpendContext};
 pub use script::{
-    Raw, ScriptDescr, ScriptItem, Sh, ShScript, ShWsh, ShWshScript, WitnessItem, Wsh, WshScript,
+    Addr, Raw, ScriptDescr, ScriptItem, Sh, ShScript, ShWsh, ShWshScript, WitnessItem, Wsh,
+    WshScript,
 };
-pub use singlesig::{Pkh, ShWpkh, Wpkh};
-pub use tr::{Tr, TrKey, TrMulti, TrScript, TrSortedMulti};
+pub use singlesig::{Combo, Pkh, ShWpkh, Wpkh};
+pub use tr::{musig_agg_origin, RawTr, Tr, TrKey, TrMulti, TrMusig, TrScript, TrSortedMulti};
+pub use weight::{SatisfactionWeight, SpendingPath};
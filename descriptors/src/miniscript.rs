@@ -0,0 +1,1714 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Miniscript fragments, their type system, compilation into script items and satisfaction.
+
+use std::cmp;
+use std::fmt::{self, Display, Formatter, Write};
+
+use amplify::hex::ToHex;
+use amplify::{Bytes20, Bytes32};
+use commit_verify::{DigestExt, Ripemd160, Sha256};
+use derive::opcodes::*;
+use derive::{KeyOrigin, LockTime, SeqNo, XkeyOrigin, SEQ_NO_CSV_TYPE_MASK};
+use indexmap::IndexMap;
+
+use crate::{LegacyKeySig, ScriptItem, TaprootKeySig};
+
+/// Maximal number of keys in a `multi` fragment.
+pub const MAX_MULTI_KEYS: usize = 20;
+/// Maximal number of keys in a `multi_a` fragment.
+pub const MAX_MULTI_A_KEYS: usize = 999;
+
+/// Script context in which a miniscript is used.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+pub enum MsContext {
+    /// Bare and P2SH scripts.
+    #[display("legacy script")]
+    Legacy,
+
+    /// Segwit v0 witness scripts.
+    #[display("segwit v0 script")]
+    Segwit,
+
+    /// Taproot leaf scripts.
+    #[display("tapscript")]
+    Tapscript,
+}
+
+/// Basic miniscript type.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+pub enum MsBase {
+    /// Takes its inputs from the top of the stack and pushes a nonzero value on satisfaction or
+    /// an exact zero on dissatisfaction.
+    #[display("B")]
+    Base,
+
+    /// Takes its inputs from the top of the stack and pushes nothing; can't be dissatisfied.
+    #[display("V")]
+    Verify,
+
+    /// Takes its inputs from the top of the stack and pushes a public key.
+    #[display("K")]
+    Key,
+
+    /// Takes its inputs from one below the top of the stack and pushes a value above the input.
+    #[display("W")]
+    Wrapped,
+}
+
+/// Miniscript type: basic type with correctness and malleability properties.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct MsType {
+    pub base: MsBase,
+    /// Zero-arg: always consumes exactly 0 stack elements.
+    pub z: bool,
+    /// One-arg: always consumes exactly 1 stack element.
+    pub o: bool,
+    /// Nonzero: satisfaction can't have a zero top stack element.
+    pub n: bool,
+    /// Dissatisfiable: a dissatisfaction exists.
+    pub d: bool,
+    /// Unit: on satisfaction pushes exactly 1 on the stack.
+    pub u: bool,
+    /// Safe: every satisfaction requires a signature.
+    pub s: bool,
+    /// Forced: dissatisfactions (if any) require a signature.
+    pub f: bool,
+    /// Expressive: has a unique dissatisfaction, and all other dissatisfactions require a
+    /// signature.
+    pub e: bool,
+    /// Non-malleable: a non-malleable satisfaction is always possible.
+    pub m: bool,
+}
+
+impl MsType {
+    const fn with(base: MsBase) -> Self {
+        MsType {
+            base,
+            z: false,
+            o: false,
+            n: false,
+            d: false,
+            u: false,
+            s: false,
+            f: false,
+            e: false,
+            m: true,
+        }
+    }
+
+    const fn with_malleability_of(self, other: MsType) -> Self {
+        MsType {
+            s: other.s,
+            f: other.f,
+            e: other.e,
+            m: other.m,
+            ..self
+        }
+    }
+}
+
+impl Display for MsType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.base, f)?;
+        let props = [
+            (self.z, 'z'),
+            (self.o, 'o'),
+            (self.n, 'n'),
+            (self.d, 'd'),
+            (self.u, 'u'),
+            (self.s, 's'),
+            (self.f, 'f'),
+            (self.e, 'e'),
+            (self.m, 'm'),
+        ];
+        for (_, c) in props.into_iter().filter(|(flag, _)| *flag) {
+            f.write_char(c)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum MiniscriptError {
+    /// unknown miniscript fragment '{0}'.
+    UnknownFragment(String),
+
+    /// unknown miniscript wrapper '{0}'.
+    UnknownWrapper(char),
+
+    /// invalid arguments for the miniscript fragment '{0}'.
+    InvalidArgs(String),
+
+    /// invalid key expression '{0}'.
+    InvalidKey(String),
+
+    /// invalid number '{0}'.
+    InvalidNumber(String),
+
+    /// invalid hash value '{0}'.
+    InvalidHash(String),
+
+    /// invalid timelock value {0}; it must be above zero and below 2^31.
+    InvalidTimelock(u32),
+
+    /// invalid threshold {0} for {1} subexpressions.
+    InvalidThreshold(usize, usize),
+
+    /// fragment '{0}' requires a subexpression of type {1}, while {2} is provided.
+    TypeMismatch(&'static str, &'static str, MsType),
+
+    /// fragment '{0}' is not allowed in {1}.
+    ContextMismatch(&'static str, MsContext),
+
+    /// top-level miniscript must have type B, while it has type {0}.
+    NotTopLevel(MsType),
+
+    /// miniscript is malleable.
+    Malleable,
+
+    /// miniscript can be satisfied without a signature.
+    NoSignature,
+}
+
+/// Hash lock: a hash value which preimage must be revealed to satisfy the fragment.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub enum HashLock {
+    Sha256(Bytes32),
+    Hash256(Bytes32),
+    Ripemd160(Bytes20),
+    Hash160(Bytes20),
+}
+
+impl HashLock {
+    /// Constructs `sha256` hash lock for the given preimage.
+    pub fn sha256(preimage: &[u8]) -> Self { HashLock::Sha256(sha256(preimage).into()) }
+
+    /// Constructs `hash256` (double SHA256) hash lock for the given preimage.
+    pub fn hash256(preimage: &[u8]) -> Self { HashLock::Hash256(sha256(&sha256(preimage)).into()) }
+
+    /// Constructs `ripemd160` hash lock for the given preimage.
+    pub fn ripemd160(preimage: &[u8]) -> Self { HashLock::Ripemd160(ripemd160(preimage).into()) }
+
+    /// Constructs `hash160` (SHA256 followed by RIPEMD160) hash lock for the given preimage.
+    pub fn hash160(preimage: &[u8]) -> Self {
+        HashLock::Hash160(ripemd160(&sha256(preimage)).into())
+    }
+
+    /// Checks whether the preimage matches the hash lock.
+    pub fn verify(&self, preimage: &[u8]) -> bool {
+        let lock = match self {
+            HashLock::Sha256(_) => HashLock::sha256(preimage),
+            HashLock::Hash256(_) => HashLock::hash256(preimage),
+            HashLock::Ripemd160(_) => HashLock::ripemd160(preimage),
+            HashLock::Hash160(_) => HashLock::hash160(preimage),
+        };
+        lock == *self
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashLock::Sha256(_) => "sha256",
+            HashLock::Hash256(_) => "hash256",
+            HashLock::Ripemd160(_) => "ripemd160",
+            HashLock::Hash160(_) => "hash160",
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        match self {
+            HashLock::Sha256(h) | HashLock::Hash256(h) => h.as_slice(),
+            HashLock::Ripemd160(h) | HashLock::Hash160(h) => h.as_slice(),
+        }
+    }
+
+    fn opcode(&self) -> u8 {
+        match self {
+            HashLock::Sha256(_) => OP_SHA256,
+            HashLock::Hash256(_) => OP_HASH256,
+            HashLock::Ripemd160(_) => OP_RIPEMD160,
+            HashLock::Hash160(_) => OP_HASH160,
+        }
+    }
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut engine = Sha256::default();
+    engine.input_raw(data);
+    engine.finish()
+}
+
+fn ripemd160(data: &[u8]) -> [u8; 20] {
+    let mut engine = Ripemd160::default();
+    engine.input_raw(data);
+    engine.finish()
+}
+
+impl Display for HashLock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.name(), self.as_slice().to_hex())
+    }
+}
+
+/// Timelocks which must expire before a spending path can be used.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub struct Timelocks {
+    /// Absolute timelock set by `after` fragments, which the transaction lock time must reach.
+    pub after: Option<LockTime>,
+    /// Relative timelock set by `older` fragments, which the input sequence number must reach.
+    pub older: Option<SeqNo>,
+}
+
+impl Timelocks {
+    /// Spending path requiring only the absolute timelock.
+    pub fn after(n: u32) -> Self {
+        Timelocks {
+            after: Some(LockTime::from_consensus_u32(n)),
+            older: None,
+        }
+    }
+
+    /// Spending path requiring only the relative timelock.
+    pub fn older(n: u32) -> Self {
+        Timelocks {
+            after: None,
+            older: Some(SeqNo::from_consensus_u32(n)),
+        }
+    }
+
+    /// Whether the spending path doesn't require any timelocks.
+    pub fn is_empty(&self) -> bool { self.after.is_none() && self.older.is_none() }
+
+    /// Combines timelocks of two spending paths which both must be satisfied, returning `None`
+    /// if they mix height- and time-based locks of the same kind and thus can't be satisfied
+    /// together.
+    pub fn combine(self, other: Timelocks) -> Option<Timelocks> {
+        let after = match (self.after, other.after) {
+            (Some(a), Some(b)) if a.is_height_based() != b.is_height_based() => return None,
+            (Some(a), Some(b)) => Some(cmp::max_by_key(a, b, LockTime::to_consensus_u32)),
+            (a, b) => a.or(b),
+        };
+        let older = match (self.older, other.older) {
+            (Some(a), Some(b))
+                if a.to_consensus_u32() & SEQ_NO_CSV_TYPE_MASK
+                    != b.to_consensus_u32() & SEQ_NO_CSV_TYPE_MASK =>
+            {
+                return None;
+            }
+            (Some(a), Some(b)) => {
+                Some(cmp::max_by_key(a, b, |seq| seq.to_consensus_u32() & 0xFFFF))
+            }
+            (a, b) => a.or(b),
+        };
+        Some(Timelocks { after, older })
+    }
+}
+
+/// Spending paths satisfying both of the sub-expressions.
+fn combine_paths(x: Vec<Timelocks>, y: Vec<Timelocks>) -> Vec<Timelocks> {
+    let mut paths = vec![];
+    for a in &x {
+        merge_paths(&mut paths, y.iter().filter_map(|b| a.combine(*b)));
+    }
+    paths
+}
+
+/// Spending paths satisfying `k` of the sub-expressions; the rest of them are dissatisfied,
+/// which doesn't require any timelocks.
+fn thresh_paths<K>(k: usize, subs: &[Miniscript<K>]) -> Vec<Timelocks> {
+    match subs {
+        _ if k == 0 => vec![Timelocks::default()],
+        _ if subs.len() < k => vec![],
+        [first, rest @ ..] => {
+            let mut paths = combine_paths(first.timelocks(), thresh_paths(k - 1, rest));
+            merge_paths(&mut paths, thresh_paths(k, rest));
+            paths
+        }
+        [] => unreachable!("k > 0 exceeds the number of sub-expressions"),
+    }
+}
+
+fn merge_paths(paths: &mut Vec<Timelocks>, other: impl IntoIterator<Item = Timelocks>) {
+    for path in other {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+}
+
+/// Miniscript wrappers which are not desugared into other fragments.
+///
+/// Wrappers `t:`, `l:` and `u:` are represented as `and_v(X,1)`, `or_i(0,X)` and `or_i(X,0)`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub enum MsWrapper {
+    #[display("a")]
+    Alt,
+    #[display("s")]
+    Swap,
+    #[display("c")]
+    Check,
+    #[display("d")]
+    DupIf,
+    #[display("v")]
+    Verify,
+    #[display("j")]
+    NonZero,
+    #[display("n")]
+    ZeroNotEqual,
+}
+
+impl MsWrapper {
+    fn name(self) -> &'static str {
+        match self {
+            MsWrapper::Alt => "a:",
+            MsWrapper::Swap => "s:",
+            MsWrapper::Check => "c:",
+            MsWrapper::DupIf => "d:",
+            MsWrapper::Verify => "v:",
+            MsWrapper::NonZero => "j:",
+            MsWrapper::ZeroNotEqual => "n:",
+        }
+    }
+}
+
+/// Miniscript expression.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub enum Miniscript<K> {
+    False,
+    True,
+    PkK(K),
+    PkH(K),
+    Older(u32),
+    After(u32),
+    Hash(HashLock),
+    AndOr(Box<Miniscript<K>>, Box<Miniscript<K>>, Box<Miniscript<K>>),
+    AndV(Box<Miniscript<K>>, Box<Miniscript<K>>),
+    AndB(Box<Miniscript<K>>, Box<Miniscript<K>>),
+    OrB(Box<Miniscript<K>>, Box<Miniscript<K>>),
+    OrC(Box<Miniscript<K>>, Box<Miniscript<K>>),
+    OrD(Box<Miniscript<K>>, Box<Miniscript<K>>),
+    OrI(Box<Miniscript<K>>, Box<Miniscript<K>>),
+    Thresh(usize, Vec<Miniscript<K>>),
+    Multi(usize, Vec<K>),
+    MultiA(usize, Vec<K>),
+    Wrap(MsWrapper, Box<Miniscript<K>>),
+}
+
+impl<K> Miniscript<K> {
+    /// Name of the fragment, as used in descriptors.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Miniscript::False => "0",
+            Miniscript::True => "1",
+            Miniscript::PkK(_) => "pk_k",
+            Miniscript::PkH(_) => "pk_h",
+            Miniscript::Older(_) => "older",
+            Miniscript::After(_) => "after",
+            Miniscript::Hash(lock) => lock.name(),
+            Miniscript::AndOr(_, _, _) => "andor",
+            Miniscript::AndV(_, _) => "and_v",
+            Miniscript::AndB(_, _) => "and_b",
+            Miniscript::OrB(_, _) => "or_b",
+            Miniscript::OrC(_, _) => "or_c",
+            Miniscript::OrD(_, _) => "or_d",
+            Miniscript::OrI(_, _) => "or_i",
+            Miniscript::Thresh(_, _) => "thresh",
+            Miniscript::Multi(_, _) => "multi",
+            Miniscript::MultiA(_, _) => "multi_a",
+            Miniscript::Wrap(w, _) => w.name(),
+        }
+    }
+
+    /// Returns all keys used in the miniscript, in the order of their appearance.
+    pub fn keys(&self) -> Vec<&K> {
+        let mut keys = vec![];
+        self.collect_keys(&mut keys);
+        keys
+    }
+
+    fn collect_keys<'a>(&'a self, keys: &mut Vec<&'a K>) {
+        match self {
+            Miniscript::False
+            | Miniscript::True
+            | Miniscript::Older(_)
+            | Miniscript::After(_)
+            | Miniscript::Hash(_) => {}
+            Miniscript::PkK(key) | Miniscript::PkH(key) => keys.push(key),
+            Miniscript::Multi(_, k) | Miniscript::MultiA(_, k) => keys.extend(k),
+            Miniscript::AndOr(x, y, z) => {
+                x.collect_keys(keys);
+                y.collect_keys(keys);
+                z.collect_keys(keys);
+            }
+            Miniscript::AndV(x, y)
+            | Miniscript::AndB(x, y)
+            | Miniscript::OrB(x, y)
+            | Miniscript::OrC(x, y)
+            | Miniscript::OrD(x, y)
+            | Miniscript::OrI(x, y) => {
+                x.collect_keys(keys);
+                y.collect_keys(keys);
+            }
+            Miniscript::Thresh(_, subs) => subs.iter().for_each(|sub| sub.collect_keys(keys)),
+            Miniscript::Wrap(_, x) => x.collect_keys(keys),
+        }
+    }
+
+    /// Constructs the same miniscript with each key replaced by the result of `f`.
+    pub fn map_keys<M>(&self, f: &impl Fn(&K) -> M) -> Miniscript<M> {
+        let map = |x: &Miniscript<K>| Box::new(x.map_keys(f));
+        match self {
+            Miniscript::False => Miniscript::False,
+            Miniscript::True => Miniscript::True,
+            Miniscript::PkK(key) => Miniscript::PkK(f(key)),
+            Miniscript::PkH(key) => Miniscript::PkH(f(key)),
+            Miniscript::Older(n) => Miniscript::Older(*n),
+            Miniscript::After(n) => Miniscript::After(*n),
+            Miniscript::Hash(lock) => Miniscript::Hash(*lock),
+            Miniscript::AndOr(x, y, z) => Miniscript::AndOr(map(x), map(y), map(z)),
+            Miniscript::AndV(x, y) => Miniscript::AndV(map(x), map(y)),
+            Miniscript::AndB(x, y) => Miniscript::AndB(map(x), map(y)),
+            Miniscript::OrB(x, y) => Miniscript::OrB(map(x), map(y)),
+            Miniscript::OrC(x, y) => Miniscript::OrC(map(x), map(y)),
+            Miniscript::OrD(x, y) => Miniscript::OrD(map(x), map(y)),
+            Miniscript::OrI(x, y) => Miniscript::OrI(map(x), map(y)),
+            Miniscript::Thresh(k, subs) => {
+                Miniscript::Thresh(*k, subs.iter().map(|sub| sub.map_keys(f)).collect())
+            }
+            Miniscript::Multi(k, keys) => Miniscript::Multi(*k, keys.iter().map(f).collect()),
+            Miniscript::MultiA(k, keys) => Miniscript::MultiA(*k, keys.iter().map(f).collect()),
+            Miniscript::Wrap(w, x) => Miniscript::Wrap(*w, map(x)),
+        }
+    }
+
+    /// Returns all hash locks used in the miniscript, in the order of their appearance.
+    pub fn hash_locks(&self) -> Vec<&HashLock> {
+        let mut locks = vec![];
+        self.collect_hash_locks(&mut locks);
+        locks
+    }
+
+    fn collect_hash_locks<'a>(&'a self, locks: &mut Vec<&'a HashLock>) {
+        match self {
+            Miniscript::False
+            | Miniscript::True
+            | Miniscript::PkK(_)
+            | Miniscript::PkH(_)
+            | Miniscript::Older(_)
+            | Miniscript::After(_)
+            | Miniscript::Multi(_, _)
+            | Miniscript::MultiA(_, _) => {}
+            Miniscript::Hash(lock) => locks.push(lock),
+            Miniscript::AndOr(x, y, z) => {
+                x.collect_hash_locks(locks);
+                y.collect_hash_locks(locks);
+                z.collect_hash_locks(locks);
+            }
+            Miniscript::AndV(x, y)
+            | Miniscript::AndB(x, y)
+            | Miniscript::OrB(x, y)
+            | Miniscript::OrC(x, y)
+            | Miniscript::OrD(x, y)
+            | Miniscript::OrI(x, y) => {
+                x.collect_hash_locks(locks);
+                y.collect_hash_locks(locks);
+            }
+            Miniscript::Thresh(_, subs) => {
+                subs.iter().for_each(|sub| sub.collect_hash_locks(locks))
+            }
+            Miniscript::Wrap(_, x) => x.collect_hash_locks(locks),
+        }
+    }
+
+    /// Returns timelocks required by each of the miniscript satisfaction paths. Paths which
+    /// can't be satisfied since they mix height- and time-based locks are skipped; a miniscript
+    /// without timelocks has a single path requiring none of them.
+    pub fn timelocks(&self) -> Vec<Timelocks> {
+        match self {
+            Miniscript::False => vec![],
+            Miniscript::True
+            | Miniscript::PkK(_)
+            | Miniscript::PkH(_)
+            | Miniscript::Hash(_)
+            | Miniscript::Multi(_, _)
+            | Miniscript::MultiA(_, _) => vec![Timelocks::default()],
+            Miniscript::Older(n) => vec![Timelocks::older(*n)],
+            Miniscript::After(n) => vec![Timelocks::after(*n)],
+            Miniscript::AndOr(x, y, z) => {
+                let mut paths = combine_paths(x.timelocks(), y.timelocks());
+                merge_paths(&mut paths, z.timelocks());
+                paths
+            }
+            Miniscript::AndV(x, y) | Miniscript::AndB(x, y) => {
+                combine_paths(x.timelocks(), y.timelocks())
+            }
+            Miniscript::OrB(x, y)
+            | Miniscript::OrC(x, y)
+            | Miniscript::OrD(x, y)
+            | Miniscript::OrI(x, y) => {
+                let mut paths = x.timelocks();
+                merge_paths(&mut paths, y.timelocks());
+                paths
+            }
+            Miniscript::Thresh(k, subs) => thresh_paths(*k, subs),
+            Miniscript::Wrap(_, x) => x.timelocks(),
+        }
+    }
+
+    /// Detects fragments which are written as wrappers in descriptors.
+    fn as_wrapper(&self) -> Option<(char, &Miniscript<K>)> {
+        match self {
+            Miniscript::Wrap(w, x) => Some((w.name().chars().next().expect("wrapper name"), x)),
+            Miniscript::AndV(x, y) if matches!(**y, Miniscript::True) => Some(('t', x)),
+            Miniscript::OrI(x, y) if matches!(**x, Miniscript::False) => Some(('l', y)),
+            Miniscript::OrI(x, y) if matches!(**y, Miniscript::False) => Some(('u', x)),
+            _ => None,
+        }
+    }
+
+    /// Computes miniscript type, checking all type requirements of the fragments.
+    pub fn ty(&self, ctx: MsContext) -> Result<MsType, MiniscriptError> {
+        use MsBase::*;
+
+        let name = self.name();
+        let require = |ty: MsType, base: MsBase, expected: &'static str, cond: bool| {
+            if ty.base == base && cond {
+                Ok(ty)
+            } else {
+                Err(MiniscriptError::TypeMismatch(name, expected, ty))
+            }
+        };
+
+        Ok(match self {
+            Miniscript::False => MsType {
+                z: true,
+                u: true,
+                d: true,
+                s: true,
+                e: true,
+                ..MsType::with(Base)
+            },
+            Miniscript::True => MsType {
+                z: true,
+                u: true,
+                f: true,
+                ..MsType::with(Base)
+            },
+            Miniscript::PkK(_) => MsType {
+                o: true,
+                n: true,
+                d: true,
+                u: true,
+                s: true,
+                e: true,
+                ..MsType::with(Key)
+            },
+            Miniscript::PkH(_) => MsType {
+                n: true,
+                d: true,
+                u: true,
+                s: true,
+                e: true,
+                ..MsType::with(Key)
+            },
+            Miniscript::Older(n) | Miniscript::After(n) => {
+                if *n == 0 || *n >= 0x8000_0000 {
+                    return Err(MiniscriptError::InvalidTimelock(*n));
+                }
+                MsType {
+                    z: true,
+                    f: true,
+                    ..MsType::with(Base)
+                }
+            }
+            Miniscript::Hash(_) => MsType {
+                o: true,
+                n: true,
+                d: true,
+                u: true,
+                ..MsType::with(Base)
+            },
+            Miniscript::Multi(k, keys) | Miniscript::MultiA(k, keys) => {
+                let multi = matches!(self, Miniscript::Multi(_, _));
+                if multi == (ctx == MsContext::Tapscript) {
+                    return Err(MiniscriptError::ContextMismatch(name, ctx));
+                }
+                let max = if multi { MAX_MULTI_KEYS } else { MAX_MULTI_A_KEYS };
+                if *k == 0 || *k > keys.len() || keys.len() > max {
+                    return Err(MiniscriptError::InvalidThreshold(*k, keys.len()));
+                }
+                MsType {
+                    n: multi,
+                    d: true,
+                    u: true,
+                    s: true,
+                    e: true,
+                    ..MsType::with(Base)
+                }
+            }
+            Miniscript::AndOr(x, y, z) => {
+                let x = x.ty(ctx)?;
+                let y = y.ty(ctx)?;
+                let z = z.ty(ctx)?;
+                require(x, Base, "Bdu", x.d && x.u)?;
+                if y.base != z.base || y.base == Wrapped {
+                    return Err(MiniscriptError::TypeMismatch(name, "B, K or V", z));
+                }
+                MsType {
+                    base: y.base,
+                    z: x.z && y.z && z.z,
+                    o: (x.z && y.o && z.o) || (x.o && y.z && z.z),
+                    n: false,
+                    d: z.d,
+                    u: y.u && z.u,
+                    s: z.s && (x.s || y.s),
+                    f: z.f && (x.s || y.f),
+                    e: z.e && (x.s || y.f),
+                    m: x.m && y.m && z.m && x.e && (x.s || y.s || z.s),
+                }
+            }
+            Miniscript::AndV(x, y) => {
+                let x = require(x.ty(ctx)?, Verify, "V", true)?;
+                let y = y.ty(ctx)?;
+                if y.base == Wrapped {
+                    return Err(MiniscriptError::TypeMismatch(name, "B, K or V", y));
+                }
+                MsType {
+                    base: y.base,
+                    z: x.z && y.z,
+                    o: (x.z && y.o) || (x.o && y.z),
+                    n: x.n || (x.z && y.n),
+                    d: false,
+                    u: y.u,
+                    s: x.s || y.s,
+                    f: x.s || y.f,
+                    e: false,
+                    m: x.m && y.m,
+                }
+            }
+            Miniscript::AndB(x, y) => {
+                let x = require(x.ty(ctx)?, Base, "B", true)?;
+                let y = require(y.ty(ctx)?, Wrapped, "W", true)?;
+                MsType {
+                    base: Base,
+                    z: x.z && y.z,
+                    o: (x.z && y.o) || (x.o && y.z),
+                    n: x.n || (x.z && y.n),
+                    d: x.d && y.d,
+                    u: true,
+                    s: x.s || y.s,
+                    f: (x.f && (y.f || x.s)) || (y.s && y.f),
+                    e: x.e && y.e && x.s && y.s,
+                    m: x.m && y.m,
+                }
+            }
+            Miniscript::OrB(x, z) => {
+                let x = require(x.ty(ctx)?, Base, "Bd", true)?;
+                let x = require(x, Base, "Bd", x.d)?;
+                let z = require(z.ty(ctx)?, Wrapped, "Wd", true)?;
+                let z = require(z, Wrapped, "Wd", z.d)?;
+                MsType {
+                    base: Base,
+                    z: x.z && z.z,
+                    o: (x.z && z.o) || (x.o && z.z),
+                    n: false,
+                    d: true,
+                    u: true,
+                    s: x.s && z.s,
+                    f: false,
+                    e: x.e && z.e,
+                    m: x.m && z.m && x.e && z.e && (x.s || z.s),
+                }
+            }
+            Miniscript::OrC(x, z) => {
+                let x = x.ty(ctx)?;
+                require(x, Base, "Bdu", x.d && x.u)?;
+                let z = require(z.ty(ctx)?, Verify, "V", true)?;
+                MsType {
+                    base: Verify,
+                    z: x.z && z.z,
+                    o: x.o && z.z,
+                    n: false,
+                    d: false,
+                    u: false,
+                    s: x.s && z.s,
+                    f: true,
+                    e: false,
+                    m: x.m && z.m && x.e && (x.s || z.s),
+                }
+            }
+            Miniscript::OrD(x, z) => {
+                let x = x.ty(ctx)?;
+                require(x, Base, "Bdu", x.d && x.u)?;
+                let z = require(z.ty(ctx)?, Base, "B", true)?;
+                MsType {
+                    base: Base,
+                    z: x.z && z.z,
+                    o: x.o && z.z,
+                    n: false,
+                    d: z.d,
+                    u: z.u,
+                    s: x.s && z.s,
+                    f: z.f,
+                    e: z.e,
+                    m: x.m && z.m && x.e && (x.s || z.s),
+                }
+            }
+            Miniscript::OrI(x, z) => {
+                let x = x.ty(ctx)?;
+                let z = z.ty(ctx)?;
+                if x.base != z.base || x.base == Wrapped {
+                    return Err(MiniscriptError::TypeMismatch(name, "B, K or V", z));
+                }
+                MsType {
+                    base: x.base,
+                    z: false,
+                    o: x.z && z.z,
+                    n: false,
+                    d: x.d || z.d,
+                    u: x.u && z.u,
+                    s: x.s && z.s,
+                    f: x.f && z.f,
+                    e: (x.e && z.f) || (z.e && x.f),
+                    m: x.m && z.m && (x.s || z.s),
+                }
+            }
+            Miniscript::Thresh(k, subs) => {
+                if *k == 0 || *k > subs.len() {
+                    return Err(MiniscriptError::InvalidThreshold(*k, subs.len()));
+                }
+                let mut z_count = 0usize;
+                let mut o_count = 0usize;
+                let mut s_count = 0usize;
+                let mut all_e = true;
+                let mut all_m = true;
+                for (no, sub) in subs.iter().enumerate() {
+                    let ty = sub.ty(ctx)?;
+                    if no == 0 {
+                        require(ty, Base, "Bdu", ty.d && ty.u)?;
+                    } else {
+                        require(ty, Wrapped, "Wdu", ty.d && ty.u)?;
+                    }
+                    z_count += ty.z as usize;
+                    o_count += ty.o as usize;
+                    s_count += ty.s as usize;
+                    all_e &= ty.e;
+                    all_m &= ty.m;
+                }
+                let n = subs.len();
+                MsType {
+                    base: Base,
+                    z: z_count == n,
+                    o: z_count == n - 1 && o_count == 1,
+                    n: false,
+                    d: true,
+                    u: true,
+                    s: s_count > n - k,
+                    f: false,
+                    e: all_e && s_count == n,
+                    m: all_m && all_e && s_count >= n - k,
+                }
+            }
+            Miniscript::Wrap(wrapper, x) => {
+                let x = x.ty(ctx)?;
+                match wrapper {
+                    MsWrapper::Alt | MsWrapper::Swap => {
+                        if *wrapper == MsWrapper::Alt {
+                            require(x, Base, "B", true)?;
+                        } else {
+                            require(x, Base, "Bo", x.o)?;
+                        }
+                        MsType {
+                            d: x.d,
+                            u: x.u,
+                            ..MsType::with(Wrapped).with_malleability_of(x)
+                        }
+                    }
+                    MsWrapper::Check => {
+                        require(x, Key, "K", true)?;
+                        MsType {
+                            o: x.o,
+                            n: x.n,
+                            d: x.d,
+                            u: true,
+                            ..MsType::with(Base).with_malleability_of(x)
+                        }
+                    }
+                    MsWrapper::DupIf => {
+                        require(x, Verify, "Vz", x.z)?;
+                        MsType {
+                            o: true,
+                            n: true,
+                            d: true,
+                            u: ctx == MsContext::Tapscript,
+                            s: x.s,
+                            e: true,
+                            m: x.m,
+                            ..MsType::with(Base)
+                        }
+                    }
+                    MsWrapper::Verify => {
+                        require(x, Base, "B", true)?;
+                        MsType {
+                            z: x.z,
+                            o: x.o,
+                            n: x.n,
+                            s: x.s,
+                            f: true,
+                            m: x.m,
+                            ..MsType::with(Verify)
+                        }
+                    }
+                    MsWrapper::NonZero => {
+                        require(x, Base, "Bn", x.n)?;
+                        MsType {
+                            o: x.o,
+                            n: true,
+                            d: true,
+                            u: x.u,
+                            s: x.s,
+                            e: x.f,
+                            m: x.m,
+                            ..MsType::with(Base)
+                        }
+                    }
+                    MsWrapper::ZeroNotEqual => {
+                        require(x, Base, "B", true)?;
+                        MsType {
+                            z: x.z,
+                            o: x.o,
+                            n: x.n,
+                            d: x.d,
+                            u: true,
+                            ..MsType::with(Base).with_malleability_of(x)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Checks that the miniscript can be used as a top-level script: it must have type `B`,
+    /// be non-malleable and require a signature for each of its satisfactions.
+    pub fn check_top_level(&self, ctx: MsContext) -> Result<MsType, MiniscriptError> {
+        let ty = self.ty(ctx)?;
+        if ty.base != MsBase::Base {
+            return Err(MiniscriptError::NotTopLevel(ty));
+        }
+        if !ty.m {
+            return Err(MiniscriptError::Malleable);
+        }
+        if !ty.s {
+            return Err(MiniscriptError::NoSignature);
+        }
+        Ok(ty)
+    }
+
+    /// Compiles miniscript into a sequence of script items.
+    ///
+    /// # Arguments
+    ///
+    /// - `origin` provides extended key origin for each of the miniscript keys.
+    pub fn compile<S: Copy + Into<u8> + TryFrom<u8>>(
+        &self,
+        origin: impl Fn(&K) -> XkeyOrigin,
+    ) -> Vec<ScriptItem<S, K>>
+    where
+        K: Clone,
+    {
+        let mut builder = ItemBuilder {
+            items: vec![],
+            origin: &origin,
+        };
+        builder.push_ms(self);
+        builder.items
+    }
+
+    /// Constructs non-malleable satisfaction witness stack for the miniscript, ordered from the
+    /// bottom to the top of the stack (i.e. in the order of witness serialization).
+    ///
+    /// Returns `None` if the miniscript can't be satisfied with the data provided by the
+    /// satisfier.
+    pub fn satisfy(&self, satisfier: &impl Satisfier<K>) -> Option<Vec<Vec<u8>>> {
+        self.sat(satisfier, Choice::Min).into_items()
+    }
+
+    /// Constructs the largest of the alternative satisfaction witness stacks for the miniscript,
+    /// ignoring the malleability. Used for estimating the worst-case satisfaction size with a
+    /// satisfier providing placeholder data for all keys and hash locks.
+    ///
+    /// Returns `None` if the miniscript can't be satisfied with the data provided by the
+    /// satisfier.
+    pub fn max_satisfaction(&self, satisfier: &impl Satisfier<K>) -> Option<Vec<Vec<u8>>> {
+        self.sat(satisfier, Choice::Max).into_items()
+    }
+
+    fn sat(&self, satisfier: &impl Satisfier<K>, choice: Choice) -> Sat {
+        match self {
+            Miniscript::False => Sat::impossible(),
+            Miniscript::True => Sat::empty(),
+            Miniscript::PkK(key) => match satisfier.signature(key) {
+                Some(sig) => Sat::item(sig),
+                None => Sat::unavailable(),
+            }
+            .with_sig(),
+            Miniscript::PkH(key) => match (satisfier.signature(key), satisfier.public_key(key)) {
+                (Some(sig), Some(pk)) => Sat::item(sig).push(pk),
+                _ => Sat::unavailable(),
+            }
+            .with_sig(),
+            Miniscript::Older(n) if satisfier.check_older(*n) => Sat::empty(),
+            Miniscript::After(n) if satisfier.check_after(*n) => Sat::empty(),
+            Miniscript::Older(_) | Miniscript::After(_) => Sat::impossible(),
+            // The compiled fragment checks the preimage size, so the other preimages can't be used
+            Miniscript::Hash(lock) => match satisfier.preimage(lock) {
+                Some(preimage) if preimage.len() == 32 => Sat::item(preimage),
+                _ => Sat::unavailable(),
+            },
+            Miniscript::AndOr(x, y, z) => Sat::choose(
+                choice,
+                x.sat(satisfier, choice).then(y.sat(satisfier, choice)),
+                x.dsat(satisfier, choice).then(z.sat(satisfier, choice)),
+            ),
+            Miniscript::AndV(x, y) | Miniscript::AndB(x, y) => {
+                x.sat(satisfier, choice).then(y.sat(satisfier, choice))
+            }
+            Miniscript::OrB(x, z) => Sat::choose(
+                choice,
+                x.sat(satisfier, choice).then(z.dsat(satisfier, choice)),
+                x.dsat(satisfier, choice).then(z.sat(satisfier, choice)),
+            ),
+            Miniscript::OrC(x, z) | Miniscript::OrD(x, z) => Sat::choose(
+                choice,
+                x.sat(satisfier, choice),
+                x.dsat(satisfier, choice).then(z.sat(satisfier, choice)),
+            ),
+            Miniscript::OrI(x, z) => Sat::choose(
+                choice,
+                x.sat(satisfier, choice).push(vec![1]),
+                z.sat(satisfier, choice).push(vec![]),
+            ),
+            Miniscript::Thresh(k, subs) => {
+                let sats = subs.iter().map(|sub| sub.sat(satisfier, choice)).collect::<Vec<_>>();
+                let dsats = subs.iter().map(|sub| sub.dsat(satisfier, choice)).collect::<Vec<_>>();
+                let mut order = (0..subs.len()).collect::<Vec<_>>();
+                // Subs which are the cheapest (or the most expensive, when looking for the largest
+                // satisfaction) to satisfy instead of dissatisfying go first
+                let sign = match choice {
+                    Choice::Min => 1,
+                    Choice::Max => -1,
+                };
+                order.sort_by_key(|no| match (sats[*no].size(), dsats[*no].size()) {
+                    (Some(sat), Some(dsat)) => (false, sign * (sat as isize - dsat as isize)),
+                    (Some(sat), None) => (false, sign * sat as isize),
+                    (None, _) => (true, 0),
+                });
+                let chosen = &order[..*k];
+                let mut acc = Sat::empty();
+                for (no, (sat, dsat)) in sats.into_iter().zip(dsats).enumerate().rev() {
+                    let part = if chosen.contains(&no) { sat } else { dsat };
+                    acc = part.then(acc);
+                }
+                acc
+            }
+            Miniscript::Multi(k, keys) => {
+                let mut stack = vec![vec![]];
+                stack.extend(keys.iter().filter_map(|key| satisfier.signature(key)).take(*k));
+                if stack.len() <= *k {
+                    return Sat::unavailable().with_sig();
+                }
+                Sat::items(stack).with_sig()
+            }
+            Miniscript::MultiA(k, keys) => {
+                let mut count = 0;
+                let mut stack = vec![];
+                for key in keys {
+                    let sig = if count < *k { satisfier.signature(key) } else { None };
+                    count += sig.is_some() as usize;
+                    stack.push(sig.unwrap_or_default());
+                }
+                if count < *k {
+                    return Sat::unavailable().with_sig();
+                }
+                stack.reverse();
+                Sat::items(stack).with_sig()
+            }
+            Miniscript::Wrap(MsWrapper::DupIf, x) => x.sat(satisfier, choice).push(vec![1]),
+            Miniscript::Wrap(_, x) => x.sat(satisfier, choice),
+        }
+    }
+
+    fn dsat(&self, satisfier: &impl Satisfier<K>, choice: Choice) -> Sat {
+        match self {
+            Miniscript::False => Sat::empty(),
+            Miniscript::True | Miniscript::Older(_) | Miniscript::After(_) => Sat::impossible(),
+            Miniscript::PkK(_) => Sat::item(vec![]),
+            Miniscript::PkH(key) => match satisfier.public_key(key) {
+                Some(pk) => Sat::item(vec![]).push(pk),
+                None => Sat::unavailable(),
+            },
+            Miniscript::Hash(_) => Sat::item(vec![0u8; 32]),
+            Miniscript::AndOr(x, _, z) | Miniscript::AndB(x, z) | Miniscript::OrB(x, z) => {
+                x.dsat(satisfier, choice).then(z.dsat(satisfier, choice))
+            }
+            Miniscript::OrD(x, z) => x.dsat(satisfier, choice).then(z.dsat(satisfier, choice)),
+            Miniscript::AndV(_, _) | Miniscript::OrC(_, _) => Sat::impossible(),
+            Miniscript::OrI(x, z) => Sat::choose(
+                choice,
+                x.dsat(satisfier, choice).push(vec![1]),
+                z.dsat(satisfier, choice).push(vec![]),
+            ),
+            Miniscript::Thresh(_, subs) => {
+                let mut acc = Sat::empty();
+                for sub in subs.iter().rev() {
+                    acc = sub.dsat(satisfier, choice).then(acc);
+                }
+                acc
+            }
+            Miniscript::Multi(k, _) => Sat::items(vec![vec![]; k + 1]),
+            Miniscript::MultiA(_, keys) => Sat::items(vec![vec![]; keys.len()]),
+            Miniscript::Wrap(MsWrapper::DupIf | MsWrapper::NonZero, _) => Sat::item(vec![]),
+            Miniscript::Wrap(MsWrapper::Verify, _) => Sat::impossible(),
+            Miniscript::Wrap(_, x) => x.dsat(satisfier, choice),
+        }
+    }
+}
+
+impl<K: Display> Display for Miniscript<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut wrappers = String::new();
+        let mut ms = self;
+        while let Some((wrapper, inner)) = ms.as_wrapper() {
+            wrappers.push(wrapper);
+            ms = inner;
+        }
+        let sugar =
+            wrappers.ends_with('c') && matches!(ms, Miniscript::PkK(_) | Miniscript::PkH(_));
+        if sugar {
+            wrappers.pop();
+        }
+        if !wrappers.is_empty() {
+            write!(f, "{wrappers}:")?;
+        }
+
+        match ms {
+            Miniscript::False => f.write_str("0"),
+            Miniscript::True => f.write_str("1"),
+            Miniscript::PkK(key) if sugar => write!(f, "pk({key})"),
+            Miniscript::PkH(key) if sugar => write!(f, "pkh({key})"),
+            Miniscript::PkK(key) => write!(f, "pk_k({key})"),
+            Miniscript::PkH(key) => write!(f, "pk_h({key})"),
+            Miniscript::Older(n) => write!(f, "older({n})"),
+            Miniscript::After(n) => write!(f, "after({n})"),
+            Miniscript::Hash(lock) => Display::fmt(lock, f),
+            Miniscript::AndOr(x, y, z) if matches!(**z, Miniscript::False) => {
+                write!(f, "and_n({x},{y})")
+            }
+            Miniscript::AndOr(x, y, z) => write!(f, "andor({x},{y},{z})"),
+            Miniscript::AndV(x, y)
+            | Miniscript::AndB(x, y)
+            | Miniscript::OrB(x, y)
+            | Miniscript::OrC(x, y)
+            | Miniscript::OrD(x, y)
+            | Miniscript::OrI(x, y) => write!(f, "{}({x},{y})", ms.name()),
+            Miniscript::Thresh(k, subs) => {
+                write!(f, "thresh({k}")?;
+                for sub in subs {
+                    write!(f, ",{sub}")?;
+                }
+                f.write_str(")")
+            }
+            Miniscript::Multi(k, keys) | Miniscript::MultiA(k, keys) => {
+                write!(f, "{}({k}", ms.name())?;
+                for key in keys {
+                    write!(f, ",{key}")?;
+                }
+                f.write_str(")")
+            }
+            Miniscript::Wrap(_, _) => unreachable!("wrappers are processed above"),
+        }
+    }
+}
+
+struct ItemBuilder<'f, S, K, F: Fn(&K) -> XkeyOrigin> {
+    items: Vec<ScriptItem<S, K>>,
+    origin: &'f F,
+}
+
+impl<S: Copy + Into<u8> + TryFrom<u8>, K: Clone, F: Fn(&K) -> XkeyOrigin> ItemBuilder<'_, S, K, F> {
+    fn push_op(&mut self, op: u8) {
+        let op = S::try_from(op).unwrap_or_else(|_| unreachable!("opcode {op:#04x} is unknown"));
+        if let Some(ScriptItem::Code(code)) = self.items.last_mut() {
+            code.push(op);
+        } else {
+            self.items.push(ScriptItem::Code(vec![op]));
+        }
+    }
+
+    fn push_num(&mut self, num: u32) {
+        match num {
+            0 => self.push_op(OP_PUSHBYTES_0),
+            1..=16 => self.push_op(OP_PUSHNUM_1 + num as u8 - 1),
+            _ => {
+                let mut data = num.to_le_bytes().to_vec();
+                while data.last() == Some(&0) {
+                    data.pop();
+                }
+                if data.last().is_some_and(|byte| byte & 0x80 != 0) {
+                    data.push(0);
+                }
+                self.items.push(ScriptItem::Data(data));
+            }
+        }
+    }
+
+    fn push_key(&mut self, key: &K) {
+        self.items.push(ScriptItem::Key((self.origin)(key), key.clone()));
+    }
+
+    fn push_verify(&mut self) {
+        if let Some(ScriptItem::Code(code)) = self.items.last_mut() {
+            let last = code.last().copied().map(Into::<u8>::into);
+            let verify = match last {
+                Some(OP_EQUAL) => Some(OP_EQUALVERIFY),
+                Some(OP_NUMEQUAL) => Some(OP_NUMEQUALVERIFY),
+                Some(OP_CHECKSIG) => Some(OP_CHECKSIGVERIFY),
+                Some(OP_CHECKMULTISIG) => Some(OP_CHECKMULTISIGVERIFY),
+                _ => None,
+            };
+            if let Some(verify) = verify {
+                code.pop();
+                self.push_op(verify);
+                return;
+            }
+        }
+        self.push_op(OP_VERIFY);
+    }
+
+    fn push_ms(&mut self, ms: &Miniscript<K>) {
+        match ms {
+            Miniscript::False => self.push_op(OP_PUSHBYTES_0),
+            Miniscript::True => self.push_op(OP_PUSHNUM_1),
+            Miniscript::PkK(key) => self.push_key(key),
+            Miniscript::PkH(key) => {
+                self.push_op(OP_DUP);
+                self.push_op(OP_HASH160);
+                self.items.push(ScriptItem::KeyHash((self.origin)(key), key.clone()));
+                self.push_op(OP_EQUALVERIFY);
+            }
+            Miniscript::Older(n) => {
+                self.push_num(*n);
+                self.push_op(OP_CSV);
+            }
+            Miniscript::After(n) => {
+                self.push_num(*n);
+                self.push_op(OP_CLTV);
+            }
+            Miniscript::Hash(lock) => {
+                self.push_op(OP_SIZE);
+                self.push_num(32);
+                self.push_op(OP_EQUALVERIFY);
+                self.push_op(lock.opcode());
+                self.items.push(ScriptItem::Data(lock.as_slice().to_vec()));
+                self.push_op(OP_EQUAL);
+            }
+            Miniscript::AndOr(x, y, z) => {
+                self.push_ms(x);
+                self.push_op(OP_NOTIF);
+                self.push_ms(z);
+                self.push_op(OP_ELSE);
+                self.push_ms(y);
+                self.push_op(OP_ENDIF);
+            }
+            Miniscript::AndV(x, y) => {
+                self.push_ms(x);
+                self.push_ms(y);
+            }
+            Miniscript::AndB(x, y) => {
+                self.push_ms(x);
+                self.push_ms(y);
+                self.push_op(OP_BOOLAND);
+            }
+            Miniscript::OrB(x, z) => {
+                self.push_ms(x);
+                self.push_ms(z);
+                self.push_op(OP_BOOLOR);
+            }
+            Miniscript::OrC(x, z) => {
+                self.push_ms(x);
+                self.push_op(OP_NOTIF);
+                self.push_ms(z);
+                self.push_op(OP_ENDIF);
+            }
+            Miniscript::OrD(x, z) => {
+                self.push_ms(x);
+                self.push_op(OP_IFDUP);
+                self.push_op(OP_NOTIF);
+                self.push_ms(z);
+                self.push_op(OP_ENDIF);
+            }
+            Miniscript::OrI(x, z) => {
+                self.push_op(OP_IF);
+                self.push_ms(x);
+                self.push_op(OP_ELSE);
+                self.push_ms(z);
+                self.push_op(OP_ENDIF);
+            }
+            Miniscript::Thresh(k, subs) => {
+                for (no, sub) in subs.iter().enumerate() {
+                    self.push_ms(sub);
+                    if no > 0 {
+                        self.push_op(OP_ADD);
+                    }
+                }
+                self.push_num(*k as u32);
+                self.push_op(OP_EQUAL);
+            }
+            Miniscript::Multi(k, keys) => {
+                self.push_num(*k as u32);
+                for key in keys {
+                    self.push_key(key);
+                }
+                self.push_num(keys.len() as u32);
+                self.push_op(OP_CHECKMULTISIG);
+            }
+            Miniscript::MultiA(k, keys) => {
+                for (no, key) in keys.iter().enumerate() {
+                    self.push_key(key);
+                    self.push_op(if no == 0 { OP_CHECKSIG } else { OP_CHECKSIGADD });
+                }
+                self.push_num(*k as u32);
+                self.push_op(OP_NUMEQUAL);
+            }
+            Miniscript::Wrap(wrapper, x) => match wrapper {
+                MsWrapper::Alt => {
+                    self.push_op(OP_TOALTSTACK);
+                    self.push_ms(x);
+                    self.push_op(OP_FROMALTSTACK);
+                }
+                MsWrapper::Swap => {
+                    self.push_op(OP_SWAP);
+                    self.push_ms(x);
+                }
+                MsWrapper::Check => {
+                    self.push_ms(x);
+                    self.push_op(OP_CHECKSIG);
+                }
+                MsWrapper::DupIf => {
+                    self.push_op(OP_DUP);
+                    self.push_op(OP_IF);
+                    self.push_ms(x);
+                    self.push_op(OP_ENDIF);
+                }
+                MsWrapper::Verify => {
+                    self.push_ms(x);
+                    self.push_verify();
+                }
+                MsWrapper::NonZero => {
+                    self.push_op(OP_SIZE);
+                    self.push_op(OP_0NOTEQUAL);
+                    self.push_op(OP_IF);
+                    self.push_ms(x);
+                    self.push_op(OP_ENDIF);
+                }
+                MsWrapper::ZeroNotEqual => {
+                    self.push_ms(x);
+                    self.push_op(OP_0NOTEQUAL);
+                }
+            },
+        }
+    }
+}
+
+/// Source of the data required to satisfy a miniscript.
+pub trait Satisfier<K> {
+    /// Serialized signature (including sighash type) for the key.
+    fn signature(&self, key: &K) -> Option<Vec<u8>>;
+
+    /// Serialized public key for the key, required by `pk_h` fragments.
+    fn public_key(&self, key: &K) -> Option<Vec<u8>>;
+
+    /// Preimage for a hash lock.
+    fn preimage(&self, _lock: &HashLock) -> Option<Vec<u8>> { None }
+
+    /// Whether a relative timelock `older(n)` is satisfied by the spending transaction.
+    fn check_older(&self, _n: u32) -> bool { false }
+
+    /// Whether an absolute timelock `after(n)` is satisfied by the spending transaction.
+    fn check_after(&self, _n: u32) -> bool { false }
+}
+
+pub(crate) trait KeySigData {
+    fn sig_bytes(&self) -> Vec<u8>;
+    fn key_bytes(&self) -> Vec<u8>;
+}
+
+impl KeySigData for LegacyKeySig {
+    fn sig_bytes(&self) -> Vec<u8> { self.sig.to_vec() }
+    fn key_bytes(&self) -> Vec<u8> { self.key.to_vec() }
+}
+
+impl KeySigData for TaprootKeySig {
+    fn sig_bytes(&self) -> Vec<u8> { self.sig.to_vec() }
+    fn key_bytes(&self) -> Vec<u8> { self.key.to_byte_array().to_vec() }
+}
+
+/// Satisfier matching descriptor keys to the signatures provided to the descriptor finalizer.
+/// Hash locks are satisfied with the preimages known to the finalizer.
+///
+/// Timelocks are assumed to be satisfied, since the transaction constructor is responsible for
+/// setting the matching lock time and sequence numbers.
+pub(crate) struct KeySigSatisfier<'a, 'o, T, F> {
+    keysigs: &'a IndexMap<&'o KeyOrigin, T>,
+    preimages: &'a IndexMap<HashLock, Vec<u8>>,
+    is_origin_of: F,
+}
+
+impl<'a, 'o, T, F> KeySigSatisfier<'a, 'o, T, F> {
+    pub fn new(
+        keysigs: &'a IndexMap<&'o KeyOrigin, T>,
+        preimages: &'a IndexMap<HashLock, Vec<u8>>,
+        is_origin_of: F,
+    ) -> Self {
+        Self {
+            keysigs,
+            preimages,
+            is_origin_of,
+        }
+    }
+}
+
+impl<T: KeySigData, F> KeySigSatisfier<'_, '_, T, F> {
+    fn keysig<K>(&self, key: &K) -> Option<&T>
+    where F: Fn(&K, &KeyOrigin) -> bool {
+        self.keysigs
+            .iter()
+            .find(|(origin, _)| (self.is_origin_of)(key, origin))
+            .map(|(_, keysig)| keysig)
+    }
+}
+
+impl<K, T: KeySigData, F: Fn(&K, &KeyOrigin) -> bool> Satisfier<K>
+    for KeySigSatisfier<'_, '_, T, F>
+{
+    fn signature(&self, key: &K) -> Option<Vec<u8>> { self.keysig(key).map(T::sig_bytes) }
+
+    fn public_key(&self, key: &K) -> Option<Vec<u8>> { self.keysig(key).map(T::key_bytes) }
+
+    fn preimage(&self, lock: &HashLock) -> Option<Vec<u8>> { self.preimages.get(lock).cloned() }
+
+    fn check_older(&self, _n: u32) -> bool { true }
+
+    fn check_after(&self, _n: u32) -> bool { true }
+}
+
+/// Strategy for choosing between alternative satisfactions.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Choice {
+    /// The smallest non-malleable satisfaction, which is used for the finalization.
+    Min,
+    /// The largest satisfaction, used for the worst-case size estimation.
+    Max,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Stack {
+    Items(Vec<Vec<u8>>),
+    Unavailable,
+    Impossible,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Sat {
+    stack: Stack,
+    has_sig: bool,
+}
+
+impl Sat {
+    fn items(items: Vec<Vec<u8>>) -> Self {
+        Sat {
+            stack: Stack::Items(items),
+            has_sig: false,
+        }
+    }
+    fn empty() -> Self { Self::items(vec![]) }
+    fn item(item: Vec<u8>) -> Self { Self::items(vec![item]) }
+    fn unavailable() -> Self {
+        Sat {
+            stack: Stack::Unavailable,
+            has_sig: false,
+        }
+    }
+    fn impossible() -> Self {
+        Sat {
+            stack: Stack::Impossible,
+            has_sig: false,
+        }
+    }
+
+    fn with_sig(mut self) -> Self {
+        self.has_sig = true;
+        self
+    }
+
+    /// Puts an item on top of the stack.
+    fn push(mut self, item: Vec<u8>) -> Self {
+        if let Stack::Items(items) = &mut self.stack {
+            items.push(item);
+        }
+        self
+    }
+
+    /// Combines satisfaction of two sequentially executed fragments: since the first fragment
+    /// consumes its inputs first, they must be put on top of the inputs of the next fragment.
+    fn then(self, next: Sat) -> Sat {
+        let stack = match (self.stack, next.stack) {
+            (Stack::Items(first), Stack::Items(mut second)) => {
+                second.extend(first);
+                Stack::Items(second)
+            }
+            (Stack::Impossible, _) | (_, Stack::Impossible) => Stack::Impossible,
+            _ => Stack::Unavailable,
+        };
+        Sat {
+            stack,
+            has_sig: self.has_sig || next.has_sig,
+        }
+    }
+
+    fn into_items(self) -> Option<Vec<Vec<u8>>> {
+        match self.stack {
+            Stack::Items(items) => Some(items),
+            Stack::Unavailable | Stack::Impossible => None,
+        }
+    }
+
+    /// Size of the serialized witness stack.
+    fn size(&self) -> Option<usize> {
+        match &self.stack {
+            Stack::Items(items) => Some(
+                items.iter().map(|item| item.len() + if item.len() < 0xFD { 1 } else { 3 }).sum(),
+            ),
+            Stack::Unavailable | Stack::Impossible => None,
+        }
+    }
+
+    fn choose(choice: Choice, a: Sat, b: Sat) -> Sat {
+        match choice {
+            Choice::Min => Sat::min(a, b),
+            Choice::Max => Sat::max(a, b),
+        }
+    }
+
+    /// Picks one of two alternative satisfactions. A satisfaction which doesn't require a
+    /// signature is preferred, since otherwise a third party may replace ours with it.
+    fn min(a: Sat, b: Sat) -> Sat {
+        match (&a.stack, &b.stack) {
+            (Stack::Impossible, _) => return b,
+            (_, Stack::Impossible) => return a,
+            (Stack::Unavailable, _) => return b,
+            (_, Stack::Unavailable) => return a,
+            _ => {}
+        }
+        match (a.has_sig, b.has_sig) {
+            (false, true) => a,
+            (true, false) => b,
+            _ if a.size() <= b.size() => a,
+            _ => b,
+        }
+    }
+
+    /// Picks the larger of two alternative satisfactions.
+    fn max(a: Sat, b: Sat) -> Sat {
+        match (&a.stack, &b.stack) {
+            (Stack::Items(_), Stack::Items(_)) if a.size() >= b.size() => a,
+            (Stack::Items(_), Stack::Items(_)) => b,
+            (Stack::Items(_), _) => a,
+            _ => Sat::min(a, b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestSatisfier;
+    impl Satisfier<u8> for TestSatisfier {
+        fn signature(&self, key: &u8) -> Option<Vec<u8>> { (*key != 0).then(|| vec![*key; 71]) }
+        fn public_key(&self, key: &u8) -> Option<Vec<u8>> { Some(vec![*key; 33]) }
+        fn preimage(&self, lock: &HashLock) -> Option<Vec<u8>> {
+            [vec![0x42; 32], vec![0x42; 31]].into_iter().find(|preimage| lock.verify(preimage))
+        }
+        fn check_older(&self, _n: u32) -> bool { true }
+    }
+
+    fn pk(key: u8) -> Miniscript<u8> {
+        Miniscript::Wrap(MsWrapper::Check, Box::new(Miniscript::PkK(key)))
+    }
+    fn v(ms: Miniscript<u8>) -> Miniscript<u8> { Miniscript::Wrap(MsWrapper::Verify, Box::new(ms)) }
+
+    #[test]
+    fn types() {
+        let ctx = MsContext::Segwit;
+        assert_eq!(pk(1).ty(ctx).unwrap().to_string(), "Bondusem");
+        assert_eq!(v(pk(1)).ty(ctx).unwrap().to_string(), "Vonsfm");
+        assert_eq!(Miniscript::<u8>::Older(144).ty(ctx).unwrap().to_string(), "Bzfm");
+        let ms = Miniscript::AndV(Box::new(v(pk(1))), Box::new(Miniscript::Older(144)));
+        assert!(ms.check_top_level(ctx).is_ok());
+        assert_eq!(
+            Miniscript::<u8>::Older(144).check_top_level(ctx),
+            Err(MiniscriptError::NoSignature)
+        );
+        assert!(matches!(
+            Miniscript::OrD(Box::new(v(pk(1))), Box::new(pk(2))).ty(ctx),
+            Err(MiniscriptError::TypeMismatch("or_d", "Bdu", _))
+        ));
+        // `or_b` can be dissatisfied in a non-malleable way only if both its branches can
+        let a = |ms| Miniscript::Wrap(MsWrapper::Alt, Box::new(ms));
+        let or_i = Miniscript::OrI(Box::new(pk(2)), Box::new(pk(3)));
+        assert_eq!(or_i.ty(ctx).unwrap().to_string(), "Bdusm");
+        let ms = Miniscript::OrB(Box::new(pk(1)), Box::new(a(or_i)));
+        assert_eq!(ms.ty(ctx).unwrap().to_string(), "Bdus");
+        assert_eq!(
+            Miniscript::OrB(Box::new(pk(1)), Box::new(a(pk(2)))).ty(ctx).unwrap().to_string(),
+            "Bdusem"
+        );
+        // `older` has no dissatisfaction, so it can't be a branch of `or_b`
+        assert!(matches!(
+            Miniscript::OrB(Box::new(pk(1)), Box::new(a(Miniscript::Older(1)))).ty(ctx),
+            Err(MiniscriptError::TypeMismatch("or_b", "Wd", _))
+        ));
+        assert_eq!(
+            Miniscript::Multi(1, vec![1u8]).ty(MsContext::Tapscript),
+            Err(MiniscriptError::ContextMismatch("multi", MsContext::Tapscript))
+        );
+    }
+
+    #[test]
+    fn compile() {
+        let ms = Miniscript::AndV(Box::new(v(pk(1))), Box::new(Miniscript::Older(144)));
+        let items = ms.compile::<u8>(|_| XkeyOrigin::new_master(default!()));
+        assert_eq!(items.len(), 4);
+        assert!(matches!(items[0], ScriptItem::Key(_, 1)));
+        assert_eq!(items[1], ScriptItem::Code(vec![OP_CHECKSIGVERIFY]));
+        assert_eq!(items[2], ScriptItem::Data(vec![0x90, 0x00]));
+        assert_eq!(items[3], ScriptItem::Code(vec![OP_CSV]));
+    }
+
+    #[test]
+    fn satisfy() {
+        let or = Miniscript::OrD(
+            Box::new(pk(0)),
+            Box::new(Miniscript::AndV(Box::new(v(pk(2))), Box::new(Miniscript::Older(10)))),
+        );
+        // Key 0 has no signature, so the second branch is used: its satisfaction goes below the
+        // dissatisfaction of the first branch.
+        assert_eq!(or.satisfy(&TestSatisfier), Some(vec![vec![2; 71], vec![]]));
+
+        let thresh = Miniscript::Thresh(2, vec![
+            pk(1),
+            Miniscript::Wrap(MsWrapper::Swap, Box::new(pk(0))),
+            Miniscript::Wrap(MsWrapper::Swap, Box::new(pk(3))),
+        ]);
+        assert_eq!(thresh.satisfy(&TestSatisfier), Some(vec![vec![3; 71], vec![], vec![1; 71]]));
+
+        let multi = Miniscript::Multi(2, vec![1u8, 0, 3]);
+        assert_eq!(multi.satisfy(&TestSatisfier), Some(vec![vec![], vec![1; 71], vec![3; 71]]));
+        assert_eq!(Miniscript::Multi(2, vec![1u8, 0]).satisfy(&TestSatisfier), None);
+    }
+
+    #[test]
+    fn max_satisfaction() {
+        let or = Miniscript::OrI(
+            Box::new(pk(1)),
+            Box::new(Miniscript::AndV(Box::new(v(pk(2))), Box::new(pk(3)))),
+        );
+        assert_eq!(or.satisfy(&TestSatisfier), Some(vec![vec![1; 71], vec![1]]));
+        assert_eq!(
+            or.max_satisfaction(&TestSatisfier),
+            Some(vec![vec![3; 71], vec![2; 71], vec![]])
+        );
+    }
+
+    #[test]
+    fn hash_lock() {
+        assert_eq!(
+            HashLock::sha256(b"").to_string(),
+            "sha256(e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855)"
+        );
+        assert_eq!(
+            HashLock::hash160(b"").to_string(),
+            "hash160(b472a266d0bd89c13706a4132ccfb16f7c3b9fcb)"
+        );
+        assert!(HashLock::hash256(&[0x42; 32]).verify(&[0x42; 32]));
+        assert!(!HashLock::hash256(&[0x42; 32]).verify(&[0x42; 31]));
+        assert!(!HashLock::ripemd160(b"").verify(b"\0"));
+
+        let htlc = |lock| Miniscript::AndV(Box::new(v(pk(1))), Box::new(Miniscript::Hash(lock)));
+        let ms = htlc(HashLock::sha256(&[0x42; 32]));
+        assert_eq!(ms.hash_locks(), vec![&HashLock::sha256(&[0x42; 32])]);
+        // The preimage goes below the signature, since the key is checked first
+        assert_eq!(ms.satisfy(&TestSatisfier), Some(vec![vec![0x42; 32], vec![1; 71]]));
+        // Preimages of other sizes are rejected by the script
+        assert_eq!(htlc(HashLock::sha256(&[0x42; 31])).satisfy(&TestSatisfier), None);
+        assert_eq!(htlc(HashLock::sha256(&[0x43; 32])).satisfy(&TestSatisfier), None);
+    }
+
+    #[test]
+    fn timelocks() {
+        let and_v = |x, y| Miniscript::AndV(Box::new(x), Box::new(y));
+        assert_eq!(pk(1).timelocks(), vec![Timelocks::default()]);
+        assert_eq!(Miniscript::<u8>::False.timelocks(), vec![]);
+
+        // or_d(pk(1),and_v(v:pk(2),older(144)))
+        let ms =
+            Miniscript::OrD(Box::new(pk(1)), Box::new(and_v(v(pk(2)), Miniscript::Older(144))));
+        assert_eq!(ms.timelocks(), vec![Timelocks::default(), Timelocks::older(144)]);
+
+        // and_v(v:after(100),and_v(v:after(200),older(10)))
+        let after = |n| v(Miniscript::After(n));
+        let ms = and_v(after(100), and_v(after(200), Miniscript::Older(10)));
+        assert_eq!(ms.timelocks(), vec![Timelocks {
+            after: Some(LockTime::from_consensus_u32(200)),
+            older: Some(SeqNo::from_consensus_u32(10)),
+        }]);
+        // Height- and time-based locks can't be satisfied together
+        let ms = and_v(after(100), Miniscript::After(500_000_001));
+        assert_eq!(ms.timelocks(), vec![]);
+
+        // Two of the keys and an optional timelock; type wrappers are omitted
+        let older = Miniscript::OrI(Box::new(Miniscript::False), Box::new(Miniscript::Older(10)));
+        let ms = Miniscript::Thresh(2, vec![pk(1), pk(2), older]);
+        assert_eq!(ms.timelocks(), vec![Timelocks::default(), Timelocks::older(10)]);
+        let ms = Miniscript::Thresh(3, vec![pk(1), pk(2), Miniscript::Older(10)]);
+        assert_eq!(ms.timelocks(), vec![Timelocks::older(10)]);
+    }
+}
@@ -28,15 +28,19 @@ use std::{fmt, iter, vec};
 
 use amplify::confinement::ConfinedVec;
 use amplify::num::u4;
-use amplify::Wrapper;
 use derive::{
     CompressedPk, ControlBlock, Derive, DeriveCompr, DeriveKey, DeriveLegacy, DerivedScript,
-    KeyOrigin, Keychain, LegacyPk, NormalIndex, OpCode, RedeemScript, SigScript, TapDerivation,
-    Terminal, Witness, WitnessScript, XOnlyPk, XpubAccount, XpubDerivable,
+    KeyOrigin, Keychain, LegacyPk, NormalIndex, OpCode, RedeemScript, ScriptPubkey, SigScript,
+    TapDerivation, Terminal, Witness, WitnessScript, XOnlyPk, XpubAccount, XpubDerivable,
 };
 use indexmap::IndexMap;
 
-use crate::{Descriptor, LegacyKeySig, SpkClass, TaprootKeySig};
+use crate::compiler::fmt_checksummed;
+use crate::weight::{legacy_key_len, push_len, SatSize};
+use crate::{
+    Descriptor, HashLock, LegacyKeySig, PathPolicy, Policy, SatisfactionWeight, SpendingPath,
+    SpkClass, TaprootKeySig,
+};
 
 /// Representation of BIP-383 `multi` as it is used inside `sh`.
 ///
@@ -61,6 +65,12 @@ impl<K: DeriveLegacy> ShMulti<K> {
     }
     pub fn key_count(&self) -> u8 { self.keys.len() as u8 }
     pub fn threshold(&self) -> u8 { self.threshold.into_u8() }
+    pub fn map_keys<M: DeriveLegacy>(&self, f: &impl Fn(&K) -> M) -> ShMulti<M> {
+        ShMulti {
+            threshold: self.threshold,
+            keys: ConfinedVec::from_iter_checked(self.keys.iter().map(f)),
+        }
+    }
 }
 
 impl<K: DeriveLegacy> Derive<DerivedScript> for ShMulti<K> {
@@ -96,7 +106,18 @@ where Self: Derive<DerivedScript>
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(|key| key.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        legacy_weights(self.threshold, self.keys.iter().map(legacy_key_len))
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let policy = Policy::multi::<LegacyPk, _>(self.threshold.into_u8() as usize, &self.keys);
+        vec![PathPolicy::new(SpendingPath::Script, policy)]
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         legacy_keyset(&self.keys, terminal).collect()
@@ -108,7 +129,9 @@ where Self: Derive<DerivedScript>
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         mut keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -116,15 +139,13 @@ where Self: Derive<DerivedScript>
             return None;
         }
         // Check that all sigs match our keys
-        if !check_sigs(self.keys().map(K::xpub_spec), &keysigs) {
+        if !check_sigs(self.keys(), &keysigs) {
             return None;
         }
 
         // We need to put the sigs into the order that matches the ordering of the keys
         let keysigs = self.keys().filter_map(|key| {
-            let xorigin = key.xpub_spec().origin();
-            let index =
-                keysigs.iter().position(|(origin, _)| &origin.to_account_origin() == xorigin)?;
+            let index = keysigs.iter().position(|(origin, _)| key.is_origin_of(origin))?;
             keysigs.shift_remove_index(index).map(|(_, keysig)| keysig)
         });
 
@@ -135,6 +156,7 @@ where Self: Derive<DerivedScript>
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
@@ -142,6 +164,9 @@ where Self: Derive<DerivedScript>
 
 impl<S: DeriveLegacy> Display for ShMulti<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
         f.write_str("sh(")?;
         fmt(self.threshold, self.keys(), f)?;
         f.write_str(")")
@@ -171,6 +196,12 @@ impl<K: DeriveLegacy> ShSortedMulti<K> {
     }
     pub fn key_count(&self) -> u8 { self.keys.len() as u8 }
     pub fn threshold(&self) -> u8 { self.threshold.into_u8() }
+    pub fn map_keys<M: DeriveLegacy>(&self, f: &impl Fn(&K) -> M) -> ShSortedMulti<M> {
+        ShSortedMulti {
+            threshold: self.threshold,
+            keys: ConfinedVec::from_iter_checked(self.keys.iter().map(f)),
+        }
+    }
 }
 
 impl<K: DeriveLegacy> Derive<DerivedScript> for ShSortedMulti<K> {
@@ -207,7 +238,18 @@ where Self: Derive<DerivedScript>
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(|key| key.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        legacy_weights(self.threshold, self.keys.iter().map(legacy_key_len))
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let policy = Policy::multi::<LegacyPk, _>(self.threshold.into_u8() as usize, &self.keys);
+        vec![PathPolicy::new(SpendingPath::Script, policy)]
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         // BTreeMap here provides us with the key sorting
@@ -220,7 +262,9 @@ where Self: Derive<DerivedScript>
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -228,7 +272,7 @@ where Self: Derive<DerivedScript>
             return None;
         }
         // Check that all sigs match our keys
-        if !check_sigs(self.keys().map(K::xpub_spec), &keysigs) {
+        if !check_sigs(self.keys(), &keysigs) {
             return None;
         }
         // We need to put the sigs into the order that matches the ordering of the keys
@@ -241,6 +285,7 @@ where Self: Derive<DerivedScript>
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
@@ -248,6 +293,9 @@ where Self: Derive<DerivedScript>
 
 impl<S: DeriveLegacy> Display for ShSortedMulti<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
         f.write_str("sh(sorted")?;
         fmt(self.threshold, self.keys(), f)?;
         f.write_str(")")
@@ -277,6 +325,12 @@ impl<K: DeriveCompr> WshMulti<K> {
     }
     pub fn key_count(&self) -> u8 { self.keys.len() as u8 }
     pub fn threshold(&self) -> u8 { self.threshold.into_u8() }
+    pub fn map_keys<M: DeriveCompr>(&self, f: &impl Fn(&K) -> M) -> WshMulti<M> {
+        WshMulti {
+            threshold: self.threshold,
+            keys: ConfinedVec::from_iter_checked(self.keys.iter().map(f)),
+        }
+    }
 }
 
 impl<K: DeriveCompr> Derive<DerivedScript> for WshMulti<K> {
@@ -310,7 +364,19 @@ where Self: Derive<DerivedScript>
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(|key| key.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        segwit_weights(self.threshold, self.keys.len(), false)
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let policy =
+            Policy::multi::<CompressedPk, _>(self.threshold.into_u8() as usize, &self.keys);
+        vec![PathPolicy::new(SpendingPath::Script, policy)]
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         legacy_keyset(self.keys(), terminal).collect()
@@ -322,7 +388,9 @@ where Self: Derive<DerivedScript>
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         mut keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -330,14 +398,12 @@ where Self: Derive<DerivedScript>
             return None;
         }
         // Check that all sigs match our keys
-        if !check_sigs(self.keys().map(K::xpub_spec), &keysigs) {
+        if !check_sigs(self.keys(), &keysigs) {
             return None;
         }
         // We need to put the sigs into the order that matches the ordering of the keys
         let keysigs = self.keys().filter_map(|key| {
-            let xorigin = key.xpub_spec().origin();
-            let index =
-                keysigs.iter().position(|(origin, _)| &origin.to_account_origin() == xorigin)?;
+            let index = keysigs.iter().position(|(origin, _)| key.is_origin_of(origin))?;
             keysigs.shift_remove_index(index).map(|(_, keysig)| keysig)
         });
         Some(witness(keysigs, witness_script?))
@@ -347,6 +413,7 @@ where Self: Derive<DerivedScript>
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
@@ -354,6 +421,9 @@ where Self: Derive<DerivedScript>
 
 impl<S: DeriveCompr> Display for WshMulti<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
         f.write_str("wsh(")?;
         fmt(self.threshold, self.keys(), f)?;
         f.write_str(")")
@@ -383,6 +453,12 @@ impl<K: DeriveCompr> WshSortedMulti<K> {
     }
     pub fn key_count(&self) -> u8 { self.keys.len() as u8 }
     pub fn threshold(&self) -> u8 { self.threshold.into_u8() }
+    pub fn map_keys<M: DeriveCompr>(&self, f: &impl Fn(&K) -> M) -> WshSortedMulti<M> {
+        WshSortedMulti {
+            threshold: self.threshold,
+            keys: ConfinedVec::from_iter_checked(self.keys.iter().map(f)),
+        }
+    }
 }
 
 impl<K: DeriveCompr> Derive<DerivedScript> for WshSortedMulti<K> {
@@ -416,7 +492,19 @@ where Self: Derive<DerivedScript>
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(|key| key.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        segwit_weights(self.threshold, self.keys.len(), false)
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let policy =
+            Policy::multi::<CompressedPk, _>(self.threshold.into_u8() as usize, &self.keys);
+        vec![PathPolicy::new(SpendingPath::Script, policy)]
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         // BTreeMap here provides us with the key sorting
@@ -429,7 +517,9 @@ where Self: Derive<DerivedScript>
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -437,7 +527,7 @@ where Self: Derive<DerivedScript>
             return None;
         }
         // Check that all sigs match our keys
-        if !check_sigs(self.keys().map(K::xpub_spec), &keysigs) {
+        if !check_sigs(self.keys(), &keysigs) {
             return None;
         }
         // We need to put the sigs into the order that matches the ordering of the keys
@@ -450,6 +540,7 @@ where Self: Derive<DerivedScript>
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
@@ -457,6 +548,9 @@ where Self: Derive<DerivedScript>
 
 impl<S: DeriveCompr> Display for WshSortedMulti<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
         f.write_str("wsh(sorted")?;
         fmt(self.threshold, self.keys(), f)?;
         f.write_str(")")
@@ -500,6 +594,12 @@ impl<K: DeriveCompr> ShWshMulti<K> {
             keys: self.keys,
         }
     }
+    pub fn map_keys<M: DeriveCompr>(&self, f: &impl Fn(&K) -> M) -> ShWshMulti<M> {
+        ShWshMulti {
+            threshold: self.threshold,
+            keys: ConfinedVec::from_iter_checked(self.keys.iter().map(f)),
+        }
+    }
 }
 
 impl<K: DeriveCompr> Derive<DerivedScript> for ShWshMulti<K> {
@@ -533,7 +633,19 @@ where Self: Derive<DerivedScript>
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(|key| key.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        segwit_weights(self.threshold, self.keys.len(), true)
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let policy =
+            Policy::multi::<CompressedPk, _>(self.threshold.into_u8() as usize, &self.keys);
+        vec![PathPolicy::new(SpendingPath::Script, policy)]
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         legacy_keyset(self.keys(), terminal).collect()
@@ -545,25 +657,26 @@ where Self: Derive<DerivedScript>
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         mut keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
         redeem_script.as_ref()?;
         // Check that all sigs match our keys
-        if !check_sigs(self.keys().map(K::xpub_spec), &keysigs) {
+        if !check_sigs(self.keys(), &keysigs) {
             return None;
         }
         // We need to put the sigs into the order that matches the ordering of the keys
         let keysigs = self.keys().filter_map(|key| {
-            let xorigin = key.xpub_spec().origin();
-            let index =
-                keysigs.iter().position(|(origin, _)| &origin.to_account_origin() == xorigin)?;
+            let index = keysigs.iter().position(|(origin, _)| key.is_origin_of(origin))?;
             keysigs.shift_remove_index(index).map(|(_, keysig)| keysig)
         });
 
         let (_, witness) = witness(keysigs, witness_script?);
-        let sig_script = SigScript::from_checked(redeem_script?.into_inner().into_vec());
+        let mut sig_script = SigScript::with_capacity(35);
+        sig_script.push_slice(redeem_script?.as_slice());
         Some((sig_script, Some(witness?)))
     }
 
@@ -571,6 +684,7 @@ where Self: Derive<DerivedScript>
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
@@ -578,6 +692,9 @@ where Self: Derive<DerivedScript>
 
 impl<S: DeriveCompr> Display for ShWshMulti<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
         f.write_str("sh(wsh(")?;
         fmt(self.threshold, self.keys(), f)?;
         f.write_str("))")
@@ -607,6 +724,12 @@ impl<K: DeriveCompr> ShWshSortedMulti<K> {
     }
     pub fn key_count(&self) -> u8 { self.keys.len() as u8 }
     pub fn threshold(&self) -> u8 { self.threshold.into_u8() }
+    pub fn map_keys<M: DeriveCompr>(&self, f: &impl Fn(&K) -> M) -> ShWshSortedMulti<M> {
+        ShWshSortedMulti {
+            threshold: self.threshold,
+            keys: ConfinedVec::from_iter_checked(self.keys.iter().map(f)),
+        }
+    }
 }
 
 impl<K: DeriveCompr> Derive<DerivedScript> for ShWshSortedMulti<K> {
@@ -640,7 +763,19 @@ where Self: Derive<DerivedScript>
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(|key| key.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        segwit_weights(self.threshold, self.keys.len(), true)
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let policy =
+            Policy::multi::<CompressedPk, _>(self.threshold.into_u8() as usize, &self.keys);
+        vec![PathPolicy::new(SpendingPath::Script, policy)]
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         // BTreeMap here provides us with the key sorting
@@ -653,13 +788,15 @@ where Self: Derive<DerivedScript>
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
         redeem_script.as_ref()?;
         // Check that all sigs match our keys
-        if !check_sigs(self.keys().map(K::xpub_spec), &keysigs) {
+        if !check_sigs(self.keys(), &keysigs) {
             return None;
         }
         // We need to put the sigs into the order that matches the ordering of the keys
@@ -667,7 +804,8 @@ where Self: Derive<DerivedScript>
             keysigs.into_values().map(|keysig| (keysig.key, keysig)).collect::<BTreeMap<_, _>>();
 
         let (_, witness) = witness(sorted.into_values(), witness_script?);
-        let sig_script = SigScript::from_checked(redeem_script?.into_inner().into_vec());
+        let mut sig_script = SigScript::with_capacity(35);
+        sig_script.push_slice(redeem_script?.as_slice());
 
         Some((sig_script, Some(witness?)))
     }
@@ -676,6 +814,7 @@ where Self: Derive<DerivedScript>
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
@@ -683,6 +822,9 @@ where Self: Derive<DerivedScript>
 
 impl<S: DeriveCompr> Display for ShWshSortedMulti<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
         f.write_str("sh(wsh(sorted")?;
         fmt(self.threshold, self.keys(), f)?;
         f.write_str("))")
@@ -692,12 +834,12 @@ impl<S: DeriveCompr> Display for ShWshSortedMulti<S> {
 // ------------------------------------------------------------------------------------------------
 
 /// Check that all sigs match our keys
-fn check_sigs<'a>(
-    accounts: impl Iterator<Item = &'a XpubAccount>,
-    keysigs: &IndexMap<&'a KeyOrigin, LegacyKeySig>,
+fn check_sigs<'a, T, K: DeriveKey<T> + 'a>(
+    keys: impl Iterator<Item = &'a K>,
+    keysigs: &IndexMap<&KeyOrigin, LegacyKeySig>,
 ) -> bool {
-    let set = accounts.map(XpubAccount::origin).collect::<BTreeSet<_>>();
-    keysigs.keys().all(|origin| set.contains(&origin.to_account_origin()))
+    let keys = keys.collect::<Vec<_>>();
+    keysigs.keys().all(|origin| keys.iter().any(|key| key.is_origin_of(origin)))
 }
 
 fn derive<'k, T, K: Derive<T> + 'k, I: IntoIterator<Item = &'k K>>(
@@ -768,8 +910,32 @@ fn legacy_keyset<'k, T: Into<LegacyPk>, K: DeriveKey<T> + 'k, I: IntoIterator<It
             .derive(terminal.keychain, terminal.index)
             .next()
             .expect("multisig must derive one key per path");
-        (key.into(), KeyOrigin::with(xkey.xpub_spec().origin().clone(), terminal))
+        (key.into(), xkey.key_origin(terminal))
+    })
+}
+
+fn legacy_weights(threshold: u4, key_lens: impl Iterator<Item = usize>) -> Vec<SatisfactionWeight> {
+    // Threshold, keys, key count and `OP_CHECKMULTISIG`
+    let redeem_script_len = key_lens.map(push_len).sum::<usize>() + 3;
+    SatisfactionWeight::estimate(SpendingPath::Script, |estimate| {
+        let sigs = threshold.into_u8() as usize * push_len(estimate.ecdsa_sig_len());
+        // Includes `OP_0` consumed by the infamous OP_CHECKMULTISIG bug
+        Some(SatSize::legacy(1 + sigs + push_len(redeem_script_len)))
+    })
+    .into_iter()
+    .collect()
+}
+
+fn segwit_weights(threshold: u4, key_count: usize, nested: bool) -> Vec<SatisfactionWeight> {
+    let witness_script_len = key_count * 34 + 3;
+    let sig_script_len = if nested { push_len(34) } else { 0 };
+    SatisfactionWeight::estimate(SpendingPath::Script, |estimate| {
+        let sigs = iter::repeat_n(estimate.ecdsa_sig_len(), threshold.into_u8() as usize);
+        let witness = iter::once(0).chain(sigs).chain([witness_script_len]);
+        Some(SatSize::segwit(sig_script_len, witness))
     })
+    .into_iter()
+    .collect()
 }
 
 fn sig_script(
@@ -0,0 +1,475 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+
+use derive::{
+    DeriveKey, Idx, Keychain, LockTime, NormalIndex, SeqNo, Terminal, TimeLockInterval, XkeyOrigin,
+    XpubFp,
+};
+
+use crate::{HashLock, Miniscript, SpendingPath, Timelocks};
+
+/// Semantic policy of a spending path: the conditions which must be met for spending, abstracted
+/// from the script details.
+///
+/// Policies are normalized on construction: nested conjunctions and disjunctions are flattened,
+/// and trivial and unsatisfiable sub-policies are folded into their parents.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub enum Policy {
+    /// The path can't be used.
+    Unsatisfiable,
+    /// The path can be used by anyone.
+    Trivial,
+    /// Signature by the key with the given account origin.
+    Key(XkeyOrigin),
+    /// Absolute timelock.
+    After(LockTime),
+    /// Relative timelock.
+    Older(SeqNo),
+    /// Preimage of the hash.
+    Hash(HashLock),
+    /// At least `k` of the sub-policies.
+    Thresh(usize, Vec<Policy>),
+}
+
+impl Policy {
+    /// Policy requiring a signature of the `key`.
+    pub fn key<P>(key: &impl DeriveKey<P>) -> Policy { Policy::Key(account_origin(key)) }
+
+    /// Policy requiring signatures of `k` of the `keys`.
+    pub fn multi<'k, P, K: DeriveKey<P> + 'k>(
+        k: usize,
+        keys: impl IntoIterator<Item = &'k K>,
+    ) -> Policy {
+        Policy::thresh(k, keys.into_iter().map(Policy::key).collect())
+    }
+
+    /// Policy requiring both sub-policies.
+    pub fn and(a: Policy, b: Policy) -> Policy { Policy::thresh(2, vec![a, b]) }
+
+    /// Policy requiring any of the sub-policies.
+    pub fn or(a: Policy, b: Policy) -> Policy { Policy::thresh(1, vec![a, b]) }
+
+    /// Policy requiring at least `k` of the sub-policies.
+    pub fn thresh(mut k: usize, subs: Vec<Policy>) -> Policy {
+        let (is_and, is_or) = (k == subs.len(), k == 1);
+        let mut flat = Vec::with_capacity(subs.len());
+        for sub in subs {
+            match sub {
+                Policy::Trivial => k = k.saturating_sub(1),
+                Policy::Unsatisfiable => {}
+                // and(and(a,b),c) is and(a,b,c), or(or(a,b),c) is or(a,b,c)
+                Policy::Thresh(m, inner) if is_and && m == inner.len() => {
+                    k += inner.len() - 1;
+                    flat.extend(inner);
+                }
+                Policy::Thresh(1, inner) if is_or => flat.extend(inner),
+                sub => flat.push(sub),
+            }
+        }
+        match flat.len() {
+            _ if k == 0 => Policy::Trivial,
+            len if len < k => Policy::Unsatisfiable,
+            1 => flat.remove(0),
+            _ => Policy::Thresh(k, flat),
+        }
+    }
+
+    /// Extracts the policy of a miniscript, identifying its keys by their account origins.
+    pub fn from_miniscript<K>(ms: &Miniscript<K>, origin: &impl Fn(&K) -> XkeyOrigin) -> Policy {
+        let policy = |ms: &Miniscript<K>| Policy::from_miniscript(ms, origin);
+        let keys = |k: usize, keys: &[K]| {
+            Policy::thresh(k, keys.iter().map(|key| Policy::Key(origin(key))).collect())
+        };
+        match ms {
+            Miniscript::False => Policy::Unsatisfiable,
+            Miniscript::True => Policy::Trivial,
+            Miniscript::PkK(key) | Miniscript::PkH(key) => Policy::Key(origin(key)),
+            Miniscript::Older(n) => Policy::Older(SeqNo::from_consensus_u32(*n)),
+            Miniscript::After(n) => Policy::After(LockTime::from_consensus_u32(*n)),
+            Miniscript::Hash(lock) => Policy::Hash(*lock),
+            Miniscript::AndOr(x, y, z) => Policy::or(Policy::and(policy(x), policy(y)), policy(z)),
+            Miniscript::AndV(x, y) | Miniscript::AndB(x, y) => Policy::and(policy(x), policy(y)),
+            Miniscript::OrB(x, y)
+            | Miniscript::OrC(x, y)
+            | Miniscript::OrD(x, y)
+            | Miniscript::OrI(x, y) => Policy::or(policy(x), policy(y)),
+            Miniscript::Thresh(k, subs) => Policy::thresh(*k, subs.iter().map(policy).collect()),
+            Miniscript::Multi(k, k_keys) | Miniscript::MultiA(k, k_keys) => keys(*k, k_keys),
+            Miniscript::Wrap(_, x) => policy(x),
+        }
+    }
+
+    /// Keys which may participate in satisfying the policy.
+    pub fn keys(&self) -> BTreeSet<&XkeyOrigin> {
+        match self {
+            Policy::Key(origin) => bset![origin],
+            Policy::Thresh(_, subs) => subs.iter().flat_map(Policy::keys).collect(),
+            _ => bset![],
+        }
+    }
+
+    /// Detects whether signers holding keys with the given master key fingerprints can satisfy
+    /// the policy in the `context`.
+    pub fn can_spend(&self, signers: &[XpubFp], context: &SpendContext) -> bool {
+        !self.satisfactions(context, Some(signers)).is_empty()
+    }
+
+    /// Keys which must sign to satisfy the policy in the `context`, whichever alternative is
+    /// used. Returns `None` if the policy can't be satisfied in the `context` at all.
+    pub fn required_keys(&self, context: &SpendContext) -> Option<BTreeSet<XkeyOrigin>> {
+        self.satisfactions(context, None)
+            .into_iter()
+            .map(|(_, keys)| keys)
+            .reduce(|a, b| a.intersection(&b).cloned().collect())
+    }
+
+    /// Ways of satisfying the policy in the `context`, grouped by the timelocks they use: for
+    /// each of the groups, the keys signing in all of its satisfactions.
+    ///
+    /// Satisfactions which mix height- and time-based timelocks of the same kind are not valid by
+    /// consensus and are skipped.
+    fn satisfactions(
+        &self,
+        context: &SpendContext,
+        signers: Option<&[XpubFp]>,
+    ) -> Vec<(Timelocks, BTreeSet<XkeyOrigin>)> {
+        let none = vec![];
+        let free = |timelocks| vec![(timelocks, bset![])];
+        match self {
+            Policy::Unsatisfiable => none,
+            Policy::Trivial => free(Timelocks::default()),
+            Policy::Key(origin)
+                if signers.is_some_and(|signers| !signers.contains(&origin.master_fp())) =>
+            {
+                none
+            }
+            Policy::Key(origin) => vec![(Timelocks::default(), bset![origin.clone()])],
+            Policy::After(lock) if context.is_after(*lock) => free(Timelocks {
+                after: Some(*lock),
+                older: None,
+            }),
+            Policy::Older(seq) if context.is_older(*seq) => free(Timelocks {
+                after: None,
+                older: Some(*seq),
+            }),
+            Policy::Hash(lock) if context.preimages.contains(lock) => free(Timelocks::default()),
+            Policy::After(_) | Policy::Older(_) | Policy::Hash(_) => none,
+            Policy::Thresh(k, subs) => {
+                // Satisfactions of `j` sub-policies among the already processed ones
+                let mut sats = vec![vec![]; *k + 1];
+                sats[0] = free(Timelocks::default());
+                for sub in subs {
+                    let sub_sats = sub.satisfactions(context, signers);
+                    for j in (1..=*k).rev() {
+                        let (prev, next) = sats.split_at_mut(j);
+                        for (a, a_keys) in &prev[j - 1] {
+                            for (b, b_keys) in &sub_sats {
+                                let Some(timelocks) = a.combine(*b) else {
+                                    continue;
+                                };
+                                let keys = a_keys.union(b_keys).cloned().collect();
+                                merge_satisfaction(&mut next[0], timelocks, keys);
+                            }
+                        }
+                    }
+                }
+                sats.pop().expect("k + 1 items")
+            }
+        }
+    }
+}
+
+/// Account origin identifying the `key` in policies.
+pub(crate) fn account_origin<P>(key: &impl DeriveKey<P>) -> XkeyOrigin {
+    let terminal = Terminal::new(Keychain::OUTER, NormalIndex::ZERO);
+    key.key_origin(terminal).to_account_origin()
+}
+
+/// Adds a way of satisfying a policy, keeping in a group with the same timelocks only the keys
+/// used by all of its satisfactions.
+fn merge_satisfaction(
+    sats: &mut Vec<(Timelocks, BTreeSet<XkeyOrigin>)>,
+    timelocks: Timelocks,
+    keys: BTreeSet<XkeyOrigin>,
+) {
+    match sats.iter_mut().find(|(t, _)| *t == timelocks) {
+        Some((_, existing)) => existing.retain(|key| keys.contains(key)),
+        None => sats.push((timelocks, keys)),
+    }
+}
+
+impl Display for Policy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Policy::Unsatisfiable => f.write_str("UNSATISFIABLE"),
+            Policy::Trivial => f.write_str("TRIVIAL"),
+            Policy::Key(origin) => write!(f, "pk([{origin}])"),
+            Policy::After(lock) => write!(f, "after({})", lock.to_consensus_u32()),
+            Policy::Older(seq) => write!(f, "older({})", seq.to_consensus_u32()),
+            Policy::Hash(lock) => Display::fmt(lock, f),
+            Policy::Thresh(k, subs) => {
+                match *k {
+                    k if k == subs.len() => f.write_str("and(")?,
+                    1 => f.write_str("or(")?,
+                    k => write!(f, "thresh({k},")?,
+                }
+                for (no, sub) in subs.iter().enumerate() {
+                    if no > 0 {
+                        f.write_str(",")?;
+                    }
+                    Display::fmt(sub, f)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Policy of a descriptor spending path.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display("{path}: {policy}")]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub struct PathPolicy {
+    pub path: SpendingPath,
+    pub policy: Policy,
+}
+
+impl PathPolicy {
+    pub fn new(path: SpendingPath, policy: Policy) -> Self { PathPolicy { path, policy } }
+}
+
+/// State of the blockchain and of the spent output, against which the policy conditions are
+/// checked.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SpendContext {
+    /// Height of the last block of the chain; the spending transaction is assumed to be mined in
+    /// the next block.
+    pub height: u32,
+    /// Median time past of the last block of the chain.
+    pub time: u32,
+    /// Number of confirmations of the spent output, zero if it is not mined yet.
+    pub confirmations: u32,
+    /// Seconds elapsed since the spent output was mined, measured in median time past as in
+    /// BIP-68.
+    pub elapsed: u32,
+    /// Hash locks which preimages are known.
+    pub preimages: Vec<HashLock>,
+}
+
+impl SpendContext {
+    /// Context at the given block height, when the spent output is not mined yet and no hash
+    /// preimages are known.
+    pub fn at_height(height: u32) -> Self {
+        SpendContext {
+            height,
+            ..default!()
+        }
+    }
+
+    /// Detects whether the absolute timelock has expired.
+    pub fn is_after(&self, lock: LockTime) -> bool {
+        // Transactions are final when their lock time is below the height of the block which
+        // mines them, or below the median time past of the last block
+        if lock.is_height_based() {
+            lock.to_consensus_u32() <= self.height
+        } else {
+            lock.to_consensus_u32() < self.time
+        }
+    }
+
+    /// Detects whether the relative timelock has expired.
+    pub fn is_older(&self, seq: SeqNo) -> bool {
+        match seq.time_lock_interval() {
+            None => true,
+            Some(TimeLockInterval::Height(blocks)) => blocks as u32 <= self.confirmations,
+            Some(TimeLockInterval::Time(intervals)) => intervals as u32 * 512 <= self.elapsed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use derive::{HardenedIndex, XprivAccount, XprivDerivable, XpubDerivable, XpubFp};
+
+    use super::*;
+    use crate::{Descriptor, StdDescr};
+
+    fn xpub(seed: u8) -> XpubDerivable {
+        let xpriv = XprivAccount::with_seed(true, &[seed; 32]).derive([
+            HardenedIndex::from(84u16),
+            HardenedIndex::ONE,
+            HardenedIndex::ZERO,
+        ]);
+        XprivDerivable::with(xpriv, &[Keychain::OUTER, Keychain::INNER]).to_xpub_derivable()
+    }
+
+    fn key(fp: u8) -> Policy { Policy::Key(XkeyOrigin::new_master(XpubFp::from([fp; 4]))) }
+    fn fp(fp: u8) -> XpubFp { XpubFp::from([fp; 4]) }
+
+    #[test]
+    fn normalize() {
+        let and = Policy::and(key(1), Policy::and(key(2), key(3)));
+        assert_eq!(and, Policy::Thresh(3, vec![key(1), key(2), key(3)]));
+        let or = Policy::or(Policy::or(key(1), Policy::Unsatisfiable), key(2));
+        assert_eq!(or, Policy::Thresh(1, vec![key(1), key(2)]));
+        assert_eq!(Policy::and(key(1), Policy::Trivial), key(1));
+        assert_eq!(Policy::and(key(1), Policy::Unsatisfiable), Policy::Unsatisfiable);
+        assert_eq!(Policy::or(key(1), Policy::Trivial), Policy::Trivial);
+        assert_eq!(
+            Policy::thresh(2, vec![key(1), Policy::Trivial, Policy::and(key(2), key(3))]),
+            Policy::Thresh(1, vec![key(1), Policy::Thresh(2, vec![key(2), key(3)])])
+        );
+        assert_eq!(
+            Policy::thresh(2, vec![key(1), key(2), key(3)]).to_string(),
+            "thresh(2,pk([01010101]),pk([02020202]),pk([03030303]))"
+        );
+    }
+
+    #[test]
+    fn queries() {
+        // or(and(1,2),and(3,after(100)),and(1,sha256))
+        let lock = HashLock::sha256(b"secret");
+        let policy = Policy::thresh(1, vec![
+            Policy::and(key(1), key(2)),
+            Policy::and(key(3), Policy::After(LockTime::from_consensus_u32(100))),
+            Policy::and(key(1), Policy::Hash(lock)),
+        ]);
+
+        let before = SpendContext::at_height(99);
+        let after = SpendContext::at_height(100);
+        assert!(policy.can_spend(&[fp(1), fp(2)], &before));
+        assert!(!policy.can_spend(&[fp(3)], &before));
+        assert!(policy.can_spend(&[fp(3)], &after));
+
+        let with_preimage = SpendContext {
+            preimages: vec![lock],
+            ..before.clone()
+        };
+        assert!(!policy.can_spend(&[fp(1)], &before));
+        assert!(policy.can_spend(&[fp(1)], &with_preimage));
+
+        let origin = |fp: u8| XkeyOrigin::new_master(XpubFp::from([fp; 4]));
+        assert_eq!(policy.required_keys(&before), Some(bset![origin(1), origin(2)]));
+        assert_eq!(policy.required_keys(&with_preimage), Some(bset![origin(1)]));
+        assert_eq!(policy.required_keys(&after), Some(bset![]));
+        assert_eq!(Policy::Unsatisfiable.required_keys(&after), None);
+    }
+
+    #[test]
+    fn timelock_mix() {
+        let height = Policy::After(LockTime::from_consensus_u32(100));
+        let time = Policy::After(LockTime::from_consensus_u32(500_000_100));
+        let context = SpendContext {
+            height: 200,
+            time: 500_000_200,
+            ..default!()
+        };
+        assert!(height.can_spend(&[], &context));
+        assert!(time.can_spend(&[], &context));
+        assert!(!Policy::and(height.clone(), time.clone()).can_spend(&[], &context));
+        assert!(Policy::or(height, time).can_spend(&[], &context));
+
+        let blocks = Policy::Older(SeqNo::from_height(10));
+        let interval = Policy::Older(SeqNo::from_intervals(2));
+        let context = SpendContext {
+            confirmations: 10,
+            elapsed: 1023,
+            ..default!()
+        };
+        assert!(blocks.can_spend(&[], &context));
+        assert!(!interval.can_spend(&[], &context));
+    }
+
+    #[test]
+    fn descriptors() {
+        let (a, b, c) = (xpub(1), xpub(2), xpub(3));
+        let (fp_a, fp_b, fp_c) =
+            (a.origin().master_fp(), b.origin().master_fp(), c.origin().master_fp());
+
+        let descr =
+            StdDescr::<XpubDerivable>::from_str(&format!("wsh(multi(2,{a},{b},{c}))")).unwrap();
+        assert_eq!(descr.policies().iter().map(PathPolicy::to_string).collect::<Vec<_>>(), vec![
+            format!(
+                "script: thresh(2,pk([{}]),pk([{}]),pk([{}]))",
+                a.origin(),
+                b.origin(),
+                c.origin()
+            )
+        ]);
+        let now = SpendContext::at_height(800_000);
+        assert!(descr.can_spend(&[fp_a, fp_c], &now));
+        assert!(!descr.can_spend(&[fp_b], &now));
+        assert_eq!(descr.required_keys(&now), Some(bset![]));
+
+        // andor(pk(A),after(1000),pk(B)) is or(and(A,after(1000)),B)
+        let descr = StdDescr::<XpubDerivable>::from_str(&format!(
+            "wsh(andor(pk({a}),after(1000),pk({b})))"
+        ))
+        .unwrap();
+        assert!(!descr.can_spend(&[fp_a], &SpendContext::at_height(999)));
+        assert!(descr.can_spend(&[fp_a], &SpendContext::at_height(1000)));
+        assert_eq!(
+            descr.required_keys(&SpendContext::at_height(999)),
+            Some(bset![b.origin().clone()])
+        );
+        assert_eq!(descr.required_keys(&SpendContext::at_height(1000)), Some(bset![]));
+
+        let descr = StdDescr::<XpubDerivable>::from_str(&format!(
+            "tr({a},{{and_v(v:pk({b}),older(10)),and_v(v:pk({b}),pk({c}))}})"
+        ))
+        .unwrap();
+        let paths = descr.policies();
+        assert_eq!(paths.iter().map(|path| path.path).collect::<Vec<_>>(), vec![
+            SpendingPath::KeyPath,
+            SpendingPath::ScriptPath(0),
+            SpendingPath::ScriptPath(1)
+        ]);
+        assert_eq!(paths[0].policy, Policy::Key(a.origin().clone()));
+        let fresh = SpendContext::at_height(800_000);
+        let aged = SpendContext {
+            confirmations: 10,
+            ..fresh.clone()
+        };
+        assert!(descr.can_spend(&[fp_a], &fresh));
+        assert!(!descr.can_spend(&[fp_b], &fresh));
+        assert!(descr.can_spend(&[fp_b], &aged));
+        assert!(descr.can_spend(&[fp_b, fp_c], &fresh));
+        assert_eq!(
+            paths[2].policy.required_keys(&fresh),
+            Some(bset![b.origin().clone(), c.origin().clone()])
+        );
+
+        let descr =
+            StdDescr::<XpubDerivable>::from_str("addr(bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq)")
+                .unwrap();
+        assert_eq!(descr.policies(), vec![]);
+        assert_eq!(descr.required_keys(&fresh), None);
+    }
+}
@@ -30,25 +30,35 @@ use std::iter;
 use amplify::confinement::Collection;
 use amplify::hex::ToHex;
 use amplify::Wrapper;
+use commit_verify::{DigestExt, Ripemd160, Sha256};
 use derive::{
-    ControlBlock, Derive, DeriveCompr, DeriveLegacy, DeriveSet, DeriveXOnly, DerivedScript,
-    KeyOrigin, Keychain, LeafScript, LegacyPk, NormalIndex, OpCode, RedeemScript, ScriptPubkey,
-    SigScript, TapCode, TapDerivation, TapScript, Terminal, Witness, WitnessScript, XOnlyPk,
-    XkeyOrigin, XpubAccount, XpubDerivable,
+    Address, CompressedPk, ControlBlock, Derive, DeriveCompr, DeriveKey, DeriveLegacy, DeriveSet,
+    DeriveXOnly, DerivedScript, Idx, KeyOrigin, Keychain, LeafScript, LegacyPk, NormalIndex,
+    OpCode, PubkeyHash, RedeemScript, ScriptPubkey, SigScript, TapCode, TapDerivation, TapScript,
+    Terminal, Witness, WitnessScript, XOnlyPk, XkeyOrigin, XpubAccount, XpubDerivable,
 };
 use indexmap::IndexMap;
 
+use crate::compiler::fmt_checksummed;
+use crate::miniscript::KeySigSatisfier;
+use crate::policy::account_origin;
+use crate::weight::{legacy_key_len, push_len, sig_script_len, DummySatisfier, Estimate, SatSize};
 use crate::{
-    Descriptor, LegacyKeySig, ShMulti, ShSortedMulti, ShWpkh, ShWshMulti, ShWshSortedMulti,
-    SpkClass, TaprootKeySig, WshMulti, WshSortedMulti,
+    Descriptor, HashLock, LegacyKeySig, Miniscript, PathPolicy, Policy, SatisfactionWeight,
+    ShMulti, ShSortedMulti, ShWpkh, ShWshMulti, ShWshSortedMulti, SpendingPath, SpkClass,
+    TaprootKeySig, Timelocks, WshMulti, WshSortedMulti,
 };
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ScriptItem<S, K> {
     Key(XkeyOrigin, K),
+    /// HASH160 of the serialized key.
+    KeyHash(XkeyOrigin, K),
     Code(Vec<S>),
     Data(Vec<u8>),
+    /// Script bytes included as-is, without being pushed as data.
+    Raw(Vec<u8>),
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
@@ -63,13 +73,32 @@ pub enum WitnessItem {
 pub struct ScriptDescr<S, K> {
     pub condition: Vec<ScriptItem<S, K>>,
     pub satisfaction: Vec<WitnessItem>,
+    /// Miniscript from which the condition was compiled. If present, it is used to construct the
+    /// satisfaction instead of the `satisfaction` template.
+    pub miniscript: Option<Miniscript<K>>,
+}
+
+impl<S: Copy + Into<u8> + TryFrom<u8>, K: Clone> ScriptDescr<S, K> {
+    /// Constructs script descriptor by compiling a miniscript, which must be already
+    /// type-checked.
+    pub fn with_miniscript<P>(miniscript: Miniscript<K>) -> Self
+    where K: DeriveKey<P> {
+        let terminal = Terminal::new(Keychain::OUTER, NormalIndex::ZERO);
+        let condition =
+            miniscript.compile(|key| DeriveKey::<P>::key_origin(key, terminal).to_account_origin());
+        ScriptDescr {
+            condition,
+            satisfaction: none!(),
+            miniscript: Some(miniscript),
+        }
+    }
 }
 
 impl<S, K: Eq + Hash> ScriptDescr<S, K> {
     pub fn keys(&self) -> impl Iterator<Item = &K> {
         let mut keys = set![];
         for item in &self.condition {
-            if let ScriptItem::Key(_, key) = item {
+            if let ScriptItem::Key(_, key) | ScriptItem::KeyHash(_, key) = item {
                 keys.push(key);
             }
         }
@@ -77,6 +106,101 @@ impl<S, K: Eq + Hash> ScriptDescr<S, K> {
     }
 }
 
+impl<S: Clone, K> ScriptItem<S, K> {
+    pub fn map_key<M>(&self, f: &impl Fn(&K) -> M) -> ScriptItem<S, M> {
+        match self {
+            ScriptItem::Key(origin, key) => ScriptItem::Key(origin.clone(), f(key)),
+            ScriptItem::KeyHash(origin, key) => ScriptItem::KeyHash(origin.clone(), f(key)),
+            ScriptItem::Code(code) => ScriptItem::Code(code.clone()),
+            ScriptItem::Data(data) => ScriptItem::Data(data.clone()),
+            ScriptItem::Raw(script) => ScriptItem::Raw(script.clone()),
+        }
+    }
+}
+
+impl<S: Clone, K> ScriptDescr<S, K> {
+    /// Constructs the same script descriptor with each key replaced by the result of `f`.
+    pub fn map_keys<M>(&self, f: &impl Fn(&K) -> M) -> ScriptDescr<S, M> {
+        ScriptDescr {
+            condition: self.condition.iter().map(|item| item.map_key(f)).collect(),
+            satisfaction: self.satisfaction.clone(),
+            miniscript: self.miniscript.as_ref().map(|ms| ms.map_keys(f)),
+        }
+    }
+}
+
+impl<S, K> ScriptDescr<S, K> {
+    /// Hash locks used by the script. They are known only for the scripts compiled from miniscript.
+    pub fn hash_locks(&self) -> impl Iterator<Item = &HashLock> {
+        self.miniscript.iter().flat_map(Miniscript::hash_locks)
+    }
+
+    /// Timelocks required by each of the script spending paths. Scripts not compiled from
+    /// miniscript are assumed to have no timelock conditions.
+    pub fn timelocks(&self) -> Vec<Timelocks> {
+        match &self.miniscript {
+            Some(ms) => ms.timelocks(),
+            None => vec![Timelocks::default()],
+        }
+    }
+
+    /// Semantic policy of the script. It is known only for the scripts compiled from miniscript.
+    pub fn policy<P>(&self) -> Option<Policy>
+    where K: DeriveKey<P> {
+        let ms = self.miniscript.as_ref()?;
+        Some(Policy::from_miniscript(ms, &|key: &K| account_origin::<P>(key)))
+    }
+
+    /// Placeholder stack satisfying the script, used for estimating the satisfaction size.
+    /// Scripts not compiled from miniscript are satisfied according to the `satisfaction`
+    /// template.
+    pub(crate) fn dummy_stack(
+        &self,
+        estimate: Estimate,
+        sig_len: usize,
+        key_len: impl Fn(&K) -> usize,
+    ) -> Option<Vec<Vec<u8>>> {
+        let satisfier = DummySatisfier::new(sig_len, key_len);
+        match (&self.miniscript, estimate) {
+            (Some(ms), Estimate::Max) => ms.max_satisfaction(&satisfier),
+            (Some(ms), Estimate::Expected) => ms.satisfy(&satisfier),
+            (None, _) => Some(
+                self.satisfaction
+                    .iter()
+                    .map(|item| match item {
+                        WitnessItem::Signature(_) => vec![0xFF; sig_len],
+                        WitnessItem::Data(data) => data.clone(),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    pub(crate) fn legacy_stack(
+        &self,
+        keysigs: &IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
+        is_origin_of: impl Fn(&K, &KeyOrigin) -> bool,
+    ) -> Option<Vec<Vec<u8>>> {
+        if let Some(miniscript) = &self.miniscript {
+            return miniscript.satisfy(&KeySigSatisfier::new(keysigs, preimages, is_origin_of));
+        }
+        let mut stack = vec![];
+        for item in &self.satisfaction {
+            match item {
+                WitnessItem::Signature(origin) => {
+                    let Some(src) = keysigs.get(origin) else {
+                        break;
+                    };
+                    stack.push(src.sig.to_vec());
+                }
+                WitnessItem::Data(data) => stack.push(data.clone()),
+            }
+        }
+        Some(stack)
+    }
+}
+
 impl<K: DeriveLegacy> Derive<RedeemScript> for ScriptDescr<OpCode, K> {
     fn default_keychain(&self) -> Keychain {
         self.keys().next().map(|k| k.default_keychain()).unwrap_or_else(|| {
@@ -103,12 +227,20 @@ impl<K: DeriveLegacy> Derive<RedeemScript> for ScriptDescr<OpCode, K> {
                         xkey.derive(keychain, index).next().expect("xkey derivation is empty");
                     script.push_slice(&key.serialize());
                 }
+                ScriptItem::KeyHash(_, xkey) => {
+                    let key =
+                        xkey.derive(keychain, index).next().expect("xkey derivation is empty");
+                    script.push_slice(&<[u8; 20]>::from(PubkeyHash::from(key)));
+                }
                 ScriptItem::Code(code) => {
                     for tapcode in code {
                         script.push_opcode(*tapcode);
                     }
                 }
                 ScriptItem::Data(fata) => script.push_slice(fata),
+                ScriptItem::Raw(bytes) => {
+                    script.extend(bytes.iter().copied()).expect("script exceeding 4GB")
+                }
             }
         }
         iter::once(script)
@@ -141,12 +273,20 @@ impl<K: DeriveCompr> Derive<WitnessScript> for ScriptDescr<OpCode, K> {
                         xkey.derive(keychain, index).next().expect("xkey derivation is empty");
                     script.push_slice(&key.serialize());
                 }
+                ScriptItem::KeyHash(_, xkey) => {
+                    let key =
+                        xkey.derive(keychain, index).next().expect("xkey derivation is empty");
+                    script.push_slice(&<[u8; 20]>::from(PubkeyHash::from(key)));
+                }
                 ScriptItem::Code(code) => {
                     for tapcode in code {
                         script.push_opcode(*tapcode);
                     }
                 }
                 ScriptItem::Data(fata) => script.push_slice(fata),
+                ScriptItem::Raw(bytes) => {
+                    script.extend(bytes.iter().copied()).expect("script exceeding 4GB")
+                }
             }
         }
         iter::once(script)
@@ -179,12 +319,24 @@ impl<K: DeriveXOnly> Derive<LeafScript> for ScriptDescr<TapCode, K> {
                         xkey.derive(keychain, index).next().expect("xkey derivation is empty");
                     tap_script.push_slice(&key.to_byte_array());
                 }
+                ScriptItem::KeyHash(_, xkey) => {
+                    let key =
+                        xkey.derive(keychain, index).next().expect("xkey derivation is empty");
+                    let mut engine = Sha256::default();
+                    engine.input_raw(&key.to_byte_array());
+                    let mut engine2 = Ripemd160::default();
+                    engine2.input_raw(&engine.finish());
+                    tap_script.push_slice(&engine2.finish());
+                }
                 ScriptItem::Code(code) => {
                     for tapcode in code {
                         tap_script.push_opcode(*tapcode);
                     }
                 }
                 ScriptItem::Data(fata) => tap_script.push_slice(fata),
+                ScriptItem::Raw(bytes) => {
+                    tap_script.extend(bytes.iter().copied()).expect("script exceeding 4GB")
+                }
             }
         }
         iter::once(tap_script.into())
@@ -193,13 +345,18 @@ impl<K: DeriveXOnly> Derive<LeafScript> for ScriptDescr<TapCode, K> {
 
 impl<S: Display, K: Display> Display for ScriptDescr<S, K> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(miniscript) = &self.miniscript {
+            return Display::fmt(miniscript, f);
+        }
+
         let mut first = true;
         for item in &self.condition {
-            if first {
+            if !first {
                 f.write_char(' ')?;
             }
             match item {
                 ScriptItem::Key(_, key) => Display::fmt(key, f)?,
+                ScriptItem::KeyHash(_, key) => write!(f, "<hash160({key})>")?,
                 ScriptItem::Code(code) => {
                     for opcode in code {
                         write!(f, "{opcode}")?;
@@ -208,6 +365,7 @@ impl<S: Display, K: Display> Display for ScriptDescr<S, K> {
                 ScriptItem::Data(data) => {
                     write!(f, "<{}>", data.to_hex())?;
                 }
+                ScriptItem::Raw(bytes) => f.write_str(&bytes.to_hex())?,
             }
             first = false
         }
@@ -219,6 +377,22 @@ impl<S: Display, K: Display> Display for ScriptDescr<S, K> {
 #[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
 pub struct Raw<K: DeriveLegacy = XpubDerivable>(ScriptDescr<OpCode, K>);
 
+impl<K: DeriveLegacy> Raw<K> {
+    pub fn map_keys<M: DeriveLegacy>(&self, f: &impl Fn(&K) -> M) -> Raw<M> {
+        Raw(self.0.map_keys(f))
+    }
+}
+
+impl<K: DeriveLegacy> From<ScriptPubkey> for Raw<K> {
+    fn from(script_pubkey: ScriptPubkey) -> Self {
+        Raw(ScriptDescr {
+            condition: vec![ScriptItem::Raw(script_pubkey.to_vec())],
+            satisfaction: none!(),
+            miniscript: None,
+        })
+    }
+}
+
 impl<K: DeriveLegacy> Derive<DerivedScript> for Raw<K> {
     #[inline]
     fn default_keychain(&self) -> Keychain { self.0.default_keychain() }
@@ -239,7 +413,13 @@ impl<K: DeriveLegacy> Derive<DerivedScript> for Raw<K> {
 }
 
 impl<K: DeriveLegacy> Descriptor<K> for Raw<K> {
-    fn class(&self) -> SpkClass { SpkClass::Bare }
+    fn class(&self) -> SpkClass {
+        let script = self
+            .derive(self.default_keychain(), NormalIndex::ZERO)
+            .next()
+            .expect("raw descriptor always produces a script");
+        SpkClass::detect(&script.to_script_pubkey())
+    }
 
     fn keys<'a>(&'a self) -> impl Iterator<Item = &'a K>
     where K: 'a {
@@ -249,7 +429,9 @@ impl<K: DeriveLegacy> Descriptor<K> for Raw<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(K::xpub_spec) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         self.keys()
@@ -258,7 +440,7 @@ impl<K: DeriveLegacy> Descriptor<K> for Raw<K> {
                     .derive(terminal.keychain, terminal.index)
                     .next()
                     .expect("xkey derivation is empty");
-                (key, KeyOrigin::with(xkey.xpub_spec().origin().clone(), terminal))
+                (key, xkey.key_origin(terminal))
             })
             .collect()
     }
@@ -269,7 +451,9 @@ impl<K: DeriveLegacy> Descriptor<K> for Raw<K> {
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -294,13 +478,104 @@ impl<K: DeriveLegacy> Descriptor<K> for Raw<K> {
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
 }
 
 impl<K: DeriveLegacy> Display for Raw<K> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { write!(f, "raw({})", self.0) }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        if self.0.keys().next().is_none() {
+            let script =
+                Derive::<RedeemScript>::derive(&self.0, Keychain::OUTER, NormalIndex::ZERO)
+                    .next()
+                    .expect("raw descriptor always produces a script");
+            return write!(f, "raw({})", script.to_hex());
+        }
+        write!(f, "raw({})", self.0)
+    }
+}
+
+/// Descriptor of a fixed address. It is used for watching the address, which can't be spent
+/// with the descriptor.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, From)]
+pub struct Addr(Address);
+
+impl Addr {
+    pub fn as_address(&self) -> &Address { &self.0 }
+    pub fn into_address(self) -> Address { self.0 }
+}
+
+impl Derive<DerivedScript> for Addr {
+    #[inline]
+    fn default_keychain(&self) -> Keychain { Keychain::OUTER }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { bset![Keychain::OUTER] }
+
+    fn derive(
+        &self,
+        _keychain: impl Into<Keychain>,
+        _index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = DerivedScript> {
+        iter::once(DerivedScript::Bare(self.0.script_pubkey()))
+    }
+}
+
+impl<K> Descriptor<K> for Addr {
+    fn class(&self) -> SpkClass { SpkClass::detect(&self.0.script_pubkey()) }
+
+    fn keys<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where K: 'a {
+        iter::empty()
+    }
+    fn vars<'a>(&'a self) -> impl Iterator<Item = &'a ()>
+    where (): 'a {
+        iter::empty()
+    }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { iter::empty() }
+
+    fn legacy_keyset(&self, _terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
+        IndexMap::new()
+    }
+
+    fn xonly_keyset(&self, _terminal: Terminal) -> IndexMap<XOnlyPk, TapDerivation> {
+        IndexMap::new()
+    }
+
+    fn legacy_witness(
+        &self,
+        _script_pubkey: &ScriptPubkey,
+        _keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
+        _redeem_script: Option<RedeemScript>,
+        _witness_script: Option<WitnessScript>,
+    ) -> Option<(SigScript, Option<Witness>)> {
+        None
+    }
+
+    fn taproot_witness(
+        &self,
+        _cb: Option<&ControlBlock>,
+        _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
+    ) -> Option<Witness> {
+        None
+    }
+}
+
+impl Display for Addr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "addr({})", self.0)
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
@@ -438,6 +713,55 @@ where Self: Derive<DerivedScript>
         .into_iter()
     }
 
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> {
+        match self {
+            Sh::ShScript(d) => d.hash_locks().collect::<Vec<_>>(),
+            Sh::ShMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            Sh::ShSortedMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            Sh::Wpkh(d) => d.hash_locks().collect::<Vec<_>>(),
+            Sh::WshScript(d) => d.hash_locks().collect::<Vec<_>>(),
+            Sh::WshMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            Sh::WshSortedMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+        }
+        .into_iter()
+    }
+
+    fn timelocks(&self) -> Vec<Timelocks> {
+        match self {
+            Sh::ShScript(d) => d.timelocks(),
+            Sh::ShMulti(d) => d.timelocks(),
+            Sh::ShSortedMulti(d) => d.timelocks(),
+            Sh::Wpkh(d) => d.timelocks(),
+            Sh::WshScript(d) => d.timelocks(),
+            Sh::WshMulti(d) => d.timelocks(),
+            Sh::WshSortedMulti(d) => d.timelocks(),
+        }
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        match self {
+            Sh::ShScript(d) => d.satisfaction_weights(),
+            Sh::ShMulti(d) => d.satisfaction_weights(),
+            Sh::ShSortedMulti(d) => d.satisfaction_weights(),
+            Sh::Wpkh(d) => d.satisfaction_weights(),
+            Sh::WshScript(d) => d.satisfaction_weights(),
+            Sh::WshMulti(d) => d.satisfaction_weights(),
+            Sh::WshSortedMulti(d) => d.satisfaction_weights(),
+        }
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        match self {
+            Sh::ShScript(d) => d.policies(),
+            Sh::ShMulti(d) => d.policies(),
+            Sh::ShSortedMulti(d) => d.policies(),
+            Sh::Wpkh(d) => d.policies(),
+            Sh::WshScript(d) => d.policies(),
+            Sh::WshMulti(d) => d.policies(),
+            Sh::WshSortedMulti(d) => d.policies(),
+        }
+    }
+
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         match self {
             Sh::ShScript(d) => d.legacy_keyset(terminal),
@@ -464,18 +788,34 @@ where Self: Derive<DerivedScript>
 
     fn legacy_witness(
         &self,
+        script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
         match self {
-            Sh::ShScript(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            Sh::ShMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            Sh::ShSortedMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            Sh::Wpkh(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            Sh::WshScript(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            Sh::WshMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            Sh::WshSortedMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
+            Sh::ShScript(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            Sh::ShMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            Sh::ShSortedMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            Sh::Wpkh(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            Sh::WshScript(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            Sh::WshMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            Sh::WshSortedMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
         }
     }
 
@@ -483,15 +823,16 @@ where Self: Derive<DerivedScript>
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         match self {
-            Sh::ShScript(d) => d.taproot_witness(cb, keysigs),
-            Sh::ShMulti(d) => d.taproot_witness(cb, keysigs),
-            Sh::ShSortedMulti(d) => d.taproot_witness(cb, keysigs),
-            Sh::Wpkh(d) => d.taproot_witness(cb, keysigs),
-            Sh::WshScript(d) => d.taproot_witness(cb, keysigs),
-            Sh::WshMulti(d) => d.taproot_witness(cb, keysigs),
-            Sh::WshSortedMulti(d) => d.taproot_witness(cb, keysigs),
+            Sh::ShScript(d) => d.taproot_witness(cb, keysigs, preimages),
+            Sh::ShMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            Sh::ShSortedMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            Sh::Wpkh(d) => d.taproot_witness(cb, keysigs, preimages),
+            Sh::WshScript(d) => d.taproot_witness(cb, keysigs, preimages),
+            Sh::WshMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            Sh::WshSortedMulti(d) => d.taproot_witness(cb, keysigs, preimages),
         }
     }
 }
@@ -594,6 +935,39 @@ impl<K: DeriveCompr> Descriptor<K> for Wsh<K> {
         .into_iter()
     }
 
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> {
+        match self {
+            Self::Script(d) => d.hash_locks().collect::<Vec<_>>(),
+            Self::Multi(d) => d.hash_locks().collect::<Vec<_>>(),
+            Self::SortedMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+        }
+        .into_iter()
+    }
+
+    fn timelocks(&self) -> Vec<Timelocks> {
+        match self {
+            Self::Script(d) => d.timelocks(),
+            Self::Multi(d) => d.timelocks(),
+            Self::SortedMulti(d) => d.timelocks(),
+        }
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        match self {
+            Wsh::Script(d) => d.satisfaction_weights(),
+            Wsh::Multi(d) => d.satisfaction_weights(),
+            Wsh::SortedMulti(d) => d.satisfaction_weights(),
+        }
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        match self {
+            Wsh::Script(d) => d.policies(),
+            Wsh::Multi(d) => d.policies(),
+            Wsh::SortedMulti(d) => d.policies(),
+        }
+    }
+
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         match self {
             Self::Script(d) => d.legacy_keyset(terminal),
@@ -612,16 +986,34 @@ impl<K: DeriveCompr> Descriptor<K> for Wsh<K> {
 
     fn legacy_witness(
         &self,
+        script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
         match self {
-            Self::Script(d) => d.legacy_witness(keysigs, redeem_script.clone(), witness_script),
-            Self::Multi(d) => d.legacy_witness(keysigs, redeem_script.clone(), witness_script),
-            Self::SortedMulti(d) => {
-                d.legacy_witness(keysigs, redeem_script.clone(), witness_script)
-            }
+            Self::Script(d) => d.legacy_witness(
+                script_pubkey,
+                keysigs,
+                preimages,
+                redeem_script.clone(),
+                witness_script,
+            ),
+            Self::Multi(d) => d.legacy_witness(
+                script_pubkey,
+                keysigs,
+                preimages,
+                redeem_script.clone(),
+                witness_script,
+            ),
+            Self::SortedMulti(d) => d.legacy_witness(
+                script_pubkey,
+                keysigs,
+                preimages,
+                redeem_script.clone(),
+                witness_script,
+            ),
         }
     }
 
@@ -629,11 +1021,12 @@ impl<K: DeriveCompr> Descriptor<K> for Wsh<K> {
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         match self {
-            Self::Script(d) => d.taproot_witness(cb, keysigs),
-            Self::Multi(d) => d.taproot_witness(cb, keysigs),
-            Self::SortedMulti(d) => d.taproot_witness(cb, keysigs),
+            Self::Script(d) => d.taproot_witness(cb, keysigs, preimages),
+            Self::Multi(d) => d.taproot_witness(cb, keysigs, preimages),
+            Self::SortedMulti(d) => d.taproot_witness(cb, keysigs, preimages),
         }
     }
 }
@@ -728,6 +1121,39 @@ impl<K: DeriveCompr> Descriptor<K> for ShWsh<K> {
         .into_iter()
     }
 
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> {
+        match self {
+            Self::Script(d) => d.hash_locks().collect::<Vec<_>>(),
+            Self::Multi(d) => d.hash_locks().collect::<Vec<_>>(),
+            Self::SortedMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+        }
+        .into_iter()
+    }
+
+    fn timelocks(&self) -> Vec<Timelocks> {
+        match self {
+            Self::Script(d) => d.timelocks(),
+            Self::Multi(d) => d.timelocks(),
+            Self::SortedMulti(d) => d.timelocks(),
+        }
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        match self {
+            ShWsh::Script(d) => d.satisfaction_weights(),
+            ShWsh::Multi(d) => d.satisfaction_weights(),
+            ShWsh::SortedMulti(d) => d.satisfaction_weights(),
+        }
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        match self {
+            ShWsh::Script(d) => d.policies(),
+            ShWsh::Multi(d) => d.policies(),
+            ShWsh::SortedMulti(d) => d.policies(),
+        }
+    }
+
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         match self {
             Self::Script(d) => d.legacy_keyset(terminal),
@@ -746,16 +1172,34 @@ impl<K: DeriveCompr> Descriptor<K> for ShWsh<K> {
 
     fn legacy_witness(
         &self,
+        script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
         match self {
-            Self::Script(d) => d.legacy_witness(keysigs, redeem_script.clone(), witness_script),
-            Self::Multi(d) => d.legacy_witness(keysigs, redeem_script.clone(), witness_script),
-            Self::SortedMulti(d) => {
-                d.legacy_witness(keysigs, redeem_script.clone(), witness_script)
-            }
+            Self::Script(d) => d.legacy_witness(
+                script_pubkey,
+                keysigs,
+                preimages,
+                redeem_script.clone(),
+                witness_script,
+            ),
+            Self::Multi(d) => d.legacy_witness(
+                script_pubkey,
+                keysigs,
+                preimages,
+                redeem_script.clone(),
+                witness_script,
+            ),
+            Self::SortedMulti(d) => d.legacy_witness(
+                script_pubkey,
+                keysigs,
+                preimages,
+                redeem_script.clone(),
+                witness_script,
+            ),
         }
     }
 
@@ -763,11 +1207,12 @@ impl<K: DeriveCompr> Descriptor<K> for ShWsh<K> {
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         match self {
-            Self::Script(d) => d.taproot_witness(cb, keysigs),
-            Self::Multi(d) => d.taproot_witness(cb, keysigs),
-            Self::SortedMulti(d) => d.taproot_witness(cb, keysigs),
+            Self::Script(d) => d.taproot_witness(cb, keysigs, preimages),
+            Self::Multi(d) => d.taproot_witness(cb, keysigs, preimages),
+            Self::SortedMulti(d) => d.taproot_witness(cb, keysigs, preimages),
         }
     }
 }
@@ -786,6 +1231,12 @@ impl<K: DeriveCompr> Display for ShWsh<K> {
 #[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
 pub struct ShScript<K: DeriveLegacy = XpubDerivable>(ScriptDescr<OpCode, K>);
 
+impl<K: DeriveLegacy> ShScript<K> {
+    pub fn map_keys<M: DeriveLegacy>(&self, f: &impl Fn(&K) -> M) -> ShScript<M> {
+        ShScript(self.0.map_keys(f))
+    }
+}
+
 impl<K: DeriveLegacy> Derive<DerivedScript> for ShScript<K> {
     #[inline]
     fn default_keychain(&self) -> Keychain { self.0.default_keychain() }
@@ -813,7 +1264,29 @@ impl<K: DeriveLegacy> Descriptor<K> for ShScript<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(K::xpub_spec) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> { self.0.hash_locks() }
+    fn timelocks(&self) -> Vec<Timelocks> { self.0.timelocks() }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        let redeem_script =
+            Derive::<RedeemScript>::derive(&self.0, self.default_keychain(), NormalIndex::ZERO)
+                .next()
+                .expect("script descriptor always produces a script");
+        SatisfactionWeight::estimate(SpendingPath::Script, |estimate| {
+            let stack = self.0.dummy_stack(estimate, estimate.ecdsa_sig_len(), legacy_key_len)?;
+            Some(SatSize::legacy(sig_script_len(&stack) + push_len(redeem_script.len())))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let policy = self.0.policy::<LegacyPk>();
+        policy.map(|policy| PathPolicy::new(SpendingPath::Script, policy)).into_iter().collect()
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         self.keys()
@@ -822,7 +1295,7 @@ impl<K: DeriveLegacy> Descriptor<K> for ShScript<K> {
                     .derive(terminal.keychain, terminal.index)
                     .next()
                     .expect("xkey derivation is empty");
-                (key, KeyOrigin::with(xkey.xpub_spec().origin().clone(), terminal))
+                (key, xkey.key_origin(terminal))
             })
             .collect()
     }
@@ -833,44 +1306,53 @@ impl<K: DeriveLegacy> Descriptor<K> for ShScript<K> {
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
-        witness_script: Option<WitnessScript>,
+        _witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
-        debug_assert!(redeem_script.is_none(), "redeemScript in Wsh descriptor must is empty");
-        let mut stack = vec![];
-        for item in &self.0.satisfaction {
-            match item {
-                WitnessItem::Signature(origin) => {
-                    let Some(src) = keysigs.get(origin) else {
-                        break;
-                    };
-                    stack.push(src.sig.to_vec());
-                }
-                WitnessItem::Data(data) => stack.push(data.clone()),
+        let stack = self.0.legacy_stack(&keysigs, preimages, K::is_origin_of)?;
+        let mut sig_script = SigScript::new();
+        for item in stack {
+            match item.as_slice() {
+                [num @ 1..=16] => sig_script.push_num(*num as i64),
+                data => sig_script.push_slice(data),
             }
         }
-        stack.push(witness_script?.into_inner().into_vec());
-        Some((SigScript::new(), Some(Witness::from_consensus_stack(stack))))
+        sig_script.push_slice(redeem_script?.as_slice());
+        Some((sig_script, None))
     }
 
     fn taproot_witness(
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
 }
 
 impl<K: DeriveLegacy> Display for ShScript<K> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { write!(f, "sh({})", self.0) }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "sh({})", self.0)
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
 pub struct WshScript<K: DeriveCompr = XpubDerivable>(ScriptDescr<OpCode, K>);
 
+impl<K: DeriveCompr> WshScript<K> {
+    pub fn map_keys<M: DeriveCompr>(&self, f: &impl Fn(&K) -> M) -> WshScript<M> {
+        WshScript(self.0.map_keys(f))
+    }
+}
+
 impl<K: DeriveCompr> Derive<DerivedScript> for WshScript<K> {
     #[inline]
     fn default_keychain(&self) -> Keychain { self.0.default_keychain() }
@@ -898,7 +1380,30 @@ impl<K: DeriveCompr> Descriptor<K> for WshScript<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(K::xpub_spec) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> { self.0.hash_locks() }
+    fn timelocks(&self) -> Vec<Timelocks> { self.0.timelocks() }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        let witness_script =
+            Derive::<WitnessScript>::derive(&self.0, self.default_keychain(), NormalIndex::ZERO)
+                .next()
+                .expect("script descriptor always produces a script");
+        SatisfactionWeight::estimate(SpendingPath::Script, |estimate| {
+            let stack = self.0.dummy_stack(estimate, estimate.ecdsa_sig_len(), |_| 33)?;
+            let witness = stack.iter().map(Vec::len).chain([witness_script.len()]);
+            Some(SatSize::segwit(0, witness))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let policy = self.0.policy::<CompressedPk>();
+        policy.map(|policy| PathPolicy::new(SpendingPath::Script, policy)).into_iter().collect()
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         self.keys()
@@ -907,7 +1412,7 @@ impl<K: DeriveCompr> Descriptor<K> for WshScript<K> {
                     .derive(terminal.keychain, terminal.index)
                     .next()
                     .expect("xkey derivation is empty");
-                (key.into(), KeyOrigin::with(xkey.xpub_spec().origin().clone(), terminal))
+                (key.into(), xkey.key_origin(terminal))
             })
             .collect()
     }
@@ -918,7 +1423,9 @@ impl<K: DeriveCompr> Descriptor<K> for WshScript<K> {
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -926,18 +1433,7 @@ impl<K: DeriveCompr> Descriptor<K> for WshScript<K> {
             return None;
         }
 
-        let mut stack = vec![];
-        for item in &self.0.satisfaction {
-            match item {
-                WitnessItem::Signature(origin) => {
-                    let Some(src) = keysigs.get(origin) else {
-                        break;
-                    };
-                    stack.push(src.sig.to_vec());
-                }
-                WitnessItem::Data(data) => stack.push(data.clone()),
-            }
-        }
+        let mut stack = self.0.legacy_stack(&keysigs, preimages, K::is_origin_of)?;
         stack.push(witness_script?.into_inner().into_vec());
         Some((SigScript::new(), Some(Witness::from_consensus_stack(stack))))
     }
@@ -946,19 +1442,31 @@ impl<K: DeriveCompr> Descriptor<K> for WshScript<K> {
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
 }
 
 impl<K: DeriveCompr> Display for WshScript<K> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { write!(f, "wsh({})", self.0) }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "wsh({})", self.0)
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
 pub struct ShWshScript<K: DeriveCompr = XpubDerivable>(ScriptDescr<OpCode, K>);
 
+impl<K: DeriveCompr> ShWshScript<K> {
+    pub fn map_keys<M: DeriveCompr>(&self, f: &impl Fn(&K) -> M) -> ShWshScript<M> {
+        ShWshScript(self.0.map_keys(f))
+    }
+}
+
 impl<K: DeriveCompr> Derive<DerivedScript> for ShWshScript<K> {
     #[inline]
     fn default_keychain(&self) -> Keychain { self.0.default_keychain() }
@@ -986,7 +1494,30 @@ impl<K: DeriveCompr> Descriptor<K> for ShWshScript<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(K::xpub_spec) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> { self.0.hash_locks() }
+    fn timelocks(&self) -> Vec<Timelocks> { self.0.timelocks() }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        let witness_script =
+            Derive::<WitnessScript>::derive(&self.0, self.default_keychain(), NormalIndex::ZERO)
+                .next()
+                .expect("script descriptor always produces a script");
+        SatisfactionWeight::estimate(SpendingPath::Script, |estimate| {
+            let stack = self.0.dummy_stack(estimate, estimate.ecdsa_sig_len(), |_| 33)?;
+            let witness = stack.iter().map(Vec::len).chain([witness_script.len()]);
+            Some(SatSize::segwit(push_len(34), witness))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let policy = self.0.policy::<CompressedPk>();
+        policy.map(|policy| PathPolicy::new(SpendingPath::Script, policy)).into_iter().collect()
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         self.keys()
@@ -995,7 +1526,7 @@ impl<K: DeriveCompr> Descriptor<K> for ShWshScript<K> {
                     .derive(terminal.keychain, terminal.index)
                     .next()
                     .expect("xkey derivation is empty");
-                (key.into(), KeyOrigin::with(xkey.xpub_spec().origin().clone(), terminal))
+                (key.into(), xkey.key_origin(terminal))
             })
             .collect()
     }
@@ -1006,27 +1537,19 @@ impl<K: DeriveCompr> Descriptor<K> for ShWshScript<K> {
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
         redeem_script.as_ref()?;
 
-        let mut stack = vec![];
-        for item in &self.0.satisfaction {
-            match item {
-                WitnessItem::Signature(origin) => {
-                    let Some(src) = keysigs.get(origin) else {
-                        break;
-                    };
-                    stack.push(src.sig.to_vec());
-                }
-                WitnessItem::Data(data) => stack.push(data.clone()),
-            }
-        }
+        let mut stack = self.0.legacy_stack(&keysigs, preimages, K::is_origin_of)?;
         stack.push(witness_script?.into_inner().into_vec());
 
-        let sig_script = SigScript::from_checked(redeem_script?.into_inner().into_vec());
+        let mut sig_script = SigScript::with_capacity(35);
+        sig_script.push_slice(redeem_script?.as_slice());
         Some((sig_script, Some(Witness::from_consensus_stack(stack))))
     }
 
@@ -1034,11 +1557,17 @@ impl<K: DeriveCompr> Descriptor<K> for ShWshScript<K> {
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
 }
 
 impl<K: DeriveCompr> Display for ShWshScript<K> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { write!(f, "sh(wsh({}))", self.0) }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "sh(wsh({}))", self.0)
+    }
 }
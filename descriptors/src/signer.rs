@@ -0,0 +1,268 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amplify::Wrapper;
+use derive::secp256k1::{ecdsa, schnorr as bip340, Keypair, SecretKey, SECP256K1};
+use derive::{
+    DeriveKey, InternalKeypair, InternalPk, KeyOrigin, LegacyPk, Sighash, SighashType, Sign,
+    TapLeafHash, TapMerklePath, TapNodeHash, TapSighash, WifKey, XOnlyPk, Xpriv, XprivDerivable,
+};
+
+use crate::{Descriptor, StdDescr};
+
+fn sign_ecdsa(sk: SecretKey, message: Sighash, pk: LegacyPk) -> Option<ecdsa::Signature> {
+    if sk.public_key(SECP256K1) != pk.pubkey {
+        return None;
+    }
+    Some(sk.sign_ecdsa(message.into()))
+}
+
+fn sign_bip340_key_only(
+    keypair: Keypair,
+    message: TapSighash,
+    pk: InternalPk,
+    merkle_root: Option<TapNodeHash>,
+) -> Option<bip340::Signature> {
+    let output_pair = InternalKeypair::from(keypair).to_output_keypair(merkle_root).0;
+    if output_pair.x_only_public_key().0.serialize()
+        != pk.to_output_pk(merkle_root).0.to_byte_array()
+    {
+        return None;
+    }
+    Some(SECP256K1.sign_schnorr(message.as_ref(), &output_pair))
+}
+
+fn sign_bip340_script_path(
+    keypair: Keypair,
+    message: TapSighash,
+    pk: XOnlyPk,
+) -> Option<bip340::Signature> {
+    if keypair.x_only_public_key().0 != pk.into_inner() {
+        return None;
+    }
+    Some(SECP256K1.sign_schnorr(message.as_ref(), &keypair))
+}
+
+impl StdDescr<XprivDerivable> {
+    fn xpriv_for(&self, origin: Option<&KeyOrigin>) -> Option<Xpriv> {
+        let origin = origin?;
+        self.keys().find_map(|key| key.xpriv_for(origin))
+    }
+}
+
+impl Sign for StdDescr<XprivDerivable> {
+    fn sign_ecdsa(
+        &self,
+        message: Sighash,
+        _sighash_type: SighashType,
+        pk: LegacyPk,
+        origin: Option<&KeyOrigin>,
+    ) -> Option<ecdsa::Signature> {
+        if !pk.compressed {
+            return None;
+        }
+        sign_ecdsa(self.xpriv_for(origin)?.to_private_ecdsa(), message, pk)
+    }
+
+    fn sign_bip340_key_only(
+        &self,
+        message: TapSighash,
+        _sighash_type: Option<SighashType>,
+        pk: InternalPk,
+        origin: Option<&KeyOrigin>,
+        merkle_root: Option<TapNodeHash>,
+    ) -> Option<bip340::Signature> {
+        let keypair = self.xpriv_for(origin)?.to_keypair_bip340();
+        sign_bip340_key_only(keypair, message, pk, merkle_root)
+    }
+
+    fn sign_bip340_script_path(
+        &self,
+        message: TapSighash,
+        _sighash_type: Option<SighashType>,
+        pk: XOnlyPk,
+        origin: Option<&KeyOrigin>,
+    ) -> Option<bip340::Signature> {
+        sign_bip340_script_path(self.xpriv_for(origin)?.to_keypair_bip340(), message, pk)
+    }
+
+    fn should_sign_script_path(
+        &self,
+        _index: usize,
+        _merkle_path: &TapMerklePath,
+        _leaf: TapLeafHash,
+    ) -> bool {
+        true
+    }
+
+    fn should_sign_key_path(&self, _index: usize) -> bool { true }
+}
+
+impl StdDescr<WifKey> {
+    fn wif_for(&self, origin: Option<&KeyOrigin>) -> Option<WifKey> {
+        let origin = origin?;
+        self.keys().find(|key| DeriveKey::<LegacyPk>::is_origin_of(*key, origin)).copied()
+    }
+}
+
+impl Sign for StdDescr<WifKey> {
+    fn sign_ecdsa(
+        &self,
+        message: Sighash,
+        _sighash_type: SighashType,
+        pk: LegacyPk,
+        origin: Option<&KeyOrigin>,
+    ) -> Option<ecdsa::Signature> {
+        let wif = self.wif_for(origin)?;
+        if pk.compressed != wif.compressed {
+            return None;
+        }
+        sign_ecdsa(wif.secret, message, pk)
+    }
+
+    fn sign_bip340_key_only(
+        &self,
+        message: TapSighash,
+        _sighash_type: Option<SighashType>,
+        pk: InternalPk,
+        origin: Option<&KeyOrigin>,
+        merkle_root: Option<TapNodeHash>,
+    ) -> Option<bip340::Signature> {
+        let keypair = Keypair::from_secret_key(SECP256K1, &self.wif_for(origin)?.secret);
+        sign_bip340_key_only(keypair, message, pk, merkle_root)
+    }
+
+    fn sign_bip340_script_path(
+        &self,
+        message: TapSighash,
+        _sighash_type: Option<SighashType>,
+        pk: XOnlyPk,
+        origin: Option<&KeyOrigin>,
+    ) -> Option<bip340::Signature> {
+        let keypair = Keypair::from_secret_key(SECP256K1, &self.wif_for(origin)?.secret);
+        sign_bip340_script_path(keypair, message, pk)
+    }
+
+    fn should_sign_script_path(
+        &self,
+        _index: usize,
+        _merkle_path: &TapMerklePath,
+        _leaf: TapLeafHash,
+    ) -> bool {
+        true
+    }
+
+    fn should_sign_key_path(&self, _index: usize) -> bool { true }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use derive::{
+        CompressedPk, Derive, DeriveKey, HardenedIndex, Idx, Keychain, NormalIndex, Terminal,
+        XprivAccount,
+    };
+
+    use super::*;
+
+    // Private key `0C28FCA386C7A227600B2FE50B7CAE11EC86D3BF1FBE471BE89827E19D72AA1D` from the
+    // Bitcoin wiki WIF example, in compressed and uncompressed forms
+    const WIF: &str = "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617";
+    const WIF_UNCOMPR: &str = "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ";
+
+    fn xpriv(account: u16) -> XprivDerivable {
+        let xpriv = XprivAccount::with_seed(true, &[0xA5; 32]).derive([
+            HardenedIndex::from(84u16),
+            HardenedIndex::ONE,
+            HardenedIndex::from(account),
+        ]);
+        XprivDerivable::with(xpriv, &[Keychain::OUTER, Keychain::INNER])
+    }
+
+    #[test]
+    fn private_descr_signs() {
+        let key = xpriv(0);
+        let descr = StdDescr::<XprivDerivable>::from_str(&format!("wpkh({key})")).unwrap();
+        let terminal = Terminal::new(Keychain::INNER, NormalIndex::from(7u8));
+        let origin = DeriveKey::<CompressedPk>::key_origin(&key, terminal);
+        let pk =
+            Derive::<LegacyPk>::derive(&key, terminal.keychain, terminal.index).next().unwrap();
+        let msg = Sighash::from([7u8; 32]);
+
+        let sig = descr.sign_ecdsa(msg, SighashType::all(), pk, Some(&origin)).unwrap();
+        SECP256K1.verify_ecdsa(&msg.into(), &sig, &pk.pubkey).unwrap();
+        assert_eq!(descr.sign_ecdsa(msg, SighashType::all(), pk, None), None);
+        let other = DeriveKey::<CompressedPk>::key_origin(&xpriv(1), terminal);
+        assert_eq!(descr.sign_ecdsa(msg, SighashType::all(), pk, Some(&other)), None);
+
+        let pk = Derive::<XOnlyPk>::derive(&key, terminal.keychain, terminal.index).next().unwrap();
+        let msg = TapSighash::from([7u8; 32]);
+        let sig = descr.sign_bip340_script_path(msg, None, pk, Some(&origin)).unwrap();
+        SECP256K1.verify_schnorr(&sig, msg.as_ref(), &pk.into_inner()).unwrap();
+        // Signatures use fresh auxiliary randomness, so they differ on each run.
+        let sig2 = descr.sign_bip340_script_path(msg, None, pk, Some(&origin)).unwrap();
+        SECP256K1.verify_schnorr(&sig2, msg.as_ref(), &pk.into_inner()).unwrap();
+        assert_ne!(sig, sig2);
+        let internal = InternalPk::from_unchecked(pk);
+        let sig = descr.sign_bip340_key_only(msg, None, internal, Some(&origin), None).unwrap();
+        let output_pk = internal.to_output_pk(None).0;
+        SECP256K1.verify_schnorr(&sig, msg.as_ref(), &output_pk.into_inner()).unwrap();
+    }
+
+    #[test]
+    fn wif_descr_signs() {
+        let key = WifKey::from_str(WIF).unwrap();
+        let terminal = Terminal::new(Keychain::OUTER, NormalIndex::ZERO);
+        let origin = DeriveKey::<CompressedPk>::key_origin(&key, terminal);
+        let msg = Sighash::from([7u8; 32]);
+
+        let descr = StdDescr::<WifKey>::from_str(&format!("wpkh({WIF})")).unwrap();
+        assert_eq!(descr.to_string(), format!("wpkh({WIF})"));
+        assert_eq!(descr.keys().collect::<Vec<_>>(), vec![&key]);
+        let pk = key.to_legacy_pk();
+        let sig = descr.sign_ecdsa(msg, SighashType::all(), pk, Some(&origin)).unwrap();
+        SECP256K1.verify_ecdsa(&msg.into(), &sig, &pk.pubkey).unwrap();
+        assert_eq!(descr.sign_ecdsa(msg, SighashType::all(), pk, None), None);
+
+        // Uncompressed keys are valid only outside of segwit
+        assert!(StdDescr::<WifKey>::from_str(&format!("wpkh({WIF_UNCOMPR})")).is_err());
+        let key = WifKey::from_str(WIF_UNCOMPR).unwrap();
+        let origin = DeriveKey::<LegacyPk>::key_origin(&key, terminal);
+        let descr = StdDescr::<WifKey>::from_str(&format!("pkh({WIF_UNCOMPR})")).unwrap();
+        let pk = key.to_legacy_pk();
+        let sig = descr.sign_ecdsa(msg, SighashType::all(), pk, Some(&origin)).unwrap();
+        SECP256K1.verify_ecdsa(&msg.into(), &sig, &pk.pubkey).unwrap();
+
+        let key = WifKey::from_str(WIF).unwrap();
+        let origin = DeriveKey::<XOnlyPk>::key_origin(&key, terminal);
+        let descr = StdDescr::<WifKey>::from_str(&format!("tr({WIF})")).unwrap();
+        let pk = Derive::<XOnlyPk>::derive(&key, terminal.keychain, terminal.index).next().unwrap();
+        let internal = InternalPk::from_unchecked(pk);
+        let msg = TapSighash::from([7u8; 32]);
+        let sig = descr.sign_bip340_key_only(msg, None, internal, Some(&origin), None).unwrap();
+        let output_pk = internal.to_output_pk(None).0;
+        SECP256K1.verify_schnorr(&sig, msg.as_ref(), &output_pk.into_inner()).unwrap();
+    }
+}
@@ -28,13 +28,19 @@ use std::hash::Hash;
 use std::iter;
 
 use derive::{
-    ControlBlock, Derive, DeriveCompr, DeriveLegacy, DerivedScript, KeyOrigin, Keychain, LegacyPk,
-    NormalIndex, PubkeyHash, RedeemScript, ScriptPubkey, SigScript, TapDerivation, Terminal,
-    WPubkeyHash, Witness, WitnessScript, XOnlyPk, XpubAccount, XpubDerivable,
+    CompressedPk, ControlBlock, Derive, DeriveCompr, DeriveLegacy, DerivedScript, KeyOrigin,
+    Keychain, LegacyPk, NormalIndex, OpCode, PubkeyHash, RedeemScript, ScriptPubkey, SigScript,
+    TapDerivation, Terminal, WPubkeyHash, Witness, WitnessScript, XOnlyPk, XpubAccount,
+    XpubDerivable,
 };
 use indexmap::IndexMap;
 
-use crate::{Descriptor, LegacyKeySig, SpkClass, TaprootKeySig};
+use crate::compiler::fmt_checksummed;
+use crate::weight::{legacy_key_len, push_len, SatSize};
+use crate::{
+    Descriptor, HashLock, LegacyKeySig, PathPolicy, Policy, SatisfactionWeight, SpendingPath,
+    SpkClass, TaprootKeySig,
+};
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
@@ -43,6 +49,7 @@ pub struct Pkh<K: DeriveLegacy = XpubDerivable>(K);
 impl<K: DeriveLegacy> Pkh<K> {
     pub fn as_key(&self) -> &K { &self.0 }
     pub fn into_key(self) -> K { self.0 }
+    pub fn map_keys<M: DeriveLegacy>(&self, f: &impl Fn(&K) -> M) -> Pkh<M> { Pkh(f(&self.0)) }
 }
 
 impl<K: DeriveLegacy> Derive<DerivedScript> for Pkh<K> {
@@ -74,12 +81,25 @@ impl<K: DeriveLegacy> Descriptor<K> for Pkh<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { iter::once(self.0.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.0.try_xpub_spec().into_iter() }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        let key_len = legacy_key_len(&self.0);
+        SatisfactionWeight::estimate(SpendingPath::Script, |estimate| {
+            Some(SatSize::legacy(push_len(estimate.ecdsa_sig_len()) + push_len(key_len)))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        vec![PathPolicy::new(SpendingPath::Script, Policy::key::<LegacyPk>(&self.0))]
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         self.0
             .derive(terminal.keychain, terminal.index)
-            .map(|key| (key, KeyOrigin::with(self.0.xpub_spec().origin().clone(), terminal)))
+            .map(|key| (key, self.0.key_origin(terminal)))
             .collect()
     }
 
@@ -89,13 +109,14 @@ impl<K: DeriveLegacy> Descriptor<K> for Pkh<K> {
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         _redeem_script: Option<RedeemScript>,
         _witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
-        let our_origin = self.0.xpub_spec().origin();
         let keysig =
-            keysigs.iter().find(|(origin, _)| our_origin.is_subset_of(origin)).map(|(_, ks)| ks)?;
+            keysigs.iter().find(|(origin, _)| self.0.is_origin_of(origin)).map(|(_, ks)| ks)?;
 
         let mut sig_script = SigScript::with_capacity(67 + 78);
         sig_script.push_slice(&keysig.sig.to_vec());
@@ -107,13 +128,19 @@ impl<K: DeriveLegacy> Descriptor<K> for Pkh<K> {
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
 }
 
 impl<K: DeriveLegacy> Display for Pkh<K> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { write!(f, "pkh({})", self.0) }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "pkh({})", self.0)
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -123,6 +150,7 @@ pub struct Wpkh<K: DeriveCompr = XpubDerivable>(K);
 impl<K: DeriveCompr> Wpkh<K> {
     pub fn as_key(&self) -> &K { &self.0 }
     pub fn into_key(self) -> K { self.0 }
+    pub fn map_keys<M: DeriveCompr>(&self, f: &impl Fn(&K) -> M) -> Wpkh<M> { Wpkh(f(&self.0)) }
 }
 
 impl<K: DeriveCompr> Derive<DerivedScript> for Wpkh<K> {
@@ -154,12 +182,24 @@ impl<K: DeriveCompr> Descriptor<K> for Wpkh<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { iter::once(self.0.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.0.try_xpub_spec().into_iter() }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        SatisfactionWeight::estimate(SpendingPath::Script, |estimate| {
+            Some(SatSize::segwit(0, [estimate.ecdsa_sig_len(), 33]))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        vec![PathPolicy::new(SpendingPath::Script, Policy::key::<CompressedPk>(&self.0))]
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         self.0
             .derive(terminal.keychain, terminal.index)
-            .map(|key| (key.into(), KeyOrigin::with(self.0.xpub_spec().origin().clone(), terminal)))
+            .map(|key| (key.into(), self.0.key_origin(terminal)))
             .collect()
     }
 
@@ -169,13 +209,14 @@ impl<K: DeriveCompr> Descriptor<K> for Wpkh<K> {
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         _redeem_script: Option<RedeemScript>,
         _witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
-        let our_origin = self.0.xpub_spec().origin();
         let keysig =
-            keysigs.iter().find(|(origin, _)| our_origin.is_subset_of(origin)).map(|(_, ks)| ks)?;
+            keysigs.iter().find(|(origin, _)| self.0.is_origin_of(origin)).map(|(_, ks)| ks)?;
         let witness = Witness::from_consensus_stack([keysig.sig.to_vec(), keysig.key.to_vec()]);
         Some((empty!(), Some(witness)))
     }
@@ -184,13 +225,19 @@ impl<K: DeriveCompr> Descriptor<K> for Wpkh<K> {
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
 }
 
 impl<K: DeriveCompr> Display for Wpkh<K> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { write!(f, "wpkh({})", self.0) }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "wpkh({})", self.0)
+    }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -200,6 +247,7 @@ pub struct ShWpkh<K: DeriveCompr = XpubDerivable>(K);
 impl<K: DeriveCompr> ShWpkh<K> {
     pub fn as_key(&self) -> &K { &self.0 }
     pub fn into_key(self) -> K { self.0 }
+    pub fn map_keys<M: DeriveCompr>(&self, f: &impl Fn(&K) -> M) -> ShWpkh<M> { ShWpkh(f(&self.0)) }
 }
 
 impl<K: DeriveCompr> Derive<DerivedScript> for ShWpkh<K> {
@@ -229,12 +277,24 @@ impl<K: DeriveCompr> Descriptor<K> for ShWpkh<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { iter::once(self.0.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.0.try_xpub_spec().into_iter() }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        SatisfactionWeight::estimate(SpendingPath::Script, |estimate| {
+            Some(SatSize::segwit(push_len(22), [estimate.ecdsa_sig_len(), 33]))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        vec![PathPolicy::new(SpendingPath::Script, Policy::key::<CompressedPk>(&self.0))]
+    }
 
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         self.0
             .derive(terminal.keychain, terminal.index)
-            .map(|key| (key.into(), KeyOrigin::with(self.0.xpub_spec().origin().clone(), terminal)))
+            .map(|key| (key.into(), self.0.key_origin(terminal)))
             .collect()
     }
 
@@ -244,26 +304,173 @@ impl<K: DeriveCompr> Descriptor<K> for ShWpkh<K> {
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
-        _redeem_script: Option<RedeemScript>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
+        redeem_script: Option<RedeemScript>,
         _witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
-        let our_origin = self.0.xpub_spec().origin();
         let keysig =
-            keysigs.iter().find(|(origin, _)| our_origin.is_subset_of(origin)).map(|(_, ks)| ks)?;
+            keysigs.iter().find(|(origin, _)| self.0.is_origin_of(origin)).map(|(_, ks)| ks)?;
+        let mut sig_script = SigScript::with_capacity(23);
+        sig_script.push_slice(redeem_script?.as_slice());
         let witness = Witness::from_consensus_stack([keysig.sig.to_vec(), keysig.key.to_vec()]);
-        Some((empty!(), Some(witness)))
+        Some((sig_script, Some(witness)))
     }
 
     fn taproot_witness(
         &self,
         _cb: Option<&ControlBlock>,
         _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         None
     }
 }
 
 impl<K: DeriveCompr> Display for ShWpkh<K> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { write!(f, "sh(wpkh({}))", self.0) }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "sh(wpkh({}))", self.0)
+    }
+}
+
+/// Descriptor for all single-key outputs of the same key: P2PK and P2PKH, and for compressed
+/// keys also P2WPKH and P2SH-P2WPKH.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
+pub struct Combo<K: DeriveLegacy = XpubDerivable>(K);
+
+impl<K: DeriveLegacy> Combo<K> {
+    pub fn as_key(&self) -> &K { &self.0 }
+    pub fn into_key(self) -> K { self.0 }
+    pub fn map_keys<M: DeriveLegacy>(&self, f: &impl Fn(&K) -> M) -> Combo<M> { Combo(f(&self.0)) }
+}
+
+impl<K: DeriveLegacy> Derive<DerivedScript> for Combo<K> {
+    #[inline]
+    fn default_keychain(&self) -> Keychain { self.0.default_keychain() }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { self.0.keychains() }
+
+    fn derive(
+        &self,
+        keychain: impl Into<Keychain>,
+        index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = DerivedScript> {
+        self.0.derive(keychain, index).flat_map(|key| {
+            let mut p2pk = ScriptPubkey::with_capacity(67);
+            p2pk.push_slice(&key.to_vec());
+            p2pk.push_opcode(OpCode::CheckSig);
+            let mut scripts = vec![
+                DerivedScript::Bare(p2pk),
+                DerivedScript::Bare(ScriptPubkey::p2pkh(PubkeyHash::from(key))),
+            ];
+            if key.compressed {
+                let key = CompressedPk::from(key.pubkey);
+                scripts.push(DerivedScript::Bare(ScriptPubkey::p2wpkh(WPubkeyHash::from(key))));
+                scripts.push(DerivedScript::NestedKey(key));
+            }
+            scripts
+        })
+    }
+}
+
+impl<K: DeriveLegacy> Descriptor<K> for Combo<K> {
+    // Outputs of different classes are produced; P2PKH has the highest dust limit and requires
+    // the full previous transaction, which suits all of them.
+    fn class(&self) -> SpkClass { SpkClass::P2pkh }
+
+    fn keys<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where K: 'a {
+        iter::once(&self.0)
+    }
+    fn vars<'a>(&'a self) -> impl Iterator<Item = &'a ()>
+    where (): 'a {
+        iter::empty()
+    }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.0.try_xpub_spec().into_iter() }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        // Spending P2PKH output takes more space than spending any other output of the combo
+        let key_len = legacy_key_len(&self.0);
+        SatisfactionWeight::estimate(SpendingPath::Script, |estimate| {
+            Some(SatSize::legacy(push_len(estimate.ecdsa_sig_len()) + push_len(key_len)))
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        vec![PathPolicy::new(SpendingPath::Script, Policy::key::<LegacyPk>(&self.0))]
+    }
+
+    fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
+        self.0
+            .derive(terminal.keychain, terminal.index)
+            .map(|key| (key, self.0.key_origin(terminal)))
+            .collect()
+    }
+
+    fn xonly_keyset(&self, _terminal: Terminal) -> IndexMap<XOnlyPk, TapDerivation> {
+        IndexMap::new()
+    }
+
+    fn legacy_witness(
+        &self,
+        script_pubkey: &ScriptPubkey,
+        keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
+        redeem_script: Option<RedeemScript>,
+        _witness_script: Option<WitnessScript>,
+    ) -> Option<(SigScript, Option<Witness>)> {
+        let keysig =
+            keysigs.iter().find(|(origin, _)| self.0.is_origin_of(origin)).map(|(_, ks)| ks)?;
+        let mut sig_script = SigScript::with_capacity(67 + 78);
+        if script_pubkey.is_p2pkh() {
+            sig_script.push_slice(&keysig.sig.to_vec());
+            sig_script.push_slice(&keysig.key.to_vec());
+            return Some((sig_script, None));
+        }
+        if script_pubkey.is_p2sh() || script_pubkey.is_p2wpkh() {
+            if !keysig.key.compressed {
+                return None;
+            }
+            if script_pubkey.is_p2sh() {
+                sig_script.push_slice(redeem_script?.as_slice());
+            }
+            let witness = Witness::from_consensus_stack([keysig.sig.to_vec(), keysig.key.to_vec()]);
+            return Some((sig_script, Some(witness)));
+        }
+        // The remaining output of the combo is P2PK, which is satisfied with the signature alone
+        let mut p2pk = ScriptPubkey::with_capacity(67);
+        p2pk.push_slice(&keysig.key.to_vec());
+        p2pk.push_opcode(OpCode::CheckSig);
+        if *script_pubkey != p2pk {
+            return None;
+        }
+        sig_script.push_slice(&keysig.sig.to_vec());
+        Some((sig_script, None))
+    }
+
+    fn taproot_witness(
+        &self,
+        _cb: Option<&ControlBlock>,
+        _keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
+    ) -> Option<Witness> {
+        None
+    }
+}
+
+impl<K: DeriveLegacy> Display for Combo<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "combo({})", self.0)
+    }
 }
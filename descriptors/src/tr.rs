@@ -27,15 +27,21 @@ use std::fmt::{self, Display, Formatter};
 use std::iter;
 
 use amplify::confinement::ConfinedVec;
+use amplify::ByteArray;
 use derive::{
-    ConsensusEncode, ControlBlock, Derive, DeriveXOnly, DerivedScript, InternalPk, KeyOrigin,
-    Keychain, LeafScript, LegacyPk, NormalIndex, RedeemScript, SigScript, TapCode, TapDerivation,
-    TapScript, TapTree, Terminal, Witness, WitnessScript, XOnlyPk, XpubAccount, XpubDerivable,
+    CompressedPk, ControlBlock, DerivationPath, Derive, DeriveCompr, DeriveXOnly, DerivedScript,
+    Idx, InternalPk, KeyAggContext, KeyOrigin, Keychain, LeafScript, LegacyPk, NormalIndex,
+    OutputPk, RedeemScript, ScriptPubkey, SigScript, TapCode, TapDerivation, TapScript, TapTree,
+    Terminal, Witness, WitnessScript, XOnlyPk, XpubAccount, XpubDerivable, XpubFp,
 };
 use indexmap::IndexMap;
 
+use crate::compiler::fmt_checksummed;
+use crate::miniscript::KeySigSatisfier;
+use crate::weight::SatSize;
 use crate::{
-    Descriptor, LegacyKeySig, ScriptDescr, ScriptItem, SpkClass, TaprootKeySig, WitnessItem,
+    Descriptor, HashLock, LegacyKeySig, PathPolicy, Policy, SatisfactionWeight, ScriptDescr,
+    SpendingPath, SpkClass, TaprootKeySig, Timelocks, WitnessItem,
 };
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
@@ -142,6 +148,43 @@ impl<K: DeriveXOnly> Descriptor<K> for Tr<K> {
         .into_iter()
     }
 
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> {
+        match self {
+            Tr::KeyOnly(d) => d.hash_locks().collect::<Vec<_>>(),
+            Tr::Multi(d) => d.hash_locks().collect::<Vec<_>>(),
+            Tr::SortedMulti(d) => d.hash_locks().collect::<Vec<_>>(),
+            Tr::Script(d) => d.hash_locks().collect::<Vec<_>>(),
+        }
+        .into_iter()
+    }
+
+    fn timelocks(&self) -> Vec<Timelocks> {
+        match self {
+            Tr::KeyOnly(d) => d.timelocks(),
+            Tr::Multi(d) => d.timelocks(),
+            Tr::SortedMulti(d) => d.timelocks(),
+            Tr::Script(d) => d.timelocks(),
+        }
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        match self {
+            Tr::KeyOnly(d) => d.satisfaction_weights(),
+            Tr::Multi(d) => d.satisfaction_weights(),
+            Tr::SortedMulti(d) => d.satisfaction_weights(),
+            Tr::Script(d) => d.satisfaction_weights(),
+        }
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        match self {
+            Tr::KeyOnly(d) => d.policies(),
+            Tr::Multi(d) => d.policies(),
+            Tr::SortedMulti(d) => d.policies(),
+            Tr::Script(d) => d.policies(),
+        }
+    }
+
     fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         match self {
             Tr::KeyOnly(d) => d.legacy_keyset(terminal),
@@ -162,15 +205,25 @@ impl<K: DeriveXOnly> Descriptor<K> for Tr<K> {
 
     fn legacy_witness(
         &self,
+        script_pubkey: &ScriptPubkey,
         keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
         redeem_script: Option<RedeemScript>,
         witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
         match self {
-            Tr::KeyOnly(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            Tr::Multi(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            Tr::SortedMulti(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
-            Tr::Script(d) => d.legacy_witness(keysigs, redeem_script, witness_script),
+            Tr::KeyOnly(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            Tr::Multi(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            Tr::SortedMulti(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
+            Tr::Script(d) => {
+                d.legacy_witness(script_pubkey, keysigs, preimages, redeem_script, witness_script)
+            }
         }
     }
 
@@ -178,12 +231,13 @@ impl<K: DeriveXOnly> Descriptor<K> for Tr<K> {
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         match self {
-            Tr::KeyOnly(d) => d.taproot_witness(cb, keysigs),
-            Tr::Multi(d) => d.taproot_witness(cb, keysigs),
-            Tr::SortedMulti(d) => d.taproot_witness(cb, keysigs),
-            Tr::Script(d) => d.taproot_witness(cb, keysigs),
+            Tr::KeyOnly(d) => d.taproot_witness(cb, keysigs, preimages),
+            Tr::Multi(d) => d.taproot_witness(cb, keysigs, preimages),
+            Tr::SortedMulti(d) => d.taproot_witness(cb, keysigs, preimages),
+            Tr::Script(d) => d.taproot_witness(cb, keysigs, preimages),
         }
     }
 }
@@ -206,6 +260,7 @@ pub struct TrKey<K: DeriveXOnly = XpubDerivable>(K);
 impl<K: DeriveXOnly> TrKey<K> {
     pub fn as_internal_key(&self) -> &K { &self.0 }
     pub fn into_internal_key(self) -> K { self.0 }
+    pub fn map_keys<M: DeriveXOnly>(&self, f: &impl Fn(&K) -> M) -> TrKey<M> { TrKey(f(&self.0)) }
 }
 
 impl<K: DeriveXOnly> Derive<DerivedScript> for TrKey<K> {
@@ -237,7 +292,13 @@ impl<K: DeriveXOnly> Descriptor<K> for TrKey<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { iter::once(self.0.xpub_spec()) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.0.try_xpub_spec().into_iter() }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> { vec![key_path_weight()] }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        vec![PathPolicy::new(SpendingPath::KeyPath, Policy::key::<XOnlyPk>(&self.0))]
+    }
 
     fn legacy_keyset(&self, _terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         IndexMap::new()
@@ -246,18 +307,15 @@ impl<K: DeriveXOnly> Descriptor<K> for TrKey<K> {
     fn xonly_keyset(&self, terminal: Terminal) -> IndexMap<XOnlyPk, TapDerivation> {
         self.0
             .derive(terminal.keychain, terminal.index)
-            .map(|key| {
-                (
-                    key,
-                    TapDerivation::with_internal_pk(self.0.xpub_spec().origin().clone(), terminal),
-                )
-            })
+            .map(|key| (key, TapDerivation::with_origin(self.0.key_origin(terminal))))
             .collect()
     }
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         _keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         _redeem_script: Option<RedeemScript>,
         _witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -268,20 +326,121 @@ impl<K: DeriveXOnly> Descriptor<K> for TrKey<K> {
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         if cb.is_some() {
             // TrKey doesn't support script path spending
             return None;
         }
-        let our_origin = self.0.xpub_spec().origin();
         let keysig =
-            keysigs.iter().find(|(origin, _)| our_origin.is_subset_of(origin)).map(|(_, ks)| ks)?;
+            keysigs.iter().find(|(origin, _)| self.0.is_origin_of(origin)).map(|(_, ks)| ks)?;
         Some(Witness::from_consensus_stack([keysig.sig.to_vec()]))
     }
 }
 
 impl<K: DeriveXOnly> Display for TrKey<K> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { write!(f, "tr({})", self.0) }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "tr({})", self.0)
+    }
+}
+
+/// Taproot output which uses the key as the output key directly, without tweaking it with a
+/// script tree commitment.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
+pub struct RawTr<K: DeriveXOnly = XpubDerivable>(K);
+
+impl<K: DeriveXOnly> RawTr<K> {
+    pub fn as_output_key(&self) -> &K { &self.0 }
+    pub fn into_output_key(self) -> K { self.0 }
+    pub fn map_keys<M: DeriveXOnly>(&self, f: &impl Fn(&K) -> M) -> RawTr<M> { RawTr(f(&self.0)) }
+}
+
+impl<K: DeriveXOnly> Derive<DerivedScript> for RawTr<K> {
+    #[inline]
+    fn default_keychain(&self) -> Keychain { self.0.default_keychain() }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { self.0.keychains() }
+
+    fn derive(
+        &self,
+        keychain: impl Into<Keychain>,
+        index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = DerivedScript> {
+        self.0.derive(keychain, index).map(|output_key| {
+            DerivedScript::Bare(ScriptPubkey::p2tr_tweaked(OutputPk::from_unchecked(output_key)))
+        })
+    }
+}
+
+impl<K: DeriveXOnly> Descriptor<K> for RawTr<K> {
+    fn class(&self) -> SpkClass { SpkClass::P2tr }
+
+    fn keys<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where K: 'a {
+        iter::once(&self.0)
+    }
+    fn vars<'a>(&'a self) -> impl Iterator<Item = &'a ()>
+    where (): 'a {
+        iter::empty()
+    }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.0.try_xpub_spec().into_iter() }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> { vec![key_path_weight()] }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        vec![PathPolicy::new(SpendingPath::KeyPath, Policy::key::<XOnlyPk>(&self.0))]
+    }
+
+    fn legacy_keyset(&self, _terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
+        IndexMap::new()
+    }
+
+    fn xonly_keyset(&self, terminal: Terminal) -> IndexMap<XOnlyPk, TapDerivation> {
+        self.0
+            .derive(terminal.keychain, terminal.index)
+            .map(|key| (key, TapDerivation::with_origin(self.0.key_origin(terminal))))
+            .collect()
+    }
+
+    fn legacy_witness(
+        &self,
+        _script_pubkey: &ScriptPubkey,
+        _keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
+        _redeem_script: Option<RedeemScript>,
+        _witness_script: Option<WitnessScript>,
+    ) -> Option<(SigScript, Option<Witness>)> {
+        None
+    }
+
+    fn taproot_witness(
+        &self,
+        cb: Option<&ControlBlock>,
+        keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
+    ) -> Option<Witness> {
+        if cb.is_some() {
+            // Output key commits to no scripts
+            return None;
+        }
+        let keysig =
+            keysigs.iter().find(|(origin, _)| self.0.is_origin_of(origin)).map(|(_, ks)| ks)?;
+        Some(Witness::from_consensus_stack([keysig.sig.to_vec()]))
+    }
+}
+
+impl<K: DeriveXOnly> Display for RawTr<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "rawtr({})", self.0)
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
@@ -302,6 +461,13 @@ impl<K: DeriveXOnly> TrMulti<K> {
     }
     pub fn as_internal_key(&self) -> &K { &self.internal_key }
     pub fn into_internal_key(self) -> K { self.internal_key }
+    pub fn map_keys<M: DeriveXOnly>(&self, f: &impl Fn(&K) -> M) -> TrMulti<M> {
+        TrMulti {
+            internal_key: f(&self.internal_key),
+            threshold: self.threshold,
+            script_keys: ConfinedVec::from_iter_checked(self.script_keys.iter().map(f)),
+        }
+    }
 }
 
 impl<K: DeriveXOnly> Derive<DerivedScript> for TrMulti<K> {
@@ -341,7 +507,21 @@ impl<K: DeriveXOnly> Descriptor<K> for TrMulti<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(K::xpub_spec) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        multi_a_weights(self, self.threshold, self.script_keys.len())
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let script_policy = Policy::multi::<XOnlyPk, _>(self.threshold as usize, &self.script_keys);
+        vec![
+            PathPolicy::new(SpendingPath::KeyPath, Policy::key::<XOnlyPk>(&self.internal_key)),
+            PathPolicy::new(SpendingPath::ScriptPath(0), script_policy),
+        ]
+    }
 
     fn legacy_keyset(&self, _terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         IndexMap::new()
@@ -353,7 +533,9 @@ impl<K: DeriveXOnly> Descriptor<K> for TrMulti<K> {
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         _keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         _redeem_script: Option<RedeemScript>,
         _witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -364,6 +546,7 @@ impl<K: DeriveXOnly> Descriptor<K> for TrMulti<K> {
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         witness(&self.internal_key, self.threshold, cb, keysigs)
     }
@@ -373,6 +556,9 @@ impl<K: DeriveXOnly> Display for TrMulti<K>
 where K: Display
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
         fmt(&self.internal_key, self.threshold, &self.script_keys, false, f)
     }
 }
@@ -395,6 +581,13 @@ impl<K: DeriveXOnly> TrSortedMulti<K> {
     }
     pub fn as_internal_key(&self) -> &K { &self.internal_key }
     pub fn into_internal_key(self) -> K { self.internal_key }
+    pub fn map_keys<M: DeriveXOnly>(&self, f: &impl Fn(&K) -> M) -> TrSortedMulti<M> {
+        TrSortedMulti {
+            internal_key: f(&self.internal_key),
+            threshold: self.threshold,
+            script_keys: ConfinedVec::from_iter_checked(self.script_keys.iter().map(f)),
+        }
+    }
 }
 
 impl<K: DeriveXOnly> Derive<DerivedScript> for TrSortedMulti<K> {
@@ -436,7 +629,21 @@ impl<K: DeriveXOnly> Descriptor<K> for TrSortedMulti<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(K::xpub_spec) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        multi_a_weights(self, self.threshold, self.script_keys.len())
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let script_policy = Policy::multi::<XOnlyPk, _>(self.threshold as usize, &self.script_keys);
+        vec![
+            PathPolicy::new(SpendingPath::KeyPath, Policy::key::<XOnlyPk>(&self.internal_key)),
+            PathPolicy::new(SpendingPath::ScriptPath(0), script_policy),
+        ]
+    }
 
     fn legacy_keyset(&self, _terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         IndexMap::new()
@@ -449,7 +656,9 @@ impl<K: DeriveXOnly> Descriptor<K> for TrSortedMulti<K> {
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         _keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         _redeem_script: Option<RedeemScript>,
         _witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -460,6 +669,7 @@ impl<K: DeriveXOnly> Descriptor<K> for TrSortedMulti<K> {
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         witness(&self.internal_key, self.threshold, cb, keysigs)
     }
@@ -469,6 +679,9 @@ impl<K: DeriveXOnly> Display for TrSortedMulti<K>
 where K: Display
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
         fmt(&self.internal_key, self.threshold, &self.script_keys, true, f)
     }
 }
@@ -489,6 +702,12 @@ impl<K: DeriveXOnly> TrScript<K> {
     }
     pub fn as_internal_key(&self) -> &K { &self.internal_key }
     pub fn into_internal_key(self) -> K { self.internal_key }
+    pub fn map_keys<M: DeriveXOnly>(&self, f: &impl Fn(&K) -> M) -> TrScript<M> {
+        TrScript {
+            internal_key: f(&self.internal_key),
+            tap_tree: self.tap_tree.clone().map(|leaf| leaf.map_keys(f)),
+        }
+    }
 }
 
 impl<K: DeriveXOnly> Derive<DerivedScript> for TrScript<K> {
@@ -530,25 +749,85 @@ impl<K: DeriveXOnly> Descriptor<K> for TrScript<K> {
     where (): 'a {
         iter::empty()
     }
-    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> { self.keys().map(K::xpub_spec) }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+    fn hash_locks(&self) -> impl Iterator<Item = &HashLock> {
+        self.tap_tree.iter().flat_map(|leaf| leaf.script.hash_locks())
+    }
+    fn timelocks(&self) -> Vec<Timelocks> {
+        // The key path doesn't require any timelocks
+        let mut paths = vec![Timelocks::default()];
+        for path in self.tap_tree.iter().flat_map(|leaf| leaf.script.timelocks()) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> {
+        let leaves = leaf_sizes(self);
+        let script_paths = self.tap_tree.iter().zip(leaves).enumerate().filter_map(
+            |(no, (leaf, (script_len, cb_len)))| {
+                SatisfactionWeight::estimate(SpendingPath::ScriptPath(no), |estimate| {
+                    let stack =
+                        leaf.script.dummy_stack(estimate, estimate.bip340_sig_len(), |_| 32)?;
+                    let witness = stack.iter().map(Vec::len).chain([script_len, cb_len]);
+                    Some(SatSize::segwit(0, witness))
+                })
+            },
+        );
+        iter::once(key_path_weight()).chain(script_paths).collect()
+    }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        let key_path =
+            PathPolicy::new(SpendingPath::KeyPath, Policy::key::<XOnlyPk>(&self.internal_key));
+        let script_paths = self.tap_tree.iter().enumerate().filter_map(|(no, leaf)| {
+            let policy = leaf.script.policy::<XOnlyPk>()?;
+            Some(PathPolicy::new(SpendingPath::ScriptPath(no), policy))
+        });
+        iter::once(key_path).chain(script_paths).collect()
+    }
 
     fn legacy_keyset(&self, _terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
         IndexMap::new()
     }
 
     fn xonly_keyset(&self, terminal: Terminal) -> IndexMap<XOnlyPk, TapDerivation> {
-        self.keys()
+        let mut keyset = self
+            .keys()
             .map(|xkey| {
                 let key =
                     xkey.derive(terminal.keychain, terminal.index).next().expect("no key found");
-                (key, TapDerivation::with_internal_pk(xkey.xpub_spec().origin().clone(), terminal))
+                (key, TapDerivation::with_origin(xkey.key_origin(terminal)))
             })
-            .collect()
+            .collect::<IndexMap<_, _>>();
+        // Keys used in the leaf scripts must list the leaves, so signers know which of them to sign
+        for leaf in &self.tap_tree {
+            let leaf_hash = leaf
+                .script
+                .derive(terminal.keychain, terminal.index)
+                .next()
+                .expect("no derivation found")
+                .tap_leaf_hash();
+            for xkey in leaf.script.keys() {
+                let key =
+                    xkey.derive(terminal.keychain, terminal.index).next().expect("no key found");
+                if let Some(derivation) = keyset.get_mut(&key) {
+                    derivation.leaf_hashes.push(leaf_hash);
+                }
+            }
+        }
+        keyset
     }
 
     fn legacy_witness(
         &self,
+        _script_pubkey: &ScriptPubkey,
         _keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
         _redeem_script: Option<RedeemScript>,
         _witness_script: Option<WitnessScript>,
     ) -> Option<(SigScript, Option<Witness>)> {
@@ -559,58 +838,59 @@ impl<K: DeriveXOnly> Descriptor<K> for TrScript<K> {
         &self,
         cb: Option<&ControlBlock>,
         keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        preimages: &IndexMap<HashLock, Vec<u8>>,
     ) -> Option<Witness> {
         if let Some(cb) = cb {
+            // Keys in a leaf script share the same terminal derivation, which we learn from the
+            // signature origins
+            let terminal = keysigs.keys().find_map(|origin| origin.as_derivation().terminal())?;
+            // Only the leaf committed by the control block can be satisfied
+            let expected = self
+                .derive(terminal.keychain, terminal.index)
+                .next()
+                .expect("no derivation found")
+                .to_leaf_scripts()
+                .shift_remove(cb)?;
             for leaf in &self.tap_tree {
                 let descr = &leaf.script;
-                let mut stack = vec![];
-                let mut fail = false;
-                for item in &descr.satisfaction {
-                    match item {
-                        WitnessItem::Signature(origin) => {
-                            let Some(src) = keysigs.get(origin) else {
-                                fail = true;
-                                break;
-                            };
-                            stack.push(src.sig.to_vec());
-                        }
-                        WitnessItem::Data(data) => stack.push(data.clone()),
-                    }
-                }
-                if fail {
+                let leaf_script = descr
+                    .derive(terminal.keychain, terminal.index)
+                    .next()
+                    .expect("no derivation found");
+                if leaf_script != expected {
                     continue;
                 }
-                let mut script = TapScript::new();
-                for item in &descr.condition {
-                    match item {
-                        ScriptItem::Key(origin, _) => {
-                            let derivation = origin.as_derivation();
-                            let Some((_, src)) = keysigs
-                                .iter()
-                                .find(|(o, _)| o.as_derivation().starts_with(derivation))
-                            else {
-                                continue;
-                            };
-                            script.push_slice(&src.key.to_byte_array());
-                        }
-                        ScriptItem::Code(code) => {
-                            for tapcode in code {
-                                script.push_opcode(*tapcode);
-                            }
-                        }
-                        ScriptItem::Data(fata) => script.push_slice(fata),
+                let stack = match &descr.miniscript {
+                    Some(ms) => {
+                        ms.satisfy(&KeySigSatisfier::new(&keysigs, preimages, K::is_origin_of))
                     }
-                }
-                stack.push(script.to_vec());
-                stack.push(cb.consensus_serialize());
+                    None => descr
+                        .satisfaction
+                        .iter()
+                        .map(|item| match item {
+                            WitnessItem::Signature(origin) => keysigs
+                                .iter()
+                                .find(|(o, _)| {
+                                    o.master_fp() == origin.master_fp()
+                                        && o.as_derivation().starts_with(origin.as_derivation())
+                                })
+                                .map(|(_, ks)| ks.sig.to_vec()),
+                            WitnessItem::Data(data) => Some(data.clone()),
+                        })
+                        .collect::<Option<Vec<_>>>(),
+                };
+                let Some(mut stack) = stack else {
+                    continue;
+                };
+                stack.push(leaf_script.script.to_vec());
+                stack.push(control_block_bytes(cb));
                 return Some(Witness::from_consensus_stack(stack));
             }
             None
         } else {
-            let our_origin = self.internal_key.xpub_spec().origin();
             let keysig = keysigs
                 .iter()
-                .find(|(origin, _)| our_origin.is_subset_of(origin))
+                .find(|(origin, _)| self.internal_key.is_origin_of(origin))
                 .map(|(_, ks)| ks)?;
             Some(Witness::from_consensus_stack([keysig.sig.to_vec()]))
         }
@@ -621,7 +901,156 @@ impl<K: DeriveXOnly> Display for TrScript<K>
 where K: Display
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "tr({}, {})", self.internal_key, self.tap_tree)
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        write!(f, "tr({},{})", self.internal_key, self.tap_tree)
+    }
+}
+
+/// Origin under which the key path signature of a MuSig2 aggregated key is provided to
+/// [`Descriptor::taproot_witness`], since the aggregated key has no derivation of its own.
+pub fn musig_agg_origin() -> KeyOrigin { KeyOrigin::new(XpubFp::default(), DerivationPath::new()) }
+
+/// Taproot descriptor with a MuSig2 aggregated internal key (`tr(musig(...))`, BIP-390) and no
+/// script tree, which is spent via the key path with a signature produced by all participants.
+///
+/// Only the form where each participant key is derived individually is supported; script trees
+/// (`tr(musig(...),TREE)`) and derivation from the aggregated key (`musig(...)/path`, BIP-328)
+/// are rejected by the parser.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrMusig<K: DeriveCompr = XpubDerivable> {
+    pub participants: ConfinedVec<K, 1, 999>,
+}
+
+impl<K: DeriveCompr> TrMusig<K> {
+    pub fn new_checked(participants: impl IntoIterator<Item = K>) -> Self {
+        Self {
+            participants: ConfinedVec::from_iter_checked(participants),
+        }
+    }
+
+    /// Derives participant keys for a given terminal and aggregates them.
+    ///
+    /// # Panics
+    ///
+    /// If the aggregated key is the point at infinity, which can't happen unless some of the
+    /// participant keys were constructed to cancel the others.
+    pub fn key_agg(&self, terminal: Terminal) -> KeyAggContext {
+        let keys = self.participants.iter().map(|xkey| {
+            xkey.derive(terminal.keychain, terminal.index).next().expect("no derivation found")
+        });
+        KeyAggContext::new(keys).expect("MuSig2 aggregated key is the point at infinity")
+    }
+
+    pub fn map_keys<M: DeriveCompr>(&self, f: &impl Fn(&K) -> M) -> TrMusig<M> {
+        TrMusig {
+            participants: ConfinedVec::from_iter_checked(self.participants.iter().map(f)),
+        }
+    }
+}
+
+impl<K: DeriveCompr> Derive<DerivedScript> for TrMusig<K> {
+    #[inline]
+    fn default_keychain(&self) -> Keychain { self.participants[0].default_keychain() }
+
+    #[inline]
+    fn keychains(&self) -> BTreeSet<Keychain> { self.participants[0].keychains() }
+
+    fn derive(
+        &self,
+        keychain: impl Into<Keychain>,
+        index: impl Into<NormalIndex>,
+    ) -> impl Iterator<Item = DerivedScript> {
+        let terminal = Terminal::new(keychain, index.into());
+        iter::once(DerivedScript::TaprootKeyOnly(self.key_agg(terminal).internal_pk()))
+    }
+}
+
+impl<K: DeriveCompr> Descriptor<K> for TrMusig<K> {
+    fn class(&self) -> SpkClass { SpkClass::P2tr }
+
+    fn keys<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where K: 'a {
+        self.participants.iter()
+    }
+    fn vars<'a>(&'a self) -> impl Iterator<Item = &'a ()>
+    where (): 'a {
+        iter::empty()
+    }
+    fn xpubs(&self) -> impl Iterator<Item = &XpubAccount> {
+        self.keys().filter_map(K::try_xpub_spec)
+    }
+
+    fn satisfaction_weights(&self) -> Vec<SatisfactionWeight> { vec![key_path_weight()] }
+
+    fn policies(&self) -> Vec<PathPolicy> {
+        // The aggregated key is produced by all participants together
+        let policy = Policy::multi::<CompressedPk, _>(self.participants.len(), &self.participants);
+        vec![PathPolicy::new(SpendingPath::KeyPath, policy)]
+    }
+
+    fn legacy_keyset(&self, terminal: Terminal) -> IndexMap<LegacyPk, KeyOrigin> {
+        // Participant keys are not x-only, so their derivation goes into the legacy keyset
+        self.participants
+            .iter()
+            .map(|xkey| {
+                let key =
+                    xkey.derive(terminal.keychain, terminal.index).next().expect("no key found");
+                (key.into(), xkey.key_origin(terminal))
+            })
+            .collect()
+    }
+
+    fn xonly_keyset(&self, _terminal: Terminal) -> IndexMap<XOnlyPk, TapDerivation> {
+        IndexMap::new()
+    }
+
+    fn musig_keyset(&self, terminal: Terminal) -> IndexMap<CompressedPk, Vec<CompressedPk>> {
+        let key_agg = self.key_agg(terminal);
+        iter::once((key_agg.agg_pk(), key_agg.participants().to_vec())).collect()
+    }
+
+    fn legacy_witness(
+        &self,
+        _script_pubkey: &ScriptPubkey,
+        _keysigs: IndexMap<&KeyOrigin, LegacyKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
+        _redeem_script: Option<RedeemScript>,
+        _witness_script: Option<WitnessScript>,
+    ) -> Option<(SigScript, Option<Witness>)> {
+        None
+    }
+
+    fn taproot_witness(
+        &self,
+        cb: Option<&ControlBlock>,
+        keysigs: IndexMap<&KeyOrigin, TaprootKeySig>,
+        _preimages: &IndexMap<HashLock, Vec<u8>>,
+    ) -> Option<Witness> {
+        if cb.is_some() {
+            // There is no script tree
+            return None;
+        }
+        let keysig = keysigs.get(&musig_agg_origin())?;
+        Some(Witness::from_consensus_stack([keysig.sig.to_vec()]))
+    }
+}
+
+impl<K: DeriveCompr> Display for TrMusig<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt_checksummed(self, f);
+        }
+        f.write_str("tr(musig(")?;
+        for (no, key) in self.participants.iter().enumerate() {
+            if no > 0 {
+                f.write_str(",")?;
+            }
+            Display::fmt(key, f)?;
+        }
+        f.write_str("))")
     }
 }
 
@@ -641,16 +1070,66 @@ fn to_tap_tree(
     TapTree::with_single_leaf(tap_script)
 }
 
+fn key_path_weight() -> SatisfactionWeight {
+    SatisfactionWeight::estimate(SpendingPath::KeyPath, |estimate| {
+        Some(SatSize::segwit(0, [estimate.bip340_sig_len()]))
+    })
+    .expect("key path is always satisfiable")
+}
+
+/// Lengths of the leaf scripts and their control blocks, in the order of the tap tree leaves.
+fn leaf_sizes(descr: &impl Derive<DerivedScript>) -> Vec<(usize, usize)> {
+    let derived = descr.derive(descr.default_keychain(), NormalIndex::ZERO).next();
+    let Some(DerivedScript::TaprootScript(_, tree)) = derived else {
+        return vec![];
+    };
+    tree.iter()
+        .map(|leaf| (leaf.script.script.len(), 33 + 32 * leaf.depth.to_u8() as usize))
+        .collect()
+}
+
+fn multi_a_weights(
+    descr: &impl Derive<DerivedScript>,
+    threshold: u16,
+    key_count: usize,
+) -> Vec<SatisfactionWeight> {
+    let script_paths = leaf_sizes(descr).into_iter().filter_map(|(script_len, cb_len)| {
+        SatisfactionWeight::estimate(SpendingPath::ScriptPath(0), |estimate| {
+            let sigs = iter::repeat_n(estimate.bip340_sig_len(), threshold as usize);
+            let empty = iter::repeat_n(0, key_count - threshold as usize);
+            // Each key is checked with `OP_CHECKSIGADD`, thus the first of them takes the zero
+            // signature counter from the top of the stack
+            let witness = empty.chain(sigs).chain([0, script_len, cb_len]);
+            Some(SatSize::segwit(0, witness))
+        })
+    });
+    iter::once(key_path_weight()).chain(script_paths).collect()
+}
+
 fn xonly_keyset<'k, K: DeriveXOnly + 'k, I: IntoIterator<Item = &'k K>>(
     keys: I,
     terminal: Terminal,
 ) -> impl Iterator<Item = (XOnlyPk, TapDerivation)> + use<'k, K, I> {
     keys.into_iter().map(move |xkey| {
         let key = xkey.derive(terminal.keychain, terminal.index).next().expect("no key found");
-        (key, TapDerivation::with_internal_pk(xkey.xpub_spec().origin().clone(), terminal))
+        (key, TapDerivation::with_origin(xkey.key_origin(terminal)))
     })
 }
 
+/// Serializes control block for the witness stack.
+///
+/// NB: We can't use the consensus encoding from `bp-consensus` v0.12, which drops the leaf version
+/// by combining it with the output key parity using bitwise AND.
+fn control_block_bytes(cb: &ControlBlock) -> Vec<u8> {
+    let mut data = Vec::with_capacity(33 + cb.merkle_branch.len() * 32);
+    data.push(cb.leaf_version.to_consensus_u8() | cb.output_key_parity.to_consensus_u8());
+    data.extend(cb.internal_pk.to_byte_array());
+    for step in &cb.merkle_branch {
+        data.extend(step.to_byte_array());
+    }
+    data
+}
+
 fn witness<K: DeriveXOnly>(
     internal_key: &K,
     threshold: u16,
@@ -668,12 +1147,13 @@ fn witness<K: DeriveXOnly>(
         tap_script.push_num(threshold as i64);
         tap_script.push_opcode(TapCode::NumEqual);
         stack.push(tap_script.to_vec());
-        stack.push(cb.consensus_serialize());
+        stack.push(control_block_bytes(cb));
         Some(Witness::from_consensus_stack(stack))
     } else {
-        let our_origin = internal_key.xpub_spec().origin();
-        let keysig =
-            keysigs.iter().find(|(origin, _)| our_origin.is_subset_of(origin)).map(|(_, ks)| ks)?;
+        let keysig = keysigs
+            .iter()
+            .find(|(origin, _)| internal_key.is_origin_of(origin))
+            .map(|(_, ks)| ks)?;
         Some(Witness::from_consensus_stack([keysig.sig.to_vec()]))
     }
 }
@@ -0,0 +1,190 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use derive::{DeriveLegacy, Idx, NormalIndex, VarInt, WeightUnits};
+
+use crate::{HashLock, Satisfier};
+
+/// Spending path of a descriptor, which can be satisfied independently of the others.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub enum SpendingPath {
+    /// Satisfaction of a pre-taproot output by the script sig and (for segwit outputs) witness.
+    #[display("script")]
+    Script,
+
+    /// Taproot key path.
+    #[display("key path")]
+    KeyPath,
+
+    /// Taproot script path with a leaf at the given position in the tap tree, counting leaves in
+    /// the depth-first order.
+    #[display("leaf #{0}")]
+    ScriptPath(usize),
+}
+
+/// Weight of the data satisfying a spending path of a descriptor: the script sig, including its
+/// length prefix, and the witness stack, if the output is segwit.
+///
+/// Does not include the weight of the outpoint and sequence number of the spending input, as well
+/// as the empty witness which non-segwit inputs have in segwit transactions.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SatisfactionWeight {
+    pub path: SpendingPath,
+    /// The largest weight the satisfaction may have: all signatures have the maximal length, and
+    /// from the alternative satisfactions of a script the largest one is chosen.
+    pub max: WeightUnits,
+    /// Weight of the satisfaction produced by the finalizer when all the keys sign, all the
+    /// preimages are known and all the timelocks are satisfied, assuming signatures of typical
+    /// length.
+    pub expected: WeightUnits,
+}
+
+impl SatisfactionWeight {
+    /// Computes satisfaction weight of a spending path from its sizes for each of the estimates.
+    /// Returns `None` if the path can't be satisfied.
+    pub(crate) fn estimate(
+        path: SpendingPath,
+        sizes: impl Fn(Estimate) -> Option<SatSize>,
+    ) -> Option<Self> {
+        Some(SatisfactionWeight {
+            path,
+            max: sizes(Estimate::Max)?.weight(),
+            expected: sizes(Estimate::Expected)?.weight(),
+        })
+    }
+}
+
+/// Kind of satisfaction size estimate.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum Estimate {
+    Max,
+    Expected,
+}
+
+impl Estimate {
+    /// Length of DER-encoded ECDSA signature followed by a sighash type byte. Low-S signatures are
+    /// 72 bytes long, unless their R value happens to be small.
+    pub const fn ecdsa_sig_len(self) -> usize {
+        match self {
+            Estimate::Max => 73,
+            Estimate::Expected => 72,
+        }
+    }
+
+    /// Length of BIP-340 signature, which is followed by a sighash type byte only if the sighash
+    /// type is not the default one.
+    pub const fn bip340_sig_len(self) -> usize {
+        match self {
+            Estimate::Max => 65,
+            Estimate::Expected => 64,
+        }
+    }
+}
+
+/// Sizes of the satisfaction parts: length of the script sig and lengths of the witness stack
+/// elements.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub(crate) struct SatSize {
+    pub sig_script: usize,
+    pub witness: Option<Vec<usize>>,
+}
+
+impl SatSize {
+    pub fn legacy(sig_script: usize) -> Self {
+        SatSize {
+            sig_script,
+            witness: None,
+        }
+    }
+
+    pub fn segwit(sig_script: usize, witness: impl IntoIterator<Item = usize>) -> Self {
+        SatSize {
+            sig_script,
+            witness: Some(witness.into_iter().collect()),
+        }
+    }
+
+    pub fn weight(&self) -> WeightUnits {
+        let witness = self.witness.as_ref().map(|items| {
+            VarInt::with(items.len()).len()
+                + items.iter().map(|len| VarInt::with(*len).len() + len).sum::<usize>()
+        });
+        WeightUnits::no_discount(VarInt::with(self.sig_script).len() + self.sig_script)
+            + WeightUnits::witness_discount(witness.unwrap_or_default())
+    }
+}
+
+/// Length of a script instruction pushing data of the given length.
+pub(crate) fn push_len(len: usize) -> usize {
+    len + match len {
+        0..=75 => 1,
+        76..=0xFF => 2,
+        0x100..=0xFFFF => 3,
+        _ => 5,
+    }
+}
+
+/// Length of a script sig pushing the elements of the given stack, which uses single-byte
+/// opcodes for small numbers.
+pub(crate) fn sig_script_len(stack: &[Vec<u8>]) -> usize {
+    stack
+        .iter()
+        .map(|item| match item.as_slice() {
+            [1..=16] => 1,
+            data => push_len(data.len()),
+        })
+        .sum()
+}
+
+/// Satisfier providing placeholder data of the estimated size, as if all keys sign, all preimages
+/// are known and all timelocks are satisfied.
+pub(crate) struct DummySatisfier<F> {
+    sig_len: usize,
+    key_len: F,
+}
+
+impl<F> DummySatisfier<F> {
+    pub fn new(sig_len: usize, key_len: F) -> Self { DummySatisfier { sig_len, key_len } }
+}
+
+impl<K, F: Fn(&K) -> usize> Satisfier<K> for DummySatisfier<F> {
+    fn signature(&self, _key: &K) -> Option<Vec<u8>> { Some(vec![0xFF; self.sig_len]) }
+
+    fn public_key(&self, key: &K) -> Option<Vec<u8>> { Some(vec![0xFF; (self.key_len)(key)]) }
+
+    fn preimage(&self, _lock: &HashLock) -> Option<Vec<u8>> { Some(vec![0xFF; 32]) }
+
+    fn check_older(&self, _n: u32) -> bool { true }
+
+    fn check_after(&self, _n: u32) -> bool { true }
+}
+
+/// Length of the serialized keys derived from a key expression, which may be compressed or not.
+pub(crate) fn legacy_key_len<K: DeriveLegacy>(key: &K) -> usize {
+    key.derive(key.default_keychain(), NormalIndex::ZERO)
+        .next()
+        .map(|pk| pk.to_vec().len())
+        .unwrap_or(65)
+}
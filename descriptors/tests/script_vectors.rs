@@ -0,0 +1,480 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs the script interpreter against test vectors in the format of Bitcoin Core
+//! `script_tests.json`, `tx_valid.json` and `tx_invalid.json`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use amplify::hex::FromHex;
+use derive::{
+    LockTime, Outpoint, Sats, ScriptPubkey, SeqNo, SigScript, Tx, TxIn, TxOut, TxVer, Txid,
+    VarIntArray, Witness,
+};
+use descriptors::interpreter::{ScriptFailure, TxVerifyExt, VerifyFlags};
+use serde_json::Value;
+
+const NO_FLAGS: VerifyFlags = VerifyFlags {
+    p2sh: false,
+    strict_enc: false,
+    der_sig: false,
+    low_s: false,
+    null_dummy: false,
+    sig_push_only: false,
+    minimal_data: false,
+    discourage_upgradable_nops: false,
+    clean_stack: false,
+    check_lock_time_verify: false,
+    check_sequence_verify: false,
+    witness: false,
+    discourage_upgradable_witness_program: false,
+    minimal_if: false,
+    null_fail: false,
+    witness_pubkey_type: false,
+    taproot: false,
+    discourage_upgradable_taproot_version: false,
+    discourage_op_success: false,
+    discourage_upgradable_pubkey_type: false,
+};
+
+fn opcode(name: &str) -> Option<u8> {
+    let name = name.strip_prefix("OP_").unwrap_or(name);
+    Some(match name {
+        "RESERVED" => 0x50,
+        "NOP" => 0x61,
+        "VER" => 0x62,
+        "IF" => 0x63,
+        "NOTIF" => 0x64,
+        "VERIF" => 0x65,
+        "VERNOTIF" => 0x66,
+        "ELSE" => 0x67,
+        "ENDIF" => 0x68,
+        "VERIFY" => 0x69,
+        "RETURN" => 0x6a,
+        "TOALTSTACK" => 0x6b,
+        "FROMALTSTACK" => 0x6c,
+        "2DROP" => 0x6d,
+        "2DUP" => 0x6e,
+        "3DUP" => 0x6f,
+        "2OVER" => 0x70,
+        "2ROT" => 0x71,
+        "2SWAP" => 0x72,
+        "IFDUP" => 0x73,
+        "DEPTH" => 0x74,
+        "DROP" => 0x75,
+        "DUP" => 0x76,
+        "NIP" => 0x77,
+        "OVER" => 0x78,
+        "PICK" => 0x79,
+        "ROLL" => 0x7a,
+        "ROT" => 0x7b,
+        "SWAP" => 0x7c,
+        "TUCK" => 0x7d,
+        "CAT" => 0x7e,
+        "SUBSTR" => 0x7f,
+        "LEFT" => 0x80,
+        "RIGHT" => 0x81,
+        "SIZE" => 0x82,
+        "INVERT" => 0x83,
+        "AND" => 0x84,
+        "OR" => 0x85,
+        "XOR" => 0x86,
+        "EQUAL" => 0x87,
+        "EQUALVERIFY" => 0x88,
+        "RESERVED1" => 0x89,
+        "RESERVED2" => 0x8a,
+        "1ADD" => 0x8b,
+        "1SUB" => 0x8c,
+        "2MUL" => 0x8d,
+        "2DIV" => 0x8e,
+        "NEGATE" => 0x8f,
+        "ABS" => 0x90,
+        "NOT" => 0x91,
+        "0NOTEQUAL" => 0x92,
+        "ADD" => 0x93,
+        "SUB" => 0x94,
+        "MUL" => 0x95,
+        "DIV" => 0x96,
+        "MOD" => 0x97,
+        "LSHIFT" => 0x98,
+        "RSHIFT" => 0x99,
+        "BOOLAND" => 0x9a,
+        "BOOLOR" => 0x9b,
+        "NUMEQUAL" => 0x9c,
+        "NUMEQUALVERIFY" => 0x9d,
+        "NUMNOTEQUAL" => 0x9e,
+        "LESSTHAN" => 0x9f,
+        "GREATERTHAN" => 0xa0,
+        "LESSTHANOREQUAL" => 0xa1,
+        "GREATERTHANOREQUAL" => 0xa2,
+        "MIN" => 0xa3,
+        "MAX" => 0xa4,
+        "WITHIN" => 0xa5,
+        "RIPEMD160" => 0xa6,
+        "SHA1" => 0xa7,
+        "SHA256" => 0xa8,
+        "HASH160" => 0xa9,
+        "HASH256" => 0xaa,
+        "CODESEPARATOR" => 0xab,
+        "CHECKSIG" => 0xac,
+        "CHECKSIGVERIFY" => 0xad,
+        "CHECKMULTISIG" => 0xae,
+        "CHECKMULTISIGVERIFY" => 0xaf,
+        "NOP1" => 0xb0,
+        "CHECKLOCKTIMEVERIFY" => 0xb1,
+        "CHECKSEQUENCEVERIFY" => 0xb2,
+        "NOP4" => 0xb3,
+        "NOP5" => 0xb4,
+        "NOP6" => 0xb5,
+        "NOP7" => 0xb6,
+        "NOP8" => 0xb7,
+        "NOP9" => 0xb8,
+        "NOP10" => 0xb9,
+        "CHECKSIGADD" => 0xba,
+        _ => return None,
+    })
+}
+
+fn push_data(script: &mut Vec<u8>, data: &[u8]) {
+    match data.len() {
+        len @ 0..=0x4b => script.push(len as u8),
+        len @ 0x4c..=0xff => script.extend([0x4c, len as u8]),
+        len @ 0x100..=0xffff => {
+            script.push(0x4d);
+            script.extend((len as u16).to_le_bytes());
+        }
+        len => {
+            script.push(0x4e);
+            script.extend((len as u32).to_le_bytes());
+        }
+    }
+    script.extend(data);
+}
+
+fn push_num(script: &mut Vec<u8>, num: i64) {
+    match num {
+        0 => script.push(0x00),
+        -1 => script.push(0x4f),
+        1..=16 => script.push(0x50 + num as u8),
+        _ => {
+            let mut abs = num.unsigned_abs();
+            let mut data = vec![];
+            while abs > 0 {
+                data.push(abs as u8);
+                abs >>= 8;
+            }
+            let last = *data.last().expect("non-zero number");
+            if last & 0x80 != 0 {
+                data.push(if num < 0 { 0x80 } else { 0x00 });
+            } else if num < 0 {
+                *data.last_mut().expect("non-zero number") |= 0x80;
+            }
+            push_data(script, &data);
+        }
+    }
+}
+
+/// Assembles script in the human-readable notation used by Bitcoin Core test vectors.
+fn parse_script(s: &str) -> Vec<u8> {
+    let mut script = vec![];
+    for token in s.split_whitespace() {
+        if let Ok(num) = i64::from_str(token) {
+            push_num(&mut script, num);
+        } else if let Some(hex) = token.strip_prefix("0x") {
+            script.extend(Vec::<u8>::from_hex(hex).expect("invalid hex in script"));
+        } else if let Some(data) = token.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+            push_data(&mut script, data.as_bytes());
+        } else {
+            script.push(opcode(token).unwrap_or_else(|| panic!("unknown opcode {token}")));
+        }
+    }
+    script
+}
+
+/// Parses a comma-separated list of flags, returning `None` if some of them are not supported
+/// by the interpreter.
+fn parse_flags(s: &str, mut flags: VerifyFlags, value: bool) -> Option<VerifyFlags> {
+    for name in s.split(',').filter(|name| !name.is_empty() && *name != "NONE") {
+        let flag = match name {
+            "P2SH" => &mut flags.p2sh,
+            "STRICTENC" => &mut flags.strict_enc,
+            "DERSIG" => &mut flags.der_sig,
+            "LOW_S" => &mut flags.low_s,
+            "NULLDUMMY" => &mut flags.null_dummy,
+            "SIGPUSHONLY" => &mut flags.sig_push_only,
+            "MINIMALDATA" => &mut flags.minimal_data,
+            "DISCOURAGE_UPGRADABLE_NOPS" => &mut flags.discourage_upgradable_nops,
+            "CLEANSTACK" => &mut flags.clean_stack,
+            "CHECKLOCKTIMEVERIFY" => &mut flags.check_lock_time_verify,
+            "CHECKSEQUENCEVERIFY" => &mut flags.check_sequence_verify,
+            "WITNESS" => &mut flags.witness,
+            "DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM" => {
+                &mut flags.discourage_upgradable_witness_program
+            }
+            "MINIMALIF" => &mut flags.minimal_if,
+            "NULLFAIL" => &mut flags.null_fail,
+            "WITNESS_PUBKEYTYPE" => &mut flags.witness_pubkey_type,
+            "TAPROOT" => &mut flags.taproot,
+            "DISCOURAGE_UPGRADABLE_TAPROOT_VERSION" => {
+                &mut flags.discourage_upgradable_taproot_version
+            }
+            "DISCOURAGE_OP_SUCCESS" => &mut flags.discourage_op_success,
+            "DISCOURAGE_UPGRADABLE_PUBKEYTYPE" => &mut flags.discourage_upgradable_pubkey_type,
+            _ => return None,
+        };
+        *flag = value;
+    }
+    Some(flags)
+}
+
+fn parse_failure(s: &str) -> Option<ScriptFailure> {
+    Some(match s {
+        "EVAL_FALSE" => ScriptFailure::EvalFalse,
+        "OP_RETURN" => ScriptFailure::OpReturn,
+        "SCRIPT_SIZE" => ScriptFailure::ScriptSize,
+        "PUSH_SIZE" => ScriptFailure::PushSize,
+        "OP_COUNT" => ScriptFailure::OpCount,
+        "STACK_SIZE" => ScriptFailure::StackSize,
+        "SIG_COUNT" => ScriptFailure::SigCount,
+        "PUBKEY_COUNT" => ScriptFailure::PubkeyCount,
+        "VERIFY" | "EQUALVERIFY" | "CHECKMULTISIGVERIFY" | "CHECKSIGVERIFY" | "NUMEQUALVERIFY" => {
+            ScriptFailure::VerifyFailed
+        }
+        "BAD_OPCODE" => ScriptFailure::BadOpcode,
+        "DISABLED_OPCODE" => ScriptFailure::DisabledOpcode,
+        "INVALID_STACK_OPERATION" => ScriptFailure::InvalidStackOperation,
+        "INVALID_ALTSTACK_OPERATION" => ScriptFailure::InvalidAltstackOperation,
+        "UNBALANCED_CONDITIONAL" => ScriptFailure::UnbalancedConditional,
+        "NEGATIVE_LOCKTIME" => ScriptFailure::NegativeLocktime,
+        "UNSATISFIED_LOCKTIME" => ScriptFailure::UnsatisfiedLocktime,
+        "SIG_HASHTYPE" => ScriptFailure::SigHashType,
+        "SIG_DER" => ScriptFailure::SigDer,
+        "MINIMALDATA" => ScriptFailure::MinimalData,
+        "SIG_PUSHONLY" => ScriptFailure::SigPushOnly,
+        "SIG_HIGH_S" => ScriptFailure::SigHighS,
+        "SIG_NULLDUMMY" => ScriptFailure::SigNullDummy,
+        "PUBKEYTYPE" => ScriptFailure::PubkeyType,
+        "CLEANSTACK" => ScriptFailure::CleanStack,
+        "MINIMALIF" => ScriptFailure::MinimalIf,
+        "NULLFAIL" => ScriptFailure::SigNullFail,
+        "DISCOURAGE_UPGRADABLE_NOPS" => ScriptFailure::DiscourageUpgradableNops,
+        "DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM" => {
+            ScriptFailure::DiscourageUpgradableWitnessProgram
+        }
+        "DISCOURAGE_UPGRADABLE_TAPROOT_VERSION" => {
+            ScriptFailure::DiscourageUpgradableTaprootVersion
+        }
+        "DISCOURAGE_OP_SUCCESS" => ScriptFailure::DiscourageOpSuccess,
+        "DISCOURAGE_UPGRADABLE_PUBKEYTYPE" => ScriptFailure::DiscourageUpgradablePubkeyType,
+        "WITNESS_PROGRAM_WRONG_LENGTH" => ScriptFailure::WitnessProgramWrongLength,
+        "WITNESS_PROGRAM_WITNESS_EMPTY" => ScriptFailure::WitnessProgramWitnessEmpty,
+        "WITNESS_PROGRAM_MISMATCH" => ScriptFailure::WitnessProgramMismatch,
+        "WITNESS_MALLEATED" => ScriptFailure::WitnessMalleated,
+        "WITNESS_MALLEATED_P2SH" => ScriptFailure::WitnessMalleatedP2sh,
+        "WITNESS_UNEXPECTED" => ScriptFailure::WitnessUnexpected,
+        "WITNESS_PUBKEYTYPE" => ScriptFailure::WitnessPubkeyType,
+        "SCHNORR_SIG_SIZE" => ScriptFailure::SchnorrSigSize,
+        "SCHNORR_SIG_HASHTYPE" => ScriptFailure::SchnorrSigHashType,
+        "SCHNORR_SIG" => ScriptFailure::SchnorrSig,
+        "TAPROOT_WRONG_CONTROL_SIZE" => ScriptFailure::TaprootWrongControlSize,
+        "TAPSCRIPT_VALIDATION_WEIGHT" => ScriptFailure::TapscriptValidationWeight,
+        "TAPSCRIPT_CHECKMULTISIG" => ScriptFailure::TapscriptCheckMultisig,
+        "TAPSCRIPT_MINIMALIF" => ScriptFailure::TapscriptMinimalIf,
+        // Bitcoin Core reports script number decoding failures as an unknown error
+        "UNKNOWN_ERROR" => ScriptFailure::InvalidNumber,
+        _ => return None,
+    })
+}
+
+fn txout(value: u64, script_pubkey: Vec<u8>) -> TxOut {
+    TxOut {
+        value: Sats::from_sats(value),
+        script_pubkey: ScriptPubkey::from_checked(script_pubkey),
+    }
+}
+
+fn single_input_tx(
+    prev_output: Outpoint,
+    sig_script: Vec<u8>,
+    witness: Vec<Vec<u8>>,
+    output: TxOut,
+) -> Tx {
+    Tx {
+        version: TxVer::V1,
+        inputs: VarIntArray::from_checked(vec![TxIn {
+            prev_output,
+            sig_script: SigScript::from_checked(sig_script),
+            sequence: SeqNo::from_consensus_u32(u32::MAX),
+            witness: Witness::from_consensus_stack(witness),
+        }]),
+        outputs: VarIntArray::from_checked(vec![output]),
+        lock_time: LockTime::from_consensus_u32(0),
+    }
+}
+
+/// Entries consisting of a single string are comments.
+fn is_comment(entry: &[Value]) -> bool { matches!(entry, [Value::String(_)]) }
+
+#[test]
+fn script_tests() {
+    let vectors: Vec<Vec<Value>> =
+        serde_json::from_str(include_str!("data/script_tests.json")).unwrap();
+    let mut checked = 0usize;
+    for entry in vectors {
+        if is_comment(&entry) {
+            continue;
+        }
+        let (witness, amount, rest) = match &entry[0] {
+            Value::Array(wit) => {
+                let (amount, elems) = wit.split_last().unwrap();
+                let witness = elems
+                    .iter()
+                    .map(|elem| Vec::<u8>::from_hex(elem.as_str().unwrap()).unwrap())
+                    .collect();
+                let amount = (amount.as_f64().unwrap() * 100_000_000.0).round() as u64;
+                (witness, amount, &entry[1..])
+            }
+            _ => (vec![], 0, &entry[..]),
+        };
+        let [script_sig, script_pubkey, flags, expected, ..] = rest else {
+            panic!("malformed test vector {entry:?}");
+        };
+        let Some(flags) = parse_flags(flags.as_str().unwrap(), NO_FLAGS, true) else {
+            continue;
+        };
+        let expected = expected.as_str().unwrap();
+        let expected = match expected {
+            "OK" => None,
+            name => match parse_failure(name) {
+                Some(failure) => Some(failure),
+                None => continue,
+            },
+        };
+
+        let credit = single_input_tx(
+            Outpoint::new(Txid::coinbase(), u32::MAX),
+            vec![0x00, 0x00],
+            vec![],
+            txout(amount, parse_script(script_pubkey.as_str().unwrap())),
+        );
+        let spend = single_input_tx(
+            Outpoint::new(credit.txid(), 0u32),
+            parse_script(script_sig.as_str().unwrap()),
+            witness,
+            txout(amount, vec![]),
+        );
+        let result = spend.verify_scripts(&credit.outputs, flags);
+        assert_eq!(result.map_err(|err| err.failure).err(), expected, "test vector {entry:?}");
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+/// Parses Bitcoin Core transaction test vector, returning the transaction, the outputs it spends
+/// and the flags string.
+fn parse_tx_vector(entry: &[Value]) -> (Tx, Vec<TxOut>, &str) {
+    let [prevouts, tx, flags] = entry else {
+        panic!("malformed test vector {entry:?}");
+    };
+    let prevouts = prevouts
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|prevout| {
+            let prevout = prevout.as_array().unwrap();
+            let txid = Txid::from_str(prevout[0].as_str().unwrap()).unwrap();
+            let vout = prevout[1].as_i64().unwrap() as u32;
+            let script_pubkey = parse_script(prevout[2].as_str().unwrap());
+            let value = prevout.get(3).map(|value| value.as_u64().unwrap()).unwrap_or_default();
+            (Outpoint::new(txid, vout), txout(value, script_pubkey))
+        })
+        .collect::<HashMap<_, _>>();
+    let tx = Tx::from_str(tx.as_str().unwrap()).unwrap();
+    let spent = tx.inputs.iter().map(|input| prevouts[&input.prev_output].clone()).collect();
+    (tx, spent, flags.as_str().unwrap())
+}
+
+/// Enables flags required by the flags set, like Bitcoin Core `FillFlags`.
+fn fill_flags(mut flags: VerifyFlags) -> VerifyFlags {
+    flags.witness |= flags.clean_stack;
+    flags.p2sh |= flags.witness;
+    flags
+}
+
+/// Disables flags which can't be used without the rest of the flags set, like Bitcoin Core
+/// `TrimFlags`.
+fn trim_flags(mut flags: VerifyFlags) -> VerifyFlags {
+    flags.witness &= flags.p2sh;
+    flags.clean_stack &= flags.witness;
+    flags
+}
+
+#[test]
+fn tx_valid() {
+    let vectors: Vec<Vec<Value>> =
+        serde_json::from_str(include_str!("data/tx_valid.json")).unwrap();
+    let mut checked = 0usize;
+    for entry in vectors {
+        if is_comment(&entry) {
+            continue;
+        }
+        let (tx, prevouts, excluded) = parse_tx_vector(&entry);
+        let Some(flags) = parse_flags(excluded, VerifyFlags::STANDARD, false) else {
+            continue;
+        };
+        let flags = trim_flags(flags);
+        tx.verify_scripts(&prevouts, flags)
+            .unwrap_or_else(|err| panic!("test vector {entry:?} failed: {err}"));
+        // Excluded flags must be necessary for the transaction to be valid
+        if flags != VerifyFlags::STANDARD {
+            assert!(
+                tx.verify_scripts(&prevouts, VerifyFlags::STANDARD).is_err(),
+                "test vector {entry:?} has unnecessary excluded flags"
+            );
+        }
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+#[test]
+fn tx_invalid() {
+    let vectors: Vec<Vec<Value>> =
+        serde_json::from_str(include_str!("data/tx_invalid.json")).unwrap();
+    let mut checked = 0usize;
+    for entry in vectors {
+        if is_comment(&entry) {
+            continue;
+        }
+        let (tx, prevouts, flags) = parse_tx_vector(&entry);
+        let Some(flags) = parse_flags(flags, NO_FLAGS, true) else {
+            continue;
+        };
+        let flags = fill_flags(flags);
+        assert!(tx.verify_scripts(&prevouts, flags).is_err(), "test vector {entry:?} passed");
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
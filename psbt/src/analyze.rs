@@ -0,0 +1,541 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display, Formatter};
+
+use derive::{
+    Address, AddressNetwork, DerivationIndex, KeyOrigin, Keychain, LegacyPk, NormalIndex, Outpoint,
+    Sats, TapDerivation, TapLeafHash, Terminal, Txid, XOnlyPk, XkeyOrigin, Xpub,
+};
+use descriptors::{Descriptor, SpkClass};
+use indexmap::IndexMap;
+
+use crate::{FeeRate, Input, Output, Psbt, PsbtVer};
+
+/// Signature which is still missing for an input to be finalized.
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub enum MissingSig {
+    /// ECDSA signature of a pre-taproot input.
+    #[display("ECDSA signature by {key} [{origin}]")]
+    Ecdsa { key: LegacyPk, origin: KeyOrigin },
+
+    /// BIP-340 signature for the taproot key path.
+    #[display("BIP-340 key path signature by {key} [{origin}]")]
+    TapKey { key: XOnlyPk, origin: KeyOrigin },
+
+    /// BIP-340 signature for a taproot script path.
+    #[display("BIP-340 signature by {key} [{origin}] for leaf {leaf}")]
+    TapScript {
+        key: XOnlyPk,
+        leaf: TapLeafHash,
+        origin: KeyOrigin,
+    },
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+#[display(lowercase)]
+pub enum InputStatus {
+    /// The input doesn't have enough signatures to be finalized.
+    Incomplete,
+
+    /// The input has all signatures required to finalize it.
+    Finalizable,
+
+    /// The input is already finalized.
+    Finalized,
+}
+
+/// Information about a PSBT input, as provided in [`PsbtReport`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub struct InputReport {
+    pub index: usize,
+    pub outpoint: Outpoint,
+    /// Class of the spent output; `None` if the PSBT doesn't provide data on the spent output.
+    pub class: Option<SpkClass>,
+    /// Amount of the spent output; `None` if the PSBT doesn't provide data on the spent output.
+    pub amount: Option<Sats>,
+    /// Whether the input is spent with keys derived from one of the wallet accounts.
+    pub is_mine: bool,
+    pub status: InputStatus,
+    /// Signatures which may be provided by the keys with known derivation information. Empty for
+    /// finalizable and finalized inputs.
+    pub missing_sigs: Vec<MissingSig>,
+}
+
+/// Information about a PSBT output, as provided in [`PsbtReport`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub struct OutputReport {
+    pub index: usize,
+    pub amount: Sats,
+    /// Address of the output; `None` for scripts which can't be represented as an address.
+    pub address: Option<Address>,
+    /// Terminal derivation of all the output keys, if they share the same one.
+    pub terminal: Option<Terminal>,
+    /// Whether the output is controlled by the keys derived from one of the wallet accounts.
+    pub is_mine: bool,
+    /// Whether the output is ours and uses internal (change) keychain of the terminal derivation.
+    pub is_change: bool,
+}
+
+/// Report on what a PSBT does, produced by [`Psbt::analyze`].
+///
+/// Values depending on the data about the spent outputs (input value, fee, fee rate and weight)
+/// are `None` if some of the inputs lack this data.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
+pub struct PsbtReport {
+    pub version: PsbtVer,
+    /// Transaction id; `None` if the transaction lock time can't be determined.
+    pub txid: Option<Txid>,
+    pub inputs: Vec<InputReport>,
+    pub outputs: Vec<OutputReport>,
+    pub input_value: Option<Sats>,
+    pub output_value: Sats,
+    /// Transaction fee; `None` if outputs spend more than the inputs.
+    pub fee: Option<Sats>,
+    pub fee_rate: Option<FeeRate>,
+    /// Transaction weight (in weight units), using worst-case estimates for non-finalized inputs.
+    pub weight: Option<u32>,
+}
+
+impl PsbtReport {
+    pub fn is_finalized(&self) -> bool {
+        self.inputs.iter().all(|input| input.status == InputStatus::Finalized)
+    }
+
+    pub fn is_finalizable(&self) -> bool {
+        self.inputs.iter().all(|input| input.status != InputStatus::Incomplete)
+    }
+}
+
+impl Psbt {
+    /// Analyzes PSBT, reporting on its inputs and outputs, fees and readiness for finalization.
+    ///
+    /// The `descriptor` is used to detect which inputs can be finalized. Ownership of inputs and
+    /// outputs is detected by matching their keys with the keys derived from the xpubs of the
+    /// `descriptor` and the global PSBT xpubs. Addresses of the outputs are constructed for the
+    /// provided `network`.
+    pub fn analyze<K, V, D: Descriptor<K, V>>(
+        &self,
+        descriptor: &D,
+        network: impl Into<AddressNetwork>,
+    ) -> PsbtReport {
+        let network = network.into();
+        let accounts = Accounts::with(self, descriptor);
+        let inputs =
+            self.inputs().map(|input| input.analyze(descriptor, &accounts)).collect::<Vec<_>>();
+        let outputs = self.outputs().map(|output| output.analyze(network, &accounts)).collect();
+
+        let input_value = inputs.iter().map(|input| input.amount).sum::<Option<Sats>>();
+        let output_value = self.output_sum();
+        let fee = input_value.and_then(|value| value.checked_sub(output_value));
+        // Satisfactions of the inputs not spending the descriptor outputs are unknown
+        let max_satisfaction = descriptor.max_satisfaction_weight();
+        let class = descriptor.class();
+        let weight = input_value.map(|_| {
+            self.weight_with(|input| {
+                input.final_satisfaction_weight().or_else(|| {
+                    inputs
+                        .get(input.index)
+                        .filter(|report| report.is_mine && report.class == Some(class))
+                        .and(max_satisfaction)
+                })
+            })
+        });
+        let fee_rate = fee.zip(weight).map(|(fee, weight)| FeeRate::with_fee(fee, weight));
+
+        PsbtReport {
+            version: self.version,
+            txid: self.txid().ok(),
+            inputs,
+            outputs,
+            input_value,
+            output_value,
+            fee,
+            fee_rate,
+            weight: weight.map(|weight| weight.to_u32()),
+        }
+    }
+}
+
+/// Account-level extended public keys known from the PSBT and the wallet descriptor.
+struct Accounts(Vec<(Xpub, XkeyOrigin)>);
+
+impl Accounts {
+    fn with<K, V>(psbt: &Psbt, descriptor: &impl Descriptor<K, V>) -> Self {
+        let psbt_xpubs = psbt.xpubs.iter().map(|(xpub, origin)| (*xpub, origin.clone()));
+        let descr_xpubs = descriptor.xpubs().map(|spec| (*spec.xpub(), spec.origin().clone()));
+        Accounts(psbt_xpubs.chain(descr_xpubs).collect())
+    }
+
+    /// Detects whether the key with the given origin is derived from one of the accounts.
+    fn derives(&self, origin: &KeyOrigin, matches: impl Fn(&Xpub) -> bool) -> bool {
+        self.0.iter().any(|(xpub, xpub_origin)| {
+            let Some(path) = xpub_origin.child_derivation(origin) else {
+                return false;
+            };
+            let path = path.iter().map(|index| match index {
+                DerivationIndex::Normal(index) => Some(*index),
+                DerivationIndex::Hardened(_) => None,
+            });
+            match path.collect::<Option<Vec<NormalIndex>>>() {
+                Some(path) => matches(&xpub.derive_pub(path)),
+                None => false,
+            }
+        })
+    }
+
+    fn own_any(
+        &self,
+        legacy: &IndexMap<LegacyPk, KeyOrigin>,
+        xonly: &IndexMap<XOnlyPk, TapDerivation>,
+    ) -> bool {
+        legacy.iter().any(|(pk, origin)| self.derives(origin, |xpub| xpub.to_legacy_pk() == *pk))
+            || xonly.iter().any(|(pk, derivation)| {
+                self.derives(&derivation.origin, |xpub| xpub.to_xonly_pk() == *pk)
+            })
+    }
+}
+
+impl Input {
+    fn analyze<K, V, D: Descriptor<K, V>>(
+        &self,
+        descriptor: &D,
+        accounts: &Accounts,
+    ) -> InputReport {
+        let utxo = self.try_prev_txout();
+        let status = if self.is_finalized() {
+            InputStatus::Finalized
+        } else if self.clone().finalize(descriptor) {
+            InputStatus::Finalizable
+        } else {
+            InputStatus::Incomplete
+        };
+        let missing_sigs =
+            if status == InputStatus::Incomplete { self.missing_sigs() } else { vec![] };
+        let is_mine = accounts.own_any(&self.bip32_derivation, &self.tap_bip32_derivation);
+
+        InputReport {
+            index: self.index,
+            outpoint: self.previous_outpoint,
+            class: utxo.map(|txout| SpkClass::detect(&txout.script_pubkey)),
+            amount: utxo.map(|txout| txout.value),
+            is_mine,
+            status,
+            missing_sigs,
+        }
+    }
+
+    fn missing_sigs(&self) -> Vec<MissingSig> {
+        let mut missing = self
+            .bip32_derivation
+            .iter()
+            .filter(|(pk, _)| !self.partial_sigs.contains_key(*pk))
+            .map(|(pk, origin)| MissingSig::Ecdsa {
+                key: *pk,
+                origin: origin.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        for (pk, derivation) in &self.tap_bip32_derivation {
+            let is_internal = self.tap_internal_key.is_some_and(|ik| XOnlyPk::from(ik) == *pk);
+            if is_internal && self.tap_key_sig.is_none() {
+                missing.push(MissingSig::TapKey {
+                    key: *pk,
+                    origin: derivation.origin.clone(),
+                });
+            }
+            for leaf in &derivation.leaf_hashes {
+                if !self.tap_script_sig.contains_key(&(*pk, *leaf)) {
+                    missing.push(MissingSig::TapScript {
+                        key: *pk,
+                        leaf: *leaf,
+                        origin: derivation.origin.clone(),
+                    });
+                }
+            }
+        }
+        missing
+    }
+}
+
+impl Output {
+    fn analyze(&self, network: AddressNetwork, accounts: &Accounts) -> OutputReport {
+        let terminal = self.terminal_derivation();
+        let is_mine = accounts.own_any(&self.bip32_derivation, &self.tap_bip32_derivation);
+        OutputReport {
+            index: self.index,
+            amount: self.amount,
+            address: Address::with(&self.script, network).ok(),
+            terminal,
+            is_mine,
+            is_change: is_mine && terminal.is_some_and(|t| t.keychain == Keychain::INNER),
+        }
+    }
+}
+
+impl Display for PsbtReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.txid {
+            Some(txid) => writeln!(f, "Transaction {txid} (PSBT {})", self.version)?,
+            None => writeln!(f, "Transaction with undefined lock time (PSBT {})", self.version)?,
+        }
+
+        writeln!(f, "Inputs:")?;
+        for input in &self.inputs {
+            write!(f, "  #{} {}", input.index, input.outpoint)?;
+            match (input.class, input.amount) {
+                (Some(class), Some(amount)) => write!(f, " {class} {amount} sats")?,
+                _ => f.write_str(" unknown UTXO")?,
+            }
+            if input.is_mine {
+                f.write_str(", mine")?;
+            }
+            writeln!(f, ", {}", input.status)?;
+            for sig in &input.missing_sigs {
+                writeln!(f, "    missing {sig}")?;
+            }
+        }
+
+        writeln!(f, "Outputs:")?;
+        for output in &self.outputs {
+            write!(f, "  #{} ", output.index)?;
+            match output.address {
+                Some(address) => write!(f, "{address}")?,
+                None => f.write_str("non-standard script")?,
+            }
+            write!(f, " {} sats", output.amount)?;
+            if output.is_change {
+                f.write_str(", change")?;
+            } else if output.is_mine {
+                f.write_str(", mine")?;
+            }
+            writeln!(f)?;
+        }
+
+        match self.input_value {
+            Some(value) => writeln!(f, "Input value: {value} sats")?,
+            None => writeln!(f, "Input value: unknown")?,
+        }
+        writeln!(f, "Output value: {} sats", self.output_value)?;
+        match (self.fee, self.fee_rate, self.weight) {
+            (Some(fee), Some(fee_rate), Some(weight)) => {
+                writeln!(f, "Fee: {fee} sats ({fee_rate} sat/vB, weight {weight} WU)")
+            }
+            (None, _, Some(_)) => writeln!(f, "Fee: outputs exceed inputs"),
+            _ => writeln!(f, "Fee: unknown"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use derive::secp256k1::ecdsa;
+    use derive::{
+        Derive, Idx, LegacySig, LockTime, Network, SeqNo, TxOut, TxVer, VarIntArray, XpubDerivable,
+    };
+    use descriptors::Wpkh;
+
+    use super::*;
+    use crate::{Prevout, UnsignedTx, UnsignedTxIn};
+
+    fn descriptor() -> Wpkh<XpubDerivable> {
+        Wpkh::from(XpubDerivable::from_str("[643a7adc/84h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1>/*").unwrap())
+    }
+
+    /// PSBT spending a wallet coin of 100 000 sats into a payment and a change output.
+    fn psbt(descriptor: &Wpkh<XpubDerivable>) -> Psbt {
+        let script_pubkey =
+            descriptor.derive(0, NormalIndex::ZERO).next().unwrap().to_script_pubkey();
+        let funding = UnsignedTx {
+            version: TxVer::V2,
+            inputs: VarIntArray::from_checked(vec![UnsignedTxIn {
+                prev_output: Outpoint::coinbase(),
+                sequence: SeqNo::from_consensus_u32(0xFFFFFFFF),
+            }]),
+            outputs: VarIntArray::from_checked(vec![TxOut::new(script_pubkey.clone(), 100_000u64)]),
+            lock_time: LockTime::ZERO,
+        };
+        let prevout = Prevout::new(Outpoint::new(funding.txid(), 0), Sats(100_000));
+        let terminal = Terminal::new(Keychain::OUTER, NormalIndex::ZERO);
+
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        psbt.append_input_expect(
+            funding,
+            prevout,
+            descriptor,
+            terminal,
+            script_pubkey,
+            SeqNo::from_consensus_u32(0xFFFFFFFD),
+        );
+        let address = Address::from_str("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").unwrap();
+        psbt.append_output_expect(address.script_pubkey(), Sats(50_000));
+        let change = Terminal::new(Keychain::INNER, NormalIndex::ONE);
+        psbt.append_change_expect(descriptor, change, Sats(49_000));
+        psbt
+    }
+
+    #[test]
+    fn analyze() {
+        let descriptor = descriptor();
+        let mut psbt = psbt(&descriptor);
+
+        let report = psbt.analyze(&descriptor, Network::Testnet3);
+        assert_eq!(report.input_value, Some(Sats(100_000)));
+        assert_eq!(report.output_value, Sats(99_000));
+        assert_eq!(report.fee, Some(Sats(1_000)));
+        assert!(!report.is_finalizable());
+
+        let input = &report.inputs[0];
+        assert_eq!(input.class, Some(SpkClass::P2wpkh));
+        assert_eq!(input.amount, Some(Sats(100_000)));
+        assert!(input.is_mine);
+        assert_eq!(input.status, InputStatus::Incomplete);
+        assert_eq!(input.missing_sigs.len(), 1);
+        let MissingSig::Ecdsa { key, .. } = input.missing_sigs[0] else {
+            panic!("ECDSA signature must be missing");
+        };
+
+        let payment = &report.outputs[0];
+        assert!(!payment.is_mine && !payment.is_change);
+        let change = &report.outputs[1];
+        assert_eq!(change.terminal, Some(Terminal::new(Keychain::INNER, NormalIndex::ONE)));
+        assert!(change.is_mine && change.is_change);
+
+        let sig = ecdsa::Signature::from_compact(&[1u8; 64]).unwrap();
+        psbt.input_mut(0).unwrap().partial_sigs.insert(key, LegacySig::sighash_all(sig));
+        let report = psbt.analyze(&descriptor, Network::Testnet3);
+        assert_eq!(report.inputs[0].status, InputStatus::Finalizable);
+        assert!(report.inputs[0].missing_sigs.is_empty());
+        assert!(report.is_finalizable() && !report.is_finalized());
+    }
+
+    #[test]
+    fn display() {
+        let descriptor = descriptor();
+        let report = psbt(&descriptor).analyze(&descriptor, Network::Testnet3);
+        assert_eq!(
+            report.to_string(),
+            "\
+Transaction 131b15d04d26be0c9190eb916945c00d68292d198182ebd83d69c21ed73557a6 (PSBT v2)
+Inputs:
+  #0 51a266b3a5d10a0cbc13de9a5b878303f7ca1080cc057914ea50409bc7c87a38:0 p2wpkh 100000 sats, mine, \
+             incomplete
+    missing ECDSA signature by 03d39b706738c7c7fc28b06d19f574a6569253b11e794dfd7a25fb20c645a60ba5 \
+             [643a7adc/84h/1h/0h/0/0]
+Outputs:
+  #0 tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx 50000 sats
+  #1 tb1qehy7c56z9hhw05qu5azqhuqtcc80cvz4p2znzs 49000 sats, change
+Input value: 100000 sats
+Output value: 99000 sats
+Fee: 1000 sats (7.092 sat/vB, weight 563 WU)
+"
+        );
+
+        let mut overspending = psbt(&descriptor);
+        overspending.output_mut(0).unwrap().amount = Sats(100_000);
+        let report = overspending.analyze(&descriptor, Network::Testnet3);
+        assert!(report
+            .to_string()
+            .ends_with("Output value: 149000 sats\nFee: outputs exceed inputs\n"));
+
+        let mut unknown = psbt(&descriptor);
+        unknown.input_mut(0).unwrap().witness_utxo = None;
+        unknown.input_mut(0).unwrap().non_witness_tx = None;
+        let report = unknown.analyze(&descriptor, Network::Testnet3).to_string();
+        assert!(report.contains(
+            "#0 51a266b3a5d10a0cbc13de9a5b878303f7ca1080cc057914ea50409bc7c87a38:0 unknown UTXO, \
+             mine, incomplete\n"
+        ));
+        assert!(report.ends_with("Input value: unknown\nOutput value: 99000 sats\nFee: unknown\n"));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let descriptor = descriptor();
+        let report = psbt(&descriptor).analyze(&descriptor, Network::Testnet3);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": "v2",
+                "txid": "131b15d04d26be0c9190eb916945c00d68292d198182ebd83d69c21ed73557a6",
+                "inputs": [{
+                    "index": 0,
+                    "outpoint": "51a266b3a5d10a0cbc13de9a5b878303f7ca1080cc057914ea50409bc7c87a38:0",
+                    "class": "p2wpkh",
+                    "amount": 100000,
+                    "isMine": true,
+                    "status": "incomplete",
+                    "missingSigs": [{
+                        "ecdsa": {
+                            "key": {
+                                "compressed": true,
+                                "pubkey": "03d39b706738c7c7fc28b06d19f574a6569253b11e794dfd7a25fb20c645a60ba5"
+                            },
+                            "origin": {
+                                "masterFp": "643a7adc",
+                                "derivation": [
+                                    { "hardened": 84 },
+                                    { "hardened": 1 },
+                                    { "hardened": 0 },
+                                    { "normal": 0 },
+                                    { "normal": 0 }
+                                ]
+                            }
+                        }
+                    }]
+                }],
+                "outputs": [{
+                    "index": 0,
+                    "amount": 50000,
+                    "address": "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+                    "terminal": null,
+                    "isMine": false,
+                    "isChange": false
+                }, {
+                    "index": 1,
+                    "amount": 49000,
+                    "address": "tb1qehy7c56z9hhw05qu5azqhuqtcc80cvz4p2znzs",
+                    "terminal": "&1/1",
+                    "isMine": true,
+                    "isChange": true
+                }],
+                "inputValue": 100000,
+                "outputValue": 99000,
+                "fee": 1000,
+                "feeRate": 7092,
+                "weight": 563
+            })
+        );
+        assert_eq!(serde_json::from_value::<PsbtReport>(json).unwrap(), report);
+    }
+}
@@ -31,10 +31,10 @@ use derive::{
     Bip340Sig, ByteStr, CompressedPk, ConsensusDataError, ConsensusDecode, ConsensusDecodeError,
     ConsensusEncode, ControlBlock, DerivationPath, Idx, InternalPk, InvalidLeafVer, InvalidTree,
     KeyOrigin, LeafInfo, LeafScript, LeafVer, LegacyPk, LegacySig, LockHeight, LockTime,
-    LockTimestamp, NonStandardValue, Outpoint, RedeemScript, Sats, ScriptBytes, ScriptPubkey,
-    SeqNo, SigError, SigScript, SighashType, TapDerivation, TapLeafHash, TapNodeHash, TapTree, Tx,
-    TxOut, TxVer, Txid, UncompressedPk, VarInt, VarIntArray, Vout, Witness, WitnessScript, XOnlyPk,
-    XkeyDecodeError, XkeyOrigin, Xpub, XpubFp,
+    LockTimestamp, NonStandardValue, Outpoint, PartialSig, PubNonce, RedeemScript, Sats,
+    ScriptBytes, ScriptPubkey, SeqNo, SigError, SigScript, SighashType, TapDerivation, TapLeafHash,
+    TapNodeHash, TapTree, Tx, TxOut, TxVer, Txid, UncompressedPk, VarInt, VarIntArray, Vout,
+    Witness, WitnessScript, XOnlyPk, XkeyDecodeError, XkeyOrigin, Xpub, XpubFp,
 };
 
 use crate::keys::KeyValue;
@@ -468,6 +468,34 @@ impl Decode for CompressedPk {
     }
 }
 
+/// List of compressed keys, used for MuSig2 participant keys, which is serialized as a
+/// concatenation of the keys.
+impl Encode for Vec<CompressedPk> {
+    fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
+        let mut counter = 0;
+        for pk in self {
+            counter += pk.encode(writer)?;
+        }
+        Ok(counter)
+    }
+}
+
+impl Decode for Vec<CompressedPk> {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        let mut iter = buf.chunks_exact(33);
+        let keys = iter
+            .by_ref()
+            .map(|chunk| CompressedPk::decode(&mut Cursor::new(chunk)))
+            .collect::<Result<_, _>>()?;
+        if !iter.remainder().is_empty() {
+            return Err(PsbtError::UnexpectedEod.into());
+        }
+        Ok(keys)
+    }
+}
+
 impl Encode for UncompressedPk {
     fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
         writer.write_all(&self.to_byte_array())?;
@@ -590,6 +618,30 @@ impl Decode for Bip340Sig {
     }
 }
 
+impl Encode for PubNonce {
+    fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
+        self.as_inner().encode(writer)
+    }
+}
+
+impl Decode for PubNonce {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Bytes::<66>::decode(reader).map(Self::from_inner)
+    }
+}
+
+impl Encode for PartialSig {
+    fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
+        self.as_inner().encode(writer)
+    }
+}
+
+impl Decode for PartialSig {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Bytes32::decode(reader).map(Self::from_inner)
+    }
+}
+
 impl Encode for SighashType {
     fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
         self.to_consensus_u32().encode(writer)
@@ -741,7 +793,22 @@ impl Decode for LockHeight {
 }
 
 psbt_code_using_consensus!(Witness);
-psbt_code_using_consensus!(ControlBlock);
+psbt_decode_from_consensus!(ControlBlock);
+
+// The consensus encoding in `bp-consensus` v0.12 drops the leaf version by combining it with the
+// output key parity using bitwise AND, so we encode the first byte on our own
+impl Encode for ControlBlock {
+    fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
+        let first_byte =
+            self.leaf_version.to_consensus_u8() | self.output_key_parity.to_consensus_u8();
+        let mut counter = first_byte.encode(writer)?;
+        counter += self.internal_pk.encode(writer)?;
+        for step in &self.merkle_branch {
+            counter += step.into_inner().encode(writer)?;
+        }
+        Ok(counter)
+    }
+}
 
 impl Encode for ScriptBytes {
     fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
@@ -768,18 +835,24 @@ impl Decode for ScriptPubkey {
     }
 }
 
+// BIP-371 serializes leaf scripts in `PSBT_IN_TAP_LEAF_SCRIPT` as the script followed by the
+// leaf version byte
 impl Encode for LeafScript {
     fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
-        let mut counter = self.version.to_consensus_u8().encode(writer)?;
-        counter += self.script.encode(writer)?;
+        let mut counter = self.script.encode(writer)?;
+        counter += self.version.to_consensus_u8().encode(writer)?;
         Ok(counter)
     }
 }
 
 impl Decode for LeafScript {
     fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
-        let version = LeafVer::from_consensus_u8(u8::decode(reader)?)?;
-        let script = ScriptBytes::decode(reader)?;
+        let mut bytes = RawBytes::<Vec<u8>>::decode(reader)?.0;
+        let version = bytes.pop().ok_or(PsbtError::InvalidTapLeafScriptSize(0))?;
+        let version = LeafVer::from_consensus_u8(version)?;
+        let len = bytes.len();
+        let script =
+            ScriptBytes::try_from(bytes).map_err(|_| PsbtError::InvalidTapLeafScriptSize(len))?;
         Ok(Self { version, script })
     }
 }
@@ -977,6 +1050,19 @@ impl<T: Encode> Encode for Option<T> {
     }
 }
 
+/// Optional data may be present only at the end of the key or value, thus `None` is decoded when
+/// no more data are left.
+impl<T: Decode> Decode for Option<T> {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        T::deserialize(buf).map(Some).map_err(DecodeError::from)
+    }
+}
+
 impl Encode for Box<dyn Encode> {
     fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
         self.as_ref().encode(writer)
@@ -997,6 +1083,21 @@ impl<A: Decode, B: Decode> Decode for (A, B) {
     }
 }
 
+impl<A: Encode, B: Encode, C: Encode> Encode for (A, B, C) {
+    fn encode(&self, writer: &mut dyn Write) -> Result<usize, IoError> {
+        Ok(self.0.encode(writer)? + self.1.encode(writer)? + self.2.encode(writer)?)
+    }
+}
+
+impl<A: Decode, B: Decode, C: Decode> Decode for (A, B, C) {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let a = A::decode(reader)?;
+        let b = B::decode(reader)?;
+        let c = C::decode(reader)?;
+        Ok((a, b, c))
+    }
+}
+
 impl Encode for () {
     fn encode(&self, _writer: &mut dyn Write) -> Result<usize, IoError> { Ok(0) }
 }
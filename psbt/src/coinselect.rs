@@ -0,0 +1,491 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Reverse;
+
+use derive::{Sats, WeightUnits};
+#[cfg(feature = "rand")]
+use secp256k1::rand::{self, RngCore};
+
+use crate::{FeeRate, Utxo};
+
+/// Default number of the branch-and-bound search iterations, matching the one used by Bitcoin
+/// Core.
+pub const BNB_MAX_TRIES: usize = 100_000;
+
+#[derive(Clone, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SelectionError {
+    /// insufficient funds: coins available for spending have {available} sats of effective value,
+    /// while {required} sats are required.
+    InsufficientFunds { available: Sats, required: Sats },
+
+    /// no combination of the available coins matches the target without a change output.
+    NoChangelessSolution,
+}
+
+/// Unspent output which may be selected for spending.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Candidate {
+    pub utxo: Utxo,
+    /// Height of the block mining the output, or `None` for unconfirmed outputs.
+    pub height: Option<u32>,
+    /// Weight of the signed transaction input spending the output.
+    pub weight: WeightUnits,
+}
+
+/// Value which has to be covered by the selected coins, together with the information required
+/// to compute their effective value and the waste metric.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SelectionTarget {
+    /// Value of the transaction outputs, plus the fee for the transaction data not related to
+    /// the inputs.
+    pub value: Sats,
+    /// Fee rate of the constructed transaction.
+    pub fee_rate: FeeRate,
+    /// Fee rate at which the coins are expected to be spendable in the future.
+    pub long_term_fee_rate: FeeRate,
+    /// Weight of the change output.
+    pub change_weight: WeightUnits,
+    /// Weight of a future transaction input spending the change output.
+    pub change_spend_weight: WeightUnits,
+    /// Minimal value of the change output; any smaller excess goes to the fees.
+    pub min_change: Sats,
+}
+
+impl SelectionTarget {
+    /// Value of the coin after paying the fee for its own input.
+    pub fn effective_value(&self, coin: &Candidate) -> i64 {
+        coin.utxo.value.sats_i64() - self.fee_rate.fee(coin.weight).sats_i64()
+    }
+
+    /// Cost of creating a change output now and spending it later.
+    pub fn cost_of_change(&self) -> Sats {
+        self.fee_rate.fee(self.change_weight)
+            + self.long_term_fee_rate.fee(self.change_spend_weight)
+    }
+
+    /// Minimal excess value over the target for which a change output is created.
+    pub fn change_threshold(&self) -> Sats {
+        self.fee_rate.fee(self.change_weight) + self.min_change
+    }
+
+    /// Difference between the fee paid for spending the coin now and in the future.
+    fn input_waste(&self, coin: &Candidate) -> i64 {
+        self.fee_rate.fee(coin.weight).sats_i64()
+            - self.long_term_fee_rate.fee(coin.weight).sats_i64()
+    }
+
+    /// Creates selection result out of the coins, computing its waste metric.
+    ///
+    /// The waste metric follows the one used by Bitcoin Core: it is the sum of the differences
+    /// between the fee paid for each input at the current and long-term fee rate, plus either the
+    /// cost of the change (if the change output is created) or the excess value paid to miners.
+    ///
+    /// # Panics
+    ///
+    /// If the effective value of the coins is less than the target value.
+    pub fn selection(&self, coins: impl IntoIterator<Item = Candidate>) -> Selection {
+        let mut selection = Selection::default();
+        let mut effective_value = 0i64;
+        for coin in coins {
+            effective_value += self.effective_value(&coin);
+            selection.waste += self.input_waste(&coin);
+            selection.value += coin.utxo.value;
+            selection.coins.push(coin.utxo);
+        }
+        let excess = effective_value - self.value.sats_i64();
+        assert!(excess >= 0, "selected coins don't cover the target value");
+        selection.change = excess > self.change_threshold().sats_i64();
+        selection.waste += if selection.change { self.cost_of_change().sats_i64() } else { excess };
+        selection
+    }
+
+    /// Selects all coins having positive effective value, for transactions sending all the funds
+    /// to some of the outputs. Such transactions have no change and no excess value, so their
+    /// waste consists of the input part only.
+    pub fn sweep(&self, coins: &[Candidate]) -> Result<Selection, SelectionError> {
+        let mut selection = Selection::default();
+        let mut effective_value = 0i64;
+        for coin in coins.iter().filter(|coin| self.effective_value(coin) > 0) {
+            effective_value += self.effective_value(coin);
+            selection.waste += self.input_waste(coin);
+            selection.value += coin.utxo.value;
+            selection.coins.push(coin.utxo);
+        }
+        if effective_value < self.value.sats_i64() {
+            return Err(insufficient_funds(coins, self));
+        }
+        Ok(selection)
+    }
+}
+
+/// Result of the coin selection.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Selection {
+    /// Coins to spend.
+    pub coins: Vec<Utxo>,
+    /// Total value of the coins.
+    pub value: Sats,
+    /// Whether the transaction will require a change output.
+    pub change: bool,
+    /// Waste metric of the selection, in sats; the lower value is the better one. May be
+    /// negative when the current fee rate is lower than the long-term one.
+    pub waste: i64,
+}
+
+/// Coin selection strategy.
+pub trait CoinSelection {
+    /// Selects coins out of the candidates to cover the target value.
+    fn select(
+        &mut self,
+        candidates: &[Candidate],
+        target: &SelectionTarget,
+    ) -> Result<Selection, SelectionError>;
+}
+
+fn insufficient_funds<'a>(
+    candidates: impl IntoIterator<Item = &'a Candidate>,
+    target: &SelectionTarget,
+) -> SelectionError {
+    let available = candidates
+        .into_iter()
+        .map(|coin| target.effective_value(coin))
+        .filter(|value| *value > 0)
+        .sum::<i64>();
+    SelectionError::InsufficientFunds {
+        available: Sats::from_sats(available as u64),
+        required: target.value,
+    }
+}
+
+/// Accumulates coins in the provided order until they cover the target value plus the
+/// `extra` value; if they don't, accepts any selection covering the target value. Coins with a
+/// non-positive effective value are skipped.
+fn accumulate<'a>(
+    coins: impl IntoIterator<Item = &'a Candidate>,
+    target: &SelectionTarget,
+    extra: Sats,
+) -> Result<Selection, SelectionError> {
+    let mut selected = vec![];
+    let mut effective_value = 0i64;
+    for coin in coins {
+        let value = target.effective_value(coin);
+        if value <= 0 {
+            continue;
+        }
+        effective_value += value;
+        selected.push(*coin);
+        if effective_value >= (target.value + extra).sats_i64() {
+            break;
+        }
+    }
+    if effective_value < target.value.sats_i64() {
+        return Err(insufficient_funds(&selected, target));
+    }
+    Ok(target.selection(selected))
+}
+
+/// Branch-and-bound selection searching for a combination of coins which doesn't require a change
+/// output and has the least waste.
+///
+/// The algorithm follows the one used in Bitcoin Core, but restricts the excess over the target
+/// value to the change threshold, so the constructed transaction is guaranteed to be changeless.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct BranchAndBound {
+    /// Maximal number of the search iterations.
+    pub max_tries: usize,
+}
+
+impl Default for BranchAndBound {
+    fn default() -> Self {
+        BranchAndBound {
+            max_tries: BNB_MAX_TRIES,
+        }
+    }
+}
+
+impl CoinSelection for BranchAndBound {
+    fn select(
+        &mut self,
+        candidates: &[Candidate],
+        target: &SelectionTarget,
+    ) -> Result<Selection, SelectionError> {
+        let mut pool = candidates
+            .iter()
+            .map(|coin| (target.effective_value(coin), target.input_waste(coin), coin))
+            .filter(|(value, ..)| *value > 0)
+            .collect::<Vec<_>>();
+        pool.sort_by_key(|(value, ..)| Reverse(*value));
+
+        let mut available = pool.iter().map(|(value, ..)| value).sum::<i64>();
+        let selection_target = target.value.sats_i64();
+        if available < selection_target {
+            return Err(insufficient_funds(candidates, target));
+        }
+        let upper_bound =
+            selection_target + target.cost_of_change().min(target.change_threshold()).sats_i64();
+        let is_fee_high = target.fee_rate > target.long_term_fee_rate;
+
+        let mut value = 0i64;
+        let mut waste = 0i64;
+        let mut selected = Vec::<usize>::new();
+        let mut best = None::<(i64, Vec<usize>)>;
+        let mut index = 0usize;
+        for _ in 0..self.max_tries {
+            let best_waste = best.as_ref().map(|(waste, _)| *waste).unwrap_or(i64::MAX);
+            let mut backtrack = false;
+            if value + available < selection_target
+                || value > upper_bound
+                || (waste > best_waste && is_fee_high)
+            {
+                backtrack = true;
+            } else if value >= selection_target {
+                let total_waste = waste + value - selection_target;
+                if total_waste <= best_waste {
+                    best = Some((total_waste, selected.clone()));
+                }
+                backtrack = true;
+            }
+
+            if backtrack {
+                let Some(&last) = selected.last() else {
+                    break;
+                };
+                // Return the omitted coins back before trying the branch omitting the last
+                // selected coin
+                index -= 1;
+                while index > last {
+                    available += pool[index].0;
+                    index -= 1;
+                }
+                value -= pool[index].0;
+                waste -= pool[index].1;
+                selected.pop();
+            } else {
+                let (coin_value, coin_waste, coin) = pool[index];
+                available -= coin_value;
+                // Skip coins equivalent to the previous one if that one was omitted, since this
+                // branch was already explored
+                if selected.last().is_none_or(|last| last + 1 == index)
+                    || coin_value != pool[index - 1].0
+                    || coin.weight != pool[index - 1].2.weight
+                {
+                    selected.push(index);
+                    value += coin_value;
+                    waste += coin_waste;
+                }
+            }
+            index += 1;
+        }
+
+        let (_, best) = best.ok_or(SelectionError::NoChangelessSolution)?;
+        Ok(target.selection(best.into_iter().map(|index| *pool[index].2)))
+    }
+}
+
+/// Single random draw selection, accumulating coins in a random order until the selection is
+/// sufficient to create a change output.
+#[cfg(feature = "rand")]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SingleRandomDraw<R: RngCore = rand::rngs::ThreadRng>(pub R);
+
+#[cfg(feature = "rand")]
+impl Default for SingleRandomDraw {
+    fn default() -> Self { SingleRandomDraw(rand::thread_rng()) }
+}
+
+#[cfg(feature = "rand")]
+impl<R: RngCore> CoinSelection for SingleRandomDraw<R> {
+    fn select(
+        &mut self,
+        candidates: &[Candidate],
+        target: &SelectionTarget,
+    ) -> Result<Selection, SelectionError> {
+        let mut coins = candidates.iter().collect::<Vec<_>>();
+        // Fisher-Yates shuffle
+        for i in (1..coins.len()).rev() {
+            let j = (self.0.next_u64() % (i as u64 + 1)) as usize;
+            coins.swap(i, j);
+        }
+        accumulate(coins, target, target.change_threshold())
+    }
+}
+
+/// Selection of coins with the largest value first.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct LargestFirst;
+
+impl CoinSelection for LargestFirst {
+    fn select(
+        &mut self,
+        candidates: &[Candidate],
+        target: &SelectionTarget,
+    ) -> Result<Selection, SelectionError> {
+        let mut coins = candidates.iter().collect::<Vec<_>>();
+        coins.sort_by_key(|coin| Reverse(coin.utxo.value));
+        accumulate(coins, target, Sats::ZERO)
+    }
+}
+
+/// Selection of the oldest coins first; unconfirmed coins are selected the last.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct OldestFirst;
+
+impl CoinSelection for OldestFirst {
+    fn select(
+        &mut self,
+        candidates: &[Candidate],
+        target: &SelectionTarget,
+    ) -> Result<Selection, SelectionError> {
+        let mut coins = candidates.iter().collect::<Vec<_>>();
+        coins.sort_by_key(|coin| coin.height.unwrap_or(u32::MAX));
+        accumulate(coins, target, Sats::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::ByteArray;
+    use bc::Txid;
+    use derive::{Idx, Keychain, NormalIndex, Outpoint, Terminal};
+
+    use super::*;
+
+    fn coins(coins: &[(u64, Option<u32>)]) -> Vec<Candidate> {
+        coins
+            .iter()
+            .enumerate()
+            .map(|(vout, (value, height))| Candidate {
+                utxo: Utxo {
+                    outpoint: Outpoint::new(Txid::from_byte_array([0xA5; 32]), vout as u32),
+                    value: Sats(*value),
+                    terminal: Terminal::new(Keychain::OUTER, NormalIndex::ZERO),
+                },
+                height: *height,
+                // 68 vbytes of a P2WPKH input
+                weight: WeightUnits::witness_discount(272),
+            })
+            .collect()
+    }
+
+    fn target(value: u64, fee_rate: u64, long_term_fee_rate: u64) -> SelectionTarget {
+        SelectionTarget {
+            value: Sats(value),
+            fee_rate: FeeRate::from_sat_per_vb(fee_rate).unwrap(),
+            long_term_fee_rate: FeeRate::from_sat_per_vb(long_term_fee_rate).unwrap(),
+            // 31 vbytes of a P2WPKH output
+            change_weight: WeightUnits::witness_discount(124),
+            change_spend_weight: WeightUnits::witness_discount(272),
+            min_change: Sats(294),
+        }
+    }
+
+    #[test]
+    fn waste() {
+        let target = target(10_000, 10, 5);
+        assert_eq!(target.cost_of_change(), Sats(310 + 340));
+        assert_eq!(target.change_threshold(), Sats(310 + 294));
+
+        let selection = target.selection(coins(&[(20_000, None)]));
+        assert!(selection.change);
+        assert_eq!(selection.waste, 340 + 650);
+
+        let selection = target.selection(coins(&[(10_900, None)]));
+        assert!(!selection.change);
+        assert_eq!(selection.waste, 340 + 220);
+    }
+
+    #[test]
+    fn bnb_exact() {
+        let candidates = coins(&[(1000, None), (2000, None), (3000, None), (5000, None)]);
+        let selection = BranchAndBound::default().select(&candidates, &target(5000, 0, 0)).unwrap();
+        assert_eq!(selection.value, Sats(5000));
+        assert_eq!(selection.waste, 0);
+        assert!(!selection.change);
+    }
+
+    #[test]
+    fn bnb_waste() {
+        // Effective values are 5000, 2000 and 3000 sats
+        let candidates = coins(&[(5680, None), (2680, None), (3680, None)]);
+        let selection =
+            BranchAndBound::default().select(&candidates, &target(5000, 10, 5)).unwrap();
+        assert_eq!(selection.coins, vec![candidates[0].utxo]);
+        assert_eq!(selection.waste, 340);
+
+        // With the fee rate lower than the long-term one it is better to consolidate
+        let candidates = coins(&[(5340, None), (2340, None), (3340, None)]);
+        let selection =
+            BranchAndBound::default().select(&candidates, &target(5000, 5, 10)).unwrap();
+        assert_eq!(selection.coins.len(), 2);
+        assert_eq!(selection.value, Sats(5680));
+        assert_eq!(selection.waste, -680);
+    }
+
+    #[test]
+    fn bnb_failures() {
+        let candidates = coins(&[(20_000, None)]);
+        assert!(matches!(
+            BranchAndBound::default().select(&candidates, &target(5000, 0, 0)),
+            Err(SelectionError::NoChangelessSolution)
+        ));
+        assert!(matches!(
+            BranchAndBound::default().select(&candidates, &target(50_000, 0, 0)),
+            Err(SelectionError::InsufficientFunds { .. })
+        ));
+    }
+
+    #[test]
+    fn largest_first() {
+        let candidates = coins(&[(1000, Some(5)), (5000, None), (3000, Some(1))]);
+        let selection = LargestFirst.select(&candidates, &target(4000, 0, 0)).unwrap();
+        assert_eq!(selection.coins, vec![candidates[1].utxo]);
+        assert!(selection.change);
+        assert!(LargestFirst.select(&candidates, &target(10_000, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn oldest_first() {
+        let candidates = coins(&[(1000, Some(5)), (5000, None), (3000, Some(1))]);
+        let selection = OldestFirst.select(&candidates, &target(4000, 0, 0)).unwrap();
+        assert_eq!(selection.coins, vec![candidates[2].utxo, candidates[0].utxo]);
+        assert!(!selection.change);
+        assert_eq!(selection.waste, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn single_random_draw() {
+        use rand::SeedableRng;
+
+        let candidates = coins(&[(1000, None), (2000, None), (3000, None), (4000, None)]);
+        let target = target(1500, 1, 1);
+        let mut srd = SingleRandomDraw(rand::rngs::StdRng::seed_from_u64(42));
+        for _ in 0..10 {
+            let selection = srd.select(&candidates, &target).unwrap();
+            assert!(selection.change);
+        }
+    }
+}
@@ -0,0 +1,470 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::hash::Hash;
+
+use derive::{Txid, XkeyOrigin, Xpub};
+use indexmap::IndexMap;
+
+use crate::{
+    Input, KeyData, LockTimeConflict, ModifiableFlags, Output, PropKey, Psbt, PsbtVer, ValueData,
+    VerifyError,
+};
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum CombineError {
+    /// PSBTs can't be combined since they describe different transactions {0} and {1}.
+    TxidMismatch(Txid, Txid),
+
+    /// PSBTs have different sets of inputs, but the inputs are not modifiable.
+    InputsUnmodifiable,
+
+    /// PSBTs have different sets of outputs, but the outputs are not modifiable.
+    OutputsUnmodifiable,
+
+    /// PSBTs have conflicting values of the global {0} field.
+    GlobalConflict(&'static str),
+
+    /// PSBTs have conflicting values of the {1} field in input #{0}.
+    InputConflict(usize, &'static str),
+
+    /// PSBTs have conflicting values of the {1} field in output #{0}.
+    OutputConflict(usize, &'static str),
+
+    #[from]
+    #[display(inner)]
+    LockTime(LockTimeConflict),
+
+    #[from]
+    #[display(inner)]
+    Verify(VerifyError),
+}
+
+/// Merging of PSBT fields, failing if both sides have different values under the same key.
+trait Combine {
+    #[must_use]
+    fn combine(&mut self, other: Self) -> bool;
+}
+
+impl<T: Eq> Combine for Option<T> {
+    fn combine(&mut self, other: Self) -> bool {
+        match (self.as_ref(), other) {
+            (_, None) => true,
+            (None, other) => {
+                *self = other;
+                true
+            }
+            (Some(a), Some(b)) => *a == b,
+        }
+    }
+}
+
+impl<K: Hash + Eq, V: Eq> Combine for IndexMap<K, V> {
+    fn combine(&mut self, other: Self) -> bool {
+        for (key, value) in other {
+            match self.get(&key) {
+                None => {
+                    self.insert(key, value);
+                }
+                Some(existing) if *existing != value => return false,
+                Some(_) => {}
+            }
+        }
+        true
+    }
+}
+
+fn combine_unknown(
+    dst: &mut IndexMap<u8, IndexMap<KeyData, ValueData>>,
+    src: IndexMap<u8, IndexMap<KeyData, ValueData>>,
+) -> bool {
+    src.into_iter().all(|(key_type, map)| dst.entry(key_type).or_default().combine(map))
+}
+
+macro_rules! combine {
+    ($dst:ident, $src:ident, $err:expr; $($field:ident),+ $(,)?) => {
+        $( if !$dst.$field.combine($src.$field) {
+            return Err($err(stringify!($field)));
+        } )+
+    };
+}
+
+impl Psbt {
+    /// Combines two PSBTs describing the same transaction, as defined for the BIP-174 Combiner
+    /// role: signatures, derivation information, scripts, hash preimages, proprietary and unknown
+    /// keys of each input and output are united.
+    ///
+    /// If the transactions differ, they still can be combined for PSBTv2 when the differing
+    /// inputs (or outputs) are modifiable in both PSBTs. In this case inputs are matched by the
+    /// outpoint they spend and outputs by their script and amount, while the unmatched ones are
+    /// appended. Modifiable flags of the resulting PSBT allow only modifications allowed by both
+    /// PSBTs.
+    ///
+    /// Fails if the PSBTs have different values for the same field or key, including the global
+    /// ones, or if they set different unknown modifiable flags. In this case the PSBT is left
+    /// unchanged.
+    pub fn combine(&mut self, other: Psbt) -> Result<(), CombineError> {
+        let mut psbt = self.clone();
+        if self.txid()? == other.txid()? {
+            psbt.combine_same_tx(other)?;
+        } else {
+            psbt.combine_modified_tx(other)?;
+        }
+        psbt.lock_time()?;
+        *self = psbt;
+        Ok(())
+    }
+
+    /// Combines PSBTs like [`Psbt::combine`], but fails if any of the signatures in the combined
+    /// PSBT is invalid, so a faulty cosigner can't poison the PSBT. In this case the PSBT is left
+    /// unchanged.
+    pub fn combine_verified(&mut self, other: Psbt) -> Result<(), CombineError> {
+        let mut psbt = self.clone();
+        psbt.combine(other)?;
+        psbt.check_signatures()?;
+        *self = psbt;
+        Ok(())
+    }
+
+    fn combine_same_tx(&mut self, other: Psbt) -> Result<(), CombineError> {
+        let Psbt {
+            inputs,
+            outputs,
+            xpubs,
+            tx_modifiable,
+            proprietary,
+            unknown,
+            ..
+        } = other;
+        for (input, other) in self.inputs.iter_mut().zip(inputs) {
+            input.combine(other)?;
+        }
+        for (output, other) in self.outputs.iter_mut().zip(outputs) {
+            output.combine(other)?;
+        }
+        self.combine_global(xpubs, tx_modifiable, proprietary, unknown)
+    }
+
+    fn combine_modified_tx(&mut self, other: Psbt) -> Result<(), CombineError> {
+        if self.version != PsbtVer::V2
+            || other.version != PsbtVer::V2
+            || self.tx_version != other.tx_version
+            || self.fallback_locktime != other.fallback_locktime
+        {
+            return Err(CombineError::TxidMismatch(self.txid()?, other.txid()?));
+        }
+        let inputs_modifiable = self.are_inputs_modifiable() && other.are_inputs_modifiable();
+        let outputs_modifiable = self.are_outputs_modifiable() && other.are_outputs_modifiable();
+        let Psbt {
+            inputs,
+            outputs,
+            xpubs,
+            tx_modifiable,
+            proprietary,
+            unknown,
+            ..
+        } = other;
+
+        let same_inputs = self.inputs.len() == inputs.len()
+            && self
+                .inputs
+                .iter()
+                .zip(&inputs)
+                .all(|(a, b)| a.to_unsigned_txin() == b.to_unsigned_txin());
+        if !same_inputs && !inputs_modifiable {
+            return Err(CombineError::InputsUnmodifiable);
+        }
+        for mut other in inputs {
+            match self
+                .inputs
+                .iter_mut()
+                .find(|input| input.previous_outpoint == other.previous_outpoint)
+            {
+                Some(input) => input.combine(other)?,
+                None => {
+                    other.index = self.inputs.len();
+                    self.inputs.push(other);
+                }
+            }
+        }
+
+        let same_outputs = self.outputs.len() == outputs.len()
+            && self.outputs.iter().zip(&outputs).all(|(a, b)| a.to_txout() == b.to_txout());
+        if !same_outputs && !outputs_modifiable {
+            return Err(CombineError::OutputsUnmodifiable);
+        }
+        // Identical outputs may be repeated, so each of the existing outputs is matched once only
+        let mut unmatched = (0..self.outputs.len()).collect::<Vec<_>>();
+        for mut other in outputs {
+            let pos =
+                unmatched.iter().position(|idx| self.outputs[*idx].to_txout() == other.to_txout());
+            match pos.map(|pos| unmatched.remove(pos)) {
+                Some(idx) => self.outputs[idx].combine(other)?,
+                None => {
+                    other.index = self.outputs.len();
+                    self.outputs.push(other);
+                }
+            }
+        }
+
+        self.combine_global(xpubs, tx_modifiable, proprietary, unknown)
+    }
+
+    fn combine_global(
+        &mut self,
+        xpubs: IndexMap<Xpub, XkeyOrigin>,
+        tx_modifiable: Option<ModifiableFlags>,
+        proprietary: IndexMap<PropKey, ValueData>,
+        unknown: IndexMap<u8, IndexMap<KeyData, ValueData>>,
+    ) -> Result<(), CombineError> {
+        if !self.xpubs.combine(xpubs) {
+            return Err(CombineError::GlobalConflict("xpubs"));
+        }
+        if !self.proprietary.combine(proprietary) {
+            return Err(CombineError::GlobalConflict("proprietary"));
+        }
+        if !combine_unknown(&mut self.unknown, unknown) {
+            return Err(CombineError::GlobalConflict("unknown"));
+        }
+        self.tx_modifiable = match (self.tx_modifiable.take(), tx_modifiable) {
+            // We can't know how to merge flags with an unknown meaning
+            (Some(a), Some(b)) if a.unknown != b.unknown => {
+                return Err(CombineError::GlobalConflict("tx_modifiable"));
+            }
+            (Some(a), Some(b)) => Some(ModifiableFlags {
+                inputs_modifiable: a.inputs_modifiable && b.inputs_modifiable,
+                outputs_modifiable: a.outputs_modifiable && b.outputs_modifiable,
+                sighash_single: a.sighash_single || b.sighash_single,
+                unknown: a.unknown,
+            }),
+            (a, b) => a.or(b),
+        };
+        Ok(())
+    }
+}
+
+impl Input {
+    fn combine(&mut self, other: Input) -> Result<(), CombineError> {
+        let index = self.index;
+        let err = |field| CombineError::InputConflict(index, field);
+        combine!(self, other, err;
+            sequence_number,
+            required_time_lock,
+            required_height_lock,
+            non_witness_tx,
+            witness_utxo,
+            partial_sigs,
+            sighash_type,
+            redeem_script,
+            witness_script,
+            bip32_derivation,
+            final_script_sig,
+            final_witness,
+            proof_of_reserves,
+            ripemd160,
+            sha256,
+            hash160,
+            hash256,
+            tap_key_sig,
+            tap_script_sig,
+            tap_leaf_script,
+            tap_bip32_derivation,
+            tap_internal_key,
+            tap_merkle_root,
+            musig2_participants,
+            musig2_pub_nonces,
+            musig2_partial_sigs,
+            proprietary,
+        );
+        if !combine_unknown(&mut self.unknown, other.unknown) {
+            return Err(err("unknown"));
+        }
+        Ok(())
+    }
+}
+
+impl Output {
+    fn combine(&mut self, other: Output) -> Result<(), CombineError> {
+        let index = self.index;
+        let err = |field| CombineError::OutputConflict(index, field);
+        combine!(self, other, err;
+            redeem_script,
+            witness_script,
+            bip32_derivation,
+            tap_internal_key,
+            tap_tree,
+            tap_bip32_derivation,
+            musig2_participants,
+            proprietary,
+        );
+        if !combine_unknown(&mut self.unknown, other.unknown) {
+            return Err(err("unknown"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use derive::{Outpoint, Sats, ScriptPubkey, TxOut, Vout};
+
+    use super::*;
+
+    fn psbt() -> Psbt {
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        let mut input = Input::new(0);
+        input.previous_outpoint = Outpoint::from_str(
+            "c85f81844094f9f0eec1e41f8d63e0a99e9f73dc725d7319871c9c4121d90a0b:0",
+        )
+        .unwrap();
+        psbt.inputs.push(input);
+        psbt.append_output_expect(ScriptPubkey::op_return(&[1]), Sats::ZERO);
+        psbt
+    }
+
+    #[test]
+    fn combine_modified() {
+        let mut psbt = psbt();
+        let mut other = psbt.clone();
+        let mut input = other.inputs[0].clone();
+        input.index = 1;
+        input.previous_outpoint.vout = Vout::from_u32(1);
+        other.inputs.push(input);
+        other.inputs[0].proof_of_reserves = Some(s!("proof"));
+        other.tx_modifiable = Some(ModifiableFlags {
+            outputs_modifiable: false,
+            ..ModifiableFlags::modifiable()
+        });
+
+        psbt.combine(other.clone()).unwrap();
+        assert_eq!(psbt.inputs().count(), 2);
+        assert_eq!(psbt.outputs().count(), 1);
+        assert_eq!(psbt.inputs[0].proof_of_reserves, Some(s!("proof")));
+        assert!(psbt.are_inputs_modifiable());
+        assert!(!psbt.are_outputs_modifiable());
+        assert_eq!(psbt.txid().unwrap(), other.txid().unwrap());
+
+        // Once outputs became unmodifiable we can't add more of them
+        let mut more = psbt.clone();
+        more.outputs
+            .push(Output::with_txout(TxOut::new(ScriptPubkey::op_return(&[2]), Sats::ZERO), 1));
+        assert_eq!(psbt.clone().combine(more), Err(CombineError::OutputsUnmodifiable));
+    }
+
+    #[test]
+    fn combine_duplicate_outputs() {
+        let mut psbt = psbt();
+        let mut other = psbt.clone();
+        other.append_output_expect(ScriptPubkey::op_return(&[1]), Sats::ZERO);
+        psbt.combine(other.clone()).unwrap();
+        assert_eq!(psbt.outputs().count(), 2);
+        assert_eq!(psbt.txid().unwrap(), other.txid().unwrap());
+
+        other.outputs[1]
+            .unknown
+            .insert(0x99, indexmap! { KeyData::from(vec![1]) => ValueData::from(vec![1]) });
+        psbt.combine(other.clone()).unwrap();
+        assert_eq!(psbt.outputs().count(), 2);
+        assert!(psbt.outputs[0].unknown.is_empty());
+        assert_eq!(psbt.outputs[1].unknown, other.outputs[1].unknown);
+    }
+
+    #[test]
+    fn combine_unmodifiable() {
+        let mut psbt = psbt();
+        let mut other = psbt.clone();
+        other.append_output_expect(ScriptPubkey::op_return(&[2]), Sats::ZERO);
+        psbt.complete_construction();
+        assert_eq!(psbt.combine(other.clone()), Err(CombineError::OutputsUnmodifiable));
+
+        let mut psbt = psbt.clone();
+        psbt.version = PsbtVer::V0;
+        assert!(matches!(psbt.combine(other), Err(CombineError::TxidMismatch(..))));
+    }
+
+    #[test]
+    fn combine_conflict() {
+        let mut psbt = psbt();
+        psbt.inputs[0]
+            .unknown
+            .insert(0x99, indexmap! { KeyData::from(vec![1]) => ValueData::from(vec![1]) });
+        let mut other = psbt.clone();
+        other.inputs[0]
+            .unknown
+            .insert(0x99, indexmap! { KeyData::from(vec![2]) => ValueData::from(vec![2]) });
+        psbt.combine(other.clone()).unwrap();
+        assert_eq!(psbt.inputs[0].unknown[&0x99].len(), 2);
+
+        other.inputs[0].unknown[&0x99].insert(KeyData::from(vec![1]), ValueData::from(vec![3]));
+        let before = psbt.clone();
+        assert_eq!(psbt.combine(other), Err(CombineError::InputConflict(0, "unknown")));
+        assert_eq!(psbt, before);
+    }
+
+    #[test]
+    fn combine_global() {
+        let key = PropKey {
+            identifier: s!("test"),
+            subtype: 0,
+            data: KeyData::from(vec![1]),
+        };
+        let mut other = psbt();
+        let mut psbt = psbt();
+        psbt.proprietary.insert(key.clone(), ValueData::from(vec![1]));
+        psbt.unknown.insert(0x99, indexmap! { KeyData::from(vec![1]) => ValueData::from(vec![1]) });
+        other.proprietary.insert(
+            PropKey {
+                subtype: 1,
+                ..key.clone()
+            },
+            ValueData::from(vec![2]),
+        );
+        other
+            .unknown
+            .insert(0x99, indexmap! { KeyData::from(vec![2]) => ValueData::from(vec![2]) });
+        other.unknown.insert(0x9a, indexmap! { KeyData::from(vec![]) => ValueData::from(vec![3]) });
+        psbt.combine(other.clone()).unwrap();
+        assert_eq!(psbt.proprietary.len(), 2);
+        assert_eq!(psbt.unknown[&0x99].len(), 2);
+        assert_eq!(psbt.unknown[&0x9a].len(), 1);
+
+        let before = psbt.clone();
+        let mut conflicting = other.clone();
+        conflicting.proprietary.insert(key, ValueData::from(vec![3]));
+        assert_eq!(psbt.combine(conflicting), Err(CombineError::GlobalConflict("proprietary")));
+        let mut conflicting = other.clone();
+        conflicting.unknown[&0x99].insert(KeyData::from(vec![1]), ValueData::from(vec![3]));
+        assert_eq!(psbt.combine(conflicting), Err(CombineError::GlobalConflict("unknown")));
+        assert_eq!(psbt, before);
+
+        let mut psbt = before;
+        psbt.tx_modifiable = Some(ModifiableFlags::modifiable());
+        let mut other = psbt.clone();
+        other.tx_modifiable = Some(ModifiableFlags::from_standard_u8(0b1000_0011));
+        assert_eq!(psbt.combine(other), Err(CombineError::GlobalConflict("tx_modifiable")));
+    }
+}
@@ -22,17 +22,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::iter;
 use std::num::ParseIntError;
 use std::str::FromStr;
 
 use bc::Txid;
 use derive::{
-    Address, AddressNetwork, AddressParseError, Keychain, LockTime, Network, NormalIndex, Outpoint,
-    Sats, ScriptPubkey, SeqNo, Terminal, Vout,
+    Address, AddressNetwork, AddressParseError, Idx, Keychain, LockTime, Network, NormalIndex,
+    Outpoint, Sats, ScriptPubkey, SeqNo, Terminal, TimeLockInterval, VBytes, Vout, Weight,
+    WeightUnits,
 };
-use descriptors::Descriptor;
+use descriptors::{Descriptor, HashLock, Timelocks};
 
-use crate::{Prevout, Psbt, PsbtError, PsbtVer, UnsignedTx};
+use crate::{
+    Candidate, CoinSelection, LockTimeConflict, PreimageMismatch, Prevout, Psbt, PsbtError,
+    PsbtVer, Selection, SelectionError, SelectionTarget, UnsignedTx,
+};
 
 #[derive(Clone, Debug, Display, Error, From)]
 #[display(doc_comments)]
@@ -40,6 +47,10 @@ pub enum ConstructionError {
     #[display(inner)]
     Psbt(PsbtError),
 
+    #[from]
+    #[display(inner)]
+    CoinSelection(SelectionError),
+
     /// the input spending {0} is not known for the current wallet.
     UnknownInput(Outpoint),
 
@@ -66,6 +77,24 @@ pub enum ConstructionError {
 
     /// network for address {0} mismatches the one used by the wallet.
     NetworkMismatch(Address),
+
+    #[from]
+    #[display(inner)]
+    Preimage(PreimageMismatch),
+
+    /// coin {0} can't be spent yet since all its spending paths are timelocked.
+    Timelocked(Outpoint),
+
+    /// transaction lock time {0} is not reached yet, so the transaction can't be mined.
+    NonFinal(u32),
+
+    /// the wallet descriptor doesn't define how its coins are satisfied, so the weight of the
+    /// signed transaction and the fee for a fee rate can't be computed.
+    UnknownSatisfaction,
+
+    #[from]
+    #[display(inner)]
+    LockTime(LockTimeConflict),
 }
 
 #[derive(Clone, Debug, Display, Error, From)]
@@ -120,6 +149,107 @@ impl FromStr for Payment {
     }
 }
 
+/// Fee rate, stored with a precision of 1/1000 sat per virtual byte.
+///
+/// The string representation is a decimal number of satoshis per virtual byte, like `1.5`.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct FeeRate(u64);
+
+impl FeeRate {
+    pub const ZERO: Self = FeeRate(0);
+
+    /// Fee rate of 1 sat/vB, which is the default minimal relay fee rate.
+    pub const MIN_RELAY: Self = FeeRate(1000);
+
+    /// Constructs fee rate from a number of satoshis per virtual byte. Returns `None` if the value
+    /// can't be represented with the precision of 1/1000 sat per virtual byte.
+    #[inline]
+    pub const fn from_sat_per_vb(sats: u64) -> Option<Self> {
+        match sats.checked_mul(1000) {
+            Some(rate) => Some(FeeRate(rate)),
+            None => None,
+        }
+    }
+
+    #[inline]
+    pub const fn from_sat_per_kvb(sats: u64) -> Self { FeeRate(sats) }
+
+    /// Computes fee rate of a transaction with the given fee and weight, rounding it down.
+    pub fn with_fee(fee: Sats, weight: WeightUnits) -> Self {
+        let vbytes = (weight.into_u32() as u64).div_ceil(4).max(1);
+        FeeRate(fee.0.saturating_mul(1000) / vbytes)
+    }
+
+    #[inline]
+    pub const fn sat_per_kvb(self) -> u64 { self.0 }
+
+    /// Computes fee which has to be paid by a transaction of the given weight, rounding it up to
+    /// the next satoshi. For absurdly high fee rates the fee saturates at a value exceeding the
+    /// number of sats in existence, so it can't be paid.
+    pub fn fee(self, weight: WeightUnits) -> Sats {
+        let vbytes = (weight.into_u32() as u64).div_ceil(4);
+        Sats::from_sats(vbytes.saturating_mul(self.0).div_ceil(1000))
+    }
+}
+
+impl Display for FeeRate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (int, frac) = (self.0 / 1000, self.0 % 1000);
+        if frac == 0 {
+            return write!(f, "{int}");
+        }
+        let frac = format!("{frac:03}");
+        write!(f, "{int}.{}", frac.trim_end_matches('0'))
+    }
+}
+
+impl FromStr for FeeRate {
+    type Err = FeeRateParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        if frac.len() > 3 {
+            return Err(FeeRateParseError::Precision(s.to_owned()));
+        }
+        let int = u64::from_str(int)?;
+        let frac = if frac.is_empty() { 0 } else { u64::from_str(&format!("{frac:0<3}"))? };
+        int.checked_mul(1000)
+            .and_then(|rate| rate.checked_add(frac))
+            .map(FeeRate)
+            .ok_or(FeeRateParseError::Overflow(s.to_owned()))
+    }
+}
+
+#[derive(Clone, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum FeeRateParseError {
+    #[display(inner)]
+    #[from]
+    Int(ParseIntError),
+
+    /// fee rate '{0}' has more than three decimal digits.
+    Precision(String),
+
+    /// fee rate '{0}' is too large.
+    Overflow(String),
+}
+
+/// Fee which should be paid by a constructed transaction.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, From)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Fee {
+    /// Fixed amount of fee, irrespectively of the transaction size.
+    #[from]
+    #[display(inner)]
+    Fixed(Sats),
+
+    /// Fee computed from the estimated size of the signed transaction.
+    #[from]
+    #[display("{0} sat/vB")]
+    Rate(FeeRate),
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Display)]
 #[display("{amount}@{address}", alt = "bitcoin:{address}?amount={amount}")]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -162,7 +292,7 @@ impl FromStr for Beneficiary {
 #[derive(Copy, Clone, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TxParams {
-    pub fee: Sats,
+    pub fee: Fee,
     pub lock_time: Option<LockTime>,
     pub seq_no: SeqNo,
     pub change_shift: bool,
@@ -170,9 +300,9 @@ pub struct TxParams {
 }
 
 impl TxParams {
-    pub fn with(fee: Sats) -> Self {
+    pub fn with(fee: impl Into<Fee>) -> Self {
         TxParams {
-            fee,
+            fee: fee.into(),
             lock_time: None,
             seq_no: SeqNo::from_consensus_u32(0),
             change_shift: true,
@@ -221,6 +351,147 @@ pub trait PsbtConstructor {
     fn network(&self) -> Network;
     fn next_derivation_index(&mut self, keychain: impl Into<Keychain>, shift: bool) -> NormalIndex;
 
+    /// Lists unspent outputs available for the coin selection, together with the height of the
+    /// block mining each of them (`None` for unconfirmed outputs).
+    ///
+    /// Defaults to an empty list, in which case the coins to spend have to be provided explicitly
+    /// to [`Self::construct_psbt`] and are treated as unconfirmed.
+    fn utxos(&self) -> impl Iterator<Item = (Utxo, Option<u32>)> { iter::empty() }
+
+    /// Fee rate at which the wallet expects to spend its coins in the future, used to compute the
+    /// waste metric of the coin selection. Defaults to 10 sat/vB.
+    fn long_term_fee_rate(&self) -> FeeRate { FeeRate::from_sat_per_kvb(10_000) }
+
+    /// Preimage of a hash lock used by the wallet descriptor, if it is known to the wallet. Known
+    /// preimages are added to the inputs of the constructed PSBTs, allowing to finalize them.
+    fn preimage(&self, _lock: &HashLock) -> Option<Vec<u8>> { None }
+
+    /// Height of the last block known to the wallet, used to check which timelocked spending
+    /// paths of the descriptor are already available. Defaults to `None`, in which case only the
+    /// spending paths without timelocks can be used.
+    fn tip_height(&self) -> Option<u32> { None }
+
+    /// Median time past of the block at the given height, required to check time-based
+    /// timelocks.
+    fn median_time_past(&self, _height: u32) -> Option<u32> { None }
+
+    /// Estimates weight of the signed transaction spending the wallet coins, using
+    /// [`Psbt::estimate_weight`].
+    ///
+    /// # Errors
+    ///
+    /// If some of the inputs are not finalized and the wallet descriptor doesn't define the
+    /// weight of their satisfaction.
+    fn estimate_weight(&self, psbt: &Psbt) -> Result<WeightUnits, ConstructionError> {
+        let descriptor = self.descriptor();
+        if descriptor.max_satisfaction_weight().is_none()
+            && psbt.inputs().any(|input| input.final_satisfaction_weight().is_none())
+        {
+            return Err(ConstructionError::UnknownSatisfaction);
+        }
+        Ok(psbt.estimate_weight(descriptor))
+    }
+
+    /// Selects coins from [`Self::utxos`] to pay the beneficiaries and the fee using the provided
+    /// strategy. If some of the beneficiaries receive the maximal amount, all coins are selected.
+    ///
+    /// The returned selection can be passed to [`Self::construct_psbt`]; or its waste metric may
+    /// be compared against the selections produced with other strategies.
+    fn select_coins(
+        &self,
+        strategy: &mut impl CoinSelection,
+        beneficiaries: &[Beneficiary],
+        params: &TxParams,
+    ) -> Result<Selection, ConstructionError> {
+        let descriptor = self.descriptor();
+
+        let mut probe = Psbt::create(PsbtVer::V2);
+        let mut output_value = Sats::ZERO;
+        for beneficiary in beneficiaries {
+            let amount = beneficiary.amount.unwrap_or(Sats::ZERO);
+            output_value
+                .checked_add_assign(amount)
+                .ok_or(ConstructionError::Overflow(output_value))?;
+            probe.append_output_expect(beneficiary.script_pubkey(), amount);
+        }
+        // Weight of the transaction without inputs
+        let mut base_weight = probe.weight_units();
+        if descriptor.is_segwit() {
+            base_weight += WeightUnits::witness_discount(2); // marker and flag bytes
+        }
+        let terminal = Terminal::new(params.change_keychain, NormalIndex::ZERO);
+        let change_weight =
+            probe.append_change_expect(descriptor, terminal, Sats::ZERO).to_txout().weight_units();
+
+        let mut candidates = vec![];
+        for (utxo, height) in self.utxos() {
+            // Coins whose timelocks have not expired yet can't be spent
+            let Some(timelocks) = available_timelocks(self, height) else {
+                continue;
+            };
+            let coin = utxo.outpoint;
+            let prev_tx = self.prev_tx(coin.txid).ok_or(ConstructionError::UnknownInput(coin))?;
+            let (_, spk) = self.utxo(coin).ok_or(ConstructionError::UnknownInput(coin))?;
+            let input = probe.append_input_expect(
+                prev_tx,
+                utxo.to_prevout(),
+                descriptor,
+                utxo.terminal,
+                spk,
+                params.seq_no,
+            );
+            input.set_timelocks(timelocks);
+            candidates.push(Candidate {
+                utxo,
+                height,
+                weight: input.estimate_weight(descriptor),
+            });
+        }
+
+        let (fee_rate, fee) = match params.fee {
+            Fee::Fixed(fee) => (FeeRate::ZERO, fee),
+            Fee::Rate(_) if descriptor.max_satisfaction_weight().is_none() => {
+                return Err(ConstructionError::UnknownSatisfaction);
+            }
+            Fee::Rate(fee_rate) => (fee_rate, fee_rate.fee(base_weight)),
+        };
+        let target = SelectionTarget {
+            value: output_value
+                .checked_add(fee)
+                .ok_or(ConstructionError::Overflow(output_value))?,
+            fee_rate,
+            long_term_fee_rate: self.long_term_fee_rate(),
+            change_weight,
+            // All coins are spent with the same descriptor
+            change_spend_weight: candidates
+                .iter()
+                .map(|coin| coin.weight)
+                .max_by_key(|weight| weight.to_u32())
+                .unwrap_or(WeightUnits::no_discount(0)),
+            min_change: descriptor.class().dust_limit(),
+        };
+        let selection = if beneficiaries.iter().any(Beneficiary::is_max) {
+            target.sweep(&candidates)?
+        } else {
+            strategy.select(&candidates, &target)?
+        };
+        Ok(selection)
+    }
+
+    /// Constructs PSBT paying to the beneficiaries, selecting the coins using the provided
+    /// strategy (see [`Self::select_coins`]).
+    fn construct_psbt_with(
+        &mut self,
+        strategy: &mut impl CoinSelection,
+        beneficiaries: impl IntoIterator<Item = Beneficiary>,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), ConstructionError> {
+        let beneficiaries = beneficiaries.into_iter().collect::<Vec<_>>();
+        let selection = self.select_coins(strategy, &beneficiaries, &params)?;
+        let coins = selection.coins.iter().map(|utxo| utxo.outpoint);
+        self.construct_psbt(coins, beneficiaries, params)
+    }
+
     fn construct_psbt(
         &mut self,
         coins: impl IntoIterator<Item = Outpoint>,
@@ -238,24 +509,18 @@ pub trait PsbtConstructor {
         }
 
         // 1. Add inputs
+        let heights = self.coin_heights();
         for coin in coins {
-            let prev_tx = self.prev_tx(coin.txid).ok_or(ConstructionError::UnknownInput(coin))?;
-            let (utxo, spk) = self.utxo(coin).ok_or(ConstructionError::UnknownInput(coin))?;
-            if psbt.inputs().any(|inp| inp.previous_outpoint == utxo.outpoint) {
+            if psbt.inputs().any(|inp| inp.previous_outpoint == coin) {
                 continue;
             }
-            psbt.append_input_expect(
-                prev_tx,
-                utxo.to_prevout(),
-                self.descriptor(),
-                utxo.terminal,
-                spk,
-                params.seq_no,
-            );
+            let height = heights.get(&coin).copied().flatten();
+            self.append_coin(&mut psbt, coin, height, params.seq_no)?;
         }
         if psbt.inputs().count() == 0 {
             return Err(ConstructionError::NoInputs);
         }
+        check_lock_time(self, &psbt)?;
 
         // 2. Add outputs
         let input_value = psbt.input_sum();
@@ -274,18 +539,35 @@ pub trait PsbtConstructor {
                 max.push(out.index());
             }
         }
-        let mut remaining_value = input_value
-            .checked_sub(output_value)
-            .ok_or(ConstructionError::OutputExceedsInputs {
-                input_value,
-                output_value,
-            })?
-            .checked_sub(params.fee)
-            .ok_or(ConstructionError::NoFundsForFee {
+        let surplus = input_value.checked_sub(output_value).ok_or(
+            ConstructionError::OutputExceedsInputs {
                 input_value,
                 output_value,
-                fee: params.fee,
-            })?;
+            },
+        )?;
+
+        // Fees which has to be paid by the transaction without and with a change output. For the
+        // fee rate they are computed from the estimated weight of the signed transaction; to
+        // account for the change output we measure a copy of the PSBT having it.
+        let (fee, fee_with_change) = match params.fee {
+            Fee::Fixed(fee) => (fee, fee),
+            Fee::Rate(fee_rate) => {
+                let mut probe = psbt.clone();
+                let terminal = Terminal::new(params.change_keychain, NormalIndex::ZERO);
+                probe.append_change_expect(self.descriptor(), terminal, Sats::ZERO);
+                (
+                    fee_rate.fee(self.estimate_weight(&psbt)?),
+                    fee_rate.fee(self.estimate_weight(&probe)?),
+                )
+            }
+        };
+        let remaining_value = surplus.checked_sub(fee).ok_or(ConstructionError::NoFundsForFee {
+            input_value,
+            output_value,
+            fee,
+        })?;
+
+        let mut change_value = None;
         if !max.is_empty() {
             let portion = remaining_value / max.len();
             for out in psbt.outputs_mut() {
@@ -293,17 +575,18 @@ pub trait PsbtConstructor {
                     out.amount = portion;
                 }
             }
-            remaining_value = Sats::ZERO;
+        } else {
+            // 3. Add change - only if it exceeds the dust limit after paying for its own weight
+            let dust_limit = self.descriptor().class().dust_limit();
+            change_value = surplus.checked_sub(fee_with_change).filter(|value| *value > dust_limit);
         }
 
-        // 3. Add change - only if exceeded the dust limit
-        let change = if remaining_value > self.descriptor().class().dust_limit() {
+        let change = if let Some(change_value) = change_value {
             let change_index =
                 self.next_derivation_index(params.change_keychain, params.change_shift);
             let change_terminal = Terminal::new(params.change_keychain, change_index);
-            let change_vout = psbt
-                .append_change_expect(self.descriptor(), change_terminal, remaining_value)
-                .index();
+            let change_vout =
+                psbt.append_change_expect(self.descriptor(), change_terminal, change_value).index();
             Some(ChangeInfo {
                 vout: Vout::from_u32(change_vout as u32),
                 terminal: change_terminal,
@@ -312,11 +595,13 @@ pub trait PsbtConstructor {
             None
         };
 
+        let weight = psbt.estimate_weight(self.descriptor());
         let meta = PsbtMeta {
             network: self.network().into(),
-            fee: params.fee,
-            weight: 0, // TODO: Implement weight/size computation
-            size: 0,
+            // Any leftover below the dust limit goes to miners, so we report the actual fee
+            fee: input_value - psbt.output_sum(),
+            weight: weight.into_u32(),
+            size: VBytes::from(weight).into_u32(),
             change,
         };
         self.after_construct_psbt(&psbt, &meta);
@@ -324,9 +609,254 @@ pub trait PsbtConstructor {
         Ok((psbt, meta))
     }
 
+    /// Constructs child-pays-for-parent (CPFP) transaction spending the wallet-owned outputs of
+    /// a stuck parent transaction into a new change output.
+    ///
+    /// If `params.fee` is a fee rate, the child pays enough for the package of the parent and the
+    /// child to reach this fee rate, but not less than the fee required for the child alone. The
+    /// parent is described by its virtual size and the fee it pays.
+    fn construct_cpfp(
+        &mut self,
+        parent_txid: Txid,
+        parent_vsize: u32,
+        parent_fee: Sats,
+        outputs: impl IntoIterator<Item = Vout>,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), ConstructionError> {
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        psbt.fallback_locktime = params.lock_time;
+        for spec in self.descriptor().xpubs() {
+            psbt.xpubs.insert(*spec.xpub(), spec.origin().clone());
+        }
+
+        let heights = self.coin_heights();
+        for vout in outputs {
+            let coin = Outpoint::new(parent_txid, vout);
+            if psbt.inputs().any(|inp| inp.previous_outpoint == coin) {
+                continue;
+            }
+            let height = heights.get(&coin).copied().flatten();
+            self.append_coin(&mut psbt, coin, height, params.seq_no)?;
+        }
+        if psbt.inputs().count() == 0 {
+            return Err(ConstructionError::NoInputs);
+        }
+        check_lock_time(self, &psbt)?;
+
+        let input_value = psbt.input_sum();
+        let mut probe = psbt.clone();
+        let terminal = Terminal::new(params.change_keychain, NormalIndex::ZERO);
+        probe.append_change_expect(self.descriptor(), terminal, Sats::ZERO);
+        let fee = match params.fee {
+            Fee::Fixed(fee) => fee,
+            Fee::Rate(fee_rate) => {
+                let child_weight = self.estimate_weight(&probe)?;
+                let package_weight = WeightUnits::no_discount(parent_vsize as usize) + child_weight;
+                let package_fee = fee_rate.fee(package_weight);
+                package_fee.saturating_sub(parent_fee).max(fee_rate.fee(child_weight))
+            }
+        };
+        let change_value = input_value
+            .checked_sub(fee)
+            .filter(|value| *value > self.descriptor().class().dust_limit())
+            .ok_or(ConstructionError::NoFundsForFee {
+                input_value,
+                output_value: Sats::ZERO,
+                fee,
+            })?;
+
+        let change_index = self.next_derivation_index(params.change_keychain, params.change_shift);
+        let change_terminal = Terminal::new(params.change_keychain, change_index);
+        let change_vout =
+            psbt.append_change_expect(self.descriptor(), change_terminal, change_value).vout();
+
+        let weight = psbt.estimate_weight(self.descriptor());
+        let meta = PsbtMeta {
+            network: self.network().into(),
+            fee,
+            weight: weight.into_u32(),
+            size: VBytes::from(weight).into_u32(),
+            change: Some(ChangeInfo {
+                vout: change_vout,
+                terminal: change_terminal,
+            }),
+        };
+        self.after_construct_psbt(&psbt, &meta);
+
+        Ok((psbt, meta))
+    }
+
+    /// Appends input spending a wallet coin to the PSBT, setting the timelocks and hash lock
+    /// preimages required by the descriptor spending paths.
+    ///
+    /// The `height` is the height of the block mining the coin, or `None` if it is unconfirmed.
+    ///
+    /// Fails if the coin is unknown to the wallet or if all the descriptor spending paths are
+    /// still timelocked for it.
+    fn append_coin(
+        &self,
+        psbt: &mut Psbt,
+        coin: Outpoint,
+        height: Option<u32>,
+        seq_no: SeqNo,
+    ) -> Result<(), ConstructionError> {
+        let prev_tx = self.prev_tx(coin.txid).ok_or(ConstructionError::UnknownInput(coin))?;
+        let (utxo, spk) = self.utxo(coin).ok_or(ConstructionError::UnknownInput(coin))?;
+        let timelocks =
+            available_timelocks(self, height).ok_or(ConstructionError::Timelocked(coin))?;
+        let descriptor = self.descriptor();
+        let input = psbt.append_input_expect(
+            prev_tx,
+            utxo.to_prevout(),
+            descriptor,
+            utxo.terminal,
+            spk,
+            seq_no,
+        );
+        input.set_timelocks(timelocks);
+        for lock in descriptor.hash_locks() {
+            if let Some(preimage) = self.preimage(lock) {
+                input.add_preimage(*lock, preimage)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Collects heights of the blocks mining the coins listed by [`Self::utxos`], such that they
+    /// can be looked up for each of the spent coins.
+    fn coin_heights(&self) -> BTreeMap<Outpoint, Option<u32>> {
+        self.utxos().map(|(utxo, height)| (utxo.outpoint, height)).collect()
+    }
+
     /// A hook which is called by the default `Self::construct_psbt` before returning the newly
     /// constructed PSBT to the caller.
     fn after_construct_psbt(&mut self, _psbt: &Psbt, _meta: &PsbtMeta) {
         // By default, we do not use the hook
     }
 }
+
+/// Combines timelocks of all descriptor spending paths which are already available for a coin
+/// mined at the given height, such that the transaction can use any of them. Returns `None` if
+/// all the spending paths are still locked.
+fn available_timelocks<C: PsbtConstructor + ?Sized>(
+    wallet: &C,
+    height: Option<u32>,
+) -> Option<Timelocks> {
+    let mut available = None::<Timelocks>;
+    for path in wallet.descriptor().timelocks() {
+        if !is_unlocked(wallet, path, height) {
+            continue;
+        }
+        // Paths which can't be combined with the already selected ones are skipped
+        available = Some(match available {
+            None => path,
+            Some(timelocks) => timelocks.combine(path).unwrap_or(timelocks),
+        });
+    }
+    available
+}
+
+/// Checks whether the timelocks of a spending path for a coin mined at the given height are
+/// expired, such that the transaction can be mined in the next block.
+fn is_unlocked<C: PsbtConstructor + ?Sized>(
+    wallet: &C,
+    timelocks: Timelocks,
+    height: Option<u32>,
+) -> bool {
+    if timelocks.is_empty() {
+        return true;
+    }
+    let Some(tip) = wallet.tip_height() else {
+        return false;
+    };
+    if let Some(lock_time) = timelocks.after {
+        if !is_reached(wallet, lock_time, tip) {
+            return false;
+        }
+    }
+    let Some(seq_no) = timelocks.older else {
+        return true;
+    };
+    // Unconfirmed coins can't satisfy relative timelocks
+    let Some(height) = height else {
+        return false;
+    };
+    match seq_no.time_lock_interval() {
+        None => true,
+        Some(TimeLockInterval::Height(blocks)) => (tip + 1).saturating_sub(height) >= blocks as u32,
+        // BIP-68 measures the time from the median time past of the block preceding the coin
+        Some(TimeLockInterval::Time(intervals)) => {
+            match (wallet.median_time_past(tip), wallet.median_time_past(height.saturating_sub(1)))
+            {
+                (Some(now), Some(since)) => now.saturating_sub(since) >= intervals as u32 * 512,
+                _ => false,
+            }
+        }
+    }
+}
+
+/// Checks whether the lock time allows mining the transaction in the block following the tip.
+fn is_reached<C: PsbtConstructor + ?Sized>(wallet: &C, lock_time: LockTime, tip: u32) -> bool {
+    if lock_time == LockTime::ZERO {
+        true
+    } else if lock_time.is_height_based() {
+        lock_time.to_consensus_u32() <= tip
+    } else {
+        // BIP-113 compares the time-based lock time with the median time past
+        wallet.median_time_past(tip).is_some_and(|mtp| lock_time.to_consensus_u32() < mtp)
+    }
+}
+
+/// Ensures that the lock time of the constructed transaction is consistent across the inputs and
+/// doesn't prevent mining it in the next block, if the chain tip is known to the wallet.
+fn check_lock_time<C: PsbtConstructor + ?Sized>(
+    wallet: &C,
+    psbt: &Psbt,
+) -> Result<(), ConstructionError> {
+    let lock_time = psbt.lock_time()?;
+    match wallet.tip_height() {
+        Some(tip) if !is_reached(wallet, lock_time, tip) => {
+            Err(ConstructionError::NonFinal(lock_time.to_consensus_u32()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fee_rate_str() {
+        assert_eq!(FeeRate::from_str("1").unwrap(), FeeRate::MIN_RELAY);
+        assert_eq!(FeeRate::from_str("2.5").unwrap(), FeeRate::from_sat_per_kvb(2500));
+        assert_eq!(FeeRate::from_str("0.001").unwrap(), FeeRate::from_sat_per_kvb(1));
+        assert_eq!(FeeRate::from_sat_per_kvb(2500).to_string(), "2.5");
+        assert_eq!(FeeRate::from_sat_per_kvb(12_050).to_string(), "12.05");
+        assert_eq!(FeeRate::from_sat_per_vb(3).unwrap().to_string(), "3");
+        assert!(FeeRate::from_str("1.0001").is_err());
+        assert!(FeeRate::from_str("1.").is_ok());
+        assert!(FeeRate::from_str(".").is_err());
+    }
+
+    #[test]
+    fn fee_rate_overflow() {
+        let max = u64::MAX / 1000;
+        assert_eq!(FeeRate::from_sat_per_vb(max), Some(FeeRate::from_sat_per_kvb(max * 1000)));
+        assert_eq!(FeeRate::from_sat_per_vb(max + 1), None);
+        assert_eq!(FeeRate::from_sat_per_vb(u64::MAX), None);
+        assert!(FeeRate::from_str(&max.to_string()).is_ok());
+        assert!(FeeRate::from_str(&(max + 1).to_string()).is_err());
+    }
+
+    #[test]
+    fn fee_rate_fee() {
+        let rate = FeeRate::from_str("1.5").unwrap();
+        assert_eq!(rate.fee(WeightUnits::no_discount(100)), Sats(150));
+        // 141 vbytes at 1.5 sat/vB are 211.5 sats
+        assert_eq!(rate.fee(WeightUnits::witness_discount(561)), Sats(212));
+        assert_eq!(FeeRate::ZERO.fee(WeightUnits::no_discount(100)), Sats::ZERO);
+        let max = FeeRate::from_sat_per_kvb(u64::MAX);
+        assert_eq!(max.fee(WeightUnits::no_discount(100)), Sats(u64::MAX / 1000 + 1));
+    }
+}
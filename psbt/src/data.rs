@@ -25,14 +25,15 @@
 use std::collections::BTreeSet;
 
 use amplify::num::u5;
-use amplify::{ByteArray, Bytes20, Bytes32};
+use amplify::{Bytes20, Bytes32};
 use derive::{
-    Bip340Sig, ByteStr, ControlBlock, InternalPk, KeyOrigin, LeafScript, LegacyPk, LegacySig,
-    LockHeight, LockTime, LockTimestamp, Outpoint, RedeemScript, Sats, ScriptCode, ScriptPubkey,
-    SeqNo, SigScript, SighashType, TapDerivation, TapLeafHash, TapNodeHash, TapTree, Terminal, Tx,
-    TxIn, TxOut, TxVer, Txid, VarIntArray, Vout, Witness, WitnessScript, XOnlyPk, XkeyOrigin, Xpub,
+    Bip340Sig, ByteStr, CompressedPk, ControlBlock, InternalPk, KeyOrigin, LeafScript, LegacyPk,
+    LegacySig, LockHeight, LockTime, LockTimestamp, Outpoint, PartialSig, PubNonce, RedeemScript,
+    Sats, ScriptCode, ScriptPubkey, SeqNo, SigScript, SighashFlag, SighashType, TapDerivation,
+    TapLeafHash, TapNodeHash, TapTree, Terminal, Tx, TxIn, TxOut, TxVer, Txid, VarInt, VarIntArray,
+    Vout, Weight, WeightUnits, Witness, WitnessScript, XOnlyPk, XkeyOrigin, Xpub,
 };
-use descriptors::{Descriptor, LegacyKeySig, TaprootKeySig};
+use descriptors::{musig_agg_origin, Descriptor, HashLock, LegacyKeySig, TaprootKeySig, Timelocks};
 use indexmap::IndexMap;
 
 pub use self::display_from_str::PsbtParseError;
@@ -42,12 +43,55 @@ use crate::{KeyData, PropKey, PsbtError, PsbtVer, ValueData};
 #[display("PSBT can't be modified")]
 pub struct Unmodifiable;
 
+/// Error adding a hash preimage to a PSBT input with [`Input::add_preimage`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display("the provided preimage doesn't match the hash lock {0}")]
+pub struct PreimageMismatch(pub HashLock);
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
 #[display(
     "can't extract signed transaction from PSBT since it still contains {0} non-finalized inputs"
 )]
 pub struct UnfinalizedInputs(pub usize);
 
+/// Error computing transaction lock time from PSBT inputs (see BIP-370).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(
+    "PSBT input {height_input} requires height-based lock time, while input {time_input} requires \
+     time-based lock time; the two can't be satisfied by the same transaction"
+)]
+pub struct LockTimeConflict {
+    /// Index of an input supporting only height-based lock time.
+    pub height_input: usize,
+    /// Index of an input supporting only time-based lock time.
+    pub time_input: usize,
+}
+
+/// Error setting sighash type of a PSBT input with [`Psbt::set_sighash_type`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum SighashTypeError {
+    /// PSBT has no input #{0}.
+    NoInput(usize),
+
+    /// input #{0} already has signatures, thus its sighash type can't be changed.
+    Signed(usize),
+
+    /// input #{index} can't be signed with SIGHASH_SINGLE since the transaction has only
+    /// {outputs} outputs.
+    NoSingleOutput { index: usize, outputs: usize },
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(inner)]
+pub enum ExtractError {
+    #[from]
+    Unfinalized(UnfinalizedInputs),
+
+    #[from]
+    LockTime(LockTimeConflict),
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct Prevout {
     pub txid: Txid,
@@ -165,11 +209,29 @@ impl UnsignedTxIn {
     }
 }
 
+/// Partially signed bitcoin transaction, supporting both PSBTv0 (BIP-174) and PSBTv2 (BIP-370).
+///
+/// # JSON representation
+///
+/// With `serde` feature PSBT can be (de)serialized as JSON using the following stable schema:
+/// - field names are camel-cased names of the structure fields; all fields are always present, with
+///   optional values set to `null`;
+/// - `version` is either `"v0"` or `"v2"`;
+/// - amounts, sequence numbers, lock times and transaction versions are integers; outpoints are
+///   `"<txid>:<vout>"` strings; keys, hashes, signatures, scripts and other binary data are
+///   hex-encoded strings;
+/// - maps with keys representable as strings (`xpubs`, `partialSigs`, `bip32Derivation`, hash
+///   preimages, `tapBip32Derivation`, `musig2Participants`) are JSON objects; `unknown` maps
+///   decimal key type to an object mapping hex key data to hex values;
+/// - maps with composite keys (`tapScriptSig`, `tapLeafScript`, `musig2PubNonces`,
+///   `musig2PartialSigs` and `proprietary`) are arrays of `{"key": ..., "value": ...}` objects, in
+///   the order of the keys in the PSBT;
+/// - input and output indexes are not serialized and are restored from the position of the input
+///   (output) in the `inputs` (`outputs`) array.
 #[derive(Clone, Eq, PartialEq, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "camelCase"))]
-// Serde deserialize is not implemented and require manual implementation instead of derive, since
-// we need to correctly initialize inputs and outputs with their indexes and account for unknown
-// fields.
+// Serde deserialize is implemented manually in `serde_utils` module, since we need to initialize
+// inputs and outputs with their indexes.
 pub struct Psbt {
     /// PSBT version
     pub version: PsbtVer,
@@ -194,6 +256,7 @@ pub struct Psbt {
     pub(crate) tx_modifiable: Option<ModifiableFlags>,
 
     /// Proprietary keys
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::entries"))]
     pub proprietary: IndexMap<PropKey, ValueData>,
 
     /// Unknown keys
@@ -244,16 +307,22 @@ impl Psbt {
         self.outputs = (0..output_count).map(Output::new).collect();
     }
 
-    pub fn to_unsigned_tx(&self) -> UnsignedTx {
+    pub fn to_unsigned_tx(&self) -> Result<UnsignedTx, LockTimeConflict> {
+        self.lock_time().map(|lock_time| self.to_unsigned_tx_with(lock_time))
+    }
+
+    pub(crate) fn to_unsigned_tx_with(&self, lock_time: LockTime) -> UnsignedTx {
         UnsignedTx {
             version: self.tx_version,
             inputs: VarIntArray::from_iter_checked(self.inputs().map(Input::to_unsigned_txin)),
             outputs: VarIntArray::from_iter_checked(self.outputs().map(Output::to_txout)),
-            lock_time: self.lock_time(),
+            lock_time,
         }
     }
 
-    pub fn txid(&self) -> Txid { self.to_unsigned_tx().txid() }
+    pub fn txid(&self) -> Result<Txid, LockTimeConflict> {
+        self.to_unsigned_tx().map(|tx| tx.txid())
+    }
 
     pub fn input(&self, index: usize) -> Option<&Input> { self.inputs.get(index) }
 
@@ -273,9 +342,43 @@ impl Psbt {
 
     pub fn outputs_mut(&mut self) -> impl Iterator<Item = &mut Output> { self.outputs.iter_mut() }
 
-    pub fn lock_time(&self) -> LockTime {
-        // TODO #45: Compute correct LockTime
-        self.fallback_locktime.unwrap_or(LockTime::ZERO)
+    /// Computes transaction lock time according to the BIP-370 algorithm.
+    ///
+    /// If none of the inputs requires a lock time, the fallback lock time (or zero) is used.
+    /// Otherwise, the lock time type is chosen such that it is supported by all inputs (inputs
+    /// requiring no lock time or specifying both types support either of them), preferring
+    /// height-based lock time when both types are possible, and the maximum of the required
+    /// values of that type is returned.
+    ///
+    /// # Errors
+    ///
+    /// If some input requires only height-based lock time and another one - only time-based.
+    pub fn lock_time(&self) -> Result<LockTime, LockTimeConflict> {
+        let mut height = None::<LockHeight>;
+        let mut time = None::<LockTimestamp>;
+        let mut height_input = None;
+        let mut time_input = None;
+        for (index, input) in self.inputs.iter().enumerate() {
+            match (input.required_height_lock, input.required_time_lock) {
+                (None, None) => continue,
+                (Some(_), None) => height_input = height_input.or(Some(index)),
+                (None, Some(_)) => time_input = time_input.or(Some(index)),
+                (Some(_), Some(_)) => {}
+            }
+            height = height.max(input.required_height_lock);
+            time = time.max(input.required_time_lock);
+        }
+
+        match (height, time, height_input, time_input) {
+            (None, None, ..) => Ok(self.fallback_locktime.unwrap_or(LockTime::ZERO)),
+            (Some(_), _, Some(height_input), Some(time_input)) => Err(LockTimeConflict {
+                height_input,
+                time_input,
+            }),
+            (Some(height), _, _, None) => Ok(height.into()),
+            (_, Some(time), None, _) => Ok(time.into()),
+            _ => unreachable!("inputs requiring lock time always specify at least one of types"),
+        }
     }
 
     #[inline]
@@ -289,6 +392,8 @@ impl Psbt {
 
     pub fn xpubs(&self) -> impl Iterator<Item = (&Xpub, &XkeyOrigin)> { self.xpubs.iter() }
 
+    pub fn tx_modifiable(&self) -> Option<&ModifiableFlags> { self.tx_modifiable.as_ref() }
+
     pub fn is_modifiable(&self) -> bool {
         self.tx_modifiable.as_ref().map(ModifiableFlags::is_modifiable).unwrap_or_default()
     }
@@ -303,7 +408,7 @@ impl Psbt {
     pub fn are_outputs_modifiable(&self) -> bool {
         self.tx_modifiable
             .as_ref()
-            .map(|flags| flags.inputs_modifiable && !flags.sighash_single)
+            .map(|flags| flags.outputs_modifiable && !flags.sighash_single)
             .unwrap_or_default()
     }
 
@@ -349,7 +454,6 @@ impl Psbt {
             redeem_script: script.to_redeem_script(),
             witness_script: script.to_witness_script(),
             bip32_derivation: descriptor.legacy_keyset(terminal),
-            // TODO #36: Fill hash preimages from descriptor
             final_script_sig: None,
             final_witness: None,
             proof_of_reserves: None,
@@ -363,6 +467,9 @@ impl Psbt {
             tap_bip32_derivation: descriptor.xonly_keyset(terminal),
             tap_internal_key: script.to_internal_pk(),
             tap_merkle_root: script.to_tap_root(),
+            musig2_participants: descriptor.musig_keyset(terminal),
+            musig2_pub_nonces: none!(),
+            musig2_partial_sigs: none!(),
             proprietary: none!(),
             unknown: none!(),
         };
@@ -449,6 +556,7 @@ impl Psbt {
             tap_internal_key: script.to_internal_pk(),
             tap_tree: script.to_tap_tree(),
             tap_bip32_derivation: descriptor.xonly_keyset(change_terminal),
+            musig2_participants: descriptor.musig_keyset(change_terminal),
             proprietary: none!(),
             unknown: none!(),
         };
@@ -482,8 +590,31 @@ impl Psbt {
         Ok(())
     }
 
+    /// Sets the sighash type which must be used for signing input `index`. `None` stands for
+    /// `SIGHASH_ALL` (or `SIGHASH_DEFAULT` for taproot inputs).
+    ///
+    /// Signers decide whether the requested type is acceptable for them, see
+    /// [`crate::ValidationParams::safe_sighash_types`].
+    pub fn set_sighash_type(
+        &mut self,
+        index: usize,
+        sighash_type: Option<SighashType>,
+    ) -> Result<(), SighashTypeError> {
+        let outputs = self.outputs.len();
+        let input = self.inputs.get_mut(index).ok_or(SighashTypeError::NoInput(index))?;
+        if input.has_signatures() {
+            return Err(SighashTypeError::Signed(index));
+        }
+        if sighash_type.is_some_and(|ty| ty.flag == SighashFlag::Single) && index >= outputs {
+            return Err(SighashTypeError::NoSingleOutput { index, outputs });
+        }
+        input.sighash_type = sighash_type;
+        Ok(())
+    }
+
+    /// Marks the PSBT as unmodifiable. The constructed PSBT may be checked for consistency with
+    /// [`Psbt::validate`].
     pub fn complete_construction(&mut self) {
-        // TODO #47: Check all inputs have witness_utxo or non_witness_tx
         self.tx_modifiable = Some(ModifiableFlags::unmodifiable())
     }
 
@@ -493,18 +624,19 @@ impl Psbt {
         self.inputs.iter_mut().map(|input| input.finalize(descriptor) as usize).sum()
     }
 
-    pub fn extract(&self) -> Result<Tx, UnfinalizedInputs> {
+    pub fn extract(&self) -> Result<Tx, ExtractError> {
         let finalized = self.inputs.iter().filter(|i| i.is_finalized()).count();
         let unfinalized = self.inputs.len() - finalized;
         if unfinalized > 0 {
-            return Err(UnfinalizedInputs(unfinalized));
+            return Err(UnfinalizedInputs(unfinalized).into());
         }
+        let lock_time = self.lock_time()?;
 
         Ok(Tx {
             version: self.tx_version,
             inputs: VarIntArray::from_iter_checked(self.inputs.iter().map(Input::to_signed_txin)),
             outputs: VarIntArray::from_iter_checked(self.outputs.iter().map(Output::to_txout)),
-            lock_time: self.lock_time(),
+            lock_time,
         })
     }
 }
@@ -607,26 +739,212 @@ mod display_from_str {
     }
 }
 
-/* TODO #43: Complete weight implementation
-impl Weight for Psbt {
-    fn weight_units(&self) -> WeightUnits {
+#[cfg(feature = "serde")]
+pub(crate) mod serde_utils {
+    use std::hash::Hash;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::*;
+
+    /// JSON allows only strings as map keys, thus maps with keys which are not strings are
+    /// represented as arrays of `{"key": ..., "value": ...}` objects.
+    pub mod entries {
+        use super::*;
+
+        #[derive(Serialize)]
+        struct EntryRef<'a, K, V> {
+            key: &'a K,
+            value: &'a V,
+        }
+
+        #[derive(Deserialize)]
+        struct Entry<K, V> {
+            key: K,
+            value: V,
+        }
+
+        pub fn serialize<K, V, S>(map: &IndexMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            K: Serialize,
+            V: Serialize,
+            S: Serializer,
+        {
+            serializer.collect_seq(map.iter().map(|(key, value)| EntryRef { key, value }))
+        }
+
+        pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<IndexMap<K, V>, D::Error>
+        where
+            K: Deserialize<'de> + Hash + Eq,
+            V: Deserialize<'de>,
+            D: Deserializer<'de>,
+        {
+            let entries = Vec::<Entry<K, V>>::deserialize(deserializer)?;
+            let mut map = IndexMap::with_capacity(entries.len());
+            for Entry { key, value } in entries {
+                if map.insert(key, value).is_some() {
+                    return Err(D::Error::custom("repeated map key"));
+                }
+            }
+            Ok(map)
+        }
+    }
+
+    /// Maps keyed by legacy public keys are represented as JSON objects with the keys hex-encoded
+    /// in their serialized form, which keeps whether the key is compressed.
+    pub mod legacy_pks {
+        use std::fmt::{self, Formatter};
+        use std::marker::PhantomData;
+        use std::str::FromStr;
+
+        use amplify::hex::ToHex;
+        use serde::de::{MapAccess, Visitor};
+
+        use super::*;
+
+        pub fn serialize<V, S>(
+            map: &IndexMap<LegacyPk, V>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            V: Serialize,
+            S: Serializer,
+        {
+            serializer.collect_map(map.iter().map(|(key, value)| (key.to_vec().to_hex(), value)))
+        }
+
+        pub fn deserialize<'de, V, D>(deserializer: D) -> Result<IndexMap<LegacyPk, V>, D::Error>
+        where
+            V: Deserialize<'de>,
+            D: Deserializer<'de>,
+        {
+            struct MapVisitor<V>(PhantomData<V>);
+
+            impl<'de, V: Deserialize<'de>> Visitor<'de> for MapVisitor<V> {
+                type Value = IndexMap<LegacyPk, V>;
+
+                fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                    f.write_str("map with hex-encoded public keys")
+                }
+
+                fn visit_map<A: MapAccess<'de>>(
+                    self,
+                    mut access: A,
+                ) -> Result<Self::Value, A::Error> {
+                    let mut map = IndexMap::with_capacity(access.size_hint().unwrap_or_default());
+                    while let Some((key, value)) = access.next_entry::<String, V>()? {
+                        let key = LegacyPk::from_str(&key).map_err(A::Error::custom)?;
+                        if map.insert(key, value).is_some() {
+                            return Err(A::Error::custom("repeated map key"));
+                        }
+                    }
+                    Ok(map)
+                }
+            }
+
+            deserializer.deserialize_map(MapVisitor(PhantomData))
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PsbtData {
+        version: PsbtVer,
+        tx_version: TxVer,
+        fallback_locktime: Option<LockTime>,
+        inputs: Vec<Input>,
+        outputs: Vec<Output>,
+        xpubs: IndexMap<Xpub, XkeyOrigin>,
+        tx_modifiable: Option<ModifiableFlags>,
+        #[serde(with = "entries")]
+        proprietary: IndexMap<PropKey, ValueData>,
+        unknown: IndexMap<u8, IndexMap<KeyData, ValueData>>,
+    }
+
+    /// Deserialization restores indexes of inputs and outputs from their position in the PSBT.
+    impl<'de> Deserialize<'de> for Psbt {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let PsbtData {
+                version,
+                tx_version,
+                fallback_locktime,
+                mut inputs,
+                mut outputs,
+                xpubs,
+                tx_modifiable,
+                proprietary,
+                unknown,
+            } = PsbtData::deserialize(deserializer)?;
+            for (index, input) in inputs.iter_mut().enumerate() {
+                input.index = index;
+            }
+            for (index, output) in outputs.iter_mut().enumerate() {
+                output.index = index;
+            }
+            Ok(Psbt {
+                version,
+                tx_version,
+                fallback_locktime,
+                inputs,
+                outputs,
+                xpubs,
+                tx_modifiable,
+                proprietary,
+                unknown,
+            })
+        }
+    }
+}
+
+impl Psbt {
+    /// Weight of the signed transaction, with the satisfaction weights of the inputs provided by
+    /// `satisfaction`. Inputs for which it returns `None` are counted with an empty script sig
+    /// and no witness.
+    pub(crate) fn weight_with(
+        &self,
+        satisfaction: impl Fn(&Input) -> Option<WeightUnits>,
+    ) -> WeightUnits {
         let bytes = 4 // version
             + VarInt::with(self.inputs.len()).len()
             + VarInt::with(self.outputs.len()).len()
             + 4; // lock time
         let mut weight = WeightUnits::no_discount(bytes)
-            + self.inputs().map(TxIn::weight_units).sum()
-            + self.outputs().map(TxOut::weight_units).sum();
-        if self.is_segwit() {
-            weight += WeightUnits::witness_discount(2); // marker and flag bytes
+            + self.inputs().map(|input| input.weight_with(satisfaction(input))).sum()
+            + self.outputs().map(|output| output.to_txout().weight_units()).sum();
+        let segwit = self.inputs().filter(|input| input.has_witness()).count();
+        if segwit > 0 {
+            // Marker and flag bytes, and empty witness stacks of non-segwit inputs
+            weight += WeightUnits::witness_discount(2 + self.inputs.len() - segwit);
         }
         weight
     }
+
+    /// Estimates weight of the signed transaction, all inputs of which spend outputs of the
+    /// `descriptor`. For non-finalized inputs the largest satisfaction weight among the descriptor
+    /// spending paths is used, so the value is an upper bound.
+    pub fn estimate_weight<K, V, D: Descriptor<K, V>>(&self, descriptor: &D) -> WeightUnits {
+        let max_satisfaction = descriptor.max_satisfaction_weight();
+        self.weight_with(|input| input.final_satisfaction_weight().or(max_satisfaction))
+    }
+}
+
+impl Weight for Psbt {
+    /// Weight of the transaction with the satisfactions known from the PSBT. Non-finalized inputs
+    /// are counted as unsigned, so for them the value is a lower bound; use
+    /// [`Psbt::estimate_weight`] to account for their satisfactions.
+    fn weight_units(&self) -> WeightUnits { self.weight_with(Input::final_satisfaction_weight) }
+}
+
+impl Weight for Input {
+    /// Weight of the transaction input, not accounting for the segwit marker and flag bytes.
+    /// Non-finalized inputs are counted as unsigned; use [`Input::estimate_weight`] to account
+    /// for their satisfaction.
+    fn weight_units(&self) -> WeightUnits { self.weight_with(self.final_satisfaction_weight()) }
 }
- */
 
 #[derive(Clone, Eq, PartialEq, Debug)]
-#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
 pub struct Input {
     /// The index of this input. Used in error reporting.
     #[cfg_attr(feature = "serde", serde(skip))]
@@ -661,6 +979,7 @@ pub struct Input {
     /// A map from public keys to their corresponding signature as would be
     /// pushed to the stack from a scriptSig or witness for a non-taproot
     /// inputs.
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::legacy_pks"))]
     pub partial_sigs: IndexMap<LegacyPk, LegacySig>,
 
     /// The sighash type to be used for this input. Signatures for this input
@@ -675,6 +994,7 @@ pub struct Input {
 
     /// A map from public keys needed to sign this input to their corresponding master key
     /// fingerprints and derivation paths.
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::legacy_pks"))]
     pub bip32_derivation: IndexMap<LegacyPk, KeyOrigin>,
 
     /// The finalized, fully constructed scriptSig with signatures and any other scripts necessary
@@ -711,12 +1031,14 @@ pub struct Input {
 
     /// The 64 or 65-byte Schnorr signature for this pubkey and leaf combination. Finalizers
     /// should remove this field after `PSBT_IN_FINAL_SCRIPTWITNESS` is constructed.
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::entries"))]
     pub tap_script_sig: IndexMap<(XOnlyPk, TapLeafHash), Bip340Sig>,
 
     /// The script for this leaf as would be provided in the witness stack followed by the single
     /// byte leaf version. Note that the leaves included in this field should be those that the
     /// signers of this input are expected to be able to sign for. Finalizers should remove this
     /// field after `PSBT_IN_FINAL_SCRIPTWITNESS` is constructed.
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::entries"))]
     pub tap_leaf_script: IndexMap<ControlBlock, LeafScript>,
 
     /// A compact size unsigned integer representing the number of leaf hashes, followed by a list
@@ -736,7 +1058,26 @@ pub struct Input {
     /// `PSBT_IN_FINAL_SCRIPTWITNESS` is constructed.
     pub tap_merkle_root: Option<TapNodeHash>,
 
+    /// A map from MuSig2 aggregate public keys to the participant public keys they aggregate, in
+    /// the order of the aggregation (BIP-373). Finalizers should remove this field after
+    /// `PSBT_IN_FINAL_SCRIPTWITNESS` is constructed.
+    pub musig2_participants: IndexMap<CompressedPk, Vec<CompressedPk>>,
+
+    /// MuSig2 public nonces keyed by the participant public key, the aggregate public key and an
+    /// optional hash of the leaf the signature is produced for. The key path signatures have no
+    /// leaf hash. Finalizers should remove this field after `PSBT_IN_FINAL_SCRIPTWITNESS` is
+    /// constructed.
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::entries"))]
+    pub musig2_pub_nonces: IndexMap<(CompressedPk, CompressedPk, Option<TapLeafHash>), PubNonce>,
+
+    /// MuSig2 partial signatures keyed the same way as [`Input::musig2_pub_nonces`]. Finalizers
+    /// should remove this field after `PSBT_IN_FINAL_SCRIPTWITNESS` is constructed.
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::entries"))]
+    pub musig2_partial_sigs:
+        IndexMap<(CompressedPk, CompressedPk, Option<TapLeafHash>), PartialSig>,
+
     /// Proprietary keys
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::entries"))]
     pub proprietary: IndexMap<PropKey, ValueData>,
 
     /// Unknown keys
@@ -771,6 +1112,9 @@ impl Input {
             tap_bip32_derivation: none!(),
             tap_internal_key: None,
             tap_merkle_root: None,
+            musig2_participants: none!(),
+            musig2_pub_nonces: none!(),
+            musig2_partial_sigs: none!(),
             proprietary: none!(),
             unknown: none!(),
         }
@@ -806,17 +1150,29 @@ impl Input {
         }
     }
 
+    /// Returns the output spent by the input, if the input provides it via either witness UTXO or
+    /// non-witness transaction.
     #[inline]
-    pub fn prev_txout(&self) -> &TxOut {
+    pub fn try_prev_txout(&self) -> Option<&TxOut> {
         match (&self.witness_utxo, &self.non_witness_tx) {
-            (Some(txout), _) => txout,
-            (None, Some(tx)) => &tx.outputs[self.previous_outpoint.vout.into_usize()],
-            (None, None) => unreachable!(
-                "PSBT input must contain either witness UTXO or a non-witness transaction"
-            ),
+            (Some(txout), _) => Some(txout),
+            (None, Some(tx)) => tx.outputs.get(self.previous_outpoint.vout.into_usize()),
+            (None, None) => None,
         }
     }
 
+    /// # Panics
+    ///
+    /// If the input doesn't provide data on the spent output. Use [`Psbt::validate`] to check this
+    /// in advance.
+    #[inline]
+    pub fn prev_txout(&self) -> &TxOut {
+        self.try_prev_txout().expect(
+            "PSBT input must contain either witness UTXO or a non-witness transaction with the \
+             spent output",
+        )
+    }
+
     #[inline]
     pub fn prevout(&self) -> Prevout {
         Prevout {
@@ -847,7 +1203,7 @@ impl Input {
                 ScriptCode::with_p2wsh(witness_script)
             }
             (_, Some(redeem_script)) if redeem_script.is_p2sh_wpkh() => {
-                ScriptCode::with_p2sh_wpkh(spk)
+                ScriptCode::with_p2sh_wpkh(&ScriptPubkey::from_checked(redeem_script.to_vec()))
             }
             (Some(witness_script), Some(redeem_script)) if redeem_script.is_p2sh_wsh() => {
                 ScriptCode::with_p2sh_wsh(witness_script)
@@ -856,10 +1212,66 @@ impl Input {
         })
     }
 
+    fn prev_script_pubkey(&self) -> Option<&ScriptPubkey> {
+        match (&self.witness_utxo, &self.non_witness_tx) {
+            (Some(txout), _) => Some(&txout.script_pubkey),
+            (None, Some(tx)) => tx
+                .outputs
+                .get(self.previous_outpoint.vout.into_usize())
+                .map(|txout| &txout.script_pubkey),
+            (None, None) => None,
+        }
+    }
+
+    /// Exact weight of the script sig, including its length prefix, and of the witness of a
+    /// finalized input. Returns `None` if the input is not finalized.
+    pub fn final_satisfaction_weight(&self) -> Option<WeightUnits> {
+        if !self.is_finalized() {
+            return None;
+        }
+        let sig_script = self.final_script_sig.as_ref().map(SigScript::weight_units);
+        let witness = self.final_witness.as_ref().map(Witness::weight_units);
+        Some(
+            sig_script.unwrap_or(WeightUnits::no_discount(1))
+                + witness.unwrap_or(WeightUnits::witness_discount(0)),
+        )
+    }
+
+    /// Estimates weight of the input once it is signed, using the largest satisfaction weight
+    /// among the spending paths of the `descriptor` if the input is not finalized. Does not
+    /// account for the segwit marker and flag bytes.
+    pub fn estimate_weight<K, V, D: Descriptor<K, V>>(&self, descriptor: &D) -> WeightUnits {
+        self.weight_with(self.final_satisfaction_weight().or(descriptor.max_satisfaction_weight()))
+    }
+
+    fn weight_with(&self, satisfaction: Option<WeightUnits>) -> WeightUnits {
+        WeightUnits::no_discount(
+            32 // txid
+            + 4 // vout
+            + 4, // nseq
+        ) + satisfaction.unwrap_or(WeightUnits::no_discount(1))
+    }
+
+    /// Detects whether the input is spent with a witness, which is known from its final witness
+    /// or the kind of the spent output.
+    fn has_witness(&self) -> bool {
+        if self.is_finalized() {
+            return self.final_witness.is_some();
+        }
+        self.is_bip340()
+            || self.witness_utxo.is_some()
+            || self.witness_script.is_some()
+            || self.redeem_script.as_ref().is_some_and(RedeemScript::is_p2sh_wpkh)
+            || self
+                .prev_script_pubkey()
+                .is_some_and(|spk| spk.is_p2wpkh() || spk.is_p2wsh() || spk.is_p2tr())
+    }
+
     #[inline]
     pub fn is_segwit_v0(&self) -> bool {
         self.witness_script.is_some()
             || self.witness_utxo.is_some()
+            || self.redeem_script.as_ref().is_some_and(RedeemScript::is_p2sh_wpkh)
             || self.prev_txout().script_pubkey.is_p2wpkh()
             || self.prev_txout().script_pubkey.is_p2wsh()
     }
@@ -872,26 +1284,97 @@ impl Input {
         self.final_witness.is_some() || self.final_script_sig.is_some()
     }
 
+    /// Detects whether the input is finalized or contains any partial signatures.
+    #[must_use]
+    pub fn has_signatures(&self) -> bool {
+        self.is_finalized()
+            || !self.partial_sigs.is_empty()
+            || self.tap_key_sig.is_some()
+            || !self.tap_script_sig.is_empty()
+    }
+
+    /// Adds a preimage for a hash lock used by the input scripts, which will be used by the
+    /// finalizer to satisfy the lock. Preimages are kept in the PSBT fields matching the hash type.
+    pub fn add_preimage(
+        &mut self,
+        lock: HashLock,
+        preimage: impl Into<Vec<u8>>,
+    ) -> Result<(), PreimageMismatch> {
+        let preimage = preimage.into();
+        if !lock.verify(&preimage) {
+            return Err(PreimageMismatch(lock));
+        }
+        let preimage = ByteStr::from(preimage);
+        match lock {
+            HashLock::Sha256(hash) => self.sha256.insert(hash, preimage),
+            HashLock::Hash256(hash) => self.hash256.insert(hash, preimage),
+            HashLock::Ripemd160(hash) => self.ripemd160.insert(hash, preimage),
+            HashLock::Hash160(hash) => self.hash160.insert(hash, preimage),
+        };
+        Ok(())
+    }
+
+    /// Sets the sequence number and the required lock time of the input to satisfy the timelocks
+    /// of a descriptor spending path. The sequence number is kept if the path has no relative
+    /// timelock, unless it disables the lock time check required by an absolute timelock.
+    pub fn set_timelocks(&mut self, timelocks: Timelocks) {
+        if let Some(seq_no) = timelocks.older {
+            self.sequence_number = Some(seq_no);
+        }
+        let Some(lock_time) = timelocks.after else {
+            return;
+        };
+        if self.sequence_number == Some(SeqNo::from_consensus_u32(u32::MAX)) {
+            self.sequence_number = Some(SeqNo::from_consensus_u32(u32::MAX - 1));
+        }
+        match LockHeight::try_from(lock_time) {
+            Ok(height) => self.required_height_lock = Some(height),
+            Err(_) => self.required_time_lock = LockTimestamp::try_from(lock_time).ok(),
+        }
+    }
+
+    /// Lists hash locks for which the input provides preimages, together with the preimages.
+    pub fn preimages(&self) -> IndexMap<HashLock, Vec<u8>> {
+        let sha256 = self.sha256.iter().map(|(hash, pre)| (HashLock::Sha256(*hash), pre));
+        let hash256 = self.hash256.iter().map(|(hash, pre)| (HashLock::Hash256(*hash), pre));
+        let ripemd160 = self.ripemd160.iter().map(|(hash, pre)| (HashLock::Ripemd160(*hash), pre));
+        let hash160 = self.hash160.iter().map(|(hash, pre)| (HashLock::Hash160(*hash), pre));
+        sha256
+            .chain(hash256)
+            .chain(ripemd160)
+            .chain(hash160)
+            .map(|(lock, preimage)| (lock, preimage.as_slice().to_vec()))
+            .collect()
+    }
+
     pub fn finalize<D: Descriptor<K, V>, K, V>(&mut self, descriptor: &D) -> bool {
         if self.is_finalized() {
             return false;
         }
 
+        let preimages = self.preimages();
+        // Internal keys produced by MuSig2 aggregation have no derivation
+        let no_origin = musig_agg_origin();
         let satisfaction = if descriptor.is_taproot() {
             self.tap_internal_key
                 .map(XOnlyPk::from)
-                .and_then(|pk| self.tap_bip32_derivation.get(&pk).map(|d| (&d.origin, pk)))
+                .map(|pk| match self.tap_bip32_derivation.get(&pk) {
+                    Some(derivation) => (&derivation.origin, pk),
+                    None => (&no_origin, pk),
+                })
                 .zip(self.tap_key_sig)
                 .and_then(|((origin, pk), sig)| {
                     // First, we try a key path
-                    descriptor
-                        .taproot_witness(None, indexmap! { origin => TaprootKeySig::new(pk, sig) })
+                    descriptor.taproot_witness(
+                        None,
+                        indexmap! { origin => TaprootKeySig::new(pk, sig) },
+                        &preimages,
+                    )
                 })
                 .or_else(|| {
                     // If we can't satisfy a key path, we try script paths until we succeed
-                    self.tap_leaf_script.keys().find_map(|cb| {
-                        let hash = cb.merkle_branch.first()?;
-                        let leafhash = TapLeafHash::from_byte_array(hash.to_byte_array());
+                    self.tap_leaf_script.iter().find_map(|(cb, leaf_script)| {
+                        let leafhash = leaf_script.tap_leaf_hash();
                         let keysigs = self
                             .tap_script_sig
                             .iter()
@@ -904,7 +1387,7 @@ impl Input {
                                     .map(|d| (&d.origin, ks))
                             })
                             .collect();
-                        descriptor.taproot_witness(Some(cb), keysigs)
+                        descriptor.taproot_witness(Some(cb), keysigs, &preimages)
                     })
                 })
                 .map(|witness| (empty!(), Some(witness)))
@@ -916,11 +1399,15 @@ impl Input {
                     self.partial_sigs.get(pk).map(|sig| (origin, LegacyKeySig::new(*pk, *sig)))
                 })
                 .collect();
-            descriptor.legacy_witness(
-                keysigs,
-                self.redeem_script.clone(),
-                self.witness_script.clone(),
-            )
+            self.try_prev_txout().and_then(|prevout| {
+                descriptor.legacy_witness(
+                    &prevout.script_pubkey,
+                    keysigs,
+                    &preimages,
+                    self.redeem_script.clone(),
+                    self.witness_script.clone(),
+                )
+            })
         };
         let Some((sig_script, witness)) = satisfaction else {
             return false;
@@ -947,13 +1434,16 @@ impl Input {
         self.tap_bip32_derivation.clear(); // 0x16
         self.tap_internal_key = None; // x017
         self.tap_merkle_root = None; // 0x018
+        self.musig2_participants.clear(); // 0x1a
+        self.musig2_pub_nonces.clear(); // 0x1b
+        self.musig2_partial_sigs.clear(); // 0x1c
 
         true
     }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
-#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
 pub struct Output {
     /// The index of this output. Used in error reporting.
     #[cfg_attr(feature = "serde", serde(skip))]
@@ -973,6 +1463,7 @@ pub struct Output {
 
     /// A map from public keys needed to spend this output to their corresponding master key
     /// fingerprints and derivation paths.
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::legacy_pks"))]
     pub bip32_derivation: IndexMap<LegacyPk, KeyOrigin>,
 
     /// The X-only pubkey used as the internal key in this output.
@@ -996,7 +1487,12 @@ pub struct Output {
     /// Finalizers should remove this field after `PSBT_IN_FINAL_SCRIPTWITNESS` is constructed.
     pub tap_bip32_derivation: IndexMap<XOnlyPk, TapDerivation>,
 
+    /// A map from MuSig2 aggregate public keys to the participant public keys they aggregate, in
+    /// the order of the aggregation (BIP-373).
+    pub musig2_participants: IndexMap<CompressedPk, Vec<CompressedPk>>,
+
     /// Proprietary keys
+    #[cfg_attr(feature = "serde", serde(with = "serde_utils::entries"))]
     pub proprietary: IndexMap<PropKey, ValueData>,
 
     /// Unknown keys
@@ -1015,6 +1511,7 @@ impl Output {
             tap_internal_key: None,
             tap_tree: None,
             tap_bip32_derivation: none!(),
+            musig2_participants: none!(),
             proprietary: none!(),
             unknown: none!(),
         }
@@ -1125,12 +1622,31 @@ impl ModifiableFlags {
     pub const fn is_modifiable(&self) -> bool {
         self.inputs_modifiable | self.outputs_modifiable | self.sighash_single
     }
+
+    /// Updates the flags once a signature with the given sighash type is added to the PSBT, as
+    /// required from signers by BIP-370.
+    pub fn register_signature(&mut self, sighash_type: SighashType) {
+        self.inputs_modifiable &= sighash_type.anyone_can_pay;
+        self.outputs_modifiable &= sighash_type.flag == SighashFlag::None;
+        self.sighash_single |= sighash_type.flag == SighashFlag::Single;
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn modifiable_flags() {
+        let mut flags = ModifiableFlags::modifiable();
+        flags.register_signature(SighashType::none_anyone_can_pay());
+        assert_eq!(flags, ModifiableFlags::modifiable());
+        flags.register_signature(SighashType::single_anyone_can_pay());
+        assert_eq!(flags.to_standard_u8(), 0b101);
+        flags.register_signature(SighashType::all());
+        assert_eq!(flags.to_standard_u8(), 0b100);
+    }
+
     #[test]
     fn psbt_formats() {
         let v0_psbt = Psbt {
@@ -227,6 +227,9 @@ const PSBT_IN_TAP_LEAF_SCRIPT: u8 = 0x15;
 const PSBT_IN_TAP_BIP32_DERIVATION: u8 = 0x16;
 const PSBT_IN_TAP_INTERNAL_KEY: u8 = 0x17;
 const PSBT_IN_TAP_MERKLE_ROOT: u8 = 0x18;
+const PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x1a;
+const PSBT_IN_MUSIG2_PUB_NONCE: u8 = 0x1b;
+const PSBT_IN_MUSIG2_PARTIAL_SIG: u8 = 0x1c;
 const PSBT_IN_PROPRIETARY: u8 = 0xFC;
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
@@ -307,6 +310,15 @@ pub enum InputKey {
     /// `PSBT_IN_TAP_MERKLE_ROOT`
     TapMerkleRoot,
 
+    /// `PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS`
+    Musig2ParticipantPubkeys,
+
+    /// `PSBT_IN_MUSIG2_PUB_NONCE`
+    Musig2PubNonce,
+
+    /// `PSBT_IN_MUSIG2_PARTIAL_SIG`
+    Musig2PartialSig,
+
     /// `PSBT_IN_PROPRIETARY`
     Proprietary,
 
@@ -341,6 +353,9 @@ impl KeyType for InputKey {
         Self::TapBip32Derivation,
         Self::TapInternalKey,
         Self::TapMerkleRoot,
+        Self::Musig2ParticipantPubkeys,
+        Self::Musig2PubNonce,
+        Self::Musig2PartialSig,
     ];
 
     #[inline]
@@ -373,6 +388,9 @@ impl KeyType for InputKey {
             x if x == Self::TapBip32Derivation.into_u8() => Self::TapBip32Derivation,
             x if x == Self::TapInternalKey.into_u8() => Self::TapInternalKey,
             x if x == Self::TapMerkleRoot.into_u8() => Self::TapMerkleRoot,
+            x if x == Self::Musig2ParticipantPubkeys.into_u8() => Self::Musig2ParticipantPubkeys,
+            x if x == Self::Musig2PubNonce.into_u8() => Self::Musig2PubNonce,
+            x if x == Self::Musig2PartialSig.into_u8() => Self::Musig2PartialSig,
             x if x == Self::Proprietary.into_u8() => Self::Proprietary,
             unknown => Self::Unknown(unknown),
         }
@@ -405,6 +423,9 @@ impl KeyType for InputKey {
             InputKey::TapBip32Derivation => PSBT_IN_TAP_BIP32_DERIVATION,
             InputKey::TapInternalKey => PSBT_IN_TAP_INTERNAL_KEY,
             InputKey::TapMerkleRoot => PSBT_IN_TAP_MERKLE_ROOT,
+            InputKey::Musig2ParticipantPubkeys => PSBT_IN_MUSIG2_PARTICIPANT_PUBKEYS,
+            InputKey::Musig2PubNonce => PSBT_IN_MUSIG2_PUB_NONCE,
+            InputKey::Musig2PartialSig => PSBT_IN_MUSIG2_PARTIAL_SIG,
             InputKey::Proprietary => PSBT_IN_PROPRIETARY,
             InputKey::Unknown(key_type) => key_type,
         }
@@ -441,6 +462,10 @@ impl KeyType for InputKey {
             InputKey::TapInternalKey => false,
             InputKey::TapMerkleRoot => false,
 
+            InputKey::Musig2ParticipantPubkeys => true,
+            InputKey::Musig2PubNonce => true,
+            InputKey::Musig2PartialSig => true,
+
             InputKey::Proprietary => true,
             InputKey::Unknown(_) => true,
         }
@@ -477,6 +502,10 @@ impl KeyType for InputKey {
             | InputKey::TapInternalKey
             | InputKey::TapMerkleRoot => PsbtVer::V0,
 
+            InputKey::Musig2ParticipantPubkeys
+            | InputKey::Musig2PubNonce
+            | InputKey::Musig2PartialSig => PsbtVer::V0,
+
             InputKey::Proprietary => PsbtVer::V0,
             InputKey::Unknown(_) => PsbtVer::V0,
         }
@@ -509,6 +538,9 @@ impl KeyType for InputKey {
             | InputKey::TapBip32Derivation
             | InputKey::TapInternalKey
             | InputKey::TapMerkleRoot
+            | InputKey::Musig2ParticipantPubkeys
+            | InputKey::Musig2PubNonce
+            | InputKey::Musig2PartialSig
             | InputKey::Proprietary
             | InputKey::Unknown(_) => None,
         }
@@ -542,6 +574,10 @@ impl KeyType for InputKey {
             | InputKey::TapInternalKey
             | InputKey::TapMerkleRoot => false,
 
+            InputKey::Musig2ParticipantPubkeys
+            | InputKey::Musig2PubNonce
+            | InputKey::Musig2PartialSig => false,
+
             InputKey::Proprietary => false,
             InputKey::Unknown(_) => false,
         }
@@ -558,6 +594,7 @@ const PSBT_OUT_SCRIPT: u8 = 0x04;
 const PSBT_OUT_TAP_INTERNAL_KEY: u8 = 0x05;
 const PSBT_OUT_TAP_TREE: u8 = 0x06;
 const PSBT_OUT_TAP_BIP32_DERIVATION: u8 = 0x07;
+const PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS: u8 = 0x08;
 const PSBT_OUT_PROPRIETARY: u8 = 0xFC;
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
@@ -587,6 +624,9 @@ pub enum OutputKey {
     /// `PSBT_OUT_TAP_BIP32_DERIVATION`
     TapBip32Derivation,
 
+    /// `PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS`
+    Musig2ParticipantPubkeys,
+
     /// `PSBT_OUT_PROPRIETARY`
     Proprietary,
 
@@ -604,6 +644,7 @@ impl KeyType for OutputKey {
         Self::TapInternalKey,
         Self::TapTree,
         Self::TapBip32Derivation,
+        Self::Musig2ParticipantPubkeys,
     ];
 
     #[inline]
@@ -620,6 +661,7 @@ impl KeyType for OutputKey {
             x if x == Self::TapInternalKey.into_u8() => Self::TapInternalKey,
             x if x == Self::TapTree.into_u8() => Self::TapTree,
             x if x == Self::TapBip32Derivation.into_u8() => Self::TapBip32Derivation,
+            x if x == Self::Musig2ParticipantPubkeys.into_u8() => Self::Musig2ParticipantPubkeys,
 
             x if x == Self::Proprietary.into_u8() => Self::Proprietary,
             unknown => Self::Unknown(unknown),
@@ -636,6 +678,7 @@ impl KeyType for OutputKey {
             OutputKey::TapInternalKey => PSBT_OUT_TAP_INTERNAL_KEY,
             OutputKey::TapTree => PSBT_OUT_TAP_TREE,
             OutputKey::TapBip32Derivation => PSBT_OUT_TAP_BIP32_DERIVATION,
+            OutputKey::Musig2ParticipantPubkeys => PSBT_OUT_MUSIG2_PARTICIPANT_PUBKEYS,
             OutputKey::Proprietary => PSBT_OUT_PROPRIETARY,
             OutputKey::Unknown(key_type) => key_type,
         }
@@ -649,6 +692,7 @@ impl KeyType for OutputKey {
             OutputKey::TapInternalKey => false,
             OutputKey::TapTree => false,
             OutputKey::TapBip32Derivation => true,
+            OutputKey::Musig2ParticipantPubkeys => true,
             OutputKey::Proprietary => true,
             OutputKey::Unknown(_) => true,
         }
@@ -664,6 +708,7 @@ impl KeyType for OutputKey {
             OutputKey::TapInternalKey | OutputKey::TapTree | OutputKey::TapBip32Derivation => {
                 PsbtVer::V0
             }
+            OutputKey::Musig2ParticipantPubkeys => PsbtVer::V0,
 
             OutputKey::Proprietary => PsbtVer::V0,
             OutputKey::Unknown(_) => PsbtVer::V0,
@@ -679,7 +724,8 @@ impl KeyType for OutputKey {
             | OutputKey::Script
             | OutputKey::TapInternalKey
             | OutputKey::TapTree
-            | OutputKey::TapBip32Derivation => None,
+            | OutputKey::TapBip32Derivation
+            | OutputKey::Musig2ParticipantPubkeys => None,
 
             OutputKey::Proprietary => None,
             OutputKey::Unknown(_) => None,
@@ -693,6 +739,7 @@ impl KeyType for OutputKey {
             }
             OutputKey::Amount | OutputKey::Script => true,
             OutputKey::TapInternalKey | OutputKey::TapTree | OutputKey::TapBip32Derivation => false,
+            OutputKey::Musig2ParticipantPubkeys => false,
             OutputKey::Proprietary => false,
             OutputKey::Unknown(_) => false,
         }
@@ -730,7 +777,7 @@ impl<'a, T: KeyType> KeyPair<T, Box<dyn Encode + 'a>, Box<dyn Encode + 'a>> {
 }
 
 #[derive(Clone, PartialOrd, Ord, Eq, PartialEq, Hash, Debug, Display)]
-#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
 #[display("{identifier} {subtype:#x} {data:#x}")]
 pub struct PropKey {
     pub identifier: String,
@@ -40,22 +40,40 @@ mod coders;
 #[cfg(feature = "client-side-validation")]
 mod csval;
 pub mod constructor;
+mod coinselect;
+mod analyze;
+mod combine;
+mod validate;
 mod sign;
+mod verify;
+mod musig;
 
+pub use analyze::{InputReport, InputStatus, MissingSig, OutputReport, PsbtReport};
 pub use coders::{Decode, DecodeError, Encode, PsbtError};
+#[cfg(feature = "rand")]
+pub use coinselect::SingleRandomDraw;
+pub use coinselect::{
+    BranchAndBound, Candidate, CoinSelection, LargestFirst, OldestFirst, Selection, SelectionError,
+    SelectionTarget, BNB_MAX_TRIES,
+};
+pub use combine::CombineError;
 pub use constructor::{
-    Beneficiary, BeneficiaryParseError, ChangeInfo, ConstructionError, Payment, PsbtConstructor,
-    PsbtMeta, TxParams, Utxo,
+    Beneficiary, BeneficiaryParseError, ChangeInfo, ConstructionError, Fee, FeeRate,
+    FeeRateParseError, Payment, PsbtConstructor, PsbtMeta, TxParams, Utxo,
 };
 #[cfg(feature = "client-side-validation")]
 pub use csval::*;
 pub use data::{
-    Input, ModifiableFlags, Output, Prevout, Psbt, PsbtParseError, UnfinalizedInputs, Unmodifiable,
-    UnsignedTx, UnsignedTxIn,
+    ExtractError, Input, LockTimeConflict, ModifiableFlags, Output, PreimageMismatch, Prevout,
+    Psbt, PsbtParseError, SighashTypeError, UnfinalizedInputs, Unmodifiable, UnsignedTx,
+    UnsignedTxIn,
 };
 pub use keys::{GlobalKey, InputKey, KeyPair, KeyType, OutputKey, PropKey};
 pub use maps::{KeyAlreadyPresent, KeyData, KeyMap, Map, MapName, ValueData};
+pub use musig::MusigSignError;
 pub use sign::{Rejected, SignError, Signer};
+pub use validate::{Severity, ValidationIssue, ValidationParams, ValidationReport};
+pub use verify::{InvalidSig, VerifyError};
 
 #[cfg(feature = "strict_encoding")]
 pub const LIB_NAME_PSBT: &str = "Psbt";
@@ -65,7 +83,7 @@ pub const LIB_NAME_PSBT: &str = "Psbt";
 pub struct PsbtUnsupportedVer(u32);
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
-#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "camelCase"))]
 pub enum PsbtVer {
     #[display("v0")]
     V0 = 0,
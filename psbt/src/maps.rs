@@ -27,10 +27,10 @@ use std::io::{Read, Write};
 
 use amplify::{Bytes20, Bytes32, IoError};
 use derive::{
-    Bip340Sig, ByteStr, ControlBlock, InternalPk, KeyOrigin, LeafScript, LegacyPk, LegacySig,
-    LockHeight, LockTime, LockTimestamp, RedeemScript, Sats, ScriptPubkey, SeqNo, SigScript,
-    SighashType, TapDerivation, TapLeafHash, TapNodeHash, TapTree, Tx, TxOut, TxVer, Txid, VarInt,
-    Vout, Witness, WitnessScript, XOnlyPk, XkeyOrigin, Xpub,
+    Bip340Sig, ByteStr, CompressedPk, ControlBlock, InternalPk, KeyOrigin, LeafScript, LegacyPk,
+    LegacySig, LockHeight, LockTime, LockTimestamp, PartialSig, PubNonce, RedeemScript, Sats,
+    ScriptPubkey, SeqNo, SigScript, SighashType, TapDerivation, TapLeafHash, TapNodeHash, TapTree,
+    Tx, TxOut, TxVer, Txid, VarInt, Vout, Witness, WitnessScript, XOnlyPk, XkeyOrigin, Xpub,
 };
 use indexmap::IndexMap;
 
@@ -331,7 +331,11 @@ impl KeyMap for Psbt {
         key_type: Self::Keys,
     ) -> Vec<KeyPair<Self::Keys, Box<dyn Encode + 'enc>, Box<dyn Encode + 'enc>>> {
         let mut pairs = match key_type {
-            GlobalKey::UnsignedTx => once!(self.to_unsigned_tx()),
+            // PSBTv0 can't keep per-input lock time requirements, so if they conflict we fall back
+            // to the global lock time.
+            GlobalKey::UnsignedTx => once!(self.to_unsigned_tx().unwrap_or_else(|_| {
+                self.to_unsigned_tx_with(self.fallback_locktime.unwrap_or(LockTime::ZERO))
+            })),
             GlobalKey::Xpub => iter!(self.xpubs),
             GlobalKey::TxVersion => once!(self.tx_version),
             GlobalKey::FallbackLocktime => option!(self.fallback_locktime),
@@ -447,6 +451,9 @@ impl KeyMap for Input {
             }
             InputKey::TapInternalKey => option!(self.tap_internal_key),
             InputKey::TapMerkleRoot => option!(self.tap_merkle_root),
+            InputKey::Musig2ParticipantPubkeys => iter!(self.musig2_participants),
+            InputKey::Musig2PubNonce => iter!(self.musig2_pub_nonces),
+            InputKey::Musig2PartialSig => iter!(self.musig2_partial_sigs),
 
             InputKey::Proprietary | InputKey::Unknown(_) => unreachable!(),
         };
@@ -507,7 +514,10 @@ impl KeyMap for Input {
             | InputKey::Hash256
             | InputKey::TapScriptSig
             | InputKey::TapLeafScript
-            | InputKey::TapBip32Derivation => unreachable!(),
+            | InputKey::TapBip32Derivation
+            | InputKey::Musig2ParticipantPubkeys
+            | InputKey::Musig2PubNonce
+            | InputKey::Musig2PartialSig => unreachable!(),
 
             InputKey::Proprietary | InputKey::Unknown(_) => unreachable!(),
         }
@@ -580,6 +590,23 @@ impl KeyMap for Input {
                 let derivation = TapDerivation::deserialize(value_data)?;
                 self.tap_bip32_derivation.insert(pk, derivation);
             }
+            InputKey::Musig2ParticipantPubkeys => {
+                let agg_pk = CompressedPk::deserialize(key_data)?;
+                let participants = Vec::<CompressedPk>::deserialize(value_data)?;
+                self.musig2_participants.insert(agg_pk, participants);
+            }
+            InputKey::Musig2PubNonce => {
+                let key =
+                    <(CompressedPk, CompressedPk, Option<TapLeafHash>)>::deserialize(key_data)?;
+                let nonce = PubNonce::deserialize(value_data)?;
+                self.musig2_pub_nonces.insert(key, nonce);
+            }
+            InputKey::Musig2PartialSig => {
+                let key =
+                    <(CompressedPk, CompressedPk, Option<TapLeafHash>)>::deserialize(key_data)?;
+                let sig = PartialSig::deserialize(value_data)?;
+                self.musig2_partial_sigs.insert(key, sig);
+            }
 
             InputKey::Proprietary | InputKey::Unknown(_) => unreachable!(),
         }
@@ -617,6 +644,7 @@ impl KeyMap for Output {
             OutputKey::TapBip32Derivation => {
                 iter!(self.tap_bip32_derivation)
             }
+            OutputKey::Musig2ParticipantPubkeys => iter!(self.musig2_participants),
 
             OutputKey::Proprietary | OutputKey::Unknown(_) => unreachable!(),
         };
@@ -643,7 +671,9 @@ impl KeyMap for Output {
             }
             OutputKey::TapTree => self.tap_tree = Some(TapTree::deserialize(value_data)?),
 
-            OutputKey::Bip32Derivation | OutputKey::TapBip32Derivation => unreachable!(),
+            OutputKey::Bip32Derivation
+            | OutputKey::TapBip32Derivation
+            | OutputKey::Musig2ParticipantPubkeys => unreachable!(),
 
             OutputKey::Proprietary | OutputKey::Unknown(_) => unreachable!(),
         }
@@ -674,6 +704,11 @@ impl KeyMap for Output {
                 let derivation = TapDerivation::deserialize(value_data)?;
                 self.tap_bip32_derivation.insert(pk, derivation);
             }
+            OutputKey::Musig2ParticipantPubkeys => {
+                let agg_pk = CompressedPk::deserialize(key_data)?;
+                let participants = Vec::<CompressedPk>::deserialize(value_data)?;
+                self.musig2_participants.insert(agg_pk, participants);
+            }
 
             OutputKey::Proprietary | OutputKey::Unknown(_) => unreachable!(),
         }
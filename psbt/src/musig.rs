@@ -0,0 +1,180 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! MuSig2 (BIP-327) signing of taproot key path spendings using PSBT fields from BIP-373.
+
+use amplify::ByteArray;
+use derive::secp256k1::SecretKey;
+use derive::{
+    Bip340Sig, CompressedPk, KeyAggContext, MusigError, PartialSig, PubNonce, SecNonce, Session,
+    SighashError, XOnlyPk,
+};
+
+use crate::{Input, LockTimeConflict, Psbt, ValidationIssue};
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum MusigSignError {
+    /// the PSBT has no input #{0}.
+    NoInput(usize),
+
+    /// input #{0} internal key is not a MuSig2 aggregated key with known participants.
+    NoMusigKey(usize),
+
+    /// participant {1} hasn't provided a public nonce for input #{0}.
+    NoPubNonce(usize, CompressedPk),
+
+    /// participant {1} hasn't provided a partial signature for input #{0}.
+    NoPartialSig(usize, CompressedPk),
+
+    /// partial signature of participant {1} for input #{0} is invalid.
+    InvalidPartialSig(usize, CompressedPk),
+
+    #[from]
+    #[display(inner)]
+    Musig(MusigError),
+
+    #[from]
+    #[display(inner)]
+    Sighash(SighashError),
+
+    #[from]
+    #[display(inner)]
+    LockTime(LockTimeConflict),
+
+    #[from]
+    #[display(inner)]
+    Invalid(ValidationIssue),
+}
+
+impl Input {
+    /// Returns MuSig2 aggregated key which is used as the taproot internal key, together with the
+    /// key aggregation context tweaked for the key path spending.
+    pub fn musig_key_agg(&self) -> Result<(CompressedPk, KeyAggContext), MusigSignError> {
+        let internal_key = self.tap_internal_key.ok_or(MusigSignError::NoMusigKey(self.index))?;
+        let (agg_pk, participants) = self
+            .musig2_participants
+            .iter()
+            .find(|(agg_pk, _)| XOnlyPk::from(agg_pk.x_only_public_key().0) == *internal_key)
+            .ok_or(MusigSignError::NoMusigKey(self.index))?;
+        let mut key_agg = KeyAggContext::new(participants.iter().copied())?;
+        if key_agg.agg_pk() != *agg_pk {
+            return Err(MusigSignError::NoMusigKey(self.index));
+        }
+        key_agg.apply_taproot_tweak(self.tap_merkle_root)?;
+        Ok((*agg_pk, key_agg))
+    }
+}
+
+impl Psbt {
+    fn musig_input(&self, index: usize) -> Result<&Input, MusigSignError> {
+        self.inputs.get(index).ok_or(MusigSignError::NoInput(index))
+    }
+
+    fn musig_session(&self, index: usize) -> Result<(CompressedPk, Session), MusigSignError> {
+        let input = self.musig_input(index)?;
+        let (agg_pk, key_agg) = input.musig_key_agg()?;
+        let nonces = key_agg
+            .participants()
+            .iter()
+            .map(|pk| {
+                input
+                    .musig2_pub_nonces
+                    .get(&(*pk, agg_pk, None))
+                    .ok_or(MusigSignError::NoPubNonce(index, *pk))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let agg_nonce = PubNonce::aggregate(nonces)?;
+        let sighash =
+            self.sighash_cache::<MusigSignError>()?.tap_sighash_key(index, input.sighash_type)?;
+        let session = Session::new(&key_agg, &agg_nonce, sighash.to_byte_array())?;
+        Ok((agg_pk, session))
+    }
+
+    /// Generates MuSig2 nonce of participant `pk` for the key path spending of input `index`,
+    /// adding the public nonce to the input. The returned secret nonce must be kept until
+    /// [`Psbt::musig_partial_sign`] is called and must never be reused.
+    ///
+    /// The `rand` value must be produced by a cryptographically secure random number generator.
+    /// Providing the participant secret key `sk` protects against a weak source of randomness.
+    pub fn musig_nonce_gen(
+        &mut self,
+        index: usize,
+        pk: CompressedPk,
+        sk: Option<SecretKey>,
+        rand: [u8; 32],
+    ) -> Result<SecNonce, MusigSignError> {
+        let input = self.musig_input(index)?;
+        let (agg_pk, key_agg) = input.musig_key_agg()?;
+        if !key_agg.participants().contains(&pk) {
+            return Err(MusigError::UnknownParticipant(pk).into());
+        }
+        let (sec_nonce, pub_nonce) =
+            SecNonce::generate(rand, sk, pk, Some(key_agg.output_xonly()), None, &[]);
+        self.inputs[index].musig2_pub_nonces.insert((pk, agg_pk, None), pub_nonce);
+        Ok(sec_nonce)
+    }
+
+    /// Produces MuSig2 partial signature for the key path spending of input `index` and adds it
+    /// to the input. Requires public nonces of all participants to be present in the input.
+    pub fn musig_partial_sign(
+        &mut self,
+        index: usize,
+        sec_nonce: SecNonce,
+        sk: SecretKey,
+    ) -> Result<PartialSig, MusigSignError> {
+        let (agg_pk, session) = self.musig_session(index)?;
+        let pk = CompressedPk::from(sk.public_key(derive::secp256k1::SECP256K1));
+        let sig = session.partial_sign(sec_nonce, sk)?;
+        self.inputs[index].musig2_partial_sigs.insert((pk, agg_pk, None), sig);
+        Ok(sig)
+    }
+
+    /// Verifies partial signatures of all participants for the key path spending of input
+    /// `index` and aggregates them into the input taproot key signature.
+    pub fn musig_aggregate(&mut self, index: usize) -> Result<Bip340Sig, MusigSignError> {
+        let (agg_pk, session) = self.musig_session(index)?;
+        let input = &self.inputs[index];
+        let (_, key_agg) = input.musig_key_agg()?;
+        let mut sigs = Vec::with_capacity(key_agg.participants().len());
+        for pk in key_agg.participants() {
+            let key = (*pk, agg_pk, None);
+            let sig = *input
+                .musig2_partial_sigs
+                .get(&key)
+                .ok_or(MusigSignError::NoPartialSig(index, *pk))?;
+            let nonce = &input.musig2_pub_nonces[&key];
+            if !session.partial_verify(sig, nonce, *pk) {
+                return Err(MusigSignError::InvalidPartialSig(index, *pk));
+            }
+            sigs.push(sig);
+        }
+        let sig = Bip340Sig {
+            sig: session.aggregate(&sigs)?,
+            sighash_type: input.sighash_type,
+        };
+        self.inputs[index].tap_key_sig = Some(sig);
+        Ok(sig)
+    }
+}
@@ -24,9 +24,12 @@
 
 use std::borrow::Borrow;
 
-use derive::{Bip340Sig, LegacySig, SighashCache, SighashError, Sign, Tx, TxOut, Txid};
+use derive::{
+    Bip340Sig, LegacySig, ScriptPubkey, Sighash, SighashCache, SighashError, SighashFlag,
+    SighashType, Sign, Tx, TxOut, Txid,
+};
 
-use crate::{Input, Psbt};
+use crate::{Input, LockTimeConflict, Psbt, ValidationIssue, ValidationParams};
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
 #[display("the transaction was rejected by the signer.")]
@@ -46,6 +49,14 @@ pub enum SignError {
         index: usize,
         outputs: usize,
     },
+
+    #[from]
+    #[display(inner)]
+    LockTime(LockTimeConflict),
+
+    #[from]
+    #[display(inner)]
+    Invalid(ValidationIssue),
 }
 
 impl From<SighashError> for SignError {
@@ -81,12 +92,16 @@ pub trait Signer {
     where Self: 's;
 
     /// In the implementation of this method signers must ensure that transaction is checked by the
-    /// user.
+    /// user, including the sighash types requested by the inputs.
     // TODO: Add when implemented
     // If the transaction passes the check, they must provide the caller
     // with [`Satisfier`] instance, responsible for selecting specific script paths and keys for the
     // signing.
     fn approve(&self, psbt: &Psbt) -> Result<Self::Sign<'_>, Rejected>;
+
+    /// Parameters for validating PSBT with [`Psbt::validate`] before it is provided for the
+    /// approval. Defaults to `None`, which skips the validation.
+    fn validation_params(&self) -> Option<ValidationParams> { None }
 }
 
 impl Psbt {
@@ -96,21 +111,65 @@ impl Psbt {
     ///
     /// See [`Signer`] and [`Sign`] traits for details on how the interaction with `signer`
     /// happens.
+    ///
+    /// If the signer opts in via [`Signer::validation_params`], the PSBT is validated first, and
+    /// the signing fails on the first issue preventing it.
+    ///
+    /// Inputs are signed with their sighash types, and the modifiable flags of the PSBT are
+    /// updated according to the types of the added signatures.
     pub fn sign(&mut self, signer: &impl Signer) -> Result<usize, SignError> {
+        if let Some(params) = signer.validation_params() {
+            if let Some(issue) = self.validate(&params).blocking(&params) {
+                return Err(issue.into());
+            }
+        }
+        // Legacy sighash algorithm makes SIGHASH_SINGLE signatures of inputs without a matching
+        // output valid for any transaction, so we never produce them.
+        let outputs = self.outputs.len();
+        if let Some(input) = self.inputs.iter().find(|input| {
+            !input.is_finalized()
+                && input.index >= outputs
+                && input.sighash_type.is_some_and(|ty| ty.flag == SighashFlag::Single)
+        }) {
+            return Err(SignError::SighashOnlyMismatch {
+                txid: self.txid()?,
+                index: input.index,
+                outputs,
+            });
+        }
         let satisfier = signer.approve(self)?;
 
-        let tx = self.to_unsigned_tx();
-        let prevouts = self.inputs.iter().map(Input::prev_txout).cloned().collect::<Vec<_>>();
-        let mut sig_hasher = SighashCache::new(Tx::from(tx), prevouts)
-            .expect("inputs and prevouts match algorithmically");
+        let mut sig_hasher = self.sighash_cache::<SignError>()?;
         let mut sig_count = 0usize;
 
         for input in &mut self.inputs {
-            sig_count += input.sign(&satisfier, &mut sig_hasher)?;
+            let count = input.sign(&satisfier, &mut sig_hasher)?;
+            if count > 0 {
+                if let Some(flags) = &mut self.tx_modifiable {
+                    flags.register_signature(input.sighash_type.unwrap_or_default());
+                }
+            }
+            sig_count += count;
         }
 
         Ok(sig_count)
     }
+
+    /// Constructs sighash cache for the PSBT transaction, failing if some of the inputs don't
+    /// provide data on the spent outputs.
+    pub(crate) fn sighash_cache<E>(&self) -> Result<SighashCache<TxOut>, E>
+    where E: From<LockTimeConflict> + From<ValidationIssue> {
+        let tx = self.to_unsigned_tx()?;
+        let prevouts = self
+            .inputs
+            .iter()
+            .map(|input| {
+                input.try_prev_txout().cloned().ok_or(ValidationIssue::NoUtxo(input.index))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SighashCache::new(Tx::from(tx), prevouts)
+            .expect("inputs and prevouts match algorithmically"))
+    }
 }
 
 impl Input {
@@ -133,22 +192,11 @@ impl Input {
     ) -> Result<usize, SighashError> {
         let mut signature_count = 0usize;
         let sighash_type = self.sighash_type.unwrap_or_default();
-        let sighash = if self.is_segwit_v0() {
-            let Some(script_code) = self.script_code() else {
-                return Ok(0);
-            };
-
-            sig_hasher.segwit_sighash(
-                self.index,
-                &script_code,
-                self.prevout().value,
-                sighash_type,
-            )?
-        } else {
-            sig_hasher.legacy_sighash(self.index, &self.prev_txout().script_pubkey, sighash_type)?
+        let Some(sighash) = self.ecdsa_sighash(sig_hasher, sighash_type)? else {
+            return Ok(0);
         };
         for (pk, origin) in &self.bip32_derivation {
-            let Some(sig) = signer.sign_ecdsa(sighash, *pk, Some(origin)) else {
+            let Some(sig) = signer.sign_ecdsa(sighash, sighash_type, *pk, Some(origin)) else {
                 continue;
             };
             self.partial_sigs.insert(*pk, LegacySig { sig, sighash_type });
@@ -157,6 +205,34 @@ impl Input {
         Ok(signature_count)
     }
 
+    /// Computes sighash for ECDSA signatures of a legacy or SegWit v0 input.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the input is SegWit v0, but its script code can't be computed (see
+    /// [`Input::script_code`]).
+    pub(crate) fn ecdsa_sighash<Prevout: Borrow<TxOut>>(
+        &self,
+        sig_hasher: &mut SighashCache<Prevout>,
+        sighash_type: SighashType,
+    ) -> Result<Option<Sighash>, SighashError> {
+        if self.is_segwit_v0() {
+            let Some(script_code) = self.script_code() else {
+                return Ok(None);
+            };
+            sig_hasher
+                .segwit_sighash(self.index, &script_code, self.prevout().value, sighash_type)
+                .map(Some)
+        } else {
+            // P2SH inputs commit to the redeem script instead of the script pubkey
+            let script_code = match &self.redeem_script {
+                Some(redeem_script) => ScriptPubkey::from_checked(redeem_script.to_vec()),
+                None => self.prev_txout().script_pubkey.clone(),
+            };
+            sig_hasher.legacy_sighash(self.index, &script_code, sighash_type).map(Some)
+        }
+    }
+
     fn sign_bip340<Prevout: Borrow<TxOut>>(
         &mut self,
         signer: &impl Sign,
@@ -187,7 +263,8 @@ impl Input {
                 if !tap.leaf_hashes.contains(&tapleaf_hash) {
                     continue;
                 }
-                let Some(sig) = signer.sign_bip340_script_path(sighash, *pk, Some(&tap.origin))
+                let Some(sig) =
+                    signer.sign_bip340_script_path(sighash, sighash_type, *pk, Some(&tap.origin))
                 else {
                     continue;
                 };
@@ -208,6 +285,7 @@ impl Input {
         let sighash = sig_hasher.tap_sighash_key(self.index, sighash_type)?;
         let Some(sig) = signer.sign_bip340_key_only(
             sighash,
+            sighash_type,
             internal_key,
             derivation.map(|d| &d.origin),
             self.tap_merkle_root,
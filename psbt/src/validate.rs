@@ -0,0 +1,275 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display, Formatter};
+
+use derive::{Sats, SighashType, Txid, Vout, Weight};
+
+use crate::{FeeRate, Input, Psbt};
+
+/// Severity of an issue found by [`Psbt::validate`].
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(lowercase)]
+pub enum Severity {
+    /// The PSBT can be signed, but the user should be aware of the issue.
+    Warning,
+
+    /// The PSBT is malformed or signing it is not safe.
+    Error,
+}
+
+/// Issue found in a PSBT by [`Psbt::validate`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum ValidationIssue {
+    /// input #{0} has neither witness UTXO nor non-witness transaction.
+    NoUtxo(usize),
+
+    /// non-witness transaction {found} of input #{index} doesn't match the transaction {expected}
+    /// spent by the input.
+    NonWitnessTxMismatch {
+        index: usize,
+        expected: Txid,
+        found: Txid,
+    },
+
+    /// non-witness transaction of input #{index} has no output #{vout} spent by the input.
+    NoSpentOutput { index: usize, vout: Vout },
+
+    /// witness UTXO of input #{0} doesn't match the output of its non-witness transaction.
+    WitnessUtxoMismatch(usize),
+
+    /// non-SegWit input #{0} doesn't provide non-witness transaction, which is required to sign
+    /// it.
+    NoNonWitnessTx(usize),
+
+    /// SegWit v0 input #{0} doesn't provide non-witness transaction, thus its amount can't be
+    /// verified and the signer may be tricked into overpaying fees.
+    UnverifiedAmount(usize),
+
+    /// input #{index} requires signing with unsafe sighash type {sighash_type}.
+    UnsafeSighash {
+        index: usize,
+        sighash_type: SighashType,
+    },
+
+    /// transaction outputs spend {outputs} sats, which is more than {inputs} sats provided by
+    /// the inputs.
+    Overspending { inputs: Sats, outputs: Sats },
+
+    /// transaction fee {fee} sats exceeds the maximum of {max} sats.
+    AbsurdFee { fee: Sats, max: Sats },
+
+    /// transaction fee rate {fee_rate} sat/vB exceeds the maximum of {max} sat/vB.
+    AbsurdFeeRate { fee_rate: FeeRate, max: FeeRate },
+}
+
+impl ValidationIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            ValidationIssue::UnverifiedAmount(_) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// Parameters of PSBT validation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ValidationParams {
+    /// Maximal fee which is not considered absurd.
+    pub max_fee: Sats,
+
+    /// Maximal fee rate which is not considered absurd. The fee rate is computed from the weight
+    /// of the transaction with non-finalized inputs counted as unsigned, so it is an upper bound
+    /// of the fee rate of the signed transaction.
+    pub max_fee_rate: FeeRate,
+
+    /// Sighash types which may be requested by inputs. Inputs not specifying sighash type are
+    /// always accepted, since they use `SIGHASH_ALL` (or `SIGHASH_DEFAULT` for taproot).
+    pub safe_sighash_types: Vec<SighashType>,
+
+    /// Whether issues with [`Severity::Warning`] must prevent signing.
+    pub deny_warnings: bool,
+}
+
+impl Default for ValidationParams {
+    /// Uses the limits of Bitcoin Core wallet (0.1 BTC for both `-maxtxfee` and `-maxfeerate` per
+    /// kvB) and allows all sighash types committing to the transaction outputs.
+    fn default() -> Self {
+        ValidationParams {
+            max_fee: Sats::from_sats(10_000_000u64),
+            max_fee_rate: FeeRate::from_sat_per_kvb(10_000_000),
+            safe_sighash_types: vec![
+                SighashType::all(),
+                SighashType::all_anyone_can_pay(),
+                SighashType::single(),
+                SighashType::single_anyone_can_pay(),
+            ],
+            deny_warnings: false,
+        }
+    }
+}
+
+/// Results of PSBT validation.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.issues.is_empty() }
+
+    /// Detects whether the PSBT has no issues with [`Severity::Error`].
+    #[inline]
+    pub fn is_valid(&self) -> bool { self.errors().next().is_none() }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity() == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity() == Severity::Warning)
+    }
+
+    /// Returns the first issue which must prevent signing according to the `params`.
+    pub fn blocking(&self, params: &ValidationParams) -> Option<ValidationIssue> {
+        self.issues
+            .iter()
+            .find(|issue| params.deny_warnings || issue.severity() == Severity::Error)
+            .copied()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}: {issue}", issue.severity())?;
+        }
+        Ok(())
+    }
+}
+
+impl Psbt {
+    /// Performs sanity checks of the PSBT before signing it.
+    ///
+    /// Checks that each input provides data on the spent output which is consistent with the
+    /// outpoint, that the signer is not asked to produce signatures with unsafe sighash types and
+    /// that the transaction doesn't overspend or pay an absurd fee. Fees are checked only if the
+    /// data on all spent outputs is present and consistent.
+    pub fn validate(&self, params: &ValidationParams) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let mut utxos_valid = true;
+        for input in &self.inputs {
+            utxos_valid &= input.validate(params, &mut report.issues);
+        }
+        if !utxos_valid {
+            return report;
+        }
+
+        let inputs = self.input_sum();
+        let outputs = self.output_sum();
+        let Some(fee) = inputs.checked_sub(outputs) else {
+            report.issues.push(ValidationIssue::Overspending { inputs, outputs });
+            return report;
+        };
+        if fee > params.max_fee {
+            report.issues.push(ValidationIssue::AbsurdFee {
+                fee,
+                max: params.max_fee,
+            });
+        }
+        let fee_rate = FeeRate::with_fee(fee, self.weight_units());
+        if fee_rate > params.max_fee_rate {
+            report.issues.push(ValidationIssue::AbsurdFeeRate {
+                fee_rate,
+                max: params.max_fee_rate,
+            });
+        }
+        report
+    }
+}
+
+impl Input {
+    /// Returns whether the data on the spent output are present and consistent.
+    fn validate(&self, params: &ValidationParams, issues: &mut Vec<ValidationIssue>) -> bool {
+        let index = self.index;
+        let count = issues.len();
+        let outpoint = self.previous_outpoint;
+        match (&self.witness_utxo, &self.non_witness_tx) {
+            (None, None) => issues.push(ValidationIssue::NoUtxo(index)),
+            (witness_utxo, Some(tx)) => {
+                let txid = tx.txid();
+                if txid != outpoint.txid {
+                    issues.push(ValidationIssue::NonWitnessTxMismatch {
+                        index,
+                        expected: outpoint.txid,
+                        found: txid,
+                    });
+                }
+                match tx.outputs.get(outpoint.vout.into_usize()) {
+                    None => issues.push(ValidationIssue::NoSpentOutput {
+                        index,
+                        vout: outpoint.vout,
+                    }),
+                    Some(txout) if witness_utxo.as_ref().is_some_and(|utxo| utxo != txout) => {
+                        issues.push(ValidationIssue::WitnessUtxoMismatch(index))
+                    }
+                    Some(_) => {}
+                }
+            }
+            // Finalized inputs don't need to be signed
+            (Some(_), None) if self.is_finalized() => {}
+            (Some(utxo), None) => {
+                let spk = &utxo.script_pubkey;
+                let segwit_v0 = spk.is_p2wpkh()
+                    || spk.is_p2wsh()
+                    || (spk.is_p2sh()
+                        && self
+                            .redeem_script
+                            .as_ref()
+                            .is_some_and(|script| script.is_p2sh_wpkh() || script.is_p2sh_wsh()));
+                // Taproot signatures commit to the amounts of all spent outputs, so the attack is
+                // not possible for them; while legacy inputs can't be signed at all without the
+                // non-witness transaction.
+                if segwit_v0 {
+                    issues.push(ValidationIssue::UnverifiedAmount(index));
+                } else if !spk.is_witness_program() {
+                    issues.push(ValidationIssue::NoNonWitnessTx(index));
+                }
+            }
+        }
+        let utxo_valid = issues[count..].iter().all(|issue| issue.severity() == Severity::Warning);
+
+        if let Some(sighash_type) = self.sighash_type {
+            if !self.is_finalized() && !params.safe_sighash_types.contains(&sighash_type) {
+                issues.push(ValidationIssue::UnsafeSighash {
+                    index,
+                    sighash_type,
+                });
+            }
+        }
+        utxo_valid
+    }
+}
@@ -0,0 +1,191 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Borrow;
+
+use amplify::Wrapper;
+use derive::secp256k1::SECP256K1;
+use derive::{
+    Bip340Sig, LegacyPk, LegacySig, OutputPk, SighashCache, TapLeafHash, TapSighash, TxOut, XOnlyPk,
+};
+use descriptors::Descriptor;
+
+use crate::{Input, LockTimeConflict, Psbt, ValidationIssue};
+
+/// Invalid signature found by [`Psbt::verify_signatures`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum InvalidSig {
+    /// ECDSA signature by {key} in input #{input} is invalid.
+    Ecdsa { input: usize, key: LegacyPk },
+
+    /// ECDSA signature by {key} in input #{input} has a high S value, which is non-standard.
+    EcdsaHighS { input: usize, key: LegacyPk },
+
+    /// BIP-340 key path signature in input #{input} is invalid.
+    TapKey { input: usize },
+
+    /// BIP-340 signature by {key} for the script leaf {leaf} in input #{input} is invalid.
+    TapScript {
+        input: usize,
+        key: XOnlyPk,
+        leaf: TapLeafHash,
+    },
+}
+
+impl InvalidSig {
+    pub fn input(&self) -> usize {
+        match self {
+            InvalidSig::Ecdsa { input, .. }
+            | InvalidSig::EcdsaHighS { input, .. }
+            | InvalidSig::TapKey { input }
+            | InvalidSig::TapScript { input, .. } => *input,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(inner)]
+pub enum VerifyError {
+    #[from]
+    LockTime(LockTimeConflict),
+
+    #[from]
+    Invalid(ValidationIssue),
+
+    #[from]
+    InvalidSig(InvalidSig),
+}
+
+impl Psbt {
+    /// Verifies all partial ECDSA signatures, taproot key path and script path signatures present
+    /// in the PSBT inputs against the sighashes computed for their sighash types.
+    ///
+    /// ECDSA signatures with high S values are reported as [`InvalidSig::EcdsaHighS`]: while
+    /// consensus rules accept them, they are non-standard and would prevent the transaction from
+    /// being relayed.
+    ///
+    /// Signatures for which no sighash can be computed are reported as invalid: these are ECDSA
+    /// signatures of SegWit v0 inputs lacking witness or redeem script and signatures with
+    /// `SIGHASH_SINGLE` for inputs without a corresponding output.
+    ///
+    /// # Returns
+    ///
+    /// List of invalid signatures, ordered by input. Errors if the signatures can't be verified
+    /// since the PSBT lacks data on some of the spent outputs or its lock time is undefined.
+    pub fn verify_signatures(&self) -> Result<Vec<InvalidSig>, VerifyError> {
+        let mut sig_hasher = self.sighash_cache::<VerifyError>()?;
+        let mut invalid = vec![];
+        for input in &self.inputs {
+            input.verify_signatures(&mut sig_hasher, &mut invalid);
+        }
+        Ok(invalid)
+    }
+
+    /// Checks that all signatures in the PSBT are valid, failing on the first invalid one.
+    pub fn check_signatures(&self) -> Result<(), VerifyError> {
+        match self.verify_signatures()?.first() {
+            Some(invalid) => Err((*invalid).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Finalizes inputs like [`Psbt::finalize`], but only once all the signatures in the PSBT are
+    /// verified. If some of them are invalid, the PSBT is left unchanged.
+    pub fn finalize_verified<D: Descriptor<K, V>, K, V>(
+        &mut self,
+        descriptor: &D,
+    ) -> Result<usize, VerifyError> {
+        self.check_signatures()?;
+        Ok(self.finalize(descriptor))
+    }
+}
+
+impl Input {
+    fn verify_signatures<Prevout: Borrow<TxOut>>(
+        &self,
+        sig_hasher: &mut SighashCache<Prevout>,
+        invalid: &mut Vec<InvalidSig>,
+    ) {
+        let input = self.index;
+
+        for (key, sig) in &self.partial_sigs {
+            if !is_low_s(sig) {
+                invalid.push(InvalidSig::EcdsaHighS { input, key: *key });
+            } else if !self.verify_ecdsa(sig_hasher, key, sig) {
+                invalid.push(InvalidSig::Ecdsa { input, key: *key });
+            }
+        }
+
+        if let Some(sig) = self.tap_key_sig {
+            let sighash = sig_hasher.tap_sighash_key(input, sig.sighash_type).ok();
+            let script_pubkey = &self.prev_txout().script_pubkey;
+            let output_key = script_pubkey
+                .is_p2tr()
+                .then(|| OutputPk::from_bytes(&script_pubkey[2..]).ok())
+                .flatten();
+            let valid = sighash
+                .zip(output_key)
+                .is_some_and(|(sighash, key)| verify_bip340(sighash, key.to_xonly_pk(), sig));
+            if !valid {
+                invalid.push(InvalidSig::TapKey { input });
+            }
+        }
+
+        for ((key, leaf), sig) in &self.tap_script_sig {
+            let valid = sig_hasher
+                .tap_sighash_script(input, *leaf, sig.sighash_type)
+                .is_ok_and(|sighash| verify_bip340(sighash, *key, *sig));
+            if !valid {
+                invalid.push(InvalidSig::TapScript {
+                    input,
+                    key: *key,
+                    leaf: *leaf,
+                });
+            }
+        }
+    }
+
+    fn verify_ecdsa<Prevout: Borrow<TxOut>>(
+        &self,
+        sig_hasher: &mut SighashCache<Prevout>,
+        key: &LegacyPk,
+        sig: &LegacySig,
+    ) -> bool {
+        let Ok(Some(sighash)) = self.ecdsa_sighash(sig_hasher, sig.sighash_type) else {
+            return false;
+        };
+        SECP256K1.verify_ecdsa(&sighash.into(), &sig.sig, &key.pubkey).is_ok()
+    }
+}
+
+fn is_low_s(sig: &LegacySig) -> bool {
+    let mut normalized = sig.sig;
+    normalized.normalize_s();
+    normalized == sig.sig
+}
+
+fn verify_bip340(sighash: TapSighash, key: XOnlyPk, sig: Bip340Sig) -> bool {
+    SECP256K1.verify_schnorr(&sig.sig, sighash.as_ref(), &key.into_inner()).is_ok()
+}
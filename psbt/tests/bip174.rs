@@ -24,7 +24,18 @@
 
 use std::str::FromStr;
 
-use psbt::Psbt;
+use derive::secp256k1::constants::CURVE_ORDER;
+use derive::secp256k1::{ecdsa, schnorr as bip340};
+use derive::{
+    InternalPk, KeyOrigin, LegacyPk, LegacySig, RedeemScript, Sats, SigScript, Sighash,
+    SighashType, Sign, TapLeafHash, TapMerklePath, TapNodeHash, TapSighash, Txid, Weight, Witness,
+    XOnlyPk,
+};
+use descriptors::StdDescr;
+use psbt::{
+    CombineError, FeeRate, InvalidSig, Psbt, Rejected, SignError, Signer, ValidationIssue,
+    ValidationParams, VerifyError,
+};
 
 fn parse_roundtrip(s: &str) {
     let psbt = Psbt::from_str(s).unwrap();
@@ -77,3 +88,277 @@ fn no_inputs_outputs() { parse_roundtrip(include_str!("valid.v0/no_inputs_output
 /// Case: PSBT with 0 inputs
 #[test]
 fn no_inputs() { parse_roundtrip(include_str!("valid.v0/no_inputs.psbt")); }
+
+const KEY: &str = "[643a7adc/84h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1>/*";
+
+fn descriptor(template: &str) -> StdDescr {
+    StdDescr::from_str(&template.replace("KEY", KEY)).unwrap()
+}
+
+/// Case: worst-case weight of non-finalized P2PKH and P2SH-P2WPKH inputs.
+#[test]
+fn pkh_sh_wpkh_weight() {
+    let psbt = Psbt::from_str(include_str!("valid.v0/pkh_sh_wpkh.psbt")).unwrap();
+    let input = psbt.input(0).unwrap();
+    assert_eq!(input.estimate_weight(&descriptor("pkh(KEY)")).into_u32(), 596);
+    let input = psbt.input(1).unwrap();
+    assert_eq!(input.estimate_weight(&descriptor("sh(wpkh(KEY))")).into_u32(), 365);
+    // Without a descriptor inputs are counted as unsigned
+    assert_eq!(psbt.weight_units().into_u32(), 643);
+}
+
+/// Case: worst-case weight of a non-finalized 2-of-2 multisig P2WSH and P2SH-P2WSH inputs.
+#[test]
+fn wsh_weight() {
+    let psbt = Psbt::from_str(include_str!("valid.v0/wsh.psbt")).unwrap();
+    let descr = descriptor("wsh(multi(2,KEY,KEY))");
+    assert_eq!(psbt.input(0).unwrap().estimate_weight(&descr).into_u32(), 386);
+    assert_eq!(psbt.estimate_weight(&descr).into_u32(), 552);
+
+    let psbt = Psbt::from_str(include_str!("valid.v0/sh_wsh.psbt")).unwrap();
+    let descr = descriptor("sh(wsh(multi(2,KEY,KEY)))");
+    assert_eq!(psbt.input(0).unwrap().estimate_weight(&descr).into_u32(), 526);
+}
+
+/// Case: weight of a PSBT with finalized inputs must match the weight of the extracted
+/// transaction.
+#[test]
+fn finalized_weight() {
+    let mut psbt = Psbt::from_str(include_str!("valid.v0/pkh_sh_wpkh.psbt")).unwrap();
+    let mut sig_script = SigScript::new();
+    sig_script.push_slice(&[0xAB; 71]);
+    sig_script.push_slice(&[0x02; 33]);
+    psbt.input_mut(0).unwrap().final_script_sig = Some(sig_script);
+    let mut sig_script = SigScript::new();
+    sig_script.push_slice(&[0x00; 22]);
+    psbt.input_mut(1).unwrap().final_script_sig = Some(sig_script);
+    psbt.input_mut(1).unwrap().final_witness =
+        Some(Witness::from_consensus_stack([vec![0xAB; 72], vec![0x03; 33]]));
+
+    let tx = psbt.extract().unwrap();
+    assert_eq!(psbt.weight_units(), tx.weight_units());
+    assert_eq!(psbt.vbytes(), tx.vbytes());
+}
+
+/// Case: combining PSBTs of the same transaction having different parts of the input data.
+#[test]
+fn combine() {
+    let full = Psbt::from_str(include_str!("valid.v0/pkh_sh_wpkh.psbt")).unwrap();
+
+    let mut psbt = full.clone();
+    psbt.input_mut(0).unwrap().non_witness_tx = None;
+    psbt.input_mut(1).unwrap().redeem_script = None;
+    let mut other = full.clone();
+    other.input_mut(1).unwrap().witness_utxo = None;
+    other.output_mut(1).unwrap().bip32_derivation.clear();
+
+    psbt.combine(other).unwrap();
+    assert_eq!(psbt, full);
+
+    let mut conflicting = full.clone();
+    conflicting.input_mut(1).unwrap().redeem_script = Some(RedeemScript::new());
+    assert_eq!(psbt.combine(conflicting), Err(CombineError::InputConflict(1, "redeem_script")));
+
+    let other = Psbt::from_str(include_str!("valid.v0/wsh.psbt")).unwrap();
+    assert!(matches!(psbt.combine(other), Err(CombineError::TxidMismatch(..))));
+}
+
+/// Case: verification of a partial signature of a P2SH-P2WSH multisig input, rejecting PSBTs
+/// with an invalid signature on combining.
+#[test]
+fn verify_signatures() {
+    let signed = Psbt::from_str(include_str!("valid.v0/sh_wsh.psbt")).unwrap();
+    assert_eq!(signed.verify_signatures(), Ok(vec![]));
+    let (key, sig) = signed.input(0).unwrap().partial_sigs.first().map(|(k, s)| (*k, *s)).unwrap();
+
+    let mut tampered = signed.clone();
+    let sig = LegacySig {
+        sighash_type: SighashType::none(),
+        ..sig
+    };
+    tampered.input_mut(0).unwrap().partial_sigs.insert(key, sig);
+    let invalid = InvalidSig::Ecdsa { input: 0, key };
+    assert_eq!(tampered.verify_signatures(), Ok(vec![invalid]));
+    assert_eq!(tampered.check_signatures(), Err(VerifyError::InvalidSig(invalid)));
+
+    // The same signature with S value negated is valid by consensus, but non-standard
+    let mut high_s = signed.clone();
+    let sig = signed.input(0).unwrap().partial_sigs[&key];
+    let mut compact = sig.sig.serialize_compact();
+    let mut borrow = 0;
+    for (s, n) in compact[32..].iter_mut().zip(CURVE_ORDER[..].iter()).rev() {
+        let (diff, b1) = n.overflowing_sub(*s);
+        let (diff, b2) = diff.overflowing_sub(borrow);
+        *s = diff;
+        borrow = (b1 || b2) as u8;
+    }
+    let sig_high_s = LegacySig {
+        sig: ecdsa::Signature::from_compact(&compact).unwrap(),
+        ..sig
+    };
+    assert_ne!(sig_high_s, sig);
+    high_s.input_mut(0).unwrap().partial_sigs.insert(key, sig_high_s);
+    let invalid_high_s = InvalidSig::EcdsaHighS { input: 0, key };
+    assert_eq!(high_s.verify_signatures(), Ok(vec![invalid_high_s]));
+
+    let mut psbt = signed.clone();
+    psbt.input_mut(0).unwrap().partial_sigs.clear();
+    let unsigned = psbt.clone();
+    assert_eq!(
+        psbt.combine_verified(tampered),
+        Err(CombineError::Verify(VerifyError::InvalidSig(invalid)))
+    );
+    assert_eq!(psbt, unsigned);
+    psbt.combine_verified(signed.clone()).unwrap();
+    assert_eq!(psbt, signed);
+
+    let mut psbt = signed.clone();
+    psbt.input_mut(0).unwrap().witness_utxo = None;
+    assert_eq!(psbt.verify_signatures(), Err(VerifyError::Invalid(ValidationIssue::NoUtxo(0))));
+}
+
+/// Case: sanity checks of a PSBT before signing
+#[test]
+fn validate() {
+    let params = ValidationParams::default();
+    let psbt = Psbt::from_str(include_str!("valid.v0/pkh_sh_wpkh.psbt")).unwrap();
+    let report = psbt.validate(&params);
+    assert!(report.is_valid());
+    assert_eq!(report.issues, vec![ValidationIssue::UnverifiedAmount(1)]);
+
+    // Both inputs spend outputs of the same transaction, so we can provide it for the SegWit one
+    let mut complete = psbt.clone();
+    let prev_tx = complete.input(0).unwrap().non_witness_tx.clone();
+    complete.input_mut(1).unwrap().non_witness_tx = prev_tx;
+    assert!(complete.validate(&params).is_empty());
+
+    let mut invalid = complete.clone();
+    invalid.input_mut(1).unwrap().witness_utxo.as_mut().unwrap().value = Sats(1);
+    assert_eq!(invalid.validate(&params).issues, vec![ValidationIssue::WitnessUtxoMismatch(1)]);
+
+    let mut invalid = complete.clone();
+    let found = invalid.input(0).unwrap().previous_outpoint.txid;
+    invalid.input_mut(0).unwrap().previous_outpoint.txid = Txid::coinbase();
+    assert_eq!(invalid.validate(&params).issues, vec![ValidationIssue::NonWitnessTxMismatch {
+        index: 0,
+        expected: Txid::coinbase(),
+        found
+    }]);
+
+    let mut invalid = psbt.clone();
+    invalid.input_mut(1).unwrap().witness_utxo = None;
+    let report = invalid.validate(&params);
+    assert!(!report.is_valid());
+    assert_eq!(report.issues, vec![ValidationIssue::NoUtxo(1)]);
+
+    // Legacy inputs can't be signed without the non-witness transaction
+    let mut invalid = complete.clone();
+    let input = invalid.input_mut(0).unwrap();
+    input.witness_utxo = input.try_prev_txout().cloned();
+    input.non_witness_tx = None;
+    let report = invalid.validate(&params);
+    assert!(!report.is_valid());
+    assert_eq!(report.issues, vec![ValidationIssue::NoNonWitnessTx(0)]);
+
+    // Without the redeem script the P2SH input can't be recognized as a SegWit one
+    let mut invalid = psbt.clone();
+    invalid.input_mut(1).unwrap().redeem_script = None;
+    assert_eq!(invalid.validate(&params).issues, vec![ValidationIssue::NoNonWitnessTx(1)]);
+
+    let mut invalid = complete.clone();
+    invalid.input_mut(0).unwrap().sighash_type = Some(SighashType::none());
+    invalid.output_mut(0).unwrap().amount = Sats(200_000_000);
+    assert_eq!(invalid.validate(&params).issues, vec![
+        ValidationIssue::UnsafeSighash {
+            index: 0,
+            sighash_type: SighashType::none()
+        },
+        ValidationIssue::Overspending {
+            inputs: Sats(199_999_699),
+            outputs: Sats(200_009_358)
+        }
+    ]);
+
+    let params = ValidationParams {
+        max_fee: Sats(90_000),
+        max_fee_rate: FeeRate::from_sat_per_vb(100).unwrap(),
+        ..ValidationParams::default()
+    };
+    assert_eq!(complete.validate(&params).issues, vec![
+        ValidationIssue::AbsurdFee {
+            fee: Sats(90_341),
+            max: Sats(90_000)
+        },
+        ValidationIssue::AbsurdFeeRate {
+            fee_rate: FeeRate::from_sat_per_kvb(561_124),
+            max: FeeRate::from_sat_per_vb(100).unwrap()
+        }
+    ]);
+}
+
+struct RejectingSigner(Option<ValidationParams>);
+
+impl Sign for RejectingSigner {
+    fn sign_ecdsa(
+        &self,
+        _: Sighash,
+        _: SighashType,
+        _: LegacyPk,
+        _: Option<&KeyOrigin>,
+    ) -> Option<ecdsa::Signature> {
+        None
+    }
+    fn sign_bip340_key_only(
+        &self,
+        _: TapSighash,
+        _: Option<SighashType>,
+        _: InternalPk,
+        _: Option<&KeyOrigin>,
+        _: Option<TapNodeHash>,
+    ) -> Option<bip340::Signature> {
+        None
+    }
+    fn sign_bip340_script_path(
+        &self,
+        _: TapSighash,
+        _: Option<SighashType>,
+        _: XOnlyPk,
+        _: Option<&KeyOrigin>,
+    ) -> Option<bip340::Signature> {
+        None
+    }
+    fn should_sign_script_path(&self, _: usize, _: &TapMerklePath, _: TapLeafHash) -> bool { false }
+    fn should_sign_key_path(&self, _: usize) -> bool { false }
+}
+
+impl Signer for RejectingSigner {
+    type Sign<'s> = Self;
+    fn approve(&self, _: &Psbt) -> Result<Self::Sign<'_>, Rejected> { Err(Rejected) }
+    fn validation_params(&self) -> Option<ValidationParams> { self.0.clone() }
+}
+
+/// Case: signing validates PSBT before asking for approval
+#[test]
+fn sign_validated() {
+    let mut psbt = Psbt::from_str(include_str!("valid.v0/pkh_sh_wpkh.psbt")).unwrap();
+    assert_eq!(
+        psbt.sign(&RejectingSigner(Some(ValidationParams::default()))),
+        Err(SignError::Rejected)
+    );
+
+    let strict = ValidationParams {
+        deny_warnings: true,
+        ..ValidationParams::default()
+    };
+    assert_eq!(
+        psbt.sign(&RejectingSigner(Some(strict))),
+        Err(SignError::Invalid(ValidationIssue::UnverifiedAmount(1)))
+    );
+
+    psbt.input_mut(1).unwrap().witness_utxo = None;
+    assert_eq!(
+        psbt.sign(&RejectingSigner(Some(ValidationParams::default()))),
+        Err(SignError::Invalid(ValidationIssue::NoUtxo(1)))
+    );
+    assert_eq!(psbt.sign(&RejectingSigner(None)), Err(SignError::Rejected));
+}
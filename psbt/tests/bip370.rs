@@ -22,9 +22,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::RefCell;
 use std::str::FromStr;
 
-use psbt::Psbt;
+use derive::secp256k1::{ecdsa, schnorr as bip340};
+use derive::{
+    InternalPk, KeyOrigin, LegacyPk, LockHeight, LockTime, LockTimestamp, Outpoint, SeqNo, Sighash,
+    SighashType, Sign, TapLeafHash, TapMerklePath, TapNodeHash, TapSighash, TxVer, Txid,
+    VarIntArray, XOnlyPk,
+};
+use psbt::{
+    LockTimeConflict, ModifiableFlags, Psbt, Rejected, SighashTypeError, Signer, UnsignedTx,
+    UnsignedTxIn,
+};
 
 fn parse_roundtrip(s: &str) {
     let psbt = Psbt::from_str(s).unwrap();
@@ -41,7 +51,15 @@ fn updated() { parse_roundtrip(include_str!("valid.v2/updated.psbt")); }
 fn nseq() { parse_roundtrip(include_str!("valid.v2/nseq.psbt")); }
 
 #[test]
-fn locks() { parse_roundtrip(include_str!("valid.v2/locks.psbt")); }
+fn locks() {
+    let s = include_str!("valid.v2/locks.psbt");
+    parse_roundtrip(s);
+    // Input supports both lock types, so the height-based one must be chosen
+    let psbt = Psbt::from_str(s).unwrap();
+    let lock_time = LockTime::from_consensus_u32(10000);
+    assert_eq!(psbt.lock_time(), Ok(lock_time));
+    assert_eq!(psbt.to_unsigned_tx().unwrap().lock_time, lock_time);
+}
 
 #[test]
 fn in_modifiable() { parse_roundtrip(include_str!("valid.v2/in_modifiable.psbt")); }
@@ -61,3 +79,143 @@ fn all_modifiable() { parse_roundtrip(include_str!("valid.v2/all_modifiable.psbt
 /// Case: 1 input, 2 output updated PSBTv2, with all PSBTv2 fields
 #[test]
 fn all() { parse_roundtrip(include_str!("valid.v2/all.psbt")); }
+
+fn lock_time_psbt(
+    fallback: Option<u32>,
+    locks: &[(Option<u32>, Option<u32>)],
+) -> Result<LockTime, LockTimeConflict> {
+    let mut psbt = Psbt::from_tx(UnsignedTx {
+        version: TxVer::V2,
+        inputs: VarIntArray::from_iter_checked((0..locks.len()).map(|vout| UnsignedTxIn {
+            prev_output: Outpoint::new(Txid::coinbase(), vout as u32),
+            sequence: SeqNo::ZERO,
+        })),
+        outputs: VarIntArray::new(),
+        lock_time: LockTime::ZERO,
+    });
+    psbt.fallback_locktime = fallback.map(LockTime::from_consensus_u32);
+    for (input, (height, time)) in psbt.inputs_mut().zip(locks.iter().copied()) {
+        input.required_height_lock = height.map(|h| LockHeight::from_height(h).unwrap());
+        input.required_time_lock = time.map(|t| LockTimestamp::from_unix_timestamp(t).unwrap());
+    }
+    psbt.lock_time()
+}
+
+/// Case: lock time determination according to BIP-370
+#[test]
+fn lock_time() {
+    const T1: u32 = 1657048460;
+    const T2: u32 = 1657048461;
+
+    assert_eq!(lock_time_psbt(None, &[]), Ok(LockTime::ZERO));
+    assert_eq!(
+        lock_time_psbt(Some(10000), &[(None, None)]),
+        Ok(LockTime::from_consensus_u32(10000))
+    );
+    // Fallback lock time is ignored once some input requires a lock time
+    assert_eq!(
+        lock_time_psbt(Some(20000), &[(None, Some(T1))]),
+        Ok(LockTime::from_consensus_u32(T1))
+    );
+    assert_eq!(
+        lock_time_psbt(None, &[(Some(10000), None), (Some(10001), None), (None, None)]),
+        Ok(LockTime::from_consensus_u32(10001))
+    );
+    // Height is preferred when both types are possible
+    assert_eq!(
+        lock_time_psbt(None, &[(Some(10000), Some(T1)), (None, None)]),
+        Ok(LockTime::from_consensus_u32(10000))
+    );
+    assert_eq!(
+        lock_time_psbt(None, &[(Some(10000), Some(T2)), (Some(10001), Some(T1))]),
+        Ok(LockTime::from_consensus_u32(10001))
+    );
+    // Time is chosen when some input supports only time-based locks
+    assert_eq!(
+        lock_time_psbt(None, &[(Some(10000), Some(T2)), (None, Some(T1))]),
+        Ok(LockTime::from_consensus_u32(T2))
+    );
+    assert_eq!(
+        lock_time_psbt(None, &[
+            (None, None),
+            (Some(10000), None),
+            (Some(10001), Some(T1)),
+            (None, Some(T2))
+        ]),
+        Err(LockTimeConflict {
+            height_input: 1,
+            time_input: 3,
+        })
+    );
+}
+
+/// Signer producing dummy ECDSA signatures and recording the sighash types it was asked to use.
+#[derive(Default)]
+struct RecordingSigner(RefCell<Vec<SighashType>>);
+
+impl Sign for &RecordingSigner {
+    fn sign_ecdsa(
+        &self,
+        _: Sighash,
+        sighash_type: SighashType,
+        _: LegacyPk,
+        _: Option<&KeyOrigin>,
+    ) -> Option<ecdsa::Signature> {
+        self.0.borrow_mut().push(sighash_type);
+        ecdsa::Signature::from_compact(&[1u8; 64]).ok()
+    }
+    fn sign_bip340_key_only(
+        &self,
+        _: TapSighash,
+        _: Option<SighashType>,
+        _: InternalPk,
+        _: Option<&KeyOrigin>,
+        _: Option<TapNodeHash>,
+    ) -> Option<bip340::Signature> {
+        None
+    }
+    fn sign_bip340_script_path(
+        &self,
+        _: TapSighash,
+        _: Option<SighashType>,
+        _: XOnlyPk,
+        _: Option<&KeyOrigin>,
+    ) -> Option<bip340::Signature> {
+        None
+    }
+    fn should_sign_script_path(&self, _: usize, _: &TapMerklePath, _: TapLeafHash) -> bool { false }
+    fn should_sign_key_path(&self, _: usize) -> bool { false }
+}
+
+impl Signer for RecordingSigner {
+    type Sign<'s> = &'s Self;
+    fn approve(&self, _: &Psbt) -> Result<Self::Sign<'_>, Rejected> { Ok(self) }
+}
+
+/// Case: inputs are signed with the requested sighash types, and the modifiable flags are updated
+/// accordingly
+#[test]
+fn sighash_types() {
+    let mut psbt = Psbt::from_str(include_str!("valid.v2/all_modifiable.psbt")).unwrap();
+    let pk =
+        LegacyPk::from_str("02d601f84846a6755f776be00e3d9de8fb10acc935fb83c45fb0162d4cad5ab792")
+            .unwrap();
+    let origin = KeyOrigin::from_str("f69d873e/84h/0h/0h/0/42").unwrap();
+    psbt.input_mut(0).unwrap().bip32_derivation.insert(pk, origin);
+
+    assert_eq!(psbt.set_sighash_type(1, None), Err(SighashTypeError::NoInput(1)));
+    psbt.set_sighash_type(0, Some(SighashType::single_anyone_can_pay())).unwrap();
+    assert_eq!(psbt.tx_modifiable().map(ModifiableFlags::to_standard_u8), Some(0b111));
+
+    let signer = RecordingSigner::default();
+    assert_eq!(psbt.sign(&signer), Ok(1));
+    assert_eq!(*signer.0.borrow(), [SighashType::single_anyone_can_pay()]);
+    assert_eq!(
+        psbt.input(0).unwrap().partial_sigs[&pk].sighash_type,
+        SighashType::single_anyone_can_pay()
+    );
+    // Outputs are committed to, while the inputs may still be added
+    assert_eq!(psbt.tx_modifiable().map(ModifiableFlags::to_standard_u8), Some(0b101));
+
+    assert_eq!(psbt.set_sighash_type(0, None), Err(SighashTypeError::Signed(0)));
+}
@@ -24,11 +24,14 @@
 
 use std::str::FromStr;
 
-use psbt::Psbt;
+use amplify::hex::ToHex;
+use derive::{SighashType, Weight, XpubDerivable};
+use descriptors::StdDescr;
+use psbt::{InvalidSig, Psbt};
 
 fn parse_roundtrip(s: &str) {
     let psbt = Psbt::from_str(s).unwrap();
-    Psbt::from_str(&psbt.to_string()).unwrap();
+    assert_eq!(Psbt::from_str(&psbt.to_string()).unwrap(), psbt);
 }
 
 /// Case: PSBT with one P2TR key only input with internal key and its derivation path
@@ -57,3 +60,72 @@ fn script_out() { parse_roundtrip(include_str!("valid.tr/script_out.psbt")); }
 /// derivation paths, merkle root, and script path signatures
 #[test]
 fn script_signed() { parse_roundtrip(include_str!("valid.tr/script_signed.psbt")); }
+
+/// Case: BIP-371 serializes `PSBT_IN_TAP_LEAF_SCRIPT` values as the leaf script followed by the
+/// leaf version byte.
+#[test]
+fn leaf_script_encoding() {
+    let psbt = Psbt::from_str(include_str!("valid.tr/script_in.psbt")).unwrap();
+    let leaf_script = psbt.input(0).unwrap().tap_leaf_script.values().next().unwrap();
+    assert_eq!(leaf_script.version.to_consensus_u8(), 0xC0);
+    assert_eq!(
+        leaf_script.script.to_hex(),
+        "202cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d2ac"
+    );
+
+    // Length-prefixed value: 34-byte script and the leaf version
+    let value = "23202cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d2acc0";
+    let data = psbt.serialize(psbt.version);
+    assert!(data.to_hex().contains(value));
+    let reparsed = Psbt::deserialize(data).unwrap();
+    assert!(reparsed.input(0).unwrap().tap_leaf_script.values().eq(psbt
+        .input(0)
+        .unwrap()
+        .tap_leaf_script
+        .values()));
+}
+
+/// Case: worst-case weight of P2TR key path and script path inputs.
+#[test]
+fn weight() {
+    let key = "[643a7adc/86h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1>/*";
+    let psbt = Psbt::from_str(include_str!("valid.tr/keyonly_in.psbt")).unwrap();
+    let descriptor = StdDescr::<XpubDerivable>::from_str(&format!("tr({key})")).unwrap();
+    assert_eq!(psbt.input(0).unwrap().estimate_weight(&descriptor).into_u32(), 231);
+    assert_eq!(psbt.estimate_weight(&descriptor).into_u32(), 397);
+    // Without the descriptor the input is counted as unsigned
+    assert_eq!(psbt.weight_units().into_u32(), 330);
+
+    // Largest leaf has a single key and two-node merkle path
+    let psbt = Psbt::from_str(include_str!("valid.tr/script_in.psbt")).unwrap();
+    let descriptor = StdDescr::<XpubDerivable>::from_str(&format!(
+        "tr({key},{{pk({key}),{{pk({key}),pk({key})}}}})"
+    ))
+    .unwrap();
+    assert_eq!(psbt.input(0).unwrap().estimate_weight(&descriptor).into_u32(), 364);
+    assert_eq!(psbt.estimate_weight(&descriptor).into_u32(), 578);
+}
+
+/// Case: verification of taproot key path and script path signatures.
+#[test]
+fn verify_signatures() {
+    let mut psbt = Psbt::from_str(include_str!("valid.tr/keyonly_signed.psbt")).unwrap();
+    assert_eq!(psbt.verify_signatures(), Ok(vec![]));
+    let input = psbt.input_mut(0).unwrap();
+    input.tap_key_sig.as_mut().unwrap().sighash_type = Some(SighashType::all());
+    assert_eq!(psbt.verify_signatures(), Ok(vec![InvalidSig::TapKey { input: 0 }]));
+
+    let mut psbt = Psbt::from_str(include_str!("valid.tr/script_signed.psbt")).unwrap();
+    assert_eq!(psbt.verify_signatures(), Ok(vec![]));
+    let input = psbt.input_mut(0).unwrap();
+    let (&(key, leaf), sig) = input.tap_script_sig.first_mut().unwrap();
+    sig.sighash_type = Some(SighashType::all());
+    assert_eq!(
+        psbt.verify_signatures(),
+        Ok(vec![InvalidSig::TapScript {
+            input: 0,
+            key,
+            leaf
+        }])
+    );
+}
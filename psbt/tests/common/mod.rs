@@ -0,0 +1,256 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wallet and signer fixtures shared by the PSBT construction, signing and finalization tests.
+
+#![allow(dead_code)]
+
+use std::str::FromStr;
+
+use derive::secp256k1::{ecdsa, schnorr as bip340, SECP256K1};
+use derive::{
+    HardenedIndex, Idx, InternalKeypair, InternalPk, KeyOrigin, Keychain, LegacyPk, LockTime,
+    Network, NormalIndex, Outpoint, Sats, ScriptPubkey, SeqNo, Sighash, SighashType, Sign,
+    TapLeafHash, TapMerklePath, TapNodeHash, TapSighash, Terminal, TxOut, TxVer, Txid, VarIntArray,
+    XOnlyPk, Xpriv, XprivAccount, XpubDerivable,
+};
+use descriptors::interpreter::{TxVerifyExt, VerifyFlags};
+use descriptors::{Descriptor, HashLock};
+use psbt::{
+    Beneficiary, ConstructionError, Psbt, PsbtConstructor, Rejected, Signer, TxParams, UnsignedTx,
+    UnsignedTxIn, Utxo,
+};
+
+/// Height of the block mining the wallet coins, unless specified otherwise.
+pub const COIN_HEIGHT: u32 = 100;
+
+pub fn account(seed: u8) -> XprivAccount {
+    XprivAccount::with_seed(true, &[seed; 32])
+        .derive([HardenedIndex::from(86u16), HardenedIndex::ONE])
+}
+
+pub fn xpub(seed: u8) -> XpubDerivable {
+    XpubDerivable::from_str(&format!("{}/<0;1>/*", account(seed).to_xpub_account())).unwrap()
+}
+
+pub fn beneficiary(amount: u64) -> Beneficiary {
+    Beneficiary::from_str(&format!("{amount}@tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx")).unwrap()
+}
+
+/// Median time past of the test chain, advancing by 10 minutes each block.
+pub fn median_time_past(height: u32) -> u32 { 1_600_000_000 + height * 600 }
+
+/// Wallet spending coins locked by its descriptor, which knows the chain up to `tip` and the hash
+/// lock `preimage`.
+pub struct Wallet<D> {
+    pub descriptor: D,
+    pub funding: UnsignedTx,
+    pub heights: Vec<Option<u32>>,
+    pub tip: Option<u32>,
+    pub preimage: Option<Vec<u8>>,
+}
+
+impl<D: Descriptor> Wallet<D> {
+    /// Wallet with a single coin of 100 000 sats.
+    pub fn with(descriptor: D) -> Self {
+        Wallet::with_coins(descriptor, &[(100_000, Some(COIN_HEIGHT))])
+    }
+
+    /// Wallet with coins of the given values mined at the given heights, all paying to the first
+    /// script pubkey of the descriptor.
+    pub fn with_coins(descriptor: D, coins: &[(u64, Option<u32>)]) -> Self {
+        let script_pubkey = descriptor
+            .derive(Keychain::OUTER, NormalIndex::ZERO)
+            .next()
+            .unwrap()
+            .to_script_pubkey();
+        let outputs = coins.iter().map(|(value, _)| TxOut::new(script_pubkey.clone(), *value));
+        let heights = coins.iter().map(|(_, height)| *height).collect();
+        Wallet::with_outputs(descriptor, outputs.collect(), heights)
+    }
+
+    /// Wallet with a coin of 100 000 sats for each script pubkey derived by the descriptor, like
+    /// the ones of a `combo()` descriptor.
+    pub fn with_each_script(descriptor: D) -> Self {
+        let outputs = descriptor
+            .derive(Keychain::OUTER, NormalIndex::ZERO)
+            .map(|script| TxOut::new(script.to_script_pubkey(), Sats(100_000)))
+            .collect::<Vec<_>>();
+        let heights = vec![Some(COIN_HEIGHT); outputs.len()];
+        Wallet::with_outputs(descriptor, outputs, heights)
+    }
+
+    fn with_outputs(descriptor: D, outputs: Vec<TxOut>, heights: Vec<Option<u32>>) -> Self {
+        let funding = UnsignedTx {
+            version: TxVer::V2,
+            inputs: VarIntArray::from_checked(vec![UnsignedTxIn {
+                prev_output: Outpoint::coinbase(),
+                sequence: SeqNo::from_consensus_u32(0xFFFFFFFF),
+            }]),
+            outputs: VarIntArray::from_checked(outputs),
+            lock_time: LockTime::ZERO,
+        };
+        Wallet {
+            descriptor,
+            funding,
+            heights,
+            tip: None,
+            preimage: None,
+        }
+    }
+
+    pub fn coin(&self, vout: u32) -> Outpoint { Outpoint::new(self.funding.txid(), vout) }
+
+    /// Constructs PSBT spending the first coin and paying 90 000 sats with a fee of 1000 sats.
+    pub fn construct(&mut self) -> Result<Psbt, ConstructionError> {
+        self.construct_with(&[0], TxParams::with(Sats(1000)))
+    }
+
+    /// Constructs PSBT spending the given coins and paying 90 000 sats.
+    pub fn construct_with(
+        &mut self,
+        vouts: &[u32],
+        params: TxParams,
+    ) -> Result<Psbt, ConstructionError> {
+        let coins = vouts.iter().map(|vout| self.coin(*vout)).collect::<Vec<_>>();
+        self.construct_psbt(coins, [beneficiary(90_000)], params).map(|(psbt, _)| psbt)
+    }
+
+    /// Checks that the transaction extracted from the finalized PSBT is valid under the standard
+    /// script rules.
+    pub fn verify(&self, psbt: &Psbt) {
+        let tx = psbt.extract().unwrap();
+        let prevouts = psbt
+            .inputs()
+            .map(|input| self.funding.outputs[input.previous_outpoint.vout.into_usize()].clone())
+            .collect::<Vec<_>>();
+        tx.verify_scripts(&prevouts, VerifyFlags::STANDARD).unwrap();
+    }
+}
+
+impl<D: Descriptor> PsbtConstructor for Wallet<D> {
+    type Key = XpubDerivable;
+    type Descr = D;
+
+    fn descriptor(&self) -> &Self::Descr { &self.descriptor }
+    fn prev_tx(&self, txid: Txid) -> Option<UnsignedTx> {
+        Some(self.funding.clone()).filter(|tx| tx.txid() == txid)
+    }
+    fn utxo(&self, outpoint: Outpoint) -> Option<(Utxo, ScriptPubkey)> {
+        let txout = self.funding.outputs.get(outpoint.vout.into_usize())?;
+        let utxo = Utxo {
+            outpoint,
+            value: txout.value,
+            terminal: Terminal::new(Keychain::OUTER, NormalIndex::ZERO),
+        };
+        Some((utxo, txout.script_pubkey.clone()))
+    }
+    fn utxos(&self) -> impl Iterator<Item = (Utxo, Option<u32>)> {
+        self.heights
+            .iter()
+            .enumerate()
+            .map(|(vout, height)| (self.utxo(self.coin(vout as u32)).unwrap().0, *height))
+    }
+    fn network(&self) -> Network { Network::Testnet3 }
+    fn next_derivation_index(&mut self, _: impl Into<Keychain>, _: bool) -> NormalIndex {
+        NormalIndex::ONE
+    }
+    fn preimage(&self, lock: &HashLock) -> Option<Vec<u8>> {
+        self.preimage.clone().filter(|preimage| lock.verify(preimage))
+    }
+    fn tip_height(&self) -> Option<u32> { self.tip }
+    fn median_time_past(&self, height: u32) -> Option<u32> {
+        Some(median_time_past(height)).filter(|_| Some(height) <= self.tip)
+    }
+}
+
+/// Signer holding account keys of some of the descriptor participants. Taproot inputs are signed
+/// either for the key path or for the script paths.
+#[derive(Copy, Clone)]
+pub struct KeySigner<'a> {
+    pub accounts: &'a [XprivAccount],
+    pub key_path: bool,
+}
+
+impl<'a> KeySigner<'a> {
+    pub fn new(accounts: &'a [XprivAccount]) -> Self {
+        KeySigner {
+            accounts,
+            key_path: false,
+        }
+    }
+
+    fn xpriv(&self, origin: Option<&KeyOrigin>) -> Option<Xpriv> {
+        let origin = origin?;
+        self.accounts.iter().find_map(|account| {
+            let path = account.origin().child_derivation(origin)?;
+            Some(account.xpriv().derive_priv(path))
+        })
+    }
+}
+
+impl Sign for KeySigner<'_> {
+    fn sign_ecdsa(
+        &self,
+        message: Sighash,
+        _: SighashType,
+        _: LegacyPk,
+        origin: Option<&KeyOrigin>,
+    ) -> Option<ecdsa::Signature> {
+        Some(self.xpriv(origin)?.to_private_ecdsa().sign_ecdsa(message.into()))
+    }
+    fn sign_bip340_key_only(
+        &self,
+        message: TapSighash,
+        _: Option<SighashType>,
+        _: InternalPk,
+        origin: Option<&KeyOrigin>,
+        merkle_root: Option<TapNodeHash>,
+    ) -> Option<bip340::Signature> {
+        let keypair = InternalKeypair::from(self.xpriv(origin)?.to_keypair_bip340());
+        let output_pair = keypair.to_output_keypair(merkle_root).0;
+        Some(SECP256K1.sign_schnorr_no_aux_rand(message.as_ref(), &output_pair))
+    }
+    fn sign_bip340_script_path(
+        &self,
+        message: TapSighash,
+        _: Option<SighashType>,
+        _: XOnlyPk,
+        origin: Option<&KeyOrigin>,
+    ) -> Option<bip340::Signature> {
+        let keypair = self.xpriv(origin)?.to_keypair_bip340();
+        Some(SECP256K1.sign_schnorr_no_aux_rand(message.as_ref(), &keypair))
+    }
+    fn should_sign_script_path(&self, _: usize, _: &TapMerklePath, _: TapLeafHash) -> bool {
+        !self.key_path
+    }
+    fn should_sign_key_path(&self, _: usize) -> bool { self.key_path }
+}
+
+impl Signer for KeySigner<'_> {
+    type Sign<'s>
+        = Self
+    where Self: 's;
+    fn approve(&self, _: &Psbt) -> Result<Self::Sign<'_>, Rejected> { Ok(*self) }
+}
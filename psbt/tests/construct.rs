@@ -0,0 +1,252 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::str::FromStr;
+
+use common::{beneficiary, xpub, Wallet};
+use derive::{Address, Sats, Vout, WeightUnits, XpubDerivable};
+use descriptors::{StdDescr, Wpkh};
+use psbt::{
+    Beneficiary, BranchAndBound, ConstructionError, FeeRate, LargestFirst, OldestFirst,
+    PsbtConstructor, SelectionError, TxParams,
+};
+
+/// Wallet with P2WPKH coins of the given values mined at the given heights.
+fn wallet(coins: &[(u64, Option<u32>)]) -> Wallet<Wpkh<XpubDerivable>> {
+    Wallet::with_coins(Wpkh::from(xpub(1)), coins)
+}
+
+#[test]
+fn construct_with_fee_rate() {
+    let mut wallet = wallet(&[(100_000, Some(1))]);
+    let rate = FeeRate::from_sat_per_vb(2).unwrap();
+    let (psbt, meta) = wallet
+        .construct_psbt([wallet.coin(0)], [beneficiary(50_000)], TxParams::with(rate))
+        .unwrap();
+    let change = meta.change.expect("change output");
+    assert_eq!(psbt.outputs().count(), 2);
+    assert_eq!(meta.weight, psbt.estimate_weight(&wallet.descriptor).into_u32());
+    assert_eq!(meta.fee, rate.fee(psbt.estimate_weight(&wallet.descriptor)));
+    assert_eq!(
+        psbt.output(change.vout.into_usize()).unwrap().value(),
+        Sats(100_000 - 50_000) - meta.fee
+    );
+}
+
+#[test]
+fn construct_with_fee_rate_no_change() {
+    let rate = FeeRate::from_sat_per_vb(2).unwrap();
+    let mut wallet = wallet(&[(100_000, Some(1))]);
+    let (psbt, _) = wallet
+        .construct_psbt([wallet.coin(0)], [beneficiary(50_000)], TxParams::with(rate))
+        .unwrap();
+    let fee_with_change = rate.fee(psbt.estimate_weight(&wallet.descriptor));
+
+    // The change would be above the dust limit only if it didn't pay for itself
+    let amount = 100_000 - fee_with_change.sats() - 200;
+    let (psbt, meta) = wallet
+        .construct_psbt([wallet.coin(0)], [beneficiary(amount)], TxParams::with(rate))
+        .unwrap();
+    assert_eq!(meta.change, None);
+    assert_eq!(psbt.outputs().count(), 1);
+    assert_eq!(meta.fee, Sats(fee_with_change.sats() + 200));
+    assert!(meta.fee > rate.fee(psbt.estimate_weight(&wallet.descriptor)));
+
+    let err = wallet
+        .construct_psbt([wallet.coin(0)], [beneficiary(99_950)], TxParams::with(rate))
+        .unwrap_err();
+    assert!(matches!(err, ConstructionError::NoFundsForFee { .. }));
+}
+
+#[test]
+fn construct_with_max() {
+    let mut wallet = wallet(&[(100_000, Some(1))]);
+    let rate = FeeRate::from_sat_per_vb(3).unwrap();
+    let beneficiary = Beneficiary::with_max(
+        Address::from_str("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").unwrap(),
+    );
+    let (psbt, meta) =
+        wallet.construct_psbt([wallet.coin(0)], [beneficiary], TxParams::with(rate)).unwrap();
+    assert_eq!(meta.change, None);
+    assert_eq!(meta.fee, rate.fee(psbt.estimate_weight(&wallet.descriptor)));
+    assert_eq!(psbt.output_sum(), Sats(100_000) - meta.fee);
+}
+
+#[test]
+fn construct_with_selection() {
+    let coins = [(30_000, Some(10)), (60_000, Some(20)), (100_000, None)];
+    let rate = FeeRate::from_sat_per_vb(2).unwrap();
+
+    let mut wallet = wallet(&coins);
+    let selection =
+        wallet.select_coins(&mut LargestFirst, &[beneficiary(50_000)], &TxParams::with(rate));
+    let selection = selection.unwrap();
+    assert_eq!(selection.value, Sats(100_000));
+    assert!(selection.change);
+
+    let (psbt, meta) = wallet
+        .construct_psbt_with(&mut OldestFirst, [beneficiary(50_000)], TxParams::with(rate))
+        .unwrap();
+    assert_eq!(psbt.input_sum(), Sats(90_000));
+    assert!(meta.change.is_some());
+    assert_eq!(meta.fee, rate.fee(psbt.estimate_weight(&wallet.descriptor)));
+
+    let err = wallet
+        .construct_psbt_with(&mut LargestFirst, [beneficiary(190_000)], TxParams::with(rate))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ConstructionError::CoinSelection(SelectionError::InsufficientFunds { .. })
+    ));
+}
+
+#[test]
+fn construct_with_bnb() {
+    let rate = FeeRate::from_sat_per_vb(2).unwrap();
+    let mut wallet = wallet(&[(30_000, Some(10)), (60_000, Some(20))]);
+    // Single input paying to a single output has 110 vbytes, so the 250 sats leave an excess
+    // too small for a change output
+    let amount = 60_000 - 250;
+    let selection = wallet
+        .select_coins(&mut BranchAndBound::default(), &[beneficiary(amount)], &TxParams::with(rate))
+        .unwrap();
+    assert_eq!(selection.value, Sats(60_000));
+    assert!(!selection.change);
+    let (psbt, meta) = wallet
+        .construct_psbt_with(
+            &mut BranchAndBound::default(),
+            [beneficiary(amount)],
+            TxParams::with(rate),
+        )
+        .unwrap();
+    assert_eq!(psbt.inputs().count(), 1);
+    assert_eq!(meta.change, None);
+    assert_eq!(meta.fee, Sats(250));
+    assert!(meta.fee > rate.fee(psbt.estimate_weight(&wallet.descriptor)));
+
+    let (psbt, meta) = wallet
+        .construct_psbt_with(
+            &mut BranchAndBound::default(),
+            [Beneficiary::with_max(beneficiary(0).address)],
+            TxParams::with(rate),
+        )
+        .unwrap();
+    assert_eq!(psbt.inputs().count(), 2);
+    assert_eq!(meta.change, None);
+}
+
+#[test]
+fn construct_cpfp() {
+    let mut wallet = wallet(&[(20_000, None), (30_000, None)]);
+    let parent = wallet.funding.txid();
+    let rate = FeeRate::from_sat_per_vb(10).unwrap();
+
+    // Parent of 200 vbytes paying 1 sat/vB
+    let (psbt, meta) = wallet
+        .construct_cpfp(parent, 200, Sats(200), [Vout::from_u32(1)], TxParams::with(rate))
+        .unwrap();
+    assert_eq!(psbt.inputs().count(), 1);
+    assert_eq!(psbt.outputs().count(), 1);
+    assert!(meta.change.is_some());
+    let package_weight = WeightUnits::no_discount(200) + psbt.estimate_weight(&wallet.descriptor);
+    assert_eq!(meta.fee + Sats(200), rate.fee(package_weight));
+    assert_eq!(psbt.output_sum(), Sats(30_000) - meta.fee);
+
+    // Parent already paying above the target fee rate
+    let (psbt, meta) = wallet
+        .construct_cpfp(parent, 200, Sats(4000), [Vout::from_u32(1)], TxParams::with(rate))
+        .unwrap();
+    assert_eq!(meta.fee, rate.fee(psbt.estimate_weight(&wallet.descriptor)));
+
+    let err = wallet
+        .construct_cpfp(parent, 200, Sats(0), [Vout::from_u32(2)], TxParams::with(rate))
+        .unwrap_err();
+    assert!(matches!(err, ConstructionError::UnknownInput(_)));
+    let err = wallet
+        .construct_cpfp(
+            parent,
+            200,
+            Sats(0),
+            [Vout::from_u32(0)],
+            TxParams::with(FeeRate::from_sat_per_vb(100).unwrap()),
+        )
+        .unwrap_err();
+    assert!(matches!(err, ConstructionError::NoFundsForFee { .. }));
+}
+
+/// Case: fee rates so high that the fee overflows can't be paid.
+#[test]
+fn construct_with_absurd_fee_rate() {
+    let mut wallet = wallet(&[(100_000, None)]);
+    let rate = FeeRate::from_sat_per_kvb(u64::MAX);
+    let err = wallet
+        .construct_psbt([wallet.coin(0)], [beneficiary(50_000)], TxParams::with(rate))
+        .unwrap_err();
+    assert!(matches!(err, ConstructionError::NoFundsForFee { .. }));
+    let err = wallet
+        .construct_psbt_with(
+            &mut BranchAndBound::default(),
+            [beneficiary(50_000)],
+            TxParams::with(rate),
+        )
+        .unwrap_err();
+    assert!(matches!(err, ConstructionError::CoinSelection(_)));
+    let parent = wallet.funding.txid();
+    let err = wallet
+        .construct_cpfp(parent, 200, Sats(200), [Vout::from_u32(0)], TxParams::with(rate))
+        .unwrap_err();
+    assert!(matches!(err, ConstructionError::NoFundsForFee { .. }));
+}
+
+/// Case: coins of a descriptor not defining its satisfaction, like `addr()`, can't be spent with
+/// a fee rate, since the weight of the signed transaction is unknown.
+#[test]
+fn construct_unknown_satisfaction() {
+    let descriptor =
+        StdDescr::from_str("addr(tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx)").unwrap();
+    let mut wallet = Wallet::with_coins(descriptor, &[(100_000, Some(1))]);
+    let rate = FeeRate::from_sat_per_vb(2).unwrap();
+
+    let err = wallet
+        .construct_psbt([wallet.coin(0)], [beneficiary(50_000)], TxParams::with(rate))
+        .unwrap_err();
+    assert!(matches!(err, ConstructionError::UnknownSatisfaction));
+    let err = wallet
+        .construct_psbt_with(&mut LargestFirst, [beneficiary(50_000)], TxParams::with(rate))
+        .unwrap_err();
+    assert!(matches!(err, ConstructionError::UnknownSatisfaction));
+    let parent = wallet.funding.txid();
+    let err = wallet
+        .construct_cpfp(parent, 200, Sats(200), [Vout::from_u32(0)], TxParams::with(rate))
+        .unwrap_err();
+    assert!(matches!(err, ConstructionError::UnknownSatisfaction));
+
+    // The fixed fee doesn't depend on the weight
+    let (_, meta) = wallet
+        .construct_psbt([wallet.coin(0)], [beneficiary(50_000)], TxParams::with(Sats(1000)))
+        .unwrap();
+    assert_eq!(meta.fee, Sats(1000));
+}
@@ -0,0 +1,108 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::str::FromStr;
+
+use common::{account, xpub, Wallet};
+use derive::secp256k1::{SecretKey, SECP256K1};
+use derive::{CompressedPk, Idx, NormalIndex};
+use descriptors::TrMusig;
+use psbt::{MusigSignError, Psbt};
+
+/// Secret key of the participant for the wallet coin, derived at `/0/0`.
+fn secret_key(seed: u8) -> SecretKey {
+    account(seed).xpriv().derive_priv([NormalIndex::ZERO, NormalIndex::ZERO]).to_private_ecdsa()
+}
+
+/// Wallet spending a single coin locked by a MuSig2 aggregated key.
+fn wallet(seeds: &[u8]) -> Wallet<TrMusig> {
+    let keys = seeds.iter().map(|seed| xpub(*seed).to_string()).collect::<Vec<_>>();
+    Wallet::with(TrMusig::from_str(&format!("tr(musig({}))", keys.join(","))).unwrap())
+}
+
+/// Case: three participants exchange nonces and partial signatures through the PSBT, which
+/// is then finalized with the aggregated signature valid for the taproot output key.
+#[test]
+fn key_path_spend() {
+    let seeds = [1u8, 2, 3];
+    let mut wallet = wallet(&seeds);
+    let mut psbt = wallet.construct().unwrap();
+    assert_eq!(psbt.inputs().next().unwrap().musig2_participants.len(), 1);
+
+    let sec_nonces = seeds
+        .iter()
+        .enumerate()
+        .map(|(no, seed)| {
+            let sk = secret_key(*seed);
+            let pk = CompressedPk::from(sk.public_key(SECP256K1));
+            psbt.musig_nonce_gen(0, pk, Some(sk), [no as u8; 32]).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    // Nonces and partial signatures survive the serialization round-trip.
+    let mut psbt = Psbt::deserialize(psbt.serialize(psbt.version)).unwrap();
+    assert_eq!(psbt.inputs().next().unwrap().musig2_pub_nonces.len(), 3);
+
+    for (seed, sec_nonce) in seeds.iter().zip(sec_nonces) {
+        psbt.musig_partial_sign(0, sec_nonce, secret_key(*seed)).unwrap();
+    }
+    let mut psbt = Psbt::deserialize(psbt.serialize(psbt.version)).unwrap();
+    assert_eq!(psbt.inputs().next().unwrap().musig2_partial_sigs.len(), 3);
+
+    psbt.musig_aggregate(0).unwrap();
+    assert_eq!(psbt.verify_signatures().unwrap(), vec![]);
+    assert_eq!(psbt.finalize(&wallet.descriptor), 1);
+    wallet.verify(&psbt);
+}
+
+/// Case: aggregation fails until every participant provides a valid partial signature.
+#[test]
+fn missing_or_invalid_partial_sig() {
+    let seeds = [1u8, 2];
+    let mut psbt = wallet(&seeds).construct().unwrap();
+
+    let pks = seeds
+        .iter()
+        .map(|seed| CompressedPk::from(secret_key(*seed).public_key(SECP256K1)))
+        .collect::<Vec<_>>();
+    let nonce1 = psbt.musig_nonce_gen(0, pks[0], None, [1u8; 32]).unwrap();
+    assert_eq!(
+        psbt.musig_partial_sign(0, nonce1, secret_key(1)).unwrap_err(),
+        MusigSignError::NoPubNonce(0, pks[1])
+    );
+
+    let nonce1 = psbt.musig_nonce_gen(0, pks[0], None, [1u8; 32]).unwrap();
+    psbt.musig_nonce_gen(0, pks[1], None, [2u8; 32]).unwrap();
+    let sig = psbt.musig_partial_sign(0, nonce1, secret_key(1)).unwrap();
+    assert_eq!(psbt.musig_aggregate(0).unwrap_err(), MusigSignError::NoPartialSig(0, pks[1]));
+
+    // A partial signature of one participant is not valid for another one.
+    let input = psbt.inputs_mut().next().unwrap();
+    let agg_pk = *input.musig2_participants.keys().next().unwrap();
+    input.musig2_partial_sigs.insert((pks[1], agg_pk, None), sig);
+    assert_eq!(psbt.musig_aggregate(0).unwrap_err(), MusigSignError::InvalidPartialSig(0, pks[1]));
+    assert!(psbt.inputs().next().unwrap().tap_key_sig.is_none());
+}
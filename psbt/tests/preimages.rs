@@ -0,0 +1,117 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::str::FromStr;
+
+use common::{account, xpub, KeySigner, Wallet};
+use descriptors::{Descriptor, HashLock, TrScript, WshScript};
+use psbt::PreimageMismatch;
+
+const PREIMAGE: [u8; 32] = [0x42; 32];
+
+/// Wallet knowing the preimage of the hash locks of its descriptor.
+fn with_preimage<D: Descriptor>(descriptor: D, preimage: Option<Vec<u8>>) -> Wallet<D> {
+    let mut wallet = Wallet::with(descriptor);
+    wallet.preimage = preimage;
+    wallet
+}
+
+/// Case: the receiver of an HTLC spends it by revealing the preimage, which the constructor adds to
+/// the PSBT input and the finalizer puts into the witness.
+#[test]
+fn wsh_htlc() {
+    let lock = HashLock::sha256(&PREIMAGE);
+    let descriptor = WshScript::from_str(&format!(
+        "wsh(andor(pk({}),{lock},and_v(v:pk({}),older(144))))",
+        xpub(1),
+        xpub(2)
+    ))
+    .unwrap();
+    assert_eq!(descriptor.hash_locks().collect::<Vec<_>>(), vec![&lock]);
+
+    let mut wallet = with_preimage(descriptor, Some(PREIMAGE.to_vec()));
+    let mut psbt = wallet.construct().unwrap();
+    let input = psbt.input(0).unwrap();
+    assert_eq!(input.sha256.len(), 1);
+    assert_eq!(input.preimages().get(&lock), Some(&PREIMAGE.to_vec()));
+
+    assert_eq!(psbt.sign(&KeySigner::new(&[account(1)])).unwrap(), 1);
+    assert_eq!(psbt.finalize(&wallet.descriptor), 1);
+    let witness = psbt.input(0).unwrap().final_witness.clone().unwrap();
+    let stack = witness.iter().map(|item| item.to_vec()).collect::<Vec<_>>();
+    // The preimage is checked after the signature, thus is placed below it
+    assert_eq!(stack[0], PREIMAGE);
+    assert!(psbt.input(0).unwrap().sha256.is_empty());
+    wallet.verify(&psbt);
+}
+
+/// Case: a PSBT lacking the preimage can't be finalized until the preimage is attached to it.
+#[test]
+fn wsh_htlc_late_preimage() {
+    let lock = HashLock::hash160(&PREIMAGE);
+    let descriptor = WshScript::from_str(&format!("wsh(and_v(v:pk({}),{lock}))", xpub(1))).unwrap();
+    let mut wallet = with_preimage(descriptor, None);
+    let mut psbt = wallet.construct().unwrap();
+    assert!(psbt.input(0).unwrap().preimages().is_empty());
+
+    assert_eq!(psbt.sign(&KeySigner::new(&[account(1)])).unwrap(), 1);
+    assert_eq!(psbt.finalize(&wallet.descriptor), 0);
+
+    let input = psbt.input_mut(0).unwrap();
+    assert_eq!(input.add_preimage(lock, [0x43; 32]), Err(PreimageMismatch(lock)));
+    input.add_preimage(lock, PREIMAGE).unwrap();
+    assert_eq!(input.hash160.len(), 1);
+    assert_eq!(psbt.finalize(&wallet.descriptor), 1);
+    wallet.verify(&psbt);
+
+    // The wallet must not add preimages which don't match the hash locks
+    let mut wallet = with_preimage(wallet.descriptor, Some(vec![0x43; 32]));
+    assert!(wallet.construct().unwrap().input(0).unwrap().preimages().is_empty());
+}
+
+/// Case: HTLC in a taproot script path, where the finalizer must use the leaf committed by the
+/// control block.
+#[test]
+fn tr_htlc() {
+    let lock = HashLock::hash256(&PREIMAGE);
+    let descriptor = TrScript::from_str(&format!(
+        "tr({},{{and_v(v:pk({}),{lock}),and_v(v:pk({}),older(144))}})",
+        xpub(3),
+        xpub(1),
+        xpub(2)
+    ))
+    .unwrap();
+    assert_eq!(descriptor.hash_locks().collect::<Vec<_>>(), vec![&lock]);
+
+    let mut wallet = with_preimage(descriptor, Some(PREIMAGE.to_vec()));
+    let mut psbt = wallet.construct().unwrap();
+    assert_eq!(psbt.sign(&KeySigner::new(&[account(1)])).unwrap(), 1);
+    assert_eq!(psbt.finalize(&wallet.descriptor), 1);
+    let witness = psbt.input(0).unwrap().final_witness.clone().unwrap();
+    assert_eq!(witness.len(), 4);
+    assert_eq!(witness.iter().next().unwrap().to_vec(), PREIMAGE);
+    wallet.verify(&psbt);
+}
@@ -0,0 +1,185 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "serde")]
+
+use std::str::FromStr;
+
+use derive::{CompressedPk, Network, XpubDerivable};
+use descriptors::Wpkh;
+use psbt::{Psbt, PsbtReport};
+use serde_json::json;
+
+fn json_roundtrip(s: &str) -> Psbt {
+    let psbt = Psbt::from_str(s).unwrap();
+    let json = serde_json::to_string(&psbt).unwrap();
+    let restored = serde_json::from_str::<Psbt>(&json).unwrap();
+    assert_eq!(restored, psbt);
+    assert_eq!(restored.to_string(), psbt.to_string());
+    restored
+}
+
+#[test]
+fn v0() {
+    for s in [
+        include_str!("valid.v0/no_inputs.psbt"),
+        include_str!("valid.v0/pkh_sh_wpkh.psbt"),
+        include_str!("valid.v0/pkh_signed.psbt"),
+        include_str!("valid.v0/sh_wsh.psbt"),
+        include_str!("valid.v0/wsh.psbt"),
+        include_str!("valid.v0/xpubs.psbt"),
+        include_str!("valid.v0/unknown_keys.psbt"),
+    ] {
+        json_roundtrip(s);
+    }
+}
+
+#[test]
+fn v2() {
+    for s in [
+        include_str!("valid.v2/base.psbt"),
+        include_str!("valid.v2/locks.psbt"),
+        include_str!("valid.v2/in_modifiable.psbt"),
+        include_str!("valid.v2/out_modifiable.psbt"),
+        include_str!("valid.v2/sighash_single.psbt"),
+        include_str!("valid.v2/undefined_flag.psbt"),
+        include_str!("valid.v2/all.psbt"),
+    ] {
+        json_roundtrip(s);
+    }
+}
+
+#[test]
+fn taproot() {
+    for s in [
+        include_str!("valid.tr/keyonly_in.psbt"),
+        include_str!("valid.tr/keyonly_signed.psbt"),
+        include_str!("valid.tr/script_in.psbt"),
+        include_str!("valid.tr/script_out.psbt"),
+        include_str!("valid.tr/script_signed.psbt"),
+    ] {
+        json_roundtrip(s);
+    }
+}
+
+/// Case: indexes of inputs and outputs are restored from their positions
+#[test]
+fn indexes() {
+    let psbt = json_roundtrip(include_str!("valid.v0/pkh_sh_wpkh.psbt"));
+    for (no, input) in psbt.inputs().enumerate() {
+        assert_eq!(input.index(), no);
+    }
+    for (no, output) in psbt.outputs().enumerate() {
+        assert_eq!(output.index(), no);
+    }
+}
+
+/// Case: representation of the fields follows the documented schema
+#[test]
+fn schema() {
+    let psbt = Psbt::from_str(include_str!("valid.v2/all.psbt")).unwrap();
+    let json = serde_json::to_value(&psbt).unwrap();
+    assert_eq!(json["version"], json!("v2"));
+    assert_eq!(json["txVersion"], json!(2));
+    assert_eq!(
+        json["txModifiable"],
+        json!({
+            "inputsModifiable": true,
+            "outputsModifiable": true,
+            "sighashSingle": true,
+            "unknown": 0
+        })
+    );
+    let input = &json["inputs"][0];
+    assert_eq!(
+        input["previousOutpoint"],
+        json!("c85f81844094f9f0eec1e41f8d63e0a99e9f73dc725d7319871c9c4121d90a0b:0")
+    );
+    assert_eq!(input["requiredTimeLock"], json!(1657048460));
+    assert_eq!(input["requiredHeightLock"], json!(10000));
+    assert_eq!(input["finalWitness"], json!(null));
+    let output = &json["outputs"][0];
+    assert_eq!(output["amount"], json!(800000000));
+    let key = "02d601f84846a6755f776be00e3d9de8fb10acc935fb83c45fb0162d4cad5ab792";
+    let derivation = &output["bip32Derivation"];
+    assert_eq!(derivation.as_object().unwrap().len(), 1);
+    assert_eq!(derivation[key]["masterFp"], json!("f69d873e"));
+
+    let mut psbt = psbt;
+    let pk = CompressedPk::from_str(key).unwrap();
+    psbt.output_mut(0).unwrap().musig2_participants.insert(pk, vec![pk, pk]);
+    let json = serde_json::to_value(&psbt).unwrap();
+    assert_eq!(json["outputs"][0]["musig2Participants"], json!({ key: [key, key] }));
+    assert_eq!(serde_json::from_value::<Psbt>(json).unwrap(), psbt);
+
+    let psbt = Psbt::from_str(include_str!("valid.v0/unknown_keys.psbt")).unwrap();
+    let json = serde_json::to_value(&psbt).unwrap();
+    assert_eq!(
+        json["inputs"][0]["unknown"],
+        json!({ "240": { "010203040506070809": "0102030405060708090a0b0c0d0e0f" } })
+    );
+}
+
+/// Case: maps can't contain repeated keys, either as arrays of entries or as objects with
+/// differently encoded keys
+#[test]
+fn repeated_keys() {
+    let psbt = Psbt::from_str(include_str!("valid.tr/script_in.psbt")).unwrap();
+    let mut json = serde_json::to_value(&psbt).unwrap();
+    let leaf_scripts = json["inputs"][0]["tapLeafScript"].as_array_mut().unwrap();
+    leaf_scripts.push(leaf_scripts[0].clone());
+    assert!(serde_json::from_value::<Psbt>(json).is_err());
+
+    let psbt = Psbt::from_str(include_str!("valid.v2/all.psbt")).unwrap();
+    let mut json = serde_json::to_value(&psbt).unwrap();
+    let derivation = json["outputs"][0]["bip32Derivation"].as_object_mut().unwrap();
+    let (key, value) = derivation.iter().next().map(|(k, v)| (k.clone(), v.clone())).unwrap();
+    derivation.insert(key.to_uppercase(), value);
+    assert!(serde_json::from_value::<Psbt>(json.clone()).is_err());
+    json["outputs"][0]["bip32Derivation"].as_object_mut().unwrap().remove(&key);
+    assert_eq!(serde_json::from_value::<Psbt>(json).unwrap(), psbt);
+}
+
+#[test]
+fn report() {
+    let psbt = Psbt::from_str(include_str!("valid.v0/wsh.psbt")).unwrap();
+    let descriptor = Wpkh::from(XpubDerivable::from_str("[643a7adc/84h/1h/0h]tpubDCNiWHaiSkgnQjuhsg9kjwaUzaxQjUcmhagvYzqQ3TYJTgFGJstVaqnu4yhtFktBhCVFmBNLQ5sN53qKzZbMksm3XEyGJsEhQPfVZdWmTE2/<0;1>/*").unwrap());
+    let report = psbt.analyze(&descriptor, Network::Testnet3);
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["fee"], json!(10000));
+    // The wallet descriptor doesn't tell how the P2WSH input is satisfied
+    assert_eq!(json["weight"], json!(330));
+    assert_eq!(json["inputs"][0]["class"], json!("p2wsh"));
+    assert_eq!(json["inputs"][0]["isMine"], json!(true));
+    assert_eq!(json["inputs"][0]["status"], json!("incomplete"));
+    assert_eq!(json["inputs"][0]["missingSigs"].as_array().unwrap().len(), 2);
+    assert_eq!(json["outputs"][0]["address"], json!("tb1q0vaqp07uznf809wzkaysr5ya5mh3xdtefjljn2"));
+    assert_eq!(json["outputs"][0]["isChange"], json!(false));
+    assert_eq!(serde_json::from_value::<PsbtReport>(json).unwrap(), report);
+
+    let display = report.to_string();
+    assert!(display.contains("p2wsh 500000000 sats, mine, incomplete"));
+    assert!(display.contains("Fee: 10000 sats (120.481 sat/vB, weight 330 WU)"));
+}
@@ -0,0 +1,53 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use common::{account, xpub, KeySigner, Wallet};
+use derive::secp256k1::{Message, SECP256K1};
+use derive::{ScriptPubkey, SighashCache, SighashType, Tx};
+use descriptors::ShMulti;
+
+/// Case: signatures for a P2SH input commit to the redeem script, which is the script code of
+/// the spent output, and not to the P2SH script pubkey.
+#[test]
+fn sh_multi() {
+    let mut wallet = Wallet::with(ShMulti::new_checked(2, [xpub(1), xpub(2)]));
+    let mut psbt = wallet.construct().unwrap();
+    assert_eq!(psbt.sign(&KeySigner::new(&[account(1)])).unwrap(), 1);
+    assert_eq!(psbt.sign(&KeySigner::new(&[account(2)])).unwrap(), 1);
+
+    let input = psbt.inputs().next().unwrap();
+    let redeem_script = input.redeem_script.clone().unwrap();
+    let tx = Tx::from(psbt.to_unsigned_tx().unwrap());
+    let sig_hasher = SighashCache::new(tx, vec![input.prev_txout().clone()]).unwrap();
+    let sighash = sig_hasher
+        .legacy_sighash(0, &ScriptPubkey::from_checked(redeem_script.to_vec()), SighashType::all())
+        .unwrap();
+    let msg = Message::from(sighash);
+    assert_eq!(input.partial_sigs.len(), 2);
+    for (pk, sig) in &input.partial_sigs {
+        SECP256K1.verify_ecdsa(&msg, &sig.sig, &pk.pubkey).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::str::FromStr;
+
+use common::{account, xpub, KeySigner, Wallet};
+use derive::Sats;
+use descriptors::{Combo, ShWpkh};
+use psbt::TxParams;
+
+/// Case: spending P2SH-wrapped P2WPKH output, which is signed with the segwit v0 sighash and
+/// finalized with both the script sig and the witness.
+#[test]
+fn sh_wpkh() {
+    let descriptor = ShWpkh::from_str(&format!("sh(wpkh({}))", xpub(1))).unwrap();
+    let mut wallet = Wallet::with(descriptor);
+    let mut psbt = wallet.construct().unwrap();
+    assert!(psbt.input(0).unwrap().redeem_script.is_some());
+
+    assert_eq!(psbt.sign(&KeySigner::new(&[account(1)])).unwrap(), 1);
+    assert_eq!(psbt.finalize(&wallet.descriptor), 1);
+    let input = psbt.input(0).unwrap();
+    assert!(input.final_script_sig.is_some());
+    assert_eq!(input.final_witness.as_ref().unwrap().len(), 2);
+    wallet.verify(&psbt);
+}
+
+/// Case: spending all four outputs of a `combo()` descriptor in the same transaction, each
+/// finalized according to the class of its script pubkey.
+#[test]
+fn combo() {
+    let descriptor = Combo::from_str(&format!("combo({})", xpub(1))).unwrap();
+    let mut wallet = Wallet::with_each_script(descriptor);
+    assert_eq!(wallet.funding.outputs.len(), 4);
+    let vouts = [0, 1, 2, 3];
+    let mut psbt = wallet.construct_with(&vouts, TxParams::with(Sats(1000))).unwrap();
+
+    assert_eq!(psbt.sign(&KeySigner::new(&[account(1)])).unwrap(), 4);
+    assert_eq!(psbt.finalize(&wallet.descriptor), 4);
+    let finalized = psbt
+        .inputs()
+        .map(|input| {
+            let vout = input.previous_outpoint.vout.into_u32();
+            let sig_script = input.final_script_sig.as_ref().unwrap();
+            (vout, sig_script.len(), input.final_witness.as_ref().map(|w| w.len()))
+        })
+        .collect::<Vec<_>>();
+    for (vout, sig_script_len, witness_len) in finalized {
+        match vout {
+            // P2PK: signature only
+            0 => assert!(sig_script_len <= 73 && witness_len.is_none()),
+            // P2PKH: signature and the public key
+            1 => assert!(sig_script_len > 100 && witness_len.is_none()),
+            // P2WPKH: empty script sig and a witness
+            2 => assert_eq!((sig_script_len, witness_len), (0, Some(2))),
+            // P2SH-P2WPKH: the redeem script and a witness
+            3 => assert_eq!((sig_script_len, witness_len), (23, Some(2))),
+            _ => unreachable!(),
+        }
+    }
+    wallet.verify(&psbt);
+}
@@ -0,0 +1,180 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::str::FromStr;
+
+use common::{account, median_time_past, xpub, KeySigner, Wallet, COIN_HEIGHT};
+use derive::{LockHeight, LockTime, LockTimestamp, Sats, SeqNo};
+use descriptors::{Descriptor, Timelocks, TrScript, WshScript};
+use psbt::{ConstructionError, Psbt, TxParams};
+
+/// Constructs PSBT with a wallet which knows the chain up to `tip`.
+fn construct_at<D: Descriptor>(
+    wallet: &mut Wallet<D>,
+    tip: Option<u32>,
+) -> Result<Psbt, ConstructionError> {
+    construct_with(wallet, tip, TxParams::with(Sats(1000)))
+}
+
+fn construct_with<D: Descriptor>(
+    wallet: &mut Wallet<D>,
+    tip: Option<u32>,
+    params: TxParams,
+) -> Result<Psbt, ConstructionError> {
+    wallet.tip = tip;
+    wallet.construct_with(&[0], params)
+}
+
+/// Signs the PSBT with the account key, finalizes it and checks that the extracted transaction is
+/// valid under the standard script rules, including the timelock checks.
+fn spend<D: Descriptor>(wallet: &Wallet<D>, psbt: &mut Psbt, seed: u8) {
+    assert_eq!(psbt.sign(&KeySigner::new(&[account(seed)])).unwrap(), 1);
+    assert_eq!(psbt.finalize(&wallet.descriptor), 1);
+    wallet.verify(psbt);
+}
+
+/// Case: the recovery path of a wallet becomes available once the coin is 10 blocks deep; before
+/// that the constructor doesn't enable it, keeping the primary key path usable.
+#[test]
+fn wsh_relative_height() {
+    let descriptor = WshScript::from_str(&format!(
+        "wsh(or_d(pk({}),and_v(v:pk({}),older(10))))",
+        xpub(1),
+        xpub(2)
+    ))
+    .unwrap();
+    assert_eq!(descriptor.timelocks(), vec![Timelocks::default(), Timelocks::older(10)]);
+    let mut wallet = Wallet::with(descriptor);
+
+    for tip in [None, Some(COIN_HEIGHT + 8)] {
+        let psbt = construct_at(&mut wallet, tip).unwrap();
+        let input = psbt.input(0).unwrap();
+        assert_eq!(input.sequence_number, Some(SeqNo::ZERO));
+        assert_eq!(psbt.lock_time().unwrap(), LockTime::ZERO);
+    }
+
+    // The coin can be mined in the block following the tip, which is 10 blocks after the coin
+    let mut psbt = construct_at(&mut wallet, Some(COIN_HEIGHT + 9)).unwrap();
+    let input = psbt.input(0).unwrap();
+    assert_eq!(input.sequence_number, Some(SeqNo::from_height(10)));
+    assert_eq!(input.required_height_lock, None);
+    spend(&wallet, &mut psbt, 2);
+}
+
+/// Case: a relative timelock measured in 512-second intervals since the median time past of the
+/// block preceding the coin.
+#[test]
+fn wsh_relative_time() {
+    let seq_no = SeqNo::from_intervals(2);
+    let descriptor = WshScript::from_str(&format!(
+        "wsh(and_v(v:pk({}),older({})))",
+        xpub(1),
+        seq_no.to_consensus_u32()
+    ))
+    .unwrap();
+    let mut wallet = Wallet::with(descriptor);
+
+    let coin = wallet.coin(0);
+    for tip in [None, Some(COIN_HEIGHT)] {
+        assert!(
+            matches!(construct_at(&mut wallet, tip), Err(ConstructionError::Timelocked(c)) if c == coin)
+        );
+    }
+
+    let mut psbt = construct_at(&mut wallet, Some(COIN_HEIGHT + 1)).unwrap();
+    assert_eq!(psbt.input(0).unwrap().sequence_number, Some(seq_no));
+    spend(&wallet, &mut psbt, 1);
+}
+
+/// Case: a coin locked until some block height can't be spent earlier, and once it can, the input
+/// requires the matching transaction lock time.
+#[test]
+fn wsh_absolute_height() {
+    let descriptor =
+        WshScript::from_str(&format!("wsh(and_v(v:pk({}),after(300)))", xpub(1))).unwrap();
+    let mut wallet = Wallet::with(descriptor);
+
+    let coin = wallet.coin(0);
+    for tip in [None, Some(299)] {
+        assert!(
+            matches!(construct_at(&mut wallet, tip), Err(ConstructionError::Timelocked(c)) if c == coin)
+        );
+    }
+
+    // The sequence number must not disable the lock time check
+    let mut params = TxParams::with(Sats(1000));
+    params.seq_no = SeqNo::from_consensus_u32(u32::MAX);
+    let mut psbt = construct_with(&mut wallet, Some(300), params).unwrap();
+    let input = psbt.input(0).unwrap();
+    assert_eq!(input.sequence_number, Some(SeqNo::from_consensus_u32(u32::MAX - 1)));
+    assert_eq!(input.required_height_lock, Some(LockHeight::try_from(300).unwrap()));
+    assert_eq!(psbt.lock_time().unwrap(), LockTime::from_consensus_u32(300));
+    spend(&wallet, &mut psbt, 1);
+}
+
+/// Case: taproot key path is always available, while the script path locked until some time gets
+/// enabled once the median time past exceeds it.
+#[test]
+fn tr_absolute_time() {
+    let lock_time = median_time_past(150);
+    let descriptor =
+        TrScript::from_str(&format!("tr({},and_v(v:pk({}),after({lock_time})))", xpub(3), xpub(1)))
+            .unwrap();
+    assert_eq!(descriptor.timelocks(), vec![Timelocks::default(), Timelocks::after(lock_time)]);
+    let mut wallet = Wallet::with(descriptor);
+
+    for tip in [None, Some(150)] {
+        let psbt = construct_at(&mut wallet, tip).unwrap();
+        assert_eq!(psbt.input(0).unwrap().required_time_lock, None);
+        assert_eq!(psbt.lock_time().unwrap(), LockTime::ZERO);
+    }
+
+    let psbt = construct_at(&mut wallet, Some(151)).unwrap();
+    assert_eq!(
+        psbt.input(0).unwrap().required_time_lock,
+        Some(LockTimestamp::try_from(lock_time).unwrap())
+    );
+    assert_eq!(psbt.lock_time().unwrap(), LockTime::from_consensus_u32(lock_time));
+}
+
+/// Case: the lock time requested by the user must be reached for the transaction to be mined.
+#[test]
+fn non_final_lock_time() {
+    let descriptor = WshScript::from_str(&format!("wsh(pk({}))", xpub(1))).unwrap();
+    let mut wallet = Wallet::with(descriptor);
+
+    let mut params = TxParams::with(Sats(1000));
+    params.lock_time = Some(LockTime::from_consensus_u32(200));
+    assert!(matches!(
+        construct_with(&mut wallet, Some(199), params),
+        Err(ConstructionError::NonFinal(200))
+    ));
+    // Without the known chain tip the lock time can't be checked
+    construct_with(&mut wallet, None, params).unwrap();
+    let mut psbt = construct_with(&mut wallet, Some(200), params).unwrap();
+    assert_eq!(psbt.lock_time().unwrap(), LockTime::from_consensus_u32(200));
+    spend(&wallet, &mut psbt, 1);
+}
@@ -0,0 +1,175 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod common;
+
+use std::str::FromStr;
+
+use common::{account, xpub, KeySigner, Wallet};
+use derive::{VarInt, Weight, XpubDerivable};
+use descriptors::{Descriptor, SpendingPath, StdDescr};
+
+/// Replaces key placeholders `@0`, `@1` etc. with xpubs of the accounts with the same seed.
+fn descriptor(template: &str) -> StdDescr {
+    let mut descr = template.to_owned();
+    for seed in (0..10u8).rev() {
+        descr = descr.replace(&format!("@{seed}"), &xpub(seed).to_string());
+    }
+    StdDescr::from_str(&descr).unwrap()
+}
+
+/// Spends the descriptor output signing with the accounts of the given seeds, and checks that the
+/// weight of the finalized input satisfaction matches the estimate for the given spending path.
+///
+/// Returns the actual satisfaction weight.
+fn check(template: &str, signers: &[u8], path: SpendingPath) -> u32 {
+    let mut wallet = Wallet::with(descriptor(template));
+    let mut psbt = wallet.construct().unwrap();
+    let accounts = signers.iter().copied().map(account).collect::<Vec<_>>();
+    let signer = KeySigner {
+        accounts: &accounts,
+        key_path: path == SpendingPath::KeyPath,
+    };
+    assert!(psbt.sign(&signer).unwrap() > 0, "{template}: no signatures");
+    // ECDSA signatures with small R or S values are shorter than the expected ones. All the
+    // signatures must be used by the satisfaction.
+    let missing_bytes = psbt
+        .input(0)
+        .unwrap()
+        .partial_sigs
+        .values()
+        .map(|sig| 72 - sig.to_vec().len() as u32)
+        .sum::<u32>();
+    assert_eq!(psbt.finalize(&wallet.descriptor), 1, "{template}: not finalized");
+
+    wallet.verify(&psbt);
+
+    let input = psbt.input(0).unwrap();
+    // Outpoint and sequence number
+    let actual = input.weight_units().into_u32() - 40 * 4;
+    // Weight which the satisfaction would have with signatures of the expected length
+    let padded = match &input.final_script_sig {
+        Some(sig_script) if input.final_witness.is_none() => {
+            let len = sig_script.len() + missing_bytes as usize;
+            (VarInt::with(len).len() + len) as u32 * 4
+        }
+        _ => actual + missing_bytes,
+    };
+
+    let weights = wallet.descriptor.satisfaction_weights();
+    let weight = weights.iter().find(|w| w.path == path).unwrap();
+    assert!(actual <= weight.max.into_u32(), "{template}: {actual} exceeds {}", weight.max);
+    assert_eq!(padded, weight.expected.into_u32(), "{template}: wrong estimate");
+    assert_eq!(wallet.descriptor.max_satisfaction_weight(), weights.iter().map(|w| w.max).max());
+    actual
+}
+
+#[test]
+fn single_key() {
+    check("pkh(@1)", &[1], SpendingPath::Script);
+    check("wpkh(@1)", &[1], SpendingPath::Script);
+    check("sh(wpkh(@1))", &[1], SpendingPath::Script);
+    // The largest satisfaction of combo outputs is the one of the P2PKH output
+    assert_eq!(
+        descriptor("combo(@1)").satisfaction_weights(),
+        descriptor("pkh(@1)").satisfaction_weights()
+    );
+}
+
+#[test]
+fn multisig() {
+    check("sh(multi(2,@1,@2,@3))", &[1, 3], SpendingPath::Script);
+    check("sh(sortedmulti(2,@1,@2,@3))", &[2, 3], SpendingPath::Script);
+    check("wsh(multi(2,@1,@2,@3))", &[1, 2], SpendingPath::Script);
+    check("wsh(sortedmulti(2,@1,@2,@3))", &[1, 3], SpendingPath::Script);
+    check("sh(wsh(multi(2,@1,@2,@3)))", &[2, 3], SpendingPath::Script);
+    check("sh(wsh(sortedmulti(2,@1,@2,@3)))", &[1, 2], SpendingPath::Script);
+}
+
+/// Case: the weight of a non-finalized PSBT is estimated from the descriptor, so a k-of-n
+/// multisig input accounts for k signatures only, and the estimate bounds the weight of the
+/// signed transaction.
+#[test]
+fn psbt_estimate() {
+    let mut wallet = Wallet::with(descriptor("sh(multi(2,@1,@2,@3))"));
+    let mut psbt = wallet.construct().unwrap();
+    let max = wallet.descriptor.max_satisfaction_weight().unwrap();
+    let input = psbt.input(0).unwrap();
+    assert_eq!(input.estimate_weight(&wallet.descriptor).into_u32(), 40 * 4 + max.into_u32());
+    let estimate = psbt.estimate_weight(&wallet.descriptor);
+
+    let accounts = [account(1), account(3)];
+    psbt.sign(&KeySigner::new(&accounts)).unwrap();
+    assert_eq!(psbt.finalize(&wallet.descriptor), 1);
+    let actual = psbt.extract().unwrap().weight_units();
+    assert_eq!(psbt.estimate_weight(&wallet.descriptor), actual);
+    // Each of the two signatures may be up to 2 bytes shorter than the maximal one
+    assert!(actual <= estimate && estimate.into_u32() - actual.into_u32() <= 2 * 2 * 4);
+}
+
+#[test]
+fn miniscript() {
+    check("wsh(thresh(2,pk(@1),s:pk(@2),s:pk(@3)))", &[1, 2], SpendingPath::Script);
+    check("sh(wsh(and_v(v:pk(@1),pkh(@2))))", &[1, 2], SpendingPath::Script);
+    check("sh(or_d(pk(@1),pkh(@2)))", &[1], SpendingPath::Script);
+
+    // The expected satisfaction uses the smaller branch, while the larger one still fits the
+    // maximal weight
+    let descr = "wsh(or_i(pk(@1),and_v(v:pk(@2),pk(@3))))";
+    let small = check(descr, &[1], SpendingPath::Script);
+    let mut wallet = Wallet::with(descriptor(descr));
+    let mut psbt = wallet.construct().unwrap();
+    let accounts = [account(2), account(3)];
+    psbt.sign(&KeySigner::new(&accounts)).unwrap();
+    assert_eq!(psbt.finalize(&wallet.descriptor), 1);
+    let large = psbt.input(0).unwrap().weight_units().into_u32() - 40 * 4;
+    let weight = wallet.descriptor.max_satisfaction_weight().unwrap().into_u32();
+    assert!(small < large && large <= weight);
+}
+
+#[test]
+fn taproot() {
+    check("tr(@1)", &[1], SpendingPath::KeyPath);
+
+    let descr = "tr(@1,{pk(@2),and_v(v:pk(@3),pk(@4))})";
+    check(descr, &[1], SpendingPath::KeyPath);
+    check(descr, &[2], SpendingPath::ScriptPath(0));
+    check(descr, &[3, 4], SpendingPath::ScriptPath(1));
+
+    let paths = descriptor(descr).satisfaction_weights();
+    assert_eq!(paths.iter().map(|w| w.path).collect::<Vec<_>>(), vec![
+        SpendingPath::KeyPath,
+        SpendingPath::ScriptPath(0),
+        SpendingPath::ScriptPath(1)
+    ]);
+}
+
+#[test]
+fn unknown_satisfaction() {
+    let descr =
+        StdDescr::<XpubDerivable>::from_str("addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)")
+            .unwrap();
+    assert!(descr.satisfaction_weights().is_empty());
+    assert_eq!(descr.max_satisfaction_weight(), None);
+}
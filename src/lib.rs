@@ -22,6 +22,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[macro_use]
+extern crate amplify;
+
 #[cfg(feature = "client-side-validation")]
 pub use ::bp::{dbc, seals};
 pub use bc::{secp256k1, *};
@@ -32,5 +35,8 @@ pub use psbt::{
     Unmodifiable, UnsignedTx, UnsignedTxIn,
 };
 
+pub mod rbf;
 #[cfg(feature = "signers")]
 pub mod signers;
+
+pub use rbf::{Rbf, RbfConstructor, RbfError, SeqNoClass, SeqNoExt};
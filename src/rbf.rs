@@ -22,24 +22,63 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp::Reverse;
+use std::fmt::{self, Display, Formatter};
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use bc::{Sats, SeqNo, Tx, Txid, Vout, SEQ_NO_CSV_DISABLE_MASK, SEQ_NO_CSV_TYPE_MASK};
+use derive::{Derive, Idx, NormalIndex, Terminal, VBytes};
+use descriptors::Descriptor;
+use psbt::{
+    ChangeInfo, ConstructionError, Fee, FeeRate, LockTimeConflict, Psbt, PsbtConstructor, PsbtMeta,
+    PsbtVer, TxParams, UnsignedTx,
+};
+
 pub const SEQ_NO_MAX_VALUE: u32 = 0xFFFFFFFF;
 pub const SEQ_NO_SUBMAX_VALUE: u32 = 0xFFFFFFFE;
 
-pub trait SeqNoExt {
+pub trait SeqNoExt: Sized {
+    /// Creates `nSeq` value which is not encumbered by either RBF not relative
+    /// time locks.
+    ///
+    /// # Arguments
+    /// - `max` defines whether `nSeq` should be set to the `0xFFFFFFFF` (`true`) or `0xFFFFFFFe`.
+    fn unencumbered(max: bool) -> Self;
+
+    /// Creates `nSeq` in replace-by-fee mode with the specified order number.
+    fn from_rbf(order: u16) -> Self;
+
+    /// Creates `nSeq` in replace-by-fee mode with value 0xFFFFFFFD.
+    ///
+    /// This value is the value supported by the BitBox software.
+    fn rbf() -> Self;
+
     /// Classifies type of `nSeq` value (see [`SeqNoClass`]).
-    #[inline]
-    fn classify(self) -> SeqNoClass;
+    fn classify(&self) -> SeqNoClass;
 
     /// Checks if `nSeq` value opts-in for replace-by-fee (also always true for
     /// relative time locks).
-    #[inline]
-    fn is_rbf(self) -> bool;
+    fn is_rbf(&self) -> bool;
 }
 
 impl SeqNoExt for SeqNo {
     #[inline]
-    fn classify(self) -> SeqNoClass {
-        match self.0 {
+    fn unencumbered(max: bool) -> SeqNo {
+        SeqNo::from_consensus_u32(if max { SEQ_NO_MAX_VALUE } else { SEQ_NO_SUBMAX_VALUE })
+    }
+
+    #[inline]
+    fn from_rbf(order: u16) -> SeqNo {
+        SeqNo::from_consensus_u32(order as u32 | SEQ_NO_CSV_DISABLE_MASK)
+    }
+
+    #[inline]
+    fn rbf() -> SeqNo { SeqNo::from_consensus_u32(SEQ_NO_SUBMAX_VALUE - 1) }
+
+    #[inline]
+    fn classify(&self) -> SeqNoClass {
+        match self.to_consensus_u32() {
             SEQ_NO_MAX_VALUE | SEQ_NO_SUBMAX_VALUE => SeqNoClass::Unencumbered,
             no if no & SEQ_NO_CSV_DISABLE_MASK != 0 => SeqNoClass::RbfOnly,
             no if no & SEQ_NO_CSV_TYPE_MASK != 0 => SeqNoClass::RelativeTime,
@@ -48,7 +87,7 @@ impl SeqNoExt for SeqNo {
     }
 
     #[inline]
-    fn is_rbf(self) -> bool { self.0 < SEQ_NO_SUBMAX_VALUE }
+    fn is_rbf(&self) -> bool { self.to_consensus_u32() < SEQ_NO_SUBMAX_VALUE }
 }
 
 /// Classes for `nSeq` values
@@ -64,38 +103,19 @@ pub enum SeqNoClass {
     /// Values from `0x80000000` to `0xFFFFFFFD` inclusively
     RbfOnly,
 
-    /// Both RBF and relative height-based lock is applied.
+    /// Both RBF and relative time-based lock is applied.
     RelativeTime,
 
-    /// Both RBF and relative time-based lock is applied.
+    /// Both RBF and relative height-based lock is applied.
     RelativeHeight,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+/// Wrapper around `nSeq` value providing its human-readable representation in terms of RBF and
+/// relative time locks.
+#[derive(Wrapper, Copy, Clone, Eq, PartialEq, Hash, Debug, From)]
+#[wrapper(Deref)]
 pub struct Rbf(SeqNo);
 
-impl Rbf {
-    /// Creates `nSeq` value which is not encumbered by either RBF not relative
-    /// time locks.
-    ///
-    /// # Arguments
-    /// - `max` defines whether `nSeq` should be set to the `0xFFFFFFFF` (`true`) or `0xFFFFFFFe`.
-    #[inline]
-    pub fn unencumbered(max: bool) -> SeqNo {
-        SeqNo(if max { SEQ_NO_MAX_VALUE } else { SEQ_NO_SUBMAX_VALUE })
-    }
-
-    /// Creates `nSeq` in replace-by-fee mode with the specified order number.
-    #[inline]
-    pub fn from_rbf(order: u16) -> SeqNo { SeqNo(order as u32 | SEQ_NO_CSV_DISABLE_MASK) }
-
-    /// Creates `nSeq` in replace-by-fee mode with value 0xFFFFFFFD.
-    ///
-    /// This value is the value supported by the BitBox software.
-    #[inline]
-    pub fn rbf() -> SeqNo { SeqNo(SEQ_NO_SUBMAX_VALUE - 1) }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, From, Display)]
 #[display(doc_comments)]
 pub enum ParseError {
@@ -111,10 +131,6 @@ pub enum ParseError {
 
     /// time lock descriptor `{0}` is not recognized
     InvalidDescriptor(String),
-
-    /// use of randomly-generated RBF sequence numbers requires compilation
-    /// with `rand` feature
-    NoRand,
 }
 
 impl std::error::Error for ParseError {
@@ -127,29 +143,25 @@ impl std::error::Error for ParseError {
 }
 
 impl Display for Rbf {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self.classify() {
-            SeqNoClass::Unencumbered if self.0 == SEQ_NO_MAX_VALUE => {
-                f.write_str("final(0xFFFFFFFF)")
-            }
-            SeqNoClass::Unencumbered if self.0 == SEQ_NO_SUBMAX_VALUE => {
-                f.write_str("non-rbf(0xFFFFFFFE)")
-            }
-            SeqNoClass::Unencumbered => unreachable!(),
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let no = self.0.to_consensus_u32();
+        match self.0.classify() {
+            SeqNoClass::Unencumbered if no == SEQ_NO_MAX_VALUE => f.write_str("final(0xFFFFFFFF)"),
+            SeqNoClass::Unencumbered => f.write_str("non-rbf(0xFFFFFFFE)"),
             SeqNoClass::RbfOnly => {
                 f.write_str("rbf(")?;
-                Display::fmt(&(self.0 ^ SEQ_NO_CSV_DISABLE_MASK), f)?;
+                Display::fmt(&(no ^ SEQ_NO_CSV_DISABLE_MASK), f)?;
                 f.write_str(")")
             }
-            _ if self.0 >> 16 & 0xFFBF > 0 => Display::fmt(&self.0, f),
+            _ if no >> 16 & 0xFFBF > 0 => Display::fmt(&no, f),
             SeqNoClass::RelativeTime => {
-                let value = self.0 & 0xFFFF;
+                let value = no & 0xFFFF;
                 f.write_str("time(")?;
                 Display::fmt(&value, f)?;
                 f.write_str(")")
             }
             SeqNoClass::RelativeHeight => {
-                let value = self.0 & 0xFFFF;
+                let value = no & 0xFFFF;
                 f.write_str("height(")?;
                 Display::fmt(&value, f)?;
                 f.write_str(")")
@@ -163,27 +175,241 @@ impl FromStr for Rbf {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let s = s.to_lowercase();
-        if s == "rbf" {
-            #[cfg(feature = "rand")]
-            {
-                Ok(SeqNo::rbf())
-            }
-            #[cfg(not(feature = "rand"))]
-            {
-                Err(ParseError::NoRand)
-            }
+        let seq_no = if s == "rbf" {
+            SeqNo::rbf()
+        } else if s == "final(0xffffffff)" {
+            SeqNo::unencumbered(true)
+        } else if s == "non-rbf(0xfffffffe)" {
+            SeqNo::unencumbered(false)
         } else if s.starts_with("rbf(") && s.ends_with(')') {
             let no = s[4..].trim_end_matches(')').parse()?;
-            Ok(SeqNo::from_rbf(no))
+            SeqNo::from_rbf(no)
         } else if s.starts_with("time(") && s.ends_with(')') {
             let no = s[5..].trim_end_matches(')').parse()?;
-            Ok(SeqNo::from_intervals(no))
+            SeqNo::from_intervals(no)
         } else if s.starts_with("height(") && s.ends_with(')') {
             let no = s[7..].trim_end_matches(')').parse()?;
-            Ok(SeqNo::from_height(no))
+            SeqNo::from_height(no)
         } else {
-            let no = s.parse()?;
-            Ok(SeqNo(no))
+            let no = s.parse().map_err(|_| ParseError::InvalidDescriptor(s))?;
+            SeqNo::from_consensus_u32(no)
+        };
+        Ok(Rbf(seq_no))
+    }
+}
+
+#[derive(Clone, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum RbfError {
+    #[from]
+    #[display(inner)]
+    Construction(ConstructionError),
+
+    #[from]
+    #[display(inner)]
+    LockTime(LockTimeConflict),
+
+    /// transaction {0} can't be replaced since none of its inputs signals replaceability
+    /// (BIP-125).
+    NotReplaceable(Txid),
+}
+
+/// Construction of replacement transactions according to BIP-125. Implemented for all
+/// [`PsbtConstructor`]s.
+pub trait RbfConstructor: PsbtConstructor {
+    /// Minimal fee rate which a replacement transaction has to pay on top of the fee of the
+    /// original transaction (BIP-125 rule 4). Defaults to 1 sat/vB, the default incremental relay
+    /// fee rate of Bitcoin Core.
+    fn incremental_relay_fee_rate(&self) -> FeeRate { FeeRate::MIN_RELAY }
+
+    /// Constructs PSBT replacing the original one with a higher fee, as provided in `params.fee`.
+    ///
+    /// The change output is detected from the derivation information of the original PSBT
+    /// outputs, and must use `params.change_keychain`. See [`Self::replace_tx`] for the details.
+    fn bump_fee(
+        &mut self,
+        original: &Psbt,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), RbfError> {
+        let change = original.outputs().find_map(|output| {
+            let terminal = output.terminal_derivation()?;
+            if terminal.keychain != params.change_keychain {
+                return None;
+            }
+            let script = self.descriptor().derive(terminal.keychain, terminal.index).next()?;
+            if script.to_script_pubkey() != output.script {
+                return None;
+            }
+            Some(ChangeInfo {
+                vout: output.vout(),
+                terminal,
+            })
+        });
+        self.replace_tx(&original.to_unsigned_tx()?, change, params)
+    }
+
+    /// Constructs PSBT replacing the original transaction with a higher fee, as provided in
+    /// `params.fee`. The transaction must spend only the coins of the wallet, and its change
+    /// output (if any) must be provided by the caller.
+    ///
+    /// All the payment outputs are preserved. The additional fee is taken from the change
+    /// output first; if it is not sufficient, the change is dropped and confirmed coins of the
+    /// wallet are added (largest first), creating a new change output if needed. The new inputs
+    /// use `params.seq_no`.
+    ///
+    /// The fee of the replacement is never less than the fee of the original transaction plus the
+    /// incremental relay fee for the replacement size (see [`Self::incremental_relay_fee_rate`]),
+    /// as required by BIP-125.
+    fn replace_tx(
+        &mut self,
+        original: &UnsignedTx,
+        change: Option<ChangeInfo>,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), RbfError> {
+        let txid = original.txid();
+        if !original.inputs.iter().any(|txin| txin.sequence.is_rbf()) {
+            return Err(RbfError::NotReplaceable(txid));
+        }
+
+        let mut psbt = Psbt::create(PsbtVer::V2);
+        psbt.tx_version = original.version;
+        psbt.fallback_locktime = Some(original.lock_time);
+        for spec in self.descriptor().xpubs() {
+            psbt.xpubs.insert(*spec.xpub(), spec.origin().clone());
+        }
+        let heights = self.coin_heights();
+        for txin in &original.inputs {
+            let height = heights.get(&txin.prev_output).copied().flatten();
+            self.append_coin(&mut psbt, txin.prev_output, height, txin.sequence)?;
+        }
+        let mut payments = Sats::ZERO;
+        for (vout, txout) in original.outputs.iter().enumerate() {
+            if change.map(|c| c.vout) == Some(Vout::from_u32(vout as u32)) {
+                continue;
+            }
+            payments += txout.value;
+            psbt.append_output_expect(txout.script_pubkey.clone(), txout.value);
+        }
+        let change_value =
+            change.and_then(|c| original.outputs.get(c.vout.into_usize())).map(|txout| txout.value);
+        let input_value = psbt.input_sum();
+        let output_value = payments + change_value.unwrap_or_default();
+        let original_fee = input_value.checked_sub(output_value).ok_or(
+            ConstructionError::OutputExceedsInputs {
+                input_value,
+                output_value,
+            },
+        )?;
+
+        let incremental_fee_rate = self.incremental_relay_fee_rate();
+        let required_fee = |psbt: &Psbt| -> Result<Sats, ConstructionError> {
+            let weight = self.estimate_weight(psbt)?;
+            let fee = match params.fee {
+                Fee::Fixed(fee) => fee,
+                Fee::Rate(fee_rate) => fee_rate.fee(weight),
+            };
+            Ok(fee.max(original_fee + incremental_fee_rate.fee(weight)))
+        };
+
+        // BIP-125 rule 2: the replacement may only include confirmed new inputs
+        let mut coins = self
+            .utxos()
+            .filter(|(utxo, height)| {
+                height.is_some()
+                    && !original.inputs.iter().any(|txin| txin.prev_output == utxo.outpoint)
+            })
+            .collect::<Vec<_>>();
+        coins.sort_by_key(|(utxo, _)| Reverse(utxo.value));
+        let mut coins = coins.into_iter();
+
+        let dust_limit = self.descriptor().class().dust_limit();
+        let probe_terminal = change
+            .map(|c| c.terminal)
+            .unwrap_or(Terminal::new(params.change_keychain, NormalIndex::ZERO));
+        let (fee, change_value) = loop {
+            let surplus = psbt.input_sum() - payments;
+            let mut probe = psbt.clone();
+            probe.append_change_expect(self.descriptor(), probe_terminal, Sats::ZERO);
+            let fee_with_change = required_fee(&probe)?;
+            if let Some(value) = surplus.checked_sub(fee_with_change).filter(|v| *v > dust_limit) {
+                break (fee_with_change, Some(value));
+            }
+            let fee = required_fee(&psbt)?;
+            if surplus >= fee {
+                break (surplus, None);
+            }
+            let Some((coin, height)) = coins.next() else {
+                return Err(ConstructionError::NoFundsForFee {
+                    input_value: psbt.input_sum(),
+                    output_value: payments,
+                    fee,
+                }
+                .into());
+            };
+            match self.append_coin(&mut psbt, coin.outpoint, height, params.seq_no) {
+                // Coins whose timelocks have not expired yet can't be spent
+                Err(ConstructionError::Timelocked(_)) => continue,
+                res => res?,
+            }
+        };
+
+        let change = match change_value {
+            Some(value) => {
+                let terminal = match change {
+                    Some(change) => change.terminal,
+                    None => {
+                        let index =
+                            self.next_derivation_index(params.change_keychain, params.change_shift);
+                        Terminal::new(params.change_keychain, index)
+                    }
+                };
+                let vout = psbt.append_change_expect(self.descriptor(), terminal, value).vout();
+                Some(ChangeInfo { vout, terminal })
+            }
+            None => None,
+        };
+
+        let weight = psbt.estimate_weight(self.descriptor());
+        let meta = PsbtMeta {
+            network: self.network().into(),
+            fee,
+            weight: weight.into_u32(),
+            size: VBytes::from(weight).into_u32(),
+            change,
+        };
+        self.after_construct_psbt(&psbt, &meta);
+
+        Ok((psbt, meta))
+    }
+
+    /// Constructs PSBT replacing the original signed transaction with a higher fee. See
+    /// [`Self::replace_tx`] for the details.
+    fn bump_tx_fee(
+        &mut self,
+        original: &Tx,
+        change: Option<ChangeInfo>,
+        params: TxParams,
+    ) -> Result<(Psbt, PsbtMeta), RbfError> {
+        self.replace_tx(&UnsignedTx::with_sigs_removed(original.clone()), change, params)
+    }
+}
+
+impl<T: PsbtConstructor> RbfConstructor for T {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rbf_display_from_str() {
+        for s in ["final(0xFFFFFFFF)", "non-rbf(0xFFFFFFFE)", "rbf(5)", "time(10)", "height(100)"] {
+            assert_eq!(Rbf::from_str(s).unwrap().to_string(), s);
         }
+        assert_eq!(Rbf::from_str("rbf").unwrap(), Rbf::from(SeqNo::rbf()));
+        assert_eq!(SeqNo::rbf().classify(), SeqNoClass::RbfOnly);
+        assert_eq!(SeqNo::from_height(6).classify(), SeqNoClass::RelativeHeight);
+        assert!(SeqNo::from_intervals(6).is_rbf());
+        assert!(!SeqNo::unencumbered(false).is_rbf());
+        assert!(Rbf::from_str("rbf(x)").is_err());
     }
 }
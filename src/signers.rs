@@ -27,8 +27,8 @@ use std::collections::HashMap;
 use amplify::Wrapper;
 use bc::secp256k1::{ecdsa, schnorr as bip340, SECP256K1};
 use bc::{
-    InternalKeypair, InternalPk, LegacyPk, Sighash, TapLeafHash, TapMerklePath, TapNodeHash,
-    TapSighash, XOnlyPk,
+    InternalKeypair, InternalPk, LegacyPk, Sighash, SighashType, TapLeafHash, TapMerklePath,
+    TapNodeHash, TapSighash, XOnlyPk,
 };
 use derive::{KeyOrigin, Sign, XkeyOrigin, Xpriv, XprivAccount};
 use psbt::{Psbt, Rejected, Signer};
@@ -106,6 +106,7 @@ impl Sign for TestnetRefSigner<'_> {
     fn sign_ecdsa(
         &self,
         message: Sighash,
+        _sighash_type: SighashType,
         pk: LegacyPk,
         origin: Option<&KeyOrigin>,
     ) -> Option<ecdsa::Signature> {
@@ -123,6 +124,7 @@ impl Sign for TestnetRefSigner<'_> {
     fn sign_bip340_key_only(
         &self,
         message: TapSighash,
+        _sighash_type: Option<SighashType>,
         pk: InternalPk,
         origin: Option<&KeyOrigin>,
         merkle_root: Option<TapNodeHash>,
@@ -141,6 +143,7 @@ impl Sign for TestnetRefSigner<'_> {
     fn sign_bip340_script_path(
         &self,
         message: TapSighash,
+        _sighash_type: Option<SighashType>,
         pk: XOnlyPk,
         origin: Option<&KeyOrigin>,
     ) -> Option<bip340::Signature> {
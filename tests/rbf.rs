@@ -0,0 +1,155 @@
+// Modern, minimalistic & standard-compliant Bitcoin library.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Designed in 2019-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+// Written in 2024-2025 by Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association, Switzerland.
+// Copyright (C) 2024-2025 LNP/BP Labs, Institute for Distributed and Cognitive Systems (InDCS).
+// Copyright (C) 2019-2025 Dr Maxim Orlovsky.
+// All rights under the above copyrights are reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[path = "../psbt/tests/common/mod.rs"]
+mod common;
+
+use std::str::FromStr;
+
+use bpstd::{RbfConstructor, RbfError, SeqNoExt};
+use common::{beneficiary, xpub, Wallet};
+use derive::{Sats, SeqNo, Tx, XpubDerivable};
+use descriptors::{Descriptor, StdDescr, Wpkh, WshScript};
+use psbt::{ConstructionError, FeeRate, Psbt, PsbtConstructor, PsbtMeta, TxParams};
+
+/// Wallet with P2WPKH coins of the given values mined at the given heights.
+fn wallet(coins: &[(u64, Option<u32>)]) -> Wallet<Wpkh<XpubDerivable>> {
+    Wallet::with_coins(Wpkh::from(xpub(1)), coins)
+}
+
+/// Constructs PSBT spending the first wallet coin with the given sequence number.
+fn pay<D: Descriptor>(
+    wallet: &mut Wallet<D>,
+    amount: u64,
+    fee_rate: u64,
+    seq_no: SeqNo,
+) -> (Psbt, PsbtMeta) {
+    let mut params = TxParams::with(FeeRate::from_sat_per_vb(fee_rate).unwrap());
+    params.seq_no = seq_no;
+    wallet.construct_psbt([wallet.coin(0)], [beneficiary(amount)], params).unwrap()
+}
+
+#[test]
+fn bump_reducing_change() {
+    let mut wallet = wallet(&[(100_000, Some(1)), (50_000, Some(2))]);
+    let (original, meta) = pay(&mut wallet, 50_000, 1, SeqNo::rbf());
+    let change = meta.change.unwrap();
+
+    let fee_rate = FeeRate::from_sat_per_vb(5).unwrap();
+    let (psbt, bump) = wallet.bump_fee(&original, TxParams::with(fee_rate)).unwrap();
+    assert_eq!(psbt.inputs().count(), 1);
+    assert_eq!(psbt.input(0).unwrap().sequence_number, Some(SeqNo::rbf()));
+    assert_eq!(psbt.output(0).unwrap().to_txout(), original.output(0).unwrap().to_txout());
+    assert_eq!(bump.change.unwrap().terminal, change.terminal);
+    assert_eq!(bump.fee, fee_rate.fee(psbt.estimate_weight(&wallet.descriptor)));
+    assert_eq!(
+        psbt.output(1).unwrap().value(),
+        original.output(1).unwrap().value() - (bump.fee - meta.fee)
+    );
+}
+
+#[test]
+fn bump_incremental_fee() {
+    let mut wallet = wallet(&[(100_000, Some(1))]);
+    let (original, meta) = pay(&mut wallet, 50_000, 5, SeqNo::rbf());
+
+    // The same fee rate requires paying the incremental relay fee on top of the original fee
+    let params = TxParams::with(FeeRate::from_sat_per_vb(5).unwrap());
+    let (psbt, bump) = wallet.bump_fee(&original, params).unwrap();
+    assert_eq!(
+        bump.fee,
+        meta.fee + FeeRate::MIN_RELAY.fee(psbt.estimate_weight(&wallet.descriptor))
+    );
+}
+
+#[test]
+fn bump_adding_inputs() {
+    let mut wallet = wallet(&[(100_000, Some(1)), (50_000, Some(2))]);
+    let (original, meta) = pay(&mut wallet, 99_800, 1, SeqNo::rbf());
+    assert_eq!(meta.change, None);
+    assert_eq!(meta.fee, Sats(200));
+
+    let fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+    let (psbt, bump) = wallet.bump_fee(&original, TxParams::with(fee_rate)).unwrap();
+    assert_eq!(psbt.inputs().count(), 2);
+    assert_eq!(psbt.output(0).unwrap().value(), Sats(99_800));
+    assert!(bump.change.is_some());
+    assert_eq!(bump.fee, fee_rate.fee(psbt.estimate_weight(&wallet.descriptor)));
+    assert_eq!(psbt.input_sum() - psbt.output_sum(), bump.fee);
+
+    let tx = Tx::from(original.to_unsigned_tx().unwrap());
+    let (psbt2, _) = wallet.bump_tx_fee(&tx, None, TxParams::with(fee_rate)).unwrap();
+    assert_eq!(psbt2.to_unsigned_tx().unwrap(), psbt.to_unsigned_tx().unwrap());
+}
+
+#[test]
+fn bump_adding_timelocked_input() {
+    let descriptor =
+        WshScript::from_str(&format!("wsh(and_v(v:pk({}),older(10)))", xpub(1))).unwrap();
+    let coins = [(100_000, Some(1)), (80_000, Some(95)), (50_000, Some(2))];
+    let mut wallet = Wallet::with_coins(descriptor, &coins);
+    wallet.tip = Some(100);
+    let (original, meta) = pay(&mut wallet, 99_700, 1, SeqNo::rbf());
+    assert_eq!(meta.change, None);
+    assert_eq!(original.input(0).unwrap().sequence_number, Some(SeqNo::from_height(10)));
+
+    // The largest coin is still timelocked, so the next one is added
+    let fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+    let (psbt, _) = wallet.bump_fee(&original, TxParams::with(fee_rate)).unwrap();
+    assert_eq!(psbt.inputs().count(), 2);
+    let input = psbt.input(1).unwrap();
+    assert_eq!(input.previous_outpoint, wallet.coin(2));
+    assert_eq!(input.sequence_number, Some(SeqNo::from_height(10)));
+    assert_eq!(psbt.input(0).unwrap().sequence_number, Some(SeqNo::from_height(10)));
+}
+
+#[test]
+fn bump_failures() {
+    let mut wallet = wallet(&[(100_000, Some(1)), (50_000, None)]);
+    let (original, _) = pay(&mut wallet, 50_000, 1, SeqNo::unencumbered(false));
+    assert!(matches!(
+        wallet.bump_fee(&original, TxParams::with(FeeRate::from_sat_per_vb(5).unwrap())),
+        Err(RbfError::NotReplaceable(_))
+    ));
+
+    // Unconfirmed coins can't be added to the replacement
+    let (original, _) = pay(&mut wallet, 99_800, 1, SeqNo::rbf());
+    assert!(matches!(
+        wallet.bump_fee(&original, TxParams::with(FeeRate::from_sat_per_vb(10).unwrap())),
+        Err(RbfError::Construction(ConstructionError::NoFundsForFee { .. }))
+    ));
+
+    // The weight of the replacement is unknown for descriptors not defining their satisfaction
+    let descriptor =
+        StdDescr::from_str("addr(tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx)").unwrap();
+    let mut wallet = Wallet::with(descriptor);
+    let mut params = TxParams::with(Sats(1000));
+    params.seq_no = SeqNo::rbf();
+    let (original, _) =
+        wallet.construct_psbt([wallet.coin(0)], [beneficiary(50_000)], params).unwrap();
+    assert!(matches!(
+        wallet.bump_fee(&original, TxParams::with(Sats(2000))),
+        Err(RbfError::Construction(ConstructionError::UnknownSatisfaction))
+    ));
+}